@@ -0,0 +1,13 @@
+//! Compiles `proto/solana_node.proto` into the descriptor set served by gRPC reflection
+
+use prost::Message;
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto");
+    
+    let descriptors = protox::compile(["solana_node.proto"], ["proto"])?;
+    let out = PathBuf::from(std::env::var("OUT_DIR")?).join("solana_node_descriptor.bin");
+    std::fs::write(out, descriptors.encode_to_vec())?;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use tracing::info;
+
+/// A user-provided name for an address, e.g. "Binance hot wallet"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressLabel {
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+/// An account of a transaction matched by the labels file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabeledAccount {
+    pub address: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+impl LabeledAccount {
+    /// Whether a filter or query term names this account's label or category
+    pub fn matches(&self, term: &str) -> bool {
+        self.label == term || self.category.as_deref() == Some(term)
+    }
+}
+
+/// Address labels loaded from a TOML file of `"<address>" = { label = "...", category = "..." }` entries
+#[derive(Debug, Default)]
+pub struct AddressLabels {
+    labels: HashMap<String, AddressLabel>,
+}
+
+impl AddressLabels {
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+        
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read address labels {}", path))?;
+        let labels: HashMap<String, AddressLabel> = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse address labels {}", path))?;
+        
+        info!("Loaded {} address labels from {}", labels.len(), path);
+        Ok(Self { labels })
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+    
+    /// Label every account that has an entry in the labels file
+    pub fn tag(&self, account_keys: &[String]) -> Vec<LabeledAccount> {
+        account_keys
+            .iter()
+            .filter_map(|address| {
+                let label = self.labels.get(address)?;
+                Some(LabeledAccount {
+                    address: address.clone(),
+                    label: label.label.clone(),
+                    category: label.category.clone(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,188 @@
+//! AMQP publisher, for brokers such as RabbitMQ
+//!
+//! Each processed transaction is published to the configured exchange once per routing key derived
+//! from it: its programs' labels (`program.jupiter-v6`), their categories (`category.dex`) or its
+//! event types (`event.swap`), so queues bind to the keys they want on a topic exchange. Every
+//! publish is confirmed by the broker. After a connection failure the node reconnects and publishes
+//! the unconfirmed batch again; consumers can drop the copies by message ID (the signature).
+
+use anyhow::{Context, Result};
+use lapin::{
+    options::{BasicPublishOptions, ConfirmSelectOptions, ExchangeDeclareOptions},
+    publisher_confirm::Confirmation,
+    types::FieldTable,
+    BasicProperties, Channel, Connection, ConnectionProperties, ExchangeKind,
+};
+use prost::Message;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{sync::broadcast, time::sleep};
+use tracing::{info, warn};
+
+use crate::api::feed::StreamedTransaction;
+use crate::config::{AmqpConfig, AmqpRouting, ProgramLabel, SinkFormat};
+use crate::metrics::Metrics;
+use crate::program_registry::ProgramRegistry;
+
+/// Most transactions published before awaiting their confirms, taken from those already waiting
+const MAX_BATCH: usize = 100;
+
+/// Wait before connecting again after a failure
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Persistent messages survive broker restarts on durable queues
+const DELIVERY_MODE_PERSISTENT: u8 = 2;
+
+pub struct AmqpSink {
+    config: AmqpConfig,
+    registry: ProgramRegistry,
+    metrics: Arc<Metrics>,
+}
+
+impl AmqpSink {
+    /// `program_labels` extend the built-in labels routing keys are derived from
+    pub fn new(config: &AmqpConfig, program_labels: &BTreeMap<String, ProgramLabel>, metrics: Arc<Metrics>) -> Self {
+        Self {
+            config: config.clone(),
+            registry: ProgramRegistry::new(program_labels),
+            metrics,
+        }
+    }
+    
+    /// Publish the transactions of `feed` in the background
+    pub fn start(self, feed: &broadcast::Sender<StreamedTransaction>) {
+        tokio::spawn(self.publish(feed.subscribe()));
+    }
+    
+    async fn publish(self, mut transactions: broadcast::Receiver<StreamedTransaction>) {
+        let mut connection = None;
+        // Transactions whose publishes were not all confirmed, published again after reconnecting
+        let mut batch: Vec<StreamedTransaction> = Vec::new();
+        loop {
+            let connected = match connection.take() {
+                Some(connected) => connected,
+                None => match self.connect().await {
+                    Ok(connected) => connected,
+                    Err(e) => {
+                        warn!("Failed to connect to AMQP broker: {:#}, retrying in {:?}", e, RETRY_INTERVAL);
+                        sleep(RETRY_INTERVAL).await;
+                        continue;
+                    }
+                },
+            };
+            
+            if batch.is_empty() {
+                match transactions.recv().await {
+                    Ok(tx) => batch.push(tx),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("AMQP publisher fell behind, {} transactions not published", missed);
+                        self.metrics.amqp_failed.add(missed);
+                        connection = Some(connected);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+                while batch.len() < MAX_BATCH {
+                    match transactions.try_recv() {
+                        Ok(tx) => batch.push(tx),
+                        Err(_) => break,
+                    }
+                }
+            }
+            
+            let (_, channel) = &connected;
+            match self.publish_batch(channel, &batch).await {
+                Ok(()) => {
+                    batch.clear();
+                    connection = Some(connected);
+                }
+                Err(e) => warn!("AMQP publish failed: {:#}, reconnecting", e),
+            }
+        }
+    }
+    
+    /// The connection is kept alongside its channel, which it must outlive
+    async fn connect(&self) -> Result<(Connection, Channel)> {
+        let connection = Connection::connect(&self.config.url, ConnectionProperties::default())
+            .await
+            .context("Failed to connect")?;
+        let channel = connection.create_channel().await?;
+        channel.confirm_select(ConfirmSelectOptions::default()).await?;
+        if self.config.declare_exchange {
+            channel
+                .exchange_declare(
+                    &self.config.exchange,
+                    ExchangeKind::Topic,
+                    ExchangeDeclareOptions { durable: true, ..Default::default() },
+                    FieldTable::default(),
+                )
+                .await
+                .with_context(|| format!("Failed to declare exchange {}", self.config.exchange))?;
+        }
+        info!("Publishing transactions to AMQP exchange {}", self.config.exchange);
+        Ok((connection, channel))
+    }
+    
+    /// Publish every routing key of `batch`, then await the confirms. Messages the broker refuses
+    /// (nacks) are counted as failed, not published again
+    async fn publish_batch(&self, channel: &Channel, batch: &[StreamedTransaction]) -> Result<()> {
+        let mut confirms = Vec::new();
+        for tx in batch {
+            let (content_type, payload) = match self.config.format {
+                SinkFormat::Json => ("application/json", tx.json.as_bytes().to_vec()),
+                SinkFormat::Protobuf => ("application/x-protobuf", tx.proto.encode_to_vec()),
+            };
+            for routing_key in self.routing_keys(tx) {
+                let properties = BasicProperties::default()
+                    .with_content_type(content_type.into())
+                    .with_message_id(tx.signature.clone().into())
+                    .with_delivery_mode(DELIVERY_MODE_PERSISTENT);
+                let confirm = channel
+                    .basic_publish(&self.config.exchange, &routing_key, BasicPublishOptions::default(), &payload, properties)
+                    .await?;
+                confirms.push(confirm);
+            }
+        }
+        
+        for confirm in confirms {
+            match confirm.await? {
+                Confirmation::Nack(_) => {
+                    warn!("AMQP broker refused a message");
+                    self.metrics.amqp_failed.inc();
+                }
+                _ => self.metrics.amqp_published.inc(),
+            }
+        }
+        Ok(())
+    }
+    
+    fn routing_keys(&self, tx: &StreamedTransaction) -> BTreeSet<String> {
+        let labels = || tx.programs.iter().map(|program| (program, self.registry.get(program)));
+        let keys: BTreeSet<String> = match self.config.routing {
+            AmqpRouting::Program => labels()
+                .map(|(program, label)| format!("program.{}", label.map_or(program.clone(), |label| slug(&label.name))))
+                .collect(),
+            AmqpRouting::Category => labels()
+                .filter_map(|(_, label)| label.map(|label| format!("category.{}", slug(&label.category))))
+                .collect(),
+            AmqpRouting::Event => tx.events.iter().map(|event| format!("event.{}", event)).collect(),
+        };
+        match (keys.is_empty(), self.config.routing) {
+            (true, AmqpRouting::Category) => BTreeSet::from(["category.unknown".to_string()]),
+            (true, AmqpRouting::Event) => BTreeSet::from(["event.none".to_string()]),
+            _ => keys,
+        }
+    }
+}
+
+/// A label as a routing key word: lowercase, with runs of other characters than letters and
+/// digits replaced by `-` (`OpenBook (Serum v3)` becomes `openbook-serum-v3`)
+fn slug(label: &str) -> String {
+    label
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
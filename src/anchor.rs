@@ -0,0 +1,331 @@
+//! Decoding of Anchor events emitted as base64 `Program data:` log lines, using program IDLs
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use solana_sdk::{bs58, hash::hashv};
+use std::collections::HashMap;
+use std::fs;
+use tracing::{debug, info};
+
+/// Limits nesting of IDL types so malformed IDLs cannot recurse forever
+const MAX_TYPE_DEPTH: usize = 32;
+
+/// A decoded Anchor event, linked to the instruction that emitted it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorEvent {
+    pub program_id: String,
+    /// Index of the top-level instruction during which the event was emitted
+    pub instruction_index: usize,
+    pub name: String,
+    pub data: Value,
+}
+
+/// IDLs of the programs whose events should be decoded, keyed by program ID
+#[derive(Default)]
+pub struct IdlRegistry {
+    programs: HashMap<String, ProgramIdl>,
+}
+
+struct ProgramIdl {
+    events: Vec<EventDef>,
+    types: HashMap<String, Value>,
+}
+
+struct EventDef {
+    name: String,
+    discriminator: [u8; 8],
+    /// Struct fields of the event
+    fields: Vec<Value>,
+}
+
+impl IdlRegistry {
+    /// Load IDL JSON files (legacy and 0.30+ Anchor formats)
+    pub fn load(paths: &[String]) -> Result<Self> {
+        let mut programs = HashMap::new();
+        
+        for path in paths {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read IDL {}", path))?;
+            let idl: Value = serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse IDL {}", path))?;
+            let (program_id, program) = ProgramIdl::parse(&idl)
+                .with_context(|| format!("Invalid IDL {}", path))?;
+            
+            info!("Loaded IDL for {} with {} events", program_id, program.events.len());
+            programs.insert(program_id, program);
+        }
+        
+        Ok(Self { programs })
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+    
+    /// Decode all events in a transaction's log messages
+    pub fn decode_logs(&self, log_messages: &[String]) -> Vec<AnchorEvent> {
+        let mut events = Vec::new();
+        let mut stack: Vec<&str> = Vec::new();
+        let mut instruction_index: Option<usize> = None;
+        
+        for line in log_messages {
+            if let Some(rest) = line.strip_prefix("Program data: ") {
+                let program_id = match stack.last() {
+                    Some(program_id) => *program_id,
+                    None => continue,
+                };
+                let program = match self.programs.get(program_id) {
+                    Some(program) => program,
+                    None => continue,
+                };
+                
+                for chunk in rest.split_whitespace() {
+                    match program.decode_event(chunk) {
+                        Ok(Some((name, data))) => events.push(AnchorEvent {
+                            program_id: program_id.to_string(),
+                            instruction_index: instruction_index.unwrap_or(0),
+                            name,
+                            data,
+                        }),
+                        Ok(None) => {}
+                        Err(e) => debug!("Failed to decode event from {}: {}", program_id, e),
+                    }
+                }
+            } else if let Some(rest) = line.strip_prefix("Program ") {
+                // "Program <id> invoke [<depth>]" / "Program <id> success" / "Program <id> failed: ..."
+                let mut parts = rest.split_whitespace();
+                let program_id = parts.next().unwrap_or_default();
+                match parts.next() {
+                    Some("invoke") => {
+                        if parts.next() == Some("[1]") {
+                            instruction_index = Some(instruction_index.map_or(0, |i| i + 1));
+                        }
+                        stack.push(program_id);
+                    }
+                    Some("success") | Some("failed:") => {
+                        stack.pop();
+                    }
+                    _ => {}
+                }
+            }
+        }
+        
+        events
+    }
+}
+
+impl ProgramIdl {
+    fn parse(idl: &Value) -> Result<(String, Self)> {
+        let program_id = idl.get("address")
+            .or_else(|| idl.get("metadata").and_then(|m| m.get("address")))
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("IDL has no program address"))?
+            .to_string();
+        
+        let types: HashMap<String, Value> = idl.get("types")
+            .and_then(Value::as_array)
+            .map(|types| {
+                types.iter()
+                    .filter_map(|ty| {
+                        Some((ty.get("name")?.as_str()?.to_string(), ty.get("type")?.clone()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        let mut events = Vec::new();
+        for event in idl.get("events").and_then(Value::as_array).into_iter().flatten() {
+            let name = event.get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("Event without name"))?
+                .to_string();
+            
+            // 0.30+ IDLs carry the discriminator and describe fields in `types`
+            let discriminator = match event.get("discriminator").and_then(Value::as_array) {
+                Some(bytes) => {
+                    let bytes: Vec<u8> = bytes.iter().filter_map(|b| b.as_u64().map(|b| b as u8)).collect();
+                    bytes.try_into().map_err(|_| anyhow!("Invalid discriminator for {}", name))?
+                }
+                None => {
+                    let preimage = format!("event:{}", name);
+                    let mut discriminator = [0u8; 8];
+                    discriminator.copy_from_slice(&hashv(&[preimage.as_bytes()]).to_bytes()[..8]);
+                    discriminator
+                }
+            };
+            let fields = event.get("fields")
+                .or_else(|| types.get(&name).and_then(|ty| ty.get("fields")))
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            
+            events.push(EventDef { name, discriminator, fields });
+        }
+        
+        Ok((program_id, Self { events, types }))
+    }
+    
+    /// Decode a base64 event payload, returning None if it matches no known event
+    fn decode_event(&self, encoded: &str) -> Result<Option<(String, Value)>> {
+        let data = STANDARD.decode(encoded)?;
+        let event = match self.events.iter().find(|event| data.get(..8) == Some(&event.discriminator[..])) {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        
+        let mut reader = BorshReader { data: &data[8..] };
+        let value = self.decode_fields(&event.fields, &mut reader, 0)?;
+        Ok(Some((event.name.clone(), value)))
+    }
+    
+    fn decode_fields(&self, fields: &[Value], reader: &mut BorshReader, depth: usize) -> Result<Value> {
+        // Named fields decode to an object, tuple fields to an array
+        if fields.iter().all(|field| field.get("name").is_some()) {
+            let mut object = Map::new();
+            for field in fields {
+                let name = field["name"].as_str().unwrap_or_default().to_string();
+                let ty = field.get("type").ok_or_else(|| anyhow!("Field {} has no type", name))?;
+                object.insert(name, self.decode_type(ty, reader, depth + 1)?);
+            }
+            Ok(Value::Object(object))
+        } else {
+            let values = fields.iter()
+                .map(|ty| self.decode_type(ty, reader, depth + 1))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Value::Array(values))
+        }
+    }
+    
+    fn decode_type(&self, ty: &Value, reader: &mut BorshReader, depth: usize) -> Result<Value> {
+        if depth > MAX_TYPE_DEPTH {
+            bail!("IDL type nesting too deep");
+        }
+        
+        if let Some(primitive) = ty.as_str() {
+            return reader.primitive(primitive);
+        }
+        
+        if let Some(inner) = ty.get("option").or_else(|| ty.get("coption")) {
+            return match reader.take(1)?[0] {
+                0 => Ok(Value::Null),
+                _ => self.decode_type(inner, reader, depth + 1),
+            };
+        }
+        
+        if let Some(inner) = ty.get("vec") {
+            let len = reader.u32()? as usize;
+            // Each element takes at least one byte, so cap allocations by what is left
+            let mut values = Vec::with_capacity(len.min(reader.data.len()));
+            for _ in 0..len {
+                values.push(self.decode_type(inner, reader, depth + 1)?);
+            }
+            return Ok(Value::Array(values));
+        }
+        
+        if let Some(array) = ty.get("array").and_then(Value::as_array) {
+            let (inner, len) = match (array.first(), array.get(1).and_then(Value::as_u64)) {
+                (Some(inner), Some(len)) => (inner, len as usize),
+                _ => bail!("Invalid array type"),
+            };
+            let values = (0..len)
+                .map(|_| self.decode_type(inner, reader, depth + 1))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(Value::Array(values));
+        }
+        
+        if let Some(defined) = ty.get("defined") {
+            // Legacy IDLs name the type directly, 0.30+ wrap it in `{ "name": ... }`
+            let name = defined.as_str()
+                .or_else(|| defined.get("name").and_then(Value::as_str))
+                .ok_or_else(|| anyhow!("Invalid defined type"))?;
+            let definition = self.types.get(name)
+                .ok_or_else(|| anyhow!("Unknown type {}", name))?;
+            return self.decode_definition(definition, reader, depth + 1);
+        }
+        
+        bail!("Unsupported IDL type {}", ty)
+    }
+    
+    fn decode_definition(&self, definition: &Value, reader: &mut BorshReader, depth: usize) -> Result<Value> {
+        let empty = Vec::new();
+        match definition.get("kind").and_then(Value::as_str) {
+            Some("struct") => {
+                let fields = definition.get("fields").and_then(Value::as_array).unwrap_or(&empty);
+                self.decode_fields(fields, reader, depth)
+            }
+            Some("enum") => {
+                let variants = definition.get("variants").and_then(Value::as_array).unwrap_or(&empty);
+                let tag = reader.take(1)?[0] as usize;
+                let variant = variants.get(tag).ok_or_else(|| anyhow!("Invalid enum variant {}", tag))?;
+                let name = variant.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+                
+                match variant.get("fields").and_then(Value::as_array) {
+                    Some(fields) => {
+                        let mut object = Map::new();
+                        object.insert(name, self.decode_fields(fields, reader, depth)?);
+                        Ok(Value::Object(object))
+                    }
+                    None => Ok(Value::String(name)),
+                }
+            }
+            Some("alias") => {
+                let value = definition.get("value").ok_or_else(|| anyhow!("Invalid alias"))?;
+                self.decode_type(value, reader, depth)
+            }
+            _ => bail!("Unsupported type definition"),
+        }
+    }
+}
+
+/// Minimal Borsh reader producing JSON values
+struct BorshReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> BorshReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            bail!("Unexpected end of event data");
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+    
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+    
+    fn primitive(&mut self, name: &str) -> Result<Value> {
+        let value = match name {
+            "bool" => Value::Bool(self.take(1)?[0] != 0),
+            "u8" => Value::from(self.take(1)?[0]),
+            "i8" => Value::from(self.take(1)?[0] as i8),
+            "u16" => Value::from(u16::from_le_bytes(self.take(2)?.try_into()?)),
+            "i16" => Value::from(i16::from_le_bytes(self.take(2)?.try_into()?)),
+            "u32" => Value::from(self.u32()?),
+            "i32" => Value::from(i32::from_le_bytes(self.take(4)?.try_into()?)),
+            "u64" => Value::from(u64::from_le_bytes(self.take(8)?.try_into()?)),
+            "i64" => Value::from(i64::from_le_bytes(self.take(8)?.try_into()?)),
+            // 128-bit integers do not fit JSON numbers, so keep them as strings
+            "u128" => Value::String(u128::from_le_bytes(self.take(16)?.try_into()?).to_string()),
+            "i128" => Value::String(i128::from_le_bytes(self.take(16)?.try_into()?).to_string()),
+            "f32" => Value::from(f32::from_le_bytes(self.take(4)?.try_into()?)),
+            "f64" => Value::from(f64::from_le_bytes(self.take(8)?.try_into()?)),
+            "string" => {
+                let len = self.u32()? as usize;
+                Value::String(String::from_utf8_lossy(self.take(len)?).into_owned())
+            }
+            "bytes" => {
+                let len = self.u32()? as usize;
+                Value::String(STANDARD.encode(self.take(len)?))
+            }
+            "publicKey" | "pubkey" => Value::String(bs58::encode(self.take(32)?).into_string()),
+            other => bail!("Unsupported primitive type {}", other),
+        };
+        Ok(value)
+    }
+}
@@ -0,0 +1,150 @@
+//! Administrative endpoints, so day-to-day operations do not need a restart
+//!
+//! All of them require the `admin` scope. Pruning and compaction answer once they are done;
+//! backfills run in the background and are listed with their progress.
+
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{rest::ApiError, ApiState};
+use crate::network::BackfillJob;
+use crate::storage::PruneStats;
+use crate::webhook::{DeadLetter, Redelivery};
+
+/// Dead letters listed or redelivered per request by default, and at most
+const DEFAULT_DEAD_LETTERS: usize = 100;
+const MAX_DEAD_LETTERS: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct PruneQuery {
+    /// Defaults to the first slot within `node.storage_retention_days`
+    pub before_slot: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeadLetterQuery {
+    /// 1 to 1000 (default 100)
+    pub limit: Option<usize>,
+}
+
+impl DeadLetterQuery {
+    fn limit(&self) -> Result<usize, ApiError> {
+        match self.limit.unwrap_or(DEFAULT_DEAD_LETTERS) {
+            limit @ 1..=MAX_DEAD_LETTERS => Ok(limit),
+            _ => Err(ApiError::BadRequest(format!("limit must be between 1 and {}", MAX_DEAD_LETTERS))),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BackfillQuery {
+    pub start_slot: u64,
+    pub end_slot: u64,
+}
+
+/// `POST /admin/prune`: delete the transactions from before a slot, with their index entries
+pub async fn prune(State(state): State<ApiState>, Query(query): Query<PruneQuery>) -> Result<Json<PruneStats>, ApiError> {
+    let before_slot = match query.before_slot {
+        Some(slot) => slot,
+        None => retention_start(&state)?,
+    };
+    let storage = state.storage.clone();
+    let stats = tokio::task::spawn_blocking(move || storage.prune(before_slot))
+        .await
+        .context("Pruning panicked")??;
+    Ok(Json(stats))
+}
+
+/// `POST /admin/compact`: compact the database, reclaiming the space of pruned entries
+pub async fn compact(State(state): State<ApiState>) -> Result<Json<Value>, ApiError> {
+    let storage = state.storage.clone();
+    tokio::task::spawn_blocking(move || storage.compact())
+        .await
+        .context("Compaction panicked")?;
+    Ok(Json(json!({ "status": "compacted", "storage": state.storage.get_stats()? })))
+}
+
+/// `POST /admin/pause`
+pub async fn pause(State(state): State<ApiState>) -> Json<Value> {
+    state.control.set_paused(true);
+    Json(json!({ "paused": state.control.is_paused() }))
+}
+
+/// `POST /admin/resume`
+pub async fn resume(State(state): State<ApiState>) -> Json<Value> {
+    state.control.set_paused(false);
+    Json(json!({ "paused": state.control.is_paused() }))
+}
+
+/// `POST /admin/reload`: apply the settings of the configuration file that can change while the
+/// node runs, listing those changed and those needing a restart
+pub async fn reload(State(state): State<ApiState>) -> Result<Json<Value>, ApiError> {
+    // A broken or missing file leaves the running configuration in place
+    let reloader = state.reloader.clone();
+    let outcome = tokio::task::spawn_blocking(move || reloader.reload())
+        .await
+        .context("Reloading panicked")?
+        .map_err(|e| ApiError::BadRequest(format!("Failed to reload {}: {:#}", state.reloader.path(), e)))?;
+    Ok(Json(json!({
+        "status": "reloaded",
+        "applied": outcome.applied,
+        "restart_required": outcome.restart_required,
+    })))
+}
+
+/// `POST /admin/backfill`: fetch the blocks from `start_slot` to `end_slot` in the background
+pub async fn start_backfill(
+    State(state): State<ApiState>,
+    Query(query): Query<BackfillQuery>,
+) -> Result<(StatusCode, Json<BackfillJob>), ApiError> {
+    let job = state
+        .control
+        .backfill(query.start_slot, query.end_slot)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// `GET /admin/backfill`
+pub async fn backfills(State(state): State<ApiState>) -> Json<Vec<BackfillJob>> {
+    Json(state.control.backfills())
+}
+
+/// `GET /admin/webhooks/dead-letters`: transactions webhooks did not accept, oldest first
+pub async fn dead_letters(
+    State(state): State<ApiState>,
+    Query(query): Query<DeadLetterQuery>,
+) -> Result<Json<Vec<DeadLetter>>, ApiError> {
+    Ok(Json(state.storage.get_dead_letters(query.limit()?)?))
+}
+
+/// `POST /admin/webhooks/redeliver`: send the oldest dead letters again, once each
+pub async fn redeliver(
+    State(state): State<ApiState>,
+    Query(query): Query<DeadLetterQuery>,
+) -> Result<Json<Redelivery>, ApiError> {
+    Ok(Json(state.webhooks.redeliver(query.limit()?).await?))
+}
+
+/// First slot to keep under `node.storage_retention_days`, judged by the times of stored blocks
+fn retention_start(state: &ApiState) -> Result<u64, ApiError> {
+    if state.retention_days == 0 {
+        return Err(ApiError::BadRequest(
+            "node.storage_retention_days is 0 (keep forever), pass before_slot".to_string(),
+        ));
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+    let cutoff = now - state.retention_days as i64 * 86_400;
+    state.storage.first_slot_since(cutoff)?.ok_or_else(|| {
+        ApiError::BadRequest(format!(
+            "No stored block predates the {} day retention period, pass before_slot",
+            state.retention_days
+        ))
+    })
+}
@@ -0,0 +1,277 @@
+//! Time series of the hourly and daily rollups, for charting
+//!
+//! Series cover `start_time` to `end_time` (Unix seconds, defaulting to the last 24 hours, or the
+//! last 30 days with `interval=day`) with a point per period, periods without stored transactions
+//! included as zeros. They describe the transactions this node stored, not the whole cluster.
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{rest::ApiError, ApiState};
+use crate::storage::{RollupInterval, TimeRollup};
+
+/// Most points a series may have
+const MAX_SERIES_POINTS: i64 = 1000;
+
+/// How far past the current time a series may reach, in seconds
+const MAX_SERIES_FUTURE_SECS: i64 = 86_400;
+
+/// Longest window `/analytics/top-programs` accepts, in hours
+const MAX_TOP_PROGRAMS_HOURS: i64 = 30 * 24;
+
+/// Most programs `/analytics/top-programs` returns
+const MAX_TOP_PROGRAMS: usize = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SeriesQuery {
+    /// `hour` (default) or `day`
+    #[param(inline)]
+    pub interval: Option<RollupInterval>,
+    /// Unix seconds
+    pub start_time: Option<i64>,
+    /// Unix seconds
+    pub end_time: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TopProgramsQuery {
+    /// `<n>h` or `<n>d`, defaulting to `1h`
+    pub window: Option<String>,
+    /// Defaults to 10
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TpsPoint {
+    pub time: i64,
+    pub transactions: u64,
+    pub failed: u64,
+    /// Transactions per second over the period, or the part of it elapsed so far
+    pub tps: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeePoint {
+    pub time: i64,
+    pub transactions: u64,
+    pub total_fees: u64,
+    pub average_fee: f64,
+    pub compute_units_consumed: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TopPrograms {
+    pub window: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    /// Stored transactions in the window
+    pub transactions: u64,
+    pub programs: Vec<ProgramActivity>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProgramActivity {
+    pub program_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Transactions invoking the program, directly or through CPI
+    pub transactions: u64,
+}
+
+/// `GET /analytics/tps`
+#[utoipa::path(
+    get,
+    path = "/analytics/tps",
+    tag = "analytics",
+    params(SeriesQuery),
+    responses(
+        (status = 200, description = "Transactions per period", body = [TpsPoint]),
+        (status = 400, description = "Invalid time range", body = super::rest::ErrorResponse),
+    )
+)]
+pub async fn tps(
+    State(state): State<ApiState>,
+    Query(query): Query<SeriesQuery>,
+) -> Result<Json<Vec<TpsPoint>>, ApiError> {
+    let now = now();
+    let series = series(&state, &query, now)?;
+    let points = series
+        .into_iter()
+        .map(|(interval, rollup)| {
+            let elapsed = (now - rollup.start_time).clamp(1, interval.seconds());
+            TpsPoint {
+                time: rollup.start_time,
+                transactions: rollup.transaction_count,
+                failed: rollup.failed_count,
+                tps: rollup.transaction_count as f64 / elapsed as f64,
+            }
+        })
+        .collect();
+    Ok(Json(points))
+}
+
+/// `GET /analytics/fees`
+#[utoipa::path(
+    get,
+    path = "/analytics/fees",
+    tag = "analytics",
+    params(SeriesQuery),
+    responses(
+        (status = 200, description = "Fees per period", body = [FeePoint]),
+        (status = 400, description = "Invalid time range", body = super::rest::ErrorResponse),
+    )
+)]
+pub async fn fees(
+    State(state): State<ApiState>,
+    Query(query): Query<SeriesQuery>,
+) -> Result<Json<Vec<FeePoint>>, ApiError> {
+    let points = series(&state, &query, now())?
+        .into_iter()
+        .map(|(_, rollup)| FeePoint {
+            time: rollup.start_time,
+            transactions: rollup.transaction_count,
+            total_fees: rollup.total_fees,
+            average_fee: match rollup.transaction_count {
+                0 => 0.0,
+                count => rollup.total_fees as f64 / count as f64,
+            },
+            compute_units_consumed: rollup.compute_units_consumed,
+        })
+        .collect();
+    Ok(Json(points))
+}
+
+/// `GET /analytics/top-programs`: the programs invoked by the most stored transactions in the
+/// current hour and the preceding ones of the window
+#[utoipa::path(
+    get,
+    path = "/analytics/top-programs",
+    tag = "analytics",
+    params(TopProgramsQuery),
+    responses(
+        (status = 200, description = "Most invoked programs", body = TopPrograms),
+        (status = 400, description = "Invalid window or limit", body = super::rest::ErrorResponse),
+    )
+)]
+pub async fn top_programs(
+    State(state): State<ApiState>,
+    Query(query): Query<TopProgramsQuery>,
+) -> Result<Json<TopPrograms>, ApiError> {
+    let window = query.window.unwrap_or_else(|| "1h".to_string());
+    let hours = parse_window(&window)
+        .filter(|hours| (1..=MAX_TOP_PROGRAMS_HOURS).contains(hours))
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!("window must be <n>h or <n>d, up to {}d", MAX_TOP_PROGRAMS_HOURS / 24))
+        })?;
+    let limit = match query.limit.unwrap_or(10) {
+        limit @ 1..=MAX_TOP_PROGRAMS => limit,
+        _ => return Err(ApiError::BadRequest(format!("limit must be between 1 and {}", MAX_TOP_PROGRAMS))),
+    };
+    
+    let interval = RollupInterval::Hour;
+    let end_time = now();
+    let start_time = interval.start_of(end_time) - (hours - 1) * interval.seconds();
+    let total = state
+        .storage
+        .get_time_rollups(interval, start_time, end_time)?
+        .into_iter()
+        .fold(TimeRollup::default(), |total, rollup| total.merge(&rollup));
+    
+    let mut programs: Vec<(String, u64)> = total.program_counts.into_iter().collect();
+    programs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let programs = programs
+        .into_iter()
+        .take(limit)
+        .map(|(program_id, transactions)| {
+            let label = state.processor.program_label(&program_id);
+            ProgramActivity {
+                name: label.map(|label| label.name.clone()),
+                category: label.map(|label| label.category.clone()),
+                program_id,
+                transactions,
+            }
+        })
+        .collect();
+    
+    Ok(Json(TopPrograms {
+        window,
+        start_time,
+        end_time,
+        transactions: total.transaction_count,
+        programs,
+    }))
+}
+
+/// Rollups of every period the query covers, in time order, with empty ones for the periods
+/// without stored transactions
+fn series(state: &ApiState, query: &SeriesQuery, now: i64) -> Result<Vec<(RollupInterval, TimeRollup)>, ApiError> {
+    let interval = query.interval.unwrap_or(RollupInterval::Hour);
+    let default_points: i64 = match interval {
+        RollupInterval::Hour => 24,
+        RollupInterval::Day => 30,
+    };
+    // Bounded, so no arithmetic on the times below can overflow
+    let check_range = |time: i64| {
+        if (0..=now + MAX_SERIES_FUTURE_SECS).contains(&time) {
+            Ok(time)
+        } else {
+            Err(ApiError::BadRequest(format!(
+                "start_time and end_time must be between 0 and {} seconds from now",
+                MAX_SERIES_FUTURE_SECS
+            )))
+        }
+    };
+    let end_time = check_range(query.end_time.unwrap_or(now))?;
+    let start_time = match query.start_time {
+        Some(start_time) => check_range(start_time)?,
+        None => (end_time - (default_points - 1) * interval.seconds()).max(0),
+    };
+    let start_time = interval.start_of(start_time);
+    if end_time < start_time {
+        return Err(ApiError::BadRequest("end_time must not be before start_time".to_string()));
+    }
+    if (end_time - start_time) / interval.seconds() >= MAX_SERIES_POINTS {
+        return Err(ApiError::BadRequest(format!("A series has at most {} points", MAX_SERIES_POINTS)));
+    }
+    
+    let mut stored: BTreeMap<i64, TimeRollup> = state
+        .storage
+        .get_time_rollups(interval, start_time, end_time)?
+        .into_iter()
+        .map(|rollup| (rollup.start_time, rollup))
+        .collect();
+    let points = (start_time..=end_time)
+        .step_by(interval.seconds() as usize)
+        .map(|time| {
+            let rollup = stored.remove(&time).unwrap_or(TimeRollup {
+                start_time: time,
+                ..Default::default()
+            });
+            (interval, rollup)
+        })
+        .collect();
+    Ok(points)
+}
+
+/// Hours in a window such as `6h` or `7d`
+fn parse_window(window: &str) -> Option<i64> {
+    if let Some(hours) = window.strip_suffix('h') {
+        return hours.parse().ok();
+    }
+    let days: i64 = window.strip_suffix('d')?.parse().ok()?;
+    days.checked_mul(24)
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64
+}
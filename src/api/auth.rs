@@ -0,0 +1,129 @@
+//! API key authentication for the HTTP and gRPC servers
+//!
+//! Keys come from `api.keys` and `api.keys_file`; when neither defines any, every request is
+//! allowed. Otherwise requests must carry a key in the `x-api-key` header (or as a bearer token,
+//! for clients such as Prometheus that cannot set custom headers) whose scopes cover the endpoint:
+//! `read` for queries, `stream` for real-time feeds and `admin` for everything, `/metrics` included.
+//! `/metrics` and `/admin` are only served once a key with the `admin` scope is configured.
+
+use anyhow::{Context, Result};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+use crate::config::{ApiConfig, ApiKeyConfig, ApiScope};
+
+/// Header clients send their key in
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// The key a request was sent with: `x-api-key`, else an `Authorization: Bearer` token
+pub fn request_key(headers: &HeaderMap) -> Option<&str> {
+    match headers.get(API_KEY_HEADER) {
+        Some(value) => value.to_str().ok(),
+        None => headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer ")),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ApiKeys {
+    keys: BTreeMap<String, ApiKeyConfig>,
+}
+
+/// Why a request was refused
+#[derive(Debug)]
+pub enum AuthError {
+    /// No key, or one that is not configured
+    Unauthenticated,
+    /// A valid key without the required scope
+    Forbidden,
+}
+
+impl ApiKeys {
+    /// Keys of `api.keys` together with those of `api.keys_file`, which must be readable if set
+    pub fn load(config: &ApiConfig) -> Result<Self> {
+        let mut keys = config.keys.clone();
+        if let Some(path) = &config.keys_file {
+            let contents = fs::read_to_string(path).with_context(|| format!("Failed to read API keys {}", path))?;
+            let file_keys: BTreeMap<String, ApiKeyConfig> =
+                toml::from_str(&contents).with_context(|| format!("Failed to parse API keys {}", path))?;
+            keys.extend(file_keys);
+        }
+        
+        match keys.len() {
+            0 => info!("No API keys configured, API authentication disabled"),
+            count => info!("Loaded {} API keys", count),
+        }
+        Ok(Self { keys })
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+    
+    /// Whether a key grants the `admin` scope, without which the admin endpoints are not served
+    pub fn has_admin(&self) -> bool {
+        self.keys.values().any(|key| key.scopes.contains(&ApiScope::Admin))
+    }
+    
+    pub fn get(&self, key: &str) -> Option<&ApiKeyConfig> {
+        self.keys.get(key)
+    }
+    
+    /// Check that the key in `headers` grants `scope`
+    pub fn authorize(&self, headers: &HeaderMap, scope: ApiScope) -> Result<(), AuthError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        
+        let key = request_key(headers)
+            .and_then(|key| self.keys.get(key))
+            .ok_or(AuthError::Unauthenticated)?;
+        
+        if key.scopes.iter().any(|granted| *granted == scope || *granted == ApiScope::Admin) {
+            Ok(())
+        } else {
+            debug!("API key {} lacks the {} scope", key.name.as_deref().unwrap_or("(unnamed)"), scope.as_str());
+            Err(AuthError::Forbidden)
+        }
+    }
+}
+
+impl AuthError {
+    pub fn message(&self, scope: ApiScope) -> String {
+        match self {
+            AuthError::Unauthenticated => format!("Missing or unknown API key in the {} header", API_KEY_HEADER),
+            AuthError::Forbidden => format!("API key lacks the {} scope", scope.as_str()),
+        }
+    }
+}
+
+/// Middleware admitting only requests whose key grants the scope it was layered with
+pub async fn require(
+    State((keys, scope)): State<(Arc<ApiKeys>, ApiScope)>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    match keys.authorize(request.headers(), scope) {
+        Ok(()) => next.run(request).await,
+        Err(error) => {
+            let status = match error {
+                AuthError::Unauthenticated => StatusCode::UNAUTHORIZED,
+                AuthError::Forbidden => StatusCode::FORBIDDEN,
+            };
+            (status, Json(json!({ "error": error.message(scope) }))).into_response()
+        }
+    }
+}
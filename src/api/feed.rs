@@ -0,0 +1,152 @@
+//! Real-time data shared by the streaming APIs, and the filters clients subscribe with
+
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use super::{rest, ApiState};
+use crate::proto;
+use crate::storage::{StoredTransaction, EVENT_TYPES};
+use crate::transaction_processor::ProcessedTransaction;
+
+/// Most slots a replay may go back, from the newest stored slot
+pub const MAX_REPLAY_SLOTS: u64 = 100_000;
+
+/// Slots before the newest stored one whose replayed transactions are remembered, as the live
+/// feed may deliver them again
+const REPLAY_LIVE_SLOTS: u64 = 32;
+
+/// Longest a replay waits for the transactions streamed before it to be stored
+const REPLAY_STORE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A processed transaction as streamed to subscribers, with the fields filters match on
+#[derive(Debug, Clone)]
+pub struct StreamedTransaction {
+    pub signature: String,
+    pub programs: Vec<String>,
+    pub accounts: Vec<String>,
+    pub events: Vec<&'static str>,
+    /// The transaction as the REST API presents it, serialized once for all connections
+    pub json: Arc<String>,
+    pub proto: Arc<proto::ProcessedTransaction>,
+}
+
+impl StreamedTransaction {
+    pub fn new(processed: &ProcessedTransaction, stored: &StoredTransaction) -> serde_json::Result<Self> {
+        Ok(Self {
+            signature: stored.signature.clone(),
+            programs: processed.program_ids().into_iter().map(str::to_string).collect(),
+            accounts: processed.account_keys.clone(),
+            events: stored.event_types(),
+            json: Arc::new(rest::processed_view(stored)?.to_string()),
+            proto: Arc::new(proto::ProcessedTransaction::from(processed)),
+        })
+    }
+}
+
+/// Subscription filter; empty lists match everything
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamFilter {
+    pub programs: Vec<String>,
+    pub accounts: Vec<String>,
+    pub events: Vec<String>,
+    /// Send the matching stored transactions from this slot on before the live ones (`/ws` only)
+    pub replay_from_slot: Option<u64>,
+}
+
+impl StreamFilter {
+    /// Whether `tx` matches every non-empty list of the filter
+    pub fn matches(&self, tx: &StreamedTransaction) -> bool {
+        (self.programs.is_empty() || tx.programs.iter().any(|program| self.programs.contains(program)))
+            && (self.accounts.is_empty() || tx.accounts.iter().any(|account| self.accounts.contains(account)))
+            && (self.events.is_empty() || tx.events.iter().any(|event| self.events.iter().any(|e| e == event)))
+    }
+    
+    pub fn validate(&self) -> Result<(), String> {
+        match self.events.iter().find(|event| !EVENT_TYPES.contains(&event.as_str())) {
+            Some(event) => Err(format!("Unknown event type {}; expected one of {}", event, EVENT_TYPES.join(", "))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Stored transactions from a slot on, slot by slot, for clients catching up after downtime.
+///
+/// The live feed is subscribed to before any slot is read, and as transactions are streamed
+/// before they are stored, reading waits until those streamed before the subscription are stored,
+/// so no transaction falls between the two. The transactions replayed from the slots the feed may
+/// also deliver are remembered, to skip them when they arrive live. A replay longer than the
+/// feed's buffer leaves the live feed lagging. Reading a slot blocks, so it belongs on a blocking
+/// thread.
+pub struct Replay {
+    state: ApiState,
+    next_slot: u64,
+    live: broadcast::Receiver<StreamedTransaction>,
+    /// Replayed transactions from this slot on are remembered in `replayed`
+    dedup_from_slot: u64,
+    replayed: HashSet<String>,
+}
+
+impl Replay {
+    pub async fn new(state: ApiState, from_slot: u64) -> Result<Self, String> {
+        let live = state.transactions.subscribe();
+        // Every transaction streamed before the subscription was dispatched before it
+        let dispatched = state.metrics.transactions_dispatched.get();
+        let mut persisted = state.ingestion.persisted.clone();
+        let stored = persisted.wait_for(|&persisted| persisted >= dispatched);
+        match tokio::time::timeout(REPLAY_STORE_TIMEOUT, stored).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(_)) => return Err("Ingestion stopped".to_string()),
+            Err(_) => return Err("Storage is falling behind; replay again later".to_string()),
+        }
+        
+        let latest = state.storage.get_latest_slot_rollup().map_err(|e| e.to_string())?.map(|rollup| rollup.slot);
+        if from_slot < latest.unwrap_or(0).saturating_sub(MAX_REPLAY_SLOTS) {
+            return Err(format!(
+                "replay_from_slot must be at most {} slots before the newest stored slot",
+                MAX_REPLAY_SLOTS
+            ));
+        }
+        Ok(Self {
+            state,
+            next_slot: from_slot,
+            live,
+            dedup_from_slot: latest.unwrap_or(0).saturating_sub(REPLAY_LIVE_SLOTS),
+            replayed: HashSet::new(),
+        })
+    }
+    
+    /// Transactions of the next stored slot matching `filter`, or `None` once caught up
+    pub fn next_slot(&mut self, filter: &StreamFilter) -> anyhow::Result<Option<Vec<StreamedTransaction>>> {
+        let storage = &self.state.storage;
+        let slot = match storage.get_next_stored_slot(self.next_slot)? {
+            Some(slot) => slot,
+            None => return Ok(None),
+        };
+        
+        let signatures = storage.get_slot_signatures(slot, None, usize::MAX)?.items;
+        let mut matched = Vec::new();
+        for stored in storage.get_transactions(&signatures)?.into_iter().flatten() {
+            // Rebuilt from the stored record, as for gRPC `GetTransaction`
+            let processed = self.state.processor.process_encoded_transaction(&stored.transaction)?;
+            let tx = StreamedTransaction::new(&processed, &stored)?;
+            if filter.matches(&tx) {
+                if slot >= self.dedup_from_slot {
+                    self.replayed.insert(tx.signature.clone());
+                }
+                matched.push(tx);
+            }
+        }
+        self.next_slot = slot + 1;
+        Ok(Some(matched))
+    }
+    
+    /// The live feed, subscribed to before the replay started, and the signatures replayed that
+    /// it may deliver again
+    pub fn into_live(self) -> (broadcast::Receiver<StreamedTransaction>, HashSet<String>) {
+        (self.live, self.replayed)
+    }
+}
@@ -0,0 +1,374 @@
+//! GraphQL schema over the stored data, for ad-hoc nested queries
+//!
+//! Fields are served from what was stored: instructions from the `ix:` index and token transfers
+//! from the record, so transactions stored by releases that did not record token events list
+//! none. Queries are limited in depth and in the number of fields they may resolve.
+
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Result, Schema, SimpleObject};
+use axum::response::Html;
+use std::collections::BTreeMap;
+
+use super::ApiState;
+use crate::decoder::token::TokenEvent;
+use crate::storage::{self, Page, StoredTransaction};
+use crate::transaction_processor::{InstructionRecord, TransactionProcessor};
+
+pub type NodeSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+const MAX_LIMIT: usize = 1000;
+
+/// Nested queries deeper than this are rejected
+const MAX_DEPTH: usize = 10;
+
+/// Queries selecting more fields than this are rejected, the fields of a page counting once per
+/// item its `limit` allows
+const MAX_COMPLEXITY: usize = 10_000;
+
+pub fn schema(state: ApiState) -> NodeSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(state)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+/// `GET /graphql`: an in-browser query editor
+pub async fn graphiql() -> Html<String> {
+    Html(async_graphql::http::GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// A stored transaction
+    async fn transaction(&self, ctx: &Context<'_>, signature: String) -> Result<Option<Transaction>> {
+        load_transaction(ctx, &signature)
+    }
+    
+    /// Stored transactions of a slot, in signature order, continuing after `cursor`
+    #[graphql(complexity = "limit * child_complexity")]
+    async fn slot_transactions(
+        &self,
+        ctx: &Context<'_>,
+        slot: u64,
+        cursor: Option<String>,
+        #[graphql(default = 100)] limit: usize,
+    ) -> Result<TransactionPage> {
+        let signatures = state(ctx)?.storage.get_slot_signatures(slot, cursor.as_deref(), check_limit(limit)?)?;
+        load_page(ctx, signatures)
+    }
+    
+    /// Stored transactions referencing an account, newest first, continuing after `cursor`
+    #[graphql(complexity = "limit * child_complexity")]
+    async fn address_transactions(
+        &self,
+        ctx: &Context<'_>,
+        address: String,
+        cursor: Option<String>,
+        #[graphql(default = 100)] limit: usize,
+    ) -> Result<TransactionPage> {
+        let signatures = state(ctx)?
+            .storage
+            .get_address_signature_page(&address, cursor.as_deref(), check_limit(limit)?)?;
+        load_page(ctx, signatures)
+    }
+    
+    /// Instructions executed by a program, top-level or via CPI, in slot order, continuing after `cursor`
+    #[graphql(complexity = "limit * child_complexity")]
+    async fn program_instructions(
+        &self,
+        ctx: &Context<'_>,
+        program_id: String,
+        cursor: Option<String>,
+        #[graphql(default = 100)] limit: usize,
+    ) -> Result<InstructionPage> {
+        let page = state(ctx)?
+            .storage
+            .get_program_instructions(&program_id, cursor.as_deref(), check_limit(limit)?)?;
+        Ok(InstructionPage {
+            instructions: page
+                .items
+                .into_iter()
+                .map(|(signature, record)| ProgramInstruction { signature, instruction: record.into() })
+                .collect(),
+            next_cursor: page.next_cursor,
+        })
+    }
+    
+    /// Aggregated statistics of a slot's stored transactions
+    async fn slot_rollup(&self, ctx: &Context<'_>, slot: u64) -> Result<Option<SlotRollup>> {
+        Ok(state(ctx)?.storage.get_slot_rollup(slot)?.map(SlotRollup))
+    }
+    
+    /// Aggregated statistics of the newest slot with stored transactions
+    async fn latest_slot_rollup(&self, ctx: &Context<'_>) -> Result<Option<SlotRollup>> {
+        Ok(state(ctx)?.storage.get_latest_slot_rollup()?.map(SlotRollup))
+    }
+}
+
+pub struct Transaction {
+    stored: StoredTransaction,
+}
+
+#[Object]
+impl Transaction {
+    async fn signature(&self) -> &str {
+        &self.stored.signature
+    }
+    
+    async fn slot(&self) -> u64 {
+        self.stored.slot
+    }
+    
+    async fn block_time(&self) -> Option<i64> {
+        self.stored.transaction.block_time
+    }
+    
+    async fn fee_payer(&self) -> Option<&str> {
+        self.stored.fee_payer.as_deref()
+    }
+    
+    async fn fee(&self) -> Option<u64> {
+        self.stored.transaction.transaction.meta.as_ref().map(|meta| meta.fee)
+    }
+    
+    async fn success(&self) -> Option<bool> {
+        self.stored.transaction.transaction.meta.as_ref().map(|meta| meta.err.is_none())
+    }
+    
+    /// Transaction error as JSON
+    async fn error(&self) -> Option<String> {
+        let err = self.stored.transaction.transaction.meta.as_ref()?.err.as_ref()?;
+        serde_json::to_string(err).ok()
+    }
+    
+    async fn error_class(&self) -> Option<&str> {
+        self.stored.error_class.as_deref()
+    }
+    
+    async fn compute_units_consumed(&self) -> Option<u64> {
+        let meta = self.stored.transaction.transaction.meta.as_ref()?;
+        Option::<u64>::from(meta.compute_units_consumed.clone())
+    }
+    
+    async fn priority_fee_micro_lamports(&self) -> Option<u64> {
+        self.stored.priority_fee_micro_lamports
+    }
+    
+    async fn memo(&self) -> Option<&str> {
+        self.stored.memo.as_deref()
+    }
+    
+    async fn program_categories(&self) -> &[String] {
+        &self.stored.program_categories
+    }
+    
+    /// Every executed instruction, top-level and CPI, in execution order
+    async fn instructions(&self, ctx: &Context<'_>) -> Result<Vec<Instruction>> {
+        let encoded = &self.stored.transaction.transaction;
+        // Any account of the transaction may be a program it invoked
+        let program_ids = TransactionProcessor::extract_account_keys(&encoded.transaction, encoded.meta.as_ref())?;
+        Ok(state(ctx)?
+            .storage
+            .get_transaction_instructions(self.stored.slot, &self.stored.signature, &program_ids)?
+            .into_iter()
+            .map(Instruction::from)
+            .collect())
+    }
+    
+    /// Token transfers, including those made via CPI
+    async fn token_transfers(&self) -> Vec<TokenTransfer> {
+        self.stored
+            .token_events
+            .iter()
+            .filter_map(|event| match event {
+                TokenEvent::Transfer { source, destination, authority, mint, amount, decimals, fee } => {
+                    Some(TokenTransfer {
+                        source: source.clone(),
+                        destination: destination.clone(),
+                        authority: authority.clone(),
+                        mint: mint.clone(),
+                        amount: *amount,
+                        decimals: *decimals,
+                        fee: *fee,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+    
+    /// Aggregated statistics of the transaction's slot
+    async fn rollup(&self, ctx: &Context<'_>) -> Result<Option<SlotRollup>> {
+        Ok(state(ctx)?.storage.get_slot_rollup(self.stored.slot)?.map(SlotRollup))
+    }
+}
+
+impl Transaction {
+    fn new(stored: StoredTransaction) -> Self {
+        Self { stored }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Instruction {
+    /// Position in the call tree: `2` is the third top-level instruction, `2.0` its first CPI
+    pub index: String,
+    pub program_id: String,
+    pub accounts: Vec<String>,
+    pub discriminator: Option<String>,
+    /// 0 for top-level instructions, incremented for each level of CPI
+    pub depth: u32,
+}
+
+impl From<InstructionRecord> for Instruction {
+    fn from(record: InstructionRecord) -> Self {
+        Self {
+            index: record.index,
+            program_id: record.program_id,
+            accounts: record.accounts,
+            discriminator: record.discriminator,
+            depth: record.depth,
+        }
+    }
+}
+
+/// One page of a transaction listing; `nextCursor` is set when more may follow
+#[derive(SimpleObject)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    /// Pass as `cursor` to fetch the following page
+    pub next_cursor: Option<String>,
+}
+
+/// One page of a program's instructions; `nextCursor` is set when more may follow
+#[derive(SimpleObject)]
+pub struct InstructionPage {
+    pub instructions: Vec<ProgramInstruction>,
+    /// Pass as `cursor` to fetch the following page
+    pub next_cursor: Option<String>,
+}
+
+pub struct ProgramInstruction {
+    signature: String,
+    instruction: Instruction,
+}
+
+#[Object]
+impl ProgramInstruction {
+    async fn signature(&self) -> &str {
+        &self.signature
+    }
+    
+    async fn instruction(&self) -> &Instruction {
+        &self.instruction
+    }
+    
+    /// The transaction the instruction was executed in
+    async fn transaction(&self, ctx: &Context<'_>) -> Result<Option<Transaction>> {
+        load_transaction(ctx, &self.signature)
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct TokenTransfer {
+    pub source: String,
+    pub destination: String,
+    pub authority: Option<String>,
+    pub mint: Option<String>,
+    pub amount: u64,
+    pub decimals: Option<u8>,
+    /// Fee withheld by a Token-2022 transfer-fee mint
+    pub fee: Option<u64>,
+}
+
+/// A named count, for the per-key breakdowns of a rollup
+#[derive(SimpleObject)]
+pub struct Count {
+    pub name: String,
+    pub count: u64,
+}
+
+pub struct SlotRollup(storage::SlotRollup);
+
+#[Object]
+impl SlotRollup {
+    async fn slot(&self) -> u64 {
+        self.0.slot
+    }
+    
+    async fn transaction_count(&self) -> u64 {
+        self.0.transaction_count
+    }
+    
+    async fn total_fees(&self) -> u64 {
+        self.0.total_fees
+    }
+    
+    async fn compute_units_consumed(&self) -> u64 {
+        self.0.compute_units_consumed
+    }
+    
+    /// Transactions invoking each known program category
+    async fn category_counts(&self) -> Vec<Count> {
+        counts(&self.0.category_counts)
+    }
+    
+    /// Failed transactions per error class
+    async fn error_counts(&self) -> Vec<Count> {
+        counts(&self.0.error_counts)
+    }
+    
+    async fn accounts_created(&self) -> u64 {
+        self.0.accounts_created
+    }
+    
+    async fn accounts_closed(&self) -> u64 {
+        self.0.accounts_closed
+    }
+    
+    /// Lamports locked in newly created accounts
+    async fn rent_locked(&self) -> u64 {
+        self.0.rent_locked
+    }
+    
+    /// Lamports released by closed accounts
+    async fn rent_released(&self) -> u64 {
+        self.0.rent_released
+    }
+    
+    async fn durable_nonce_count(&self) -> u64 {
+        self.0.durable_nonce_count
+    }
+}
+
+fn state<'a>(ctx: &Context<'a>) -> Result<&'a ApiState> {
+    ctx.data::<ApiState>()
+}
+
+fn check_limit(limit: usize) -> Result<usize> {
+    match limit {
+        1..=MAX_LIMIT => Ok(limit),
+        _ => Err(format!("limit must be between 1 and {}", MAX_LIMIT).into()),
+    }
+}
+
+fn load_transaction(ctx: &Context<'_>, signature: &str) -> Result<Option<Transaction>> {
+    Ok(state(ctx)?.storage.get_transaction(signature)?.map(Transaction::new))
+}
+
+fn load_page(ctx: &Context<'_>, signatures: Page<String>) -> Result<TransactionPage> {
+    let storage = &state(ctx)?.storage;
+    let mut transactions = Vec::with_capacity(signatures.items.len());
+    for signature in signatures.items {
+        if let Some(stored) = storage.get_transaction(&signature)? {
+            transactions.push(Transaction::new(stored));
+        }
+    }
+    Ok(TransactionPage { transactions, next_cursor: signatures.next_cursor })
+}
+
+fn counts(map: &BTreeMap<String, u64>) -> Vec<Count> {
+    map.iter().map(|(name, count)| Count { name: name.clone(), count: *count }).collect()
+}
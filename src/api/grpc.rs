@@ -0,0 +1,225 @@
+//! gRPC server for the `solana_node.v1.Node` service of `proto/solana_node.proto`
+//!
+//! Like the messages in `proto`, the service glue is written out by hand rather than generated,
+//! so building needs no protoc; it must be kept in sync with the service definition. The schema
+//! itself is compiled by the build script (in Rust, still without protoc) and served through gRPC
+//! server reflection.
+
+use anyhow::Context as _;
+use futures::stream::{self, Stream, StreamExt};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::sync::broadcast;
+use tonic::{
+    body::BoxBody,
+    codec::ProstCodec,
+    codegen::{empty_body, http, BoxFuture, Service},
+    server::{Grpc, NamedService, ServerStreamingService, UnaryService},
+    transport::{
+        server::{TcpConnectInfo, TlsConnectInfo},
+        Body, Identity, Server, ServerTlsConfig,
+    },
+    Request, Response, Status,
+};
+use tracing::warn;
+
+use super::auth::AuthError;
+use super::feed::{StreamFilter, StreamedTransaction};
+use super::ApiState;
+use crate::config::{ApiScope, TlsConfig};
+use crate::proto::{
+    self, GetTransactionRequest, ProcessedTransaction, SlotUpdate, SubscribeSlotsRequest, SubscribeTransactionsRequest,
+};
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// Serve the `Node` service on `addr`, over TLS if configured, until the server fails
+pub async fn serve(addr: SocketAddr, tls: Option<&TlsConfig>, state: ApiState) -> anyhow::Result<()> {
+    let mut server = Server::builder();
+    if let Some(tls) = tls {
+        let cert = tokio::fs::read(&tls.cert_path)
+            .await
+            .with_context(|| format!("Failed to read TLS certificate {}", tls.cert_path))?;
+        let key = tokio::fs::read(&tls.key_path)
+            .await
+            .with_context(|| format!("Failed to read TLS key {}", tls.key_path))?;
+        server = server.tls_config(ServerTlsConfig::new().identity(Identity::from_pem(cert, key)))?;
+    }
+    // Lets tools such as grpcurl discover the service without a copy of the schema
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
+        .build()?;
+    server
+        .add_service(reflection)
+        .add_service(NodeServer { state })
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+#[derive(Clone)]
+struct NodeServer {
+    state: ApiState,
+}
+
+impl NamedService for NodeServer {
+    const NAME: &'static str = "solana_node.v1.Node";
+}
+
+impl Service<http::Request<Body>> for NodeServer {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let extensions = request.extensions();
+        let ip = extensions
+            .get::<TcpConnectInfo>()
+            .or_else(|| extensions.get::<TlsConnectInfo<TcpConnectInfo>>().map(|info| info.get_ref()))
+            .and_then(|info| info.remote_addr())
+            .map(|addr| addr.ip());
+        if let Err(retry_after) = self.state.limiter.check(request.headers(), ip) {
+            let status = Status::resource_exhausted(format!(
+                "Rate limit exceeded, retry in {}s",
+                (retry_after.as_secs_f64().ceil() as u64).max(1)
+            ));
+            return Box::pin(async move { Ok(status.to_http()) });
+        }
+        
+        let scope = match request.uri().path() {
+            "/solana_node.v1.Node/SubscribeTransactions" | "/solana_node.v1.Node/SubscribeSlots" => Some(ApiScope::Stream),
+            "/solana_node.v1.Node/GetTransaction" => Some(ApiScope::Read),
+            _ => None,
+        };
+        if let Some(scope) = scope {
+            if let Err(error) = self.state.keys.authorize(request.headers(), scope) {
+                let status = match error {
+                    AuthError::Unauthenticated => Status::unauthenticated(error.message(scope)),
+                    AuthError::Forbidden => Status::permission_denied(error.message(scope)),
+                };
+                return Box::pin(async move { Ok(status.to_http()) });
+            }
+        }
+        
+        let state = self.state.clone();
+        match request.uri().path() {
+            "/solana_node.v1.Node/SubscribeTransactions" => Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.server_streaming(SubscribeTransactions(state), request).await)
+            }),
+            "/solana_node.v1.Node/SubscribeSlots" => Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.server_streaming(SubscribeSlots(state), request).await)
+            }),
+            // Streams stay open, so only unary calls are timed
+            "/solana_node.v1.Node/GetTransaction" => Box::pin(async move {
+                let started = Instant::now();
+                let mut grpc = Grpc::new(ProstCodec::default());
+                let response = grpc.unary(GetTransaction(state.clone()), request).await;
+                state.metrics.record_api_request("/solana_node.v1.Node/GetTransaction", started.elapsed());
+                Ok(response)
+            }),
+            _ => Box::pin(async move {
+                Ok(http::Response::builder()
+                    .status(200)
+                    .header("grpc-status", tonic::Code::Unimplemented as i32)
+                    .header("content-type", "application/grpc")
+                    .body(empty_body())
+                    .unwrap())
+            }),
+        }
+    }
+}
+
+struct SubscribeTransactions(ApiState);
+
+impl ServerStreamingService<SubscribeTransactionsRequest> for SubscribeTransactions {
+    type Response = ProcessedTransaction;
+    type ResponseStream = ResponseStream<ProcessedTransaction>;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+    
+    fn call(&mut self, request: Request<SubscribeTransactionsRequest>) -> Self::Future {
+        let transactions = self.0.transactions.subscribe();
+        Box::pin(async move {
+            let request = request.into_inner();
+            let filter = StreamFilter {
+                programs: request.programs,
+                accounts: request.accounts,
+                events: request.events,
+                replay_from_slot: None,
+            };
+            filter.validate().map_err(Status::invalid_argument)?;
+            
+            let stream = subscribe(transactions).filter_map(move |tx: StreamedTransaction| {
+                let matched = filter.matches(&tx).then(|| Ok(tx.proto.as_ref().clone()));
+                async move { matched }
+            });
+            Ok(Response::new(Box::pin(stream) as Self::ResponseStream))
+        })
+    }
+}
+
+struct SubscribeSlots(ApiState);
+
+impl ServerStreamingService<SubscribeSlotsRequest> for SubscribeSlots {
+    type Response = SlotUpdate;
+    type ResponseStream = ResponseStream<SlotUpdate>;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+    
+    fn call(&mut self, _request: Request<SubscribeSlotsRequest>) -> Self::Future {
+        let slots = self.0.slots.subscribe();
+        Box::pin(async move {
+            let stream = subscribe(slots).map(Ok);
+            Ok(Response::new(Box::pin(stream) as Self::ResponseStream))
+        })
+    }
+}
+
+struct GetTransaction(ApiState);
+
+impl UnaryService<GetTransactionRequest> for GetTransaction {
+    type Response = ProcessedTransaction;
+    type Future = BoxFuture<Response<Self::Response>, Status>;
+    
+    fn call(&mut self, request: Request<GetTransactionRequest>) -> Self::Future {
+        let state = self.0.clone();
+        Box::pin(async move {
+            let signature = request.into_inner().signature;
+            let stored = state
+                .storage
+                .get_transaction(&signature)
+                .map_err(|e| Status::internal(e.to_string()))?
+                .ok_or_else(|| Status::not_found(format!("Transaction {} is not stored", signature)))?;
+            
+            // Rebuilt from the stored record; fields that were not stored (e.g. log messages,
+            // when disabled) come back empty
+            let processed = state
+                .processor
+                .process_encoded_transaction(&stored.transaction)
+                .map_err(|e| Status::internal(e.to_string()))?;
+            Ok(Response::new(ProcessedTransaction::from(&processed)))
+        })
+    }
+}
+
+/// Items of a broadcast feed as a stream, skipping (and reporting) items missed by a slow client
+fn subscribe<T: Clone + Send + 'static>(receiver: broadcast::Receiver<T>) -> impl Stream<Item = T> + Send {
+    stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(item) => return Some((item, receiver)),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("gRPC client fell behind, {} updates dropped", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
@@ -0,0 +1,58 @@
+//! Liveness and readiness probes for orchestrators and load balancers
+//!
+//! Both answer 200 when the check passes and 503 otherwise, with the reason in the body. They are
+//! served without API keys or rate limits so probes need no configuration.
+
+use axum::{extract::State, http::StatusCode, Json};
+use serde_json::{json, Value};
+
+use super::ApiState;
+
+/// `GET /health`: the database answers reads and at least one transaction source is connected
+pub async fn health(State(state): State<ApiState>) -> (StatusCode, Json<Value>) {
+    let sources = *state.ingestion.sources.borrow();
+    match check_health(&state, sources) {
+        Ok(()) => (StatusCode::OK, Json(json!({ "status": "ok", "sources": sources }))),
+        Err(reason) => unavailable(reason),
+    }
+}
+
+/// `GET /ready`: healthy, and processing trails the cluster by at most `ready_max_lag_slots`
+pub async fn ready(State(state): State<ApiState>) -> (StatusCode, Json<Value>) {
+    let sources = *state.ingestion.sources.borrow();
+    if let Err(reason) = check_health(&state, sources) {
+        return unavailable(reason);
+    }
+    
+    let cluster_slot = *state.ingestion.cluster_slot.borrow();
+    let processed_slot = *state.ingestion.processed_slot.borrow();
+    match state.ingestion.lag() {
+        Some(lag) if lag <= state.ready_max_lag_slots => (
+            StatusCode::OK,
+            Json(json!({
+                "status": "ok",
+                "cluster_slot": cluster_slot,
+                "processed_slot": processed_slot,
+                "lag": lag,
+            })),
+        ),
+        Some(lag) => unavailable(format!(
+            "Processing trails the cluster by {} slots (max {})",
+            lag, state.ready_max_lag_slots
+        )),
+        None if cluster_slot == 0 => unavailable("Cluster slot not known yet".to_string()),
+        None => unavailable("No transaction processed yet".to_string()),
+    }
+}
+
+fn check_health(state: &ApiState, sources: usize) -> Result<(), String> {
+    state.storage.check().map_err(|e| format!("Database unavailable: {}", e))?;
+    match sources {
+        0 => Err("No transaction source connected".to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn unavailable(reason: String) -> (StatusCode, Json<Value>) {
+    (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "status": "unavailable", "reason": reason })))
+}
@@ -0,0 +1,41 @@
+//! Prometheus scrape endpoint, and the middleware timing API requests for it
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, State},
+    http::{header, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::time::Instant;
+
+use super::ApiState;
+use crate::metrics::Snapshot;
+
+/// `GET /metrics`
+pub async fn scrape(State(state): State<ApiState>) -> impl IntoResponse {
+    let snapshot = Snapshot {
+        sources: *state.ingestion.sources.borrow(),
+        cluster_slot: *state.ingestion.cluster_slot.borrow(),
+        processed_slot: *state.ingestion.processed_slot.borrow(),
+        slot_lag: state.ingestion.lag(),
+        feed_depths: vec![("transactions", state.transactions.len()), ("slots", state.slots.len())],
+    };
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(&snapshot),
+    )
+}
+
+/// Middleware recording the latency of each request under its route pattern
+pub async fn track_latency(State(state): State<ApiState>, request: Request<Body>, next: Next<Body>) -> Response {
+    // Patterns rather than paths, so labels do not grow with every signature requested
+    let route = match request.extensions().get::<MatchedPath>() {
+        Some(path) => path.as_str().to_string(),
+        None => return next.run(request).await,
+    };
+    let started = Instant::now();
+    let response = next.run(request).await;
+    state.metrics.record_api_request(&route, started.elapsed());
+    response
+}
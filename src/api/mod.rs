@@ -0,0 +1,193 @@
+//! Query server answering from the local store
+
+pub mod admin;
+pub mod analytics;
+pub mod auth;
+pub mod feed;
+pub mod graphql;
+pub mod grpc;
+pub mod health;
+pub mod metrics;
+pub mod openapi;
+pub mod rate_limit;
+pub mod rest;
+pub mod rpc;
+pub mod sse;
+pub mod ws;
+
+use anyhow::{Context, Result};
+use async_graphql_axum::GraphQL;
+use axum::{
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware,
+    routing::{get, post},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{info, warn};
+
+use crate::config::{ApiScope, Commitment, Config, CorsConfig};
+use crate::config_reload::ConfigReloader;
+use crate::network::{IngestionControl, IngestionStatus};
+use crate::proto::SlotUpdate;
+use crate::storage::Storage;
+use crate::transaction_processor::TransactionProcessor;
+use crate::webhook::Webhooks;
+
+/// Shared by all handlers
+#[derive(Clone)]
+pub struct ApiState {
+    pub storage: Storage,
+    /// Rebuilds processed transactions from stored records
+    pub processor: TransactionProcessor,
+    /// Processed transactions, streamed to WebSocket and gRPC clients
+    pub transactions: broadcast::Sender<feed::StreamedTransaction>,
+    /// Confirmed blocks, streamed to gRPC clients
+    pub slots: broadcast::Sender<SlotUpdate>,
+    pub keys: Arc<auth::ApiKeys>,
+    pub limiter: Arc<rate_limit::RateLimiter>,
+    pub ingestion: IngestionStatus,
+    pub metrics: Arc<crate::metrics::Metrics>,
+    pub control: IngestionControl,
+    /// Applies the configuration again on `/admin/reload`
+    pub reloader: Arc<ConfigReloader>,
+    /// `api.ready_max_lag_slots`
+    pub ready_max_lag_slots: u64,
+    /// `node.storage_retention_days`, the default cutoff of `/admin/prune`
+    pub retention_days: u64,
+    /// `network.commitment`, which the stored transactions reached before they were fetched
+    pub commitment: Commitment,
+    /// `api.upstream_rpc`, answering the JSON-RPC requests the store cannot
+    pub upstream: Option<rpc::Upstream>,
+    /// Redelivers the dead letters of `webhooks`
+    pub webhooks: Webhooks,
+}
+
+/// Serve the configured API, and the gRPC service if it has a port, until a server fails
+pub async fn serve(config: &Config, state: ApiState) -> Result<()> {
+    let ip: IpAddr = config.api.bind_address
+        .parse()
+        .with_context(|| format!("Invalid API bind address {}", config.api.bind_address))?;
+    let addr = SocketAddr::new(ip, config.node.listen_port);
+    
+    let grpc = async {
+        match config.api.grpc_port {
+            Some(port) => {
+                let addr = SocketAddr::new(ip, port);
+                info!("gRPC listening on {}", addr);
+                grpc::serve(addr, config.api.tls.as_ref(), state.clone()).await.context("gRPC server failed")
+            }
+            None => Ok(()),
+        }
+    };
+    
+    let queries = Router::new()
+        .route("/", post(rpc::handle))
+        .route("/tx/:signature", get(rest::transaction))
+        .route("/txs", post(rest::transactions))
+        .route("/slot/:slot/txs", get(rest::slot_transactions))
+        .route("/slot/:slot/unconfirmed", get(rest::unconfirmed_slot_transactions))
+        .route("/address/:pubkey/txs", get(rest::address_transactions))
+        .route("/search", get(rest::search))
+        .route("/stats", get(rest::stats))
+        .route("/slots/latest", get(rest::latest_slots))
+        .route("/analytics/tps", get(analytics::tps))
+        .route("/analytics/fees", get(analytics::fees))
+        .route("/analytics/top-programs", get(analytics::top_programs))
+        .route("/graphql", get(graphql::graphiql).post_service(GraphQL::new(graphql::schema(state.clone()))))
+        .route_layer(middleware::from_fn_with_state((state.keys.clone(), ApiScope::Read), auth::require));
+    let streams = Router::new()
+        .route("/ws", get(ws::handle))
+        .route("/sse", get(sse::handle))
+        .route_layer(middleware::from_fn_with_state((state.keys.clone(), ApiScope::Stream), auth::require));
+    // Never open to anyone: left out unless a key can be required for them
+    let mut admin = Router::new();
+    if state.keys.has_admin() {
+        admin = admin
+            .route("/metrics", get(metrics::scrape))
+            .route("/admin/prune", post(admin::prune))
+            .route("/admin/compact", post(admin::compact))
+            .route("/admin/pause", post(admin::pause))
+            .route("/admin/resume", post(admin::resume))
+            .route("/admin/reload", post(admin::reload))
+            .route("/admin/backfill", get(admin::backfills).post(admin::start_backfill))
+            .route("/admin/webhooks/dead-letters", get(admin::dead_letters))
+            .route("/admin/webhooks/redeliver", post(admin::redeliver))
+            .route_layer(middleware::from_fn_with_state((state.keys.clone(), ApiScope::Admin), auth::require));
+    } else {
+        warn!("No API key has the admin scope, so /metrics and the /admin endpoints are not served");
+    }
+    // Probes and the API description are merged after the rate limit, which only wraps the
+    // routes added before it
+    let probes = Router::new()
+        .route("/health", get(health::health))
+        .route("/ready", get(health::ready))
+        .route("/openapi.json", get(openapi::spec));
+    let mut app = queries
+        .merge(streams)
+        .merge(admin)
+        .layer(middleware::from_fn_with_state(state.clone(), metrics::track_latency))
+        .layer(middleware::from_fn_with_state(state.limiter.clone(), rate_limit::limit))
+        .merge(probes)
+        .with_state(state.clone());
+    // Outermost, so preflight requests need no key and error responses stay readable to browsers
+    if let Some(cors) = cors_layer(&config.api.cors)? {
+        app = app.layer(cors);
+    }
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    
+    let http = async {
+        match &config.api.tls {
+            Some(tls) => {
+                let tls_config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+                    .await
+                    .with_context(|| {
+                        format!("Failed to load TLS certificate {} and key {}", tls.cert_path, tls.key_path)
+                    })?;
+                info!("API listening on {} (TLS)", addr);
+                axum_server::bind_rustls(addr, tls_config).serve(service).await.context("API server failed")
+            }
+            None => {
+                info!("API listening on {}", addr);
+                axum::Server::try_bind(&addr)
+                    .with_context(|| format!("Failed to bind API server to {}", addr))?
+                    .serve(service)
+                    .await
+                    .context("API server failed")
+            }
+        }
+    };
+    
+    tokio::try_join!(http, grpc)?;
+    Ok(())
+}
+
+/// CORS policy for `config`, unless no origin is allowed
+fn cors_layer(config: &CorsConfig) -> Result<Option<CorsLayer>> {
+    if config.allowed_origins.is_empty() {
+        return Ok(None);
+    }
+    let origins = match config.allowed_origins.iter().any(|origin| origin == "*") {
+        true => AllowOrigin::any(),
+        false => AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin).with_context(|| format!("Invalid CORS origin {}", origin)))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+    };
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, HeaderName::from_static(auth::API_KEY_HEADER)])
+            .expose_headers([header::RETRY_AFTER])
+            .max_age(Duration::from_secs(config.max_age_secs)),
+    ))
+}
@@ -0,0 +1,58 @@
+//! OpenAPI document of the REST endpoints, for generating clients in other languages
+//!
+//! Generated from the annotations on the handlers of `rest` and `analytics`; new REST endpoints
+//! are listed in `paths` and their response types in `components`.
+
+use axum::Json;
+use utoipa::OpenApi;
+
+use super::{analytics, rest};
+use crate::storage::{RollupInterval, SlotRollup, StorageStats};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "solana-node",
+        description = "Queries over the transactions stored by a solana-node. Once API keys are configured, \
+                       requests send one in the x-api-key header or as a bearer token."
+    ),
+    paths(
+        rest::transaction,
+        rest::transactions,
+        rest::slot_transactions,
+        rest::unconfirmed_slot_transactions,
+        rest::address_transactions,
+        rest::search,
+        rest::stats,
+        rest::latest_slots,
+        analytics::tps,
+        analytics::fees,
+        analytics::top_programs,
+    ),
+    components(schemas(
+        rest::ErrorResponse,
+        rest::TransactionsRequest,
+        rest::TransactionBatch,
+        rest::TransactionPage,
+        rest::Stats,
+        rest::LatestSlots,
+        StorageStats,
+        SlotRollup,
+        RollupInterval,
+        analytics::TpsPoint,
+        analytics::FeePoint,
+        analytics::TopPrograms,
+        analytics::ProgramActivity,
+    )),
+    tags(
+        (name = "transactions", description = "Stored transactions, in their processed form"),
+        (name = "analytics", description = "Time series of the hourly and daily rollups"),
+        (name = "node", description = "Storage and ingestion status"),
+    )
+)]
+struct ApiDoc;
+
+/// `GET /openapi.json`
+pub async fn spec() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,127 @@
+//! Per-client token buckets, so a single heavy consumer cannot starve the others or ingestion
+//!
+//! Requests carrying a configured API key draw from that key's bucket; all other requests draw
+//! from a bucket per client IP. Opening a WebSocket or gRPC stream takes one token, messages on
+//! open streams are not counted.
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::auth::{request_key, ApiKeys};
+use crate::config::RateLimitConfig;
+
+/// Above this many tracked clients, the buckets of idle clients are dropped
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+pub struct RateLimiter {
+    /// `api.rate_limit`, which can change on reload
+    default: Mutex<RateLimitConfig>,
+    keys: Arc<ApiKeys>,
+    buckets: Mutex<HashMap<Client, Bucket>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    Key(String),
+    Ip(IpAddr),
+}
+
+struct Bucket {
+    limit: RateLimitConfig,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(limit: RateLimitConfig, now: Instant) -> Self {
+        Self { limit, tokens: limit.burst as f64, updated: now }
+    }
+    
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.requests_per_second).min(self.limit.burst as f64);
+        self.updated = now;
+    }
+}
+
+impl RateLimiter {
+    pub fn new(default: RateLimitConfig, keys: Arc<ApiKeys>) -> Self {
+        Self { default: Mutex::new(default), keys, buckets: Mutex::new(HashMap::new()) }
+    }
+    
+    /// Limit clients without a limit of their own to `default` from now on, starting them over
+    /// with full buckets
+    pub fn set_default(&self, default: RateLimitConfig) {
+        let mut current = self.default.lock().unwrap();
+        if *current != default {
+            *current = default;
+            self.buckets.lock().unwrap().clear();
+        }
+    }
+    
+    /// Take a token from the bucket of the client sending `headers` from `ip`, or return how long
+    /// to wait for one
+    pub fn check(&self, headers: &HeaderMap, ip: Option<IpAddr>) -> Result<(), Duration> {
+        let default = *self.default.lock().unwrap();
+        let (client, limit) = match request_key(headers).and_then(|key| Some((key, self.keys.get(key)?))) {
+            Some((key, config)) => (Client::Key(key.to_string()), config.rate_limit.unwrap_or(default)),
+            None => match ip {
+                Some(ip) => (Client::Ip(ip), default),
+                None => return Ok(()),
+            },
+        };
+        if limit.requests_per_second <= 0.0 {
+            return Ok(());
+        }
+        
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now);
+                bucket.tokens < bucket.limit.burst as f64
+            });
+        }
+        
+        let bucket = buckets.entry(client).or_insert_with(|| Bucket::new(limit, now));
+        bucket.refill(now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / limit.requests_per_second))
+        }
+    }
+}
+
+/// Middleware answering 429 to clients that ran out of tokens
+pub async fn limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    match limiter.check(request.headers(), Some(addr.ip())) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let retry_after = (retry_after.as_secs_f64().ceil() as u64).max(1);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(json!({ "error": format!("Rate limit exceeded, retry in {}s", retry_after) })),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! REST endpoints returning the processed representation of stored transactions
+//!
+//! Transactions are returned as stored by the processor (decoded events, swaps, labels, ...)
+//! with the outcome of their status meta, but without the raw RPC transaction. Listings are
+//! paginated: a full page carries an opaque `next_cursor` to pass as `cursor` for the following one.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use utoipa::{IntoParams, ToSchema};
+
+use super::ApiState;
+use crate::storage::{InvalidCursor, Page, SearchFilter, SlotRollup, StorageStats, StoredTransaction, EVENT_TYPES};
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Most signatures a `POST /txs` request may ask for
+const MAX_BATCH_SIGNATURES: usize = 256;
+
+pub enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    Internal(anyhow::Error),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::Internal(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
+        };
+        (status, Json(ErrorResponse { error: message })).into_response()
+    }
+}
+
+/// Body of error responses
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.is::<InvalidCursor>() {
+            true => ApiError::BadRequest(error.to_string()),
+            false => ApiError::Internal(error),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(error: serde_json::Error) -> Self {
+        ApiError::Internal(error.into())
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    /// Page size, 1 to 1000 (default 100)
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl PageQuery {
+    fn limit(&self) -> Result<usize, ApiError> {
+        match self.limit.unwrap_or(DEFAULT_PAGE_SIZE) {
+            limit @ 1..=MAX_PAGE_SIZE => Ok(limit),
+            _ => Err(ApiError::BadRequest(format!("limit must be between 1 and {}", MAX_PAGE_SIZE))),
+        }
+    }
+}
+
+/// Query of `/search`; lists are comma-separated
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Addresses the transaction must all reference
+    pub accounts: Option<String>,
+    /// Programs the transaction must all invoke, directly or through CPI
+    pub programs: Option<String>,
+    /// Lamports
+    pub min_fee: Option<u64>,
+    /// Lamports
+    pub max_fee: Option<u64>,
+    pub start_slot: Option<u64>,
+    pub end_slot: Option<u64>,
+    /// Unix seconds
+    pub start_time: Option<i64>,
+    /// Unix seconds
+    pub end_time: Option<i64>,
+    /// `success` or `failed`
+    pub status: Option<String>,
+    /// Event types, at least one of which the transaction must carry
+    pub events: Option<String>,
+    /// Page size, 1 to 1000 (default 100)
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl SearchQuery {
+    fn filter(&self) -> Result<SearchFilter, ApiError> {
+        let filter = SearchFilter {
+            accounts: parse_addresses(self.accounts.as_deref())?,
+            programs: parse_addresses(self.programs.as_deref())?,
+            min_fee: self.min_fee,
+            max_fee: self.max_fee,
+            start_slot: self.start_slot,
+            end_slot: self.end_slot,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            success: match self.status.as_deref() {
+                None => None,
+                Some("success") => Some(true),
+                Some("failed") => Some(false),
+                Some(status) => {
+                    return Err(ApiError::BadRequest(format!("Invalid status {} (expected success or failed)", status)))
+                }
+            },
+            events: split_list(self.events.as_deref()).map(str::to_string).collect(),
+        };
+        
+        if let Some(event) = filter.events.iter().find(|event| !EVENT_TYPES.contains(&event.as_str())) {
+            return Err(ApiError::BadRequest(format!(
+                "Unknown event type {}; expected one of {}",
+                event,
+                EVENT_TYPES.join(", ")
+            )));
+        }
+        
+        // Without an account or program index to follow, only a bounded slot range is walked
+        let bounded = filter.start_slot.is_some() && filter.end_slot.is_some();
+        if filter.accounts.is_empty() && filter.programs.is_empty() && !bounded {
+            return Err(ApiError::BadRequest(
+                "Search needs accounts, programs, or both start_slot and end_slot".to_string(),
+            ));
+        }
+        Ok(filter)
+    }
+}
+
+/// Body of `POST /txs`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransactionsRequest {
+    /// Up to 256 signatures
+    pub signatures: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransactionBatch {
+    /// The stored transactions among those asked for, in request order
+    #[schema(value_type = Vec<Object>)]
+    pub transactions: Vec<Value>,
+    /// Signatures not stored
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransactionPage {
+    /// Processed transactions, as returned by `/tx/{signature}`
+    #[schema(value_type = Vec<Object>)]
+    pub transactions: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Stats {
+    #[serde(flatten)]
+    pub storage: StorageStats,
+    /// Rollup of the newest slot with stored transactions
+    pub latest_slot: Option<SlotRollup>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LatestSlots {
+    /// Newest slot of the cluster
+    pub cluster_slot: Option<u64>,
+    /// Slot of the newest processed transaction
+    pub ingested_slot: Option<u64>,
+    /// Newest rooted slot of the cluster
+    pub cluster_rooted_slot: Option<u64>,
+    /// Newest rooted slot with stored transactions
+    pub rooted_slot: Option<u64>,
+    /// Slots `ingested_slot` trails `cluster_slot` by
+    pub lag: Option<u64>,
+    /// Slots `rooted_slot` trails `cluster_slot` by
+    pub rooted_lag: Option<u64>,
+}
+
+/// `GET /tx/:signature`
+#[utoipa::path(
+    get,
+    path = "/tx/{signature}",
+    tag = "transactions",
+    params(("signature" = String, Path, description = "Transaction signature")),
+    responses(
+        (status = 200, description = "The processed transaction, with its outcome", body = Object),
+        (status = 400, description = "Invalid signature", body = ErrorResponse),
+        (status = 404, description = "Transaction not stored", body = ErrorResponse),
+    )
+)]
+pub async fn transaction(
+    State(state): State<ApiState>,
+    Path(signature): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    parse_signature(&signature)?;
+    match state.storage.get_transaction(&signature)? {
+        Some(tx) => Ok(Json(processed_view(&tx)?)),
+        None => Err(ApiError::NotFound(format!("Transaction {} is not stored", signature))),
+    }
+}
+
+/// `POST /txs`: several transactions in one request
+#[utoipa::path(
+    post,
+    path = "/txs",
+    tag = "transactions",
+    request_body = TransactionsRequest,
+    responses(
+        (status = 200, description = "The stored transactions and the missing signatures", body = TransactionBatch),
+        (status = 400, description = "Invalid signature, or too many", body = ErrorResponse),
+    )
+)]
+pub async fn transactions(
+    State(state): State<ApiState>,
+    Json(request): Json<TransactionsRequest>,
+) -> Result<Json<TransactionBatch>, ApiError> {
+    if request.signatures.len() > MAX_BATCH_SIGNATURES {
+        return Err(ApiError::BadRequest(format!("At most {} signatures per request", MAX_BATCH_SIGNATURES)));
+    }
+    for signature in &request.signatures {
+        parse_signature(signature)?;
+    }
+    
+    let mut batch = TransactionBatch { transactions: Vec::new(), missing: Vec::new() };
+    let stored = state.storage.get_transactions(&request.signatures)?;
+    for (signature, tx) in request.signatures.into_iter().zip(stored) {
+        match tx {
+            Some(tx) => batch.transactions.push(processed_view(&tx)?),
+            None => batch.missing.push(signature),
+        }
+    }
+    Ok(Json(batch))
+}
+
+/// `GET /slot/:slot/txs`, in signature order
+#[utoipa::path(
+    get,
+    path = "/slot/{slot}/txs",
+    tag = "transactions",
+    params(("slot" = u64, Path, description = "Slot"), PageQuery),
+    responses(
+        (status = 200, description = "A page of the slot's stored transactions", body = TransactionPage),
+        (status = 400, description = "Invalid limit or cursor", body = ErrorResponse),
+    )
+)]
+pub async fn slot_transactions(
+    State(state): State<ApiState>,
+    Path(slot): Path<u64>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<TransactionPage>, ApiError> {
+    let signatures = state.storage.get_slot_signatures(slot, page.cursor.as_deref(), page.limit()?)?;
+    Ok(Json(load_page(&state, signatures)?))
+}
+
+/// `GET /slot/:slot/unconfirmed`: transactions decoded from shreds, in signature order
+#[utoipa::path(
+    get,
+    path = "/slot/{slot}/unconfirmed",
+    tag = "transactions",
+    params(("slot" = u64, Path, description = "Slot")),
+    responses(
+        (status = 200, description = "The slot's transactions received over turbine and not stored confirmed", body = [Object]),
+    )
+)]
+pub async fn unconfirmed_slot_transactions(
+    State(state): State<ApiState>,
+    Path(slot): Path<u64>,
+) -> Result<Json<Vec<Value>>, ApiError> {
+    let transactions = state.storage.get_unconfirmed_transactions(slot)?;
+    Ok(Json(transactions.iter().map(processed_view).collect::<serde_json::Result<_>>()?))
+}
+
+/// `GET /address/:pubkey/txs`, newest first
+#[utoipa::path(
+    get,
+    path = "/address/{pubkey}/txs",
+    tag = "transactions",
+    params(("pubkey" = String, Path, description = "Account address"), PageQuery),
+    responses(
+        (status = 200, description = "A page of the transactions referencing the account", body = TransactionPage),
+        (status = 400, description = "Invalid address, limit or cursor", body = ErrorResponse),
+    )
+)]
+pub async fn address_transactions(
+    State(state): State<ApiState>,
+    Path(address): Path<String>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<TransactionPage>, ApiError> {
+    address
+        .parse::<Pubkey>()
+        .map_err(|_| ApiError::BadRequest(format!("Invalid address {}", address)))?;
+    let signatures = state.storage.get_address_signature_page(&address, page.cursor.as_deref(), page.limit()?)?;
+    Ok(Json(load_page(&state, signatures)?))
+}
+
+/// `GET /search`, newest slot first
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "transactions",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "A page of the transactions matching every filter", body = TransactionPage),
+        (status = 400, description = "Invalid or unbounded filters", body = ErrorResponse),
+    )
+)]
+pub async fn search(
+    State(state): State<ApiState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<TransactionPage>, ApiError> {
+    let filter = query.filter()?;
+    let page = PageQuery { limit: query.limit, cursor: query.cursor };
+    let signatures = state.storage.search(&filter, page.cursor.as_deref(), page.limit()?)?;
+    Ok(Json(load_page(&state, signatures)?))
+}
+
+/// `GET /slots/latest`: the cluster tip against what this node ingested and stored
+#[utoipa::path(
+    get,
+    path = "/slots/latest",
+    tag = "node",
+    responses((status = 200, description = "Latest slots and lag", body = LatestSlots))
+)]
+pub async fn latest_slots(State(state): State<ApiState>) -> Result<Json<LatestSlots>, ApiError> {
+    // 0 means not known yet
+    let known = |slot: u64| Some(slot).filter(|slot| *slot > 0);
+    let cluster_slot = known(*state.ingestion.cluster_slot.borrow());
+    let cluster_rooted_slot = known(*state.ingestion.rooted_slot.borrow());
+    let rooted_slot = match cluster_rooted_slot {
+        Some(root) => state.storage.get_latest_stored_slot(root)?,
+        None => None,
+    };
+    let lag_behind = |slot: Option<u64>| Some(cluster_slot?.saturating_sub(slot?));
+    Ok(Json(LatestSlots {
+        cluster_slot,
+        ingested_slot: known(*state.ingestion.processed_slot.borrow()),
+        cluster_rooted_slot,
+        rooted_slot,
+        lag: state.ingestion.lag(),
+        rooted_lag: lag_behind(rooted_slot),
+    }))
+}
+
+/// `GET /stats`
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "node",
+    responses((status = 200, description = "Storage statistics, refreshed every 30 seconds", body = Stats))
+)]
+pub async fn stats(State(state): State<ApiState>) -> Result<Json<Stats>, ApiError> {
+    Ok(Json(Stats {
+        storage: state.metrics.storage(),
+        latest_slot: state.storage.get_latest_slot_rollup()?,
+    }))
+}
+
+fn load_page(state: &ApiState, signatures: Page<String>) -> Result<TransactionPage, ApiError> {
+    let mut transactions = Vec::with_capacity(signatures.items.len());
+    for tx in state.storage.get_transactions(&signatures.items)?.into_iter().flatten() {
+        transactions.push(processed_view(&tx)?);
+    }
+    Ok(TransactionPage { transactions, next_cursor: signatures.next_cursor })
+}
+
+/// The stored record with the raw transaction replaced by the outcome from its status meta
+pub fn processed_view(tx: &StoredTransaction) -> serde_json::Result<Value> {
+    let meta = tx.transaction.transaction.meta.as_ref();
+    let outcome = json!({
+        "block_time": tx.transaction.block_time,
+        "success": meta.map(|meta| meta.err.is_none()),
+        "err": meta.and_then(|meta| meta.err.clone()),
+        "fee": meta.map(|meta| meta.fee),
+        "compute_units_consumed": meta.and_then(|meta| Option::<u64>::from(meta.compute_units_consumed.clone())),
+    });
+    
+    let mut view = serde_json::to_value(tx)?;
+    if let (Value::Object(view), Value::Object(outcome)) = (&mut view, outcome) {
+        view.remove("transaction");
+        view.extend(outcome);
+    }
+    Ok(view)
+}
+
+/// Items of a comma-separated query parameter
+pub fn split_list(list: Option<&str>) -> impl Iterator<Item = &str> {
+    list.unwrap_or_default().split(',').map(str::trim).filter(|item| !item.is_empty())
+}
+
+fn parse_addresses(list: Option<&str>) -> Result<Vec<String>, ApiError> {
+    split_list(list)
+        .map(|address| match address.parse::<Pubkey>() {
+            Ok(_) => Ok(address.to_string()),
+            Err(_) => Err(ApiError::BadRequest(format!("Invalid address {}", address))),
+        })
+        .collect()
+}
+
+fn parse_signature(signature: &str) -> Result<Signature, ApiError> {
+    signature
+        .parse()
+        .map_err(|_| ApiError::BadRequest(format!("Invalid signature {}", signature)))
+}
@@ -0,0 +1,564 @@
+//! JSON-RPC 2.0 methods compatible with the Solana RPC API, answered from the local store
+//!
+//! Requests for data the store does not hold get the same answer a node without it would give
+//! (e.g. `null` for an unknown signature), so clients can fall back on another endpoint. With an
+//! upstream configured, the node falls back itself: such requests, those for transactions stored
+//! in an encoding that cannot be converted to the one asked for, and the read methods of
+//! `FORWARDED_METHODS` it does not implement, are forwarded, and the transactions and blocks
+//! fetched are stored for next time.
+
+use axum::{body::Bytes, extract::State, Json};
+use futures::future;
+use serde::Serialize;
+use serde_json::{json, Value};
+use solana_client::rpc_config::{
+    RpcBlockConfig, RpcBlocksConfigWrapper, RpcContextConfig, RpcEncodingConfigWrapper,
+    RpcSignaturesForAddressConfig, RpcTransactionConfig,
+};
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+use solana_sdk::{
+    message::v0::LoadedAddresses, pubkey::Pubkey, signature::Signature, transaction::TransactionVersion,
+};
+use solana_transaction_status::{
+    EncodableWithMeta, EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, TransactionConfirmationStatus,
+    TransactionDetails, TransactionStatusMeta, UiConfirmedBlock, UiLoadedAddresses, UiMessage,
+    UiTransactionEncoding,
+};
+
+use tracing::{debug, warn};
+
+use super::ApiState;
+use crate::config::Commitment;
+
+// Error codes of the JSON-RPC specification and the Solana RPC API
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const LONG_TERM_STORAGE_SLOT_SKIPPED: i64 = -32009;
+const UNSUPPORTED_TRANSACTION_VERSION: i64 = -32015;
+
+/// Most signatures `getSignaturesForAddress` returns per request
+const MAX_SIGNATURES_FOR_ADDRESS: usize = 1000;
+
+/// Widest slot range `getBlocks` accepts, as upstream
+const MAX_GET_BLOCKS_RANGE: u64 = 500_000;
+
+/// Most requests answered in one batch
+const MAX_BATCH_REQUESTS: usize = 100;
+
+/// Most transactions `getBlock` returns, well above what fits in a block
+const MAX_BLOCK_TRANSACTIONS: usize = 10_000;
+
+/// Methods not implemented here that are forwarded to the upstream: cheap reads only, so the node
+/// never sends transactions or airdrop requests, nor runs simulations or account scans, on a
+/// client's behalf
+const FORWARDED_METHODS: &[&str] = &[
+    "getAccountInfo",
+    "getBalance",
+    "getBlockCommitment",
+    "getBlockHeight",
+    "getBlockProduction",
+    "getBlockTime",
+    "getBlocksWithLimit",
+    "getClusterNodes",
+    "getEpochInfo",
+    "getEpochSchedule",
+    "getFeeForMessage",
+    "getFirstAvailableBlock",
+    "getGenesisHash",
+    "getHealth",
+    "getHighestSnapshotSlot",
+    "getIdentity",
+    "getInflationGovernor",
+    "getInflationRate",
+    "getInflationReward",
+    "getLatestBlockhash",
+    "getLeaderSchedule",
+    "getMaxRetransmitSlot",
+    "getMaxShredInsertSlot",
+    "getMinimumBalanceForRentExemption",
+    "getMultipleAccounts",
+    "getRecentPerformanceSamples",
+    "getRecentPrioritizationFees",
+    "getSignatureStatuses",
+    "getSlot",
+    "getSlotLeader",
+    "getSlotLeaders",
+    "getStakeMinimumDelegation",
+    "getSupply",
+    "getTokenAccountBalance",
+    "getTokenAccountsByDelegate",
+    "getTokenAccountsByOwner",
+    "getTokenLargestAccounts",
+    "getTokenSupply",
+    "getTransactionCount",
+    "getVersion",
+    "getVoteAccounts",
+    "isBlockhashValid",
+    "minimumLedgerSlot",
+];
+
+#[derive(Debug, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    /// The store holds what was asked for, but not in a form it can serve, so the upstream may
+    /// answer instead
+    #[serde(skip)]
+    unservable: bool,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), unservable: false }
+    }
+    
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+    
+    fn internal(error: anyhow::Error) -> Self {
+        Self::new(INTERNAL_ERROR, format!("Internal error: {}", error))
+    }
+}
+
+/// JSON-RPC endpoint that requests the store cannot answer are forwarded to
+#[derive(Clone)]
+pub struct Upstream {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Upstream {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string(), client: reqwest::Client::new() }
+    }
+    
+    /// Send a single request and return the response as is
+    async fn forward(&self, request: &Value) -> anyhow::Result<Value> {
+        let response = self.client.post(&self.url).json(request).send().await?.error_for_status()?;
+        Ok(response.json().await?)
+    }
+}
+
+/// Answer a single request or a batch
+pub async fn handle(State(state): State<ApiState>, body: Bytes) -> Json<Value> {
+    let request: Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(_) => return Json(response(Value::Null, Err(RpcError::new(PARSE_ERROR, "Parse error")))),
+    };
+    
+    match request {
+        Value::Array(requests) if requests.is_empty() => {
+            Json(response(Value::Null, Err(RpcError::new(INVALID_REQUEST, "Invalid request"))))
+        }
+        Value::Array(requests) if requests.len() > MAX_BATCH_REQUESTS => {
+            let message = format!("Batch of {} requests; at most {} are answered at once", requests.len(), MAX_BATCH_REQUESTS);
+            Json(response(Value::Null, Err(RpcError::new(INVALID_REQUEST, message))))
+        }
+        Value::Array(requests) => Json(Value::Array(
+            future::join_all(requests.iter().map(|request| handle_request(&state, request))).await,
+        )),
+        request => Json(handle_request(&state, &request).await),
+    }
+}
+
+async fn handle_request(state: &ApiState, request: &Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) if request.get("jsonrpc").and_then(Value::as_str) == Some("2.0") => method,
+        _ => return response(id, Err(RpcError::new(INVALID_REQUEST, "Invalid request"))),
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    
+    let result = match method {
+        "getTransaction" => get_transaction(state, params),
+        "getSignaturesForAddress" => get_signatures_for_address(state, params),
+        "getBlock" => get_block(state, params),
+        "getBlocks" => get_blocks(state, params),
+        "nodeGetStats" => node_get_stats(state),
+        "nodeGetCoverage" => node_get_coverage(state, params),
+        "nodeGetLag" => Ok(node_get_lag(state)),
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, "Method not found")),
+    };
+    
+    let upstream = match &state.upstream {
+        Some(upstream) if is_miss(method, &result) => upstream,
+        _ => return response(id, result),
+    };
+    debug!("Forwarding {} to the upstream RPC", method);
+    match upstream.forward(request).await {
+        Ok(answer) => {
+            cache(state, upstream, method, request, &answer);
+            answer
+        }
+        Err(e) => response(id, Err(RpcError::new(INTERNAL_ERROR, format!("Upstream RPC failed: {}", e)))),
+    }
+}
+
+/// Whether the store lacks what a request asks for: an unknown signature, a block it has not
+/// recorded, a transaction it cannot serve in the encoding asked for, or one of the
+/// `FORWARDED_METHODS`
+fn is_miss(method: &str, result: &Result<Value, RpcError>) -> bool {
+    match result {
+        Ok(Value::Null) => method == "getTransaction",
+        Ok(_) => false,
+        Err(error) if error.unservable => true,
+        Err(error) => match error.code {
+            METHOD_NOT_FOUND => FORWARDED_METHODS.contains(&method),
+            LONG_TERM_STORAGE_SLOT_SKIPPED => true,
+            _ => false,
+        },
+    }
+}
+
+/// Fetch what the upstream answered a forwarded request with into the store, in the background.
+/// Clients may ask for other encodings than the store keeps, so it is fetched again rather than
+/// taken from the answer
+fn cache(state: &ApiState, upstream: &Upstream, method: &str, request: &Value, answer: &Value) {
+    if !matches!(answer.get("result"), Some(result) if !result.is_null()) {
+        return;
+    }
+    match (method, request.get("params").and_then(|params| params.get(0))) {
+        ("getTransaction", Some(Value::String(signature))) => {
+            let (control, url, signature) = (state.control.clone(), upstream.url.clone(), signature.clone());
+            tokio::spawn(async move {
+                if let Err(e) = control.cache_transaction(&url, &signature).await {
+                    warn!("Failed to cache transaction {}: {:#}", signature, e);
+                }
+            });
+        }
+        ("getBlock", Some(slot)) => {
+            if let Some(slot) = slot.as_u64() {
+                let (control, url) = (state.control.clone(), upstream.url.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = control.cache_block(&url, slot).await {
+                        warn!("Failed to cache block {}: {:#}", slot, e);
+                    }
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => json!({ "jsonrpc": "2.0", "error": error, "id": id }),
+    }
+}
+
+/// `getTransaction(signature, config?)`
+fn get_transaction(state: &ApiState, params: Value) -> Result<Value, RpcError> {
+    let (signature, config): (String, Option<RpcEncodingConfigWrapper<RpcTransactionConfig>>) =
+        parse_params(params)?;
+    parse_signature(&signature)?;
+    let config = config.map(|config| config.convert_to_current()).unwrap_or_default();
+    
+    let stored = match state.storage.get_transaction(&signature).map_err(RpcError::internal)? {
+        Some(stored) => stored.transaction,
+        None => return Ok(Value::Null),
+    };
+    let encoding = config.encoding.unwrap_or(UiTransactionEncoding::Json);
+    let transaction = encode_transaction(stored, encoding, config.max_supported_transaction_version)?;
+    serde_json::to_value(transaction).map_err(|e| RpcError::internal(e.into()))
+}
+
+/// `getSignaturesForAddress(address, config?)`
+fn get_signatures_for_address(state: &ApiState, params: Value) -> Result<Value, RpcError> {
+    let (address, config): (String, Option<RpcSignaturesForAddressConfig>) = parse_params(params)?;
+    address
+        .parse::<Pubkey>()
+        .map_err(|_| RpcError::invalid_params("Invalid param: Invalid address"))?;
+    let config = config.unwrap_or_default();
+    for signature in config.before.iter().chain(&config.until) {
+        parse_signature(signature)?;
+    }
+    let limit = config.limit.unwrap_or(MAX_SIGNATURES_FOR_ADDRESS);
+    if limit == 0 || limit > MAX_SIGNATURES_FOR_ADDRESS {
+        return Err(RpcError::invalid_params(format!("Invalid limit; max {}", MAX_SIGNATURES_FOR_ADDRESS)));
+    }
+    
+    let signatures = state
+        .storage
+        .get_address_signatures(&address, config.before.as_deref(), config.until.as_deref(), limit)
+        .map_err(RpcError::internal)?;
+    
+    let mut statuses = Vec::with_capacity(signatures.len());
+    for (slot, signature) in signatures {
+        let stored = state.storage.get_transaction(&signature).map_err(RpcError::internal)?;
+        let (err, memo, block_time) = match stored {
+            Some(stored) => (
+                stored.transaction.transaction.meta.and_then(|meta| meta.err),
+                stored.memo.map(|memo| format_memos(&memo)),
+                stored.transaction.block_time,
+            ),
+            None => (None, None, None),
+        };
+        statuses.push(RpcConfirmedTransactionStatusWithSignature {
+            signature,
+            slot,
+            err,
+            memo,
+            block_time,
+            // Stored transactions were fetched once they reached `network.commitment`
+            confirmation_status: Some(match state.commitment {
+                Commitment::Confirmed => TransactionConfirmationStatus::Confirmed,
+                Commitment::Finalized => TransactionConfirmationStatus::Finalized,
+            }),
+        });
+    }
+    serde_json::to_value(statuses).map_err(|e| RpcError::internal(e.into()))
+}
+
+/// `getBlock(slot, config?)`, listing the block's stored transactions in signature order
+fn get_block(state: &ApiState, params: Value) -> Result<Value, RpcError> {
+    let (slot, config): (u64, Option<RpcEncodingConfigWrapper<RpcBlockConfig>>) = parse_params(params)?;
+    let config = config.map(|config| config.convert_to_current()).unwrap_or_default();
+    
+    // Blocks the node did not track are reported like blocks missing from long-term storage
+    let block = state.storage.get_block(slot).map_err(RpcError::internal)?.ok_or_else(|| {
+        RpcError::new(
+            LONG_TERM_STORAGE_SLOT_SKIPPED,
+            format!("Slot {} was skipped, or missing in long-term storage", slot),
+        )
+    })?;
+    
+    let details = config.transaction_details.unwrap_or(TransactionDetails::Full);
+    let signatures = match details {
+        TransactionDetails::None => Vec::new(),
+        TransactionDetails::Full | TransactionDetails::Signatures => {
+            let page = state
+                .storage
+                .get_slot_signatures(slot, None, MAX_BLOCK_TRANSACTIONS + 1)
+                .map_err(RpcError::internal)?;
+            if page.items.len() > MAX_BLOCK_TRANSACTIONS {
+                return Err(RpcError::new(
+                    INTERNAL_ERROR,
+                    format!("Slot {} holds more than {} stored transactions", slot, MAX_BLOCK_TRANSACTIONS),
+                ));
+            }
+            page.items
+        }
+        TransactionDetails::Accounts => {
+            return Err(RpcError::invalid_params("transactionDetails \"accounts\" is not supported"));
+        }
+    };
+    
+    // A transaction that cannot be served in `encoding` fails the block, which is then a miss
+    let transactions = match details {
+        TransactionDetails::Full => {
+            let encoding = config.encoding.unwrap_or(UiTransactionEncoding::Json);
+            let mut transactions = Vec::with_capacity(signatures.len());
+            for signature in &signatures {
+                if let Some(stored) = state.storage.get_transaction(signature).map_err(RpcError::internal)? {
+                    let encoded = encode_transaction(stored.transaction, encoding, config.max_supported_transaction_version)?;
+                    transactions.push(encoded.transaction);
+                }
+            }
+            Some(transactions)
+        }
+        _ => None,
+    };
+    
+    let block = UiConfirmedBlock {
+        previous_blockhash: block.previous_blockhash,
+        blockhash: block.blockhash,
+        parent_slot: block.parent_slot,
+        signatures: (details == TransactionDetails::Signatures).then_some(signatures),
+        transactions,
+        // Rewards are not stored
+        rewards: None,
+        block_time: block.block_time,
+        block_height: block.block_height,
+    };
+    serde_json::to_value(block).map_err(|e| RpcError::internal(e.into()))
+}
+
+/// `getBlocks(start_slot, end_slot?, config?)`: slots with a stored block
+fn get_blocks(state: &ApiState, params: Value) -> Result<Value, RpcError> {
+    // Stored blocks are all confirmed, whatever commitment is asked for
+    let (start_slot, wrapper, _config): (u64, Option<RpcBlocksConfigWrapper>, Option<RpcContextConfig>) =
+        parse_params(params)?;
+    let (end_slot, _) = wrapper.map(|wrapper| wrapper.unzip()).unwrap_or_default();
+    let end_slot = match end_slot {
+        Some(end_slot) if end_slot < start_slot => return Ok(json!([])),
+        Some(end_slot) if end_slot - start_slot > MAX_GET_BLOCKS_RANGE => {
+            return Err(RpcError::invalid_params(format!("Slot range too large; max {}", MAX_GET_BLOCKS_RANGE)));
+        }
+        Some(end_slot) => end_slot,
+        None => start_slot.saturating_add(MAX_GET_BLOCKS_RANGE),
+    };
+    
+    let slots = state
+        .storage
+        .get_block_slots(start_slot, end_slot, MAX_GET_BLOCKS_RANGE as usize + 1)
+        .map_err(RpcError::internal)?;
+    Ok(json!(slots))
+}
+
+/// `nodeGetStats()`: what the store holds and what ingestion has done since the node started
+fn node_get_stats(state: &ApiState) -> Result<Value, RpcError> {
+    // From the periodic storage report, as counting on every call would scan the database
+    let storage = state.metrics.storage();
+    let latest_slot = state.storage.get_latest_slot_rollup().map_err(RpcError::internal)?;
+    let metrics = &state.metrics;
+    Ok(json!({
+        "transactionCount": storage.transaction_count,
+        "dbSizeBytes": storage.db_size_bytes,
+        "latestStoredSlot": latest_slot.map(|rollup| rollup.slot),
+        "transactionsReceived": metrics.transactions_received.get(),
+        "transactionsProcessed": metrics.transactions_processed.get(),
+        "transactionsFailed": metrics.transactions_failed.get(),
+        "transactionsFiltered": metrics.transactions_filtered.get(),
+        "transactionsStored": metrics.transactions_stored.get(),
+        "fetchErrors": metrics.fetch_errors.get(),
+    }))
+}
+
+/// `nodeGetCoverage(start_slot?, end_slot?)`: the slot ranges with stored transactions in every slot
+fn node_get_coverage(state: &ApiState, params: Value) -> Result<Value, RpcError> {
+    let (start_slot, end_slot): (Option<u64>, Option<u64>) = parse_params(params)?;
+    let ranges = state
+        .storage
+        .get_coverage(start_slot.unwrap_or(0), end_slot.unwrap_or(u64::MAX))
+        .map_err(RpcError::internal)?;
+    let ranges: Vec<Value> = ranges
+        .into_iter()
+        .map(|range| json!({ "startSlot": range.start_slot, "endSlot": range.end_slot, "slots": range.slots }))
+        .collect();
+    Ok(json!(ranges))
+}
+
+/// `nodeGetLag()`: how far processing trails the cluster
+fn node_get_lag(state: &ApiState) -> Value {
+    json!({
+        "clusterSlot": *state.ingestion.cluster_slot.borrow(),
+        "processedSlot": *state.ingestion.processed_slot.borrow(),
+        "lag": state.ingestion.lag(),
+        "sourcesConnected": *state.ingestion.sources.borrow(),
+        "paused": state.control.is_paused(),
+    })
+}
+
+fn parse_signature(signature: &str) -> Result<Signature, RpcError> {
+    signature
+        .parse()
+        .map_err(|_| RpcError::invalid_params("Invalid param: Invalid signature"))
+}
+
+/// Memos as the RPC API lists them: each prefixed with its length, separated by "; "
+fn format_memos(memo: &str) -> String {
+    memo.lines()
+        .map(|memo| format!("[{}] {}", memo.len(), memo))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Positional params (at most `MAX_PARAMS`), with trailing optional ones allowed to be missing
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    const MAX_PARAMS: usize = 3;
+    let params = match params {
+        Value::Null => Value::Array(Vec::new()),
+        params => params,
+    };
+    serde_json::from_value(params.clone())
+        .or_else(|e| match params {
+            // Pad missing optional params with nulls, up to the number the method takes
+            Value::Array(values) if values.len() < MAX_PARAMS => (values.len() + 1..=MAX_PARAMS)
+                .find_map(|len| {
+                    let mut padded = values.clone();
+                    padded.resize(len, Value::Null);
+                    serde_json::from_value(Value::Array(padded)).ok()
+                })
+                .ok_or(e),
+            _ => Err(e),
+        })
+        .map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))
+}
+
+/// Present a stored transaction as `getTransaction` would with these parameters
+fn encode_transaction(
+    mut stored: EncodedConfirmedTransactionWithStatusMeta,
+    encoding: UiTransactionEncoding,
+    max_supported_version: Option<u8>,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta, RpcError> {
+    // Clients that do not state a supported version only get legacy transactions
+    match (stored.transaction.version.clone(), max_supported_version) {
+        (Some(TransactionVersion::Number(version)), max) if max.map_or(true, |max| version > max) => {
+            return Err(RpcError::new(
+                UNSUPPORTED_TRANSACTION_VERSION,
+                format!(
+                    "Transaction version ({}) is not supported by the requesting client. Please try the \
+                     request again with the following configuration parameter: \
+                     \"maxSupportedTransactionVersion\": {}",
+                    version, version
+                ),
+            ));
+        }
+        (_, None) => stored.transaction.version = None,
+        (None, Some(_)) => stored.transaction.version = Some(TransactionVersion::LEGACY),
+        _ => {}
+    }
+    
+    let loaded = stored
+        .transaction
+        .meta
+        .as_ref()
+        .and_then(|meta| Option::<&UiLoadedAddresses>::from(meta.loaded_addresses.as_ref()));
+    let transaction = reencode(&stored.transaction.transaction, encoding, loaded).ok_or_else(|| RpcError {
+        unservable: true,
+        ..RpcError::invalid_params(format!(
+            "Transaction is stored as {}; it cannot be served as {:?}",
+            stored_encoding(&stored.transaction.transaction),
+            encoding
+        ))
+    })?;
+    stored.transaction.transaction = transaction;
+    Ok(stored)
+}
+
+/// Convert a transaction to `encoding`. Binary transactions convert to anything, parsed JSON of
+/// versioned messages needing the addresses they load from lookup tables; JSON stays as stored.
+fn reencode(
+    transaction: &EncodedTransaction,
+    encoding: UiTransactionEncoding,
+    loaded: Option<&UiLoadedAddresses>,
+) -> Option<EncodedTransaction> {
+    match (transaction, encoding) {
+        (EncodedTransaction::Json(ui), UiTransactionEncoding::Json) if matches!(ui.message, UiMessage::Raw(_)) => {
+            Some(transaction.clone())
+        }
+        (EncodedTransaction::Json(ui), UiTransactionEncoding::JsonParsed)
+            if matches!(ui.message, UiMessage::Parsed(_)) =>
+        {
+            Some(transaction.clone())
+        }
+        (EncodedTransaction::Json(_) | EncodedTransaction::Accounts(_), _) => None,
+        (binary, encoding) => {
+            let decoded = binary.decode()?;
+            let mut meta = TransactionStatusMeta::default();
+            if encoding == UiTransactionEncoding::JsonParsed && decoded.version() != TransactionVersion::LEGACY {
+                let loaded = loaded?;
+                let parse = |keys: &[String]| keys.iter().map(|key| key.parse()).collect::<Result<Vec<Pubkey>, _>>();
+                meta.loaded_addresses = LoadedAddresses {
+                    writable: parse(&loaded.writable).ok()?,
+                    readonly: parse(&loaded.readonly).ok()?,
+                };
+            }
+            Some(decoded.encode_with_meta(encoding, &meta))
+        }
+    }
+}
+
+fn stored_encoding(transaction: &EncodedTransaction) -> &'static str {
+    match transaction {
+        EncodedTransaction::Json(ui) if matches!(ui.message, UiMessage::Parsed(_)) => "jsonParsed",
+        EncodedTransaction::Json(_) => "json",
+        EncodedTransaction::Accounts(_) => "accounts",
+        _ => "binary",
+    }
+}
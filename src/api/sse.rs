@@ -0,0 +1,66 @@
+//! Server-sent events stream of processed transactions, for clients without WebSocket or gRPC
+//!
+//! The filter is given once, in the query string, with the lists of the WebSocket filter
+//! comma-separated: `/sse?programs=<program id>&events=swap,nft`. Each matching transaction is a
+//! `transaction` event carrying the same JSON as the WebSocket stream; clients that fall behind
+//! receive a `dropped` event with the number of transactions they missed.
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::stream::{self, Stream};
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use super::feed::{StreamFilter, StreamedTransaction};
+use super::rest::{split_list, ApiError};
+use super::ApiState;
+
+#[derive(Debug, Deserialize)]
+pub struct SseQuery {
+    pub programs: Option<String>,
+    pub accounts: Option<String>,
+    pub events: Option<String>,
+}
+
+/// `GET /sse`
+pub async fn handle(
+    State(state): State<ApiState>,
+    Query(query): Query<SseQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let list = |list: &Option<String>| split_list(list.as_deref()).map(str::to_string).collect();
+    let filter = StreamFilter {
+        programs: list(&query.programs),
+        accounts: list(&query.accounts),
+        events: list(&query.events),
+        replay_from_slot: None,
+    };
+    filter.validate().map_err(ApiError::BadRequest)?;
+    
+    let transactions = state.transactions.subscribe();
+    Ok(Sse::new(events(transactions, filter)).keep_alive(KeepAlive::default()))
+}
+
+fn events(
+    receiver: broadcast::Receiver<StreamedTransaction>,
+    filter: StreamFilter,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(tx) if filter.matches(&tx) => Event::default().event("transaction").data(tx.json.as_str()),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("SSE client fell behind, {} transactions dropped", missed);
+                    Event::default().event("dropped").data(json!({ "dropped": missed }).to_string())
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            return Some((Ok(event), (receiver, filter)));
+        }
+    })
+}
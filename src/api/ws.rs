@@ -0,0 +1,134 @@
+//! WebSocket stream of processed transactions, as they leave the processing workers
+//!
+//! Each connection starts out receiving every transaction that passes the node's plugins and
+//! filters. Sending a filter as a text message, e.g.
+//! `{"programs": ["<program id>"], "accounts": [], "events": ["swap"]}`, replaces the
+//! connection's filter: a transaction is sent when it matches every non-empty list, by invoking
+//! one of the programs, referencing one of the accounts and carrying one of the event types.
+//!
+//! A filter with `"replay_from_slot": <slot>` first sends the matching stored transactions from
+//! that slot on, in slot order, then goes live without gaps or duplicates, so a consumer can catch
+//! up after downtime. `{"replayed": <count>}` marks the switch to live. A connection replays once;
+//! later filters may not ask for another replay.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use serde_json::json;
+use std::collections::HashSet;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use super::feed::{Replay, StreamFilter, StreamedTransaction};
+use super::ApiState;
+
+/// `GET /ws`
+pub async fn handle(State(state): State<ApiState>, upgrade: WebSocketUpgrade) -> Response {
+    let transactions = state.transactions.subscribe();
+    upgrade.on_upgrade(move |socket| stream(socket, state, transactions))
+}
+
+async fn stream(mut socket: WebSocket, state: ApiState, mut transactions: broadcast::Receiver<StreamedTransaction>) {
+    let mut filter = StreamFilter::default();
+    // Replayed transactions not to send again when they arrive live
+    let mut replayed = HashSet::new();
+    let mut has_replayed = false;
+    
+    loop {
+        let message = tokio::select! {
+            received = socket.recv() => match received {
+                Some(Ok(Message::Text(text))) => match update_filter(&mut filter, &text, has_replayed) {
+                    Ok(()) => match filter.replay_from_slot {
+                        Some(from_slot) => match replay(&mut socket, &state, &filter, from_slot).await {
+                            Ok(Some((live, signatures))) => {
+                                (transactions, replayed, has_replayed) = (live, signatures, true);
+                                continue;
+                            }
+                            Ok(None) => break,
+                            Err(error) => json!({ "error": error }).to_string(),
+                        },
+                        None => continue,
+                    },
+                    Err(error) => json!({ "error": error }).to_string(),
+                },
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    debug!("WebSocket client error: {}", e);
+                    break;
+                }
+            },
+            streamed = transactions.recv() => match streamed {
+                Ok(tx) if filter.matches(&tx) && !replayed.remove(&tx.signature) => tx.json.as_ref().clone(),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("WebSocket client fell behind, {} transactions dropped", missed);
+                    json!({ "dropped": missed }).to_string()
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+        
+        if socket.send(Message::Text(message)).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Send the stored transactions matching `filter` from `from_slot` on, and return the live feed
+/// to continue with, or `None` if the client is gone
+async fn replay(
+    socket: &mut WebSocket,
+    state: &ApiState,
+    filter: &StreamFilter,
+    from_slot: u64,
+) -> Result<Option<(broadcast::Receiver<StreamedTransaction>, HashSet<String>)>, String> {
+    let mut replay = Replay::new(state.clone(), from_slot).await?;
+    let mut count = 0;
+    loop {
+        // Storage reads and reprocessing block, so they run off the runtime
+        let slot_filter = filter.clone();
+        let read = tokio::task::spawn_blocking(move || {
+            let transactions = replay.next_slot(&slot_filter);
+            (replay, transactions)
+        });
+        let transactions = match read.await {
+            Ok((next, Ok(Some(transactions)))) => {
+                replay = next;
+                transactions
+            }
+            Ok((next, Ok(None))) => {
+                replay = next;
+                break;
+            }
+            Ok((_, Err(e))) => return Err(format!("Replay failed: {}", e)),
+            Err(e) => return Err(format!("Replay failed: {}", e)),
+        };
+        for tx in transactions {
+            if socket.send(Message::Text(tx.json.as_ref().clone())).await.is_err() {
+                return Ok(None);
+            }
+            count += 1;
+        }
+    }
+    
+    let (live, replayed) = replay.into_live();
+    match socket.send(Message::Text(json!({ "replayed": count }).to_string())).await {
+        Ok(()) => Ok(Some((live, replayed))),
+        Err(_) => Ok(None),
+    }
+}
+
+fn update_filter(filter: &mut StreamFilter, text: &str, has_replayed: bool) -> Result<(), String> {
+    let update: StreamFilter = serde_json::from_str(text).map_err(|e| format!("Invalid filter: {}", e))?;
+    update.validate()?;
+    if has_replayed && update.replay_from_slot.is_some() {
+        return Err("This connection already replayed; open another to replay again".to_string());
+    }
+    *filter = update;
+    Ok(())
+}
@@ -0,0 +1,277 @@
+//! Backfills run from the command line: the blocks of a slot range, or the transactions of an
+//! address, fed through the processing pipeline until done
+//!
+//! Progress is checkpointed in the store once the transactions fed before it are stored, so running
+//! the same backfill again after an interruption resumes where it stopped. Transactions fed after
+//! the last checkpoint are fetched and stored again, but counted into the rollups and statistics
+//! only once, as storage skips aggregating transactions it already holds.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::time::{Duration, Instant};
+use tokio::time::interval;
+use tracing::info;
+
+use crate::network::{Pipeline, MAX_BACKFILL_SLOTS};
+use crate::storage::Storage;
+
+/// Signatures of an address fetched per request (the RPC maximum)
+const SIGNATURE_PAGE: usize = 1000;
+
+/// Interval between progress reports and checkpoints
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub enum BackfillTarget {
+    /// Slots `start` to `end` (inclusive)
+    Slots { start: u64, end: u64 },
+    /// Transactions referencing an address, newest first, up to `limit`
+    Address { address: Pubkey, limit: Option<u64> },
+}
+
+/// Where an interrupted backfill resumes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackfillCheckpoint {
+    /// Next slot to fetch, for slot ranges
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_slot: Option<u64>,
+    /// Oldest signature fed, for addresses; older ones are fetched next
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// Transactions fed up to here, by every run
+    #[serde(default)]
+    pub transactions: u64,
+}
+
+/// What a backfill ingested
+#[derive(Debug, Default, Serialize)]
+pub struct BackfillSummary {
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resumed_from: Option<BackfillCheckpoint>,
+    /// Blocks fetched, for slot ranges
+    pub blocks: u64,
+    /// Slots without a block, for slot ranges
+    pub skipped_slots: u64,
+    /// Signatures whose transaction the endpoint did not return, for addresses
+    pub missing_transactions: u64,
+    /// Transactions fed to the pipeline by this run
+    pub transactions: u64,
+    pub stored: u64,
+    /// Dropped by plugins or filters
+    pub filtered: u64,
+    /// Could not be decoded
+    pub failed: u64,
+    pub elapsed_secs: u64,
+}
+
+impl BackfillTarget {
+    /// Key of the target's checkpoint
+    fn key(&self) -> String {
+        match self {
+            BackfillTarget::Slots { start, end } => format!("slots:{}-{}", start, end),
+            BackfillTarget::Address { address, .. } => format!("address:{}", address),
+        }
+    }
+}
+
+/// Stores progress once the transactions fed before it are stored
+struct Checkpoints<'a> {
+    storage: &'a Storage,
+    key: String,
+    /// Progress not yet stored, and the transactions handed to the pipeline by then
+    pending: Option<(BackfillCheckpoint, u64)>,
+}
+
+impl Checkpoints<'_> {
+    fn advance(&mut self, pipeline: &Pipeline, progress: BackfillCheckpoint) -> Result<()> {
+        self.save(pipeline.persisted())?;
+        if self.pending.is_none() {
+            self.pending = Some((progress, pipeline.received()));
+        }
+        Ok(())
+    }
+    
+    /// Store the pending progress if every transaction fed before it is persisted
+    fn save(&mut self, persisted: u64) -> Result<()> {
+        if let Some((checkpoint, fed)) = &self.pending {
+            if persisted >= *fed {
+                self.storage.store_backfill_checkpoint(&self.key, &serde_json::to_value(checkpoint)?)?;
+                self.pending = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Feed `target` through `pipeline`, fetching from `rpc_url`, and wait until it is stored. Resumes
+/// from the checkpoint of an interrupted run unless `restart`
+pub async fn run(
+    pipeline: Pipeline,
+    storage: &Storage,
+    rpc_url: &str,
+    target: BackfillTarget,
+    restart: bool,
+) -> Result<BackfillSummary> {
+    let started = Instant::now();
+    let key = target.key();
+    let resumed_from: Option<BackfillCheckpoint> = if restart {
+        None
+    } else {
+        storage.get_backfill_checkpoint(&key)?.map(serde_json::from_value).transpose()?
+    };
+    if let Some(checkpoint) = &resumed_from {
+        match (&checkpoint.next_slot, &checkpoint.before) {
+            (Some(slot), _) => info!("Resuming backfill of {} at slot {}", key, slot),
+            (None, Some(signature)) => info!("Resuming backfill of {} before {}", key, signature),
+            (None, None) => info!("Resuming backfill of {}", key),
+        }
+    }
+    let resume = resumed_from.clone().unwrap_or_default();
+    let mut summary = BackfillSummary { target: key.clone(), resumed_from, ..Default::default() };
+    let mut checkpoints = Checkpoints { storage, key: key.clone(), pending: None };
+    
+    let result = match target {
+        BackfillTarget::Slots { start, end } => {
+            backfill_slots(&pipeline, &mut checkpoints, start, end, &resume, &mut summary).await
+        }
+        BackfillTarget::Address { address, limit } => {
+            let address_limit = limit.map(|limit| limit.saturating_sub(resume.transactions));
+            backfill_address(&pipeline, &mut checkpoints, rpc_url, &address, address_limit, &resume, &mut summary)
+                .await
+        }
+    };
+    
+    // Closing the pipeline lets the workers finish and the last batch be stored
+    let fed = pipeline.received();
+    let metrics = pipeline.metrics();
+    let persisted = pipeline.finish().await?;
+    match result {
+        Ok(()) if persisted >= fed => storage.delete_backfill_checkpoint(&key)?,
+        Ok(()) => {
+            checkpoints.save(persisted)?;
+            bail!("Some transactions failed to store, run the backfill again to resume");
+        }
+        Err(e) => {
+            checkpoints.save(persisted)?;
+            return Err(e.context("Backfill stopped, run it again to resume"));
+        }
+    }
+    
+    summary.stored = metrics.transactions_stored.get();
+    summary.filtered = metrics.transactions_filtered.get();
+    summary.failed = metrics.transactions_failed.get();
+    summary.elapsed_secs = started.elapsed().as_secs();
+    Ok(summary)
+}
+
+/// Backfill slots `start` to `end` (inclusive) as a series of pipeline backfills of at most
+/// `MAX_BACKFILL_SLOTS` each
+async fn backfill_slots(
+    pipeline: &Pipeline,
+    checkpoints: &mut Checkpoints<'_>,
+    start: u64,
+    end: u64,
+    resume: &BackfillCheckpoint,
+    summary: &mut BackfillSummary,
+) -> Result<()> {
+    let mut from = resume.next_slot.unwrap_or(start);
+    let mut progress = interval(PROGRESS_INTERVAL);
+    while from <= end {
+        let to = end.min(from.saturating_add(MAX_BACKFILL_SLOTS - 1));
+        let id = pipeline.control().backfill(from, to)?.id;
+        let job = loop {
+            progress.tick().await;
+            let job = pipeline.control().backfills().into_iter().find(|job| job.id == id).context("Backfill job lost")?;
+            checkpoints.advance(
+                pipeline,
+                BackfillCheckpoint {
+                    next_slot: Some(job.next_slot),
+                    before: None,
+                    transactions: resume.transactions + summary.transactions + job.transactions,
+                },
+            )?;
+            if job.done || job.error.is_some() {
+                break job;
+            }
+            info!(
+                "Backfilled {} of {} slots: {} blocks, {} transactions",
+                job.next_slot - start,
+                end - start + 1,
+                summary.blocks + job.blocks,
+                summary.transactions + job.transactions
+            );
+        };
+        
+        summary.blocks += job.blocks;
+        summary.skipped_slots += job.skipped_slots;
+        summary.transactions += job.transactions;
+        if let Some(error) = job.error {
+            bail!("Backfill failed at slot {}: {}", job.next_slot, error);
+        }
+        match to.checked_add(1) {
+            Some(next) => from = next,
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+/// Backfill the transactions referencing `address`, newest first, paging back through its
+/// signatures until they run out or `limit` transactions were fed
+async fn backfill_address(
+    pipeline: &Pipeline,
+    checkpoints: &mut Checkpoints<'_>,
+    rpc_url: &str,
+    address: &Pubkey,
+    limit: Option<u64>,
+    resume: &BackfillCheckpoint,
+    summary: &mut BackfillSummary,
+) -> Result<()> {
+    let commitment = pipeline.control().commitment();
+    let client = RpcClient::new_with_commitment(rpc_url.to_string(), commitment);
+    let mut before = resume.before.clone();
+    let mut last_report = Instant::now();
+    loop {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before: before.as_deref().map(str::parse::<Signature>).transpose()?,
+            until: None,
+            limit: Some(SIGNATURE_PAGE),
+            commitment: Some(commitment),
+        };
+        let page = client
+            .get_signatures_for_address_with_config(address, config)
+            .await
+            .with_context(|| format!("Failed to fetch the signatures of {}", address))?;
+        if page.is_empty() {
+            return Ok(());
+        }
+        
+        for status in page {
+            if matches!(limit, Some(limit) if summary.transactions >= limit) {
+                return Ok(());
+            }
+            if pipeline.control().backfill_transaction(rpc_url, &status.signature).await? {
+                summary.transactions += 1;
+            } else {
+                summary.missing_transactions += 1;
+            }
+            before = Some(status.signature);
+            
+            if last_report.elapsed() >= PROGRESS_INTERVAL {
+                checkpoints.advance(
+                    pipeline,
+                    BackfillCheckpoint {
+                        next_slot: None,
+                        before: before.clone(),
+                        transactions: resume.transactions + summary.transactions,
+                    },
+                )?;
+                info!("Backfilled {} transactions of {}, back to slot {}", summary.transactions, address, status.slot);
+                last_report = Instant::now();
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+
+/// Number of recent blockhashes remembered (blockhashes expire after 150 slots)
+const CAPACITY: usize = 1024;
+
+/// Maps recent blockhashes to the slot of the block that produced them
+#[derive(Clone, Default)]
+pub struct BlockhashCache {
+    inner: Arc<RwLock<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    slots: HashMap<String, u64>,
+    order: VecDeque<String>,
+}
+
+impl BlockhashCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn insert(&self, blockhash: String, slot: u64) {
+        let mut inner = self.inner.write().unwrap();
+        if inner.slots.insert(blockhash.clone(), slot).is_some() {
+            return;
+        }
+        inner.order.push_back(blockhash);
+        
+        while inner.order.len() > CAPACITY {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.slots.remove(&oldest);
+            }
+        }
+    }
+    
+    /// Slot that produced a blockhash, if it is still cached
+    pub fn get(&self, blockhash: &str) -> Option<u64> {
+        self.inner.read().unwrap().slots.get(blockhash).copied()
+    }
+}
@@ -0,0 +1,416 @@
+//! Command line: running the node, and the operator commands working on its storage
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, write_keypair_file, Keypair, Signer},
+};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api::{self, rest::processed_view};
+use crate::backfill::{self, BackfillTarget};
+use crate::cluster_identity;
+use crate::config::{Config, ConfigOverrides, ConfigSource};
+use crate::config_check::{self, Severity};
+use crate::export::{self, ExportFormat};
+use crate::metrics::Metrics;
+use crate::network::NetworkService;
+use crate::notifications::Notifier;
+use crate::storage::Storage;
+use crate::transaction_processor::TransactionProcessor;
+use crate::webhook::Webhooks;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// Path to configuration file
+    #[arg(short, long, default_value = "config.toml", global = true)]
+    pub config: String,
+    
+    /// Network to connect to: mainnet-beta, testnet, devnet or localhost, whose endpoints fill in
+    /// those the configuration leaves out, or any name with its endpoints configured. Data is
+    /// kept apart per network, under `storage_path/<network>`
+    #[arg(short, long, default_value = "mainnet-beta", global = true)]
+    pub network: String,
+    
+    /// Join the cluster's gossip network when running (same as `enabled = true` under `[gossip]`)
+    #[arg(long, global = true)]
+    pub enable_gossip: bool,
+    
+    /// Deprecated alias for `spy --once --interval SECONDS`: join gossip, print the peers
+    /// discovered within this many seconds, and exit
+    #[arg(long, value_name = "SECONDS")]
+    pub gossip_peers: Option<u64>,
+    
+    #[command(flatten, next_help_heading = "Configuration overrides")]
+    pub overrides: ConfigOverrides,
+    
+    /// What to do; `run` when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Args {
+    /// Where the configuration is loaded from, for this network and with these flags
+    pub fn config_source(&self) -> ConfigSource {
+        ConfigSource {
+            path: self.config.clone(),
+            network: self.network.clone(),
+            overrides: self.overrides.clone(),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Ingest transactions and serve the APIs
+    Run,
+    
+    /// Print stored transactions as JSON, reading the storage of a running node if need be
+    Query {
+        #[command(subcommand)]
+        target: QueryTarget,
+    },
+    
+    /// Write the stored transactions of a slot range to a file, reading the storage of a running
+    /// node if need be
+    Export {
+        #[arg(long)]
+        from_slot: u64,
+        
+        /// Last slot exported (inclusive)
+        #[arg(long)]
+        to_slot: u64,
+        
+        #[arg(long, value_enum, default_value_t = ExportFormat::Jsonl)]
+        format: ExportFormat,
+        
+        #[arg(long, value_name = "PATH")]
+        out: String,
+    },
+    
+    /// Fetch the blocks of a slot range or the transactions of an address from the first RPC
+    /// endpoint, process and store them, and exit. An interrupted backfill resumes when run again
+    Backfill {
+        /// Slots A to B (inclusive)
+        #[arg(
+            long,
+            value_name = "A..B",
+            value_parser = parse_slot_range,
+            required_unless_present = "address",
+            conflicts_with = "address"
+        )]
+        slots: Option<(u64, u64)>,
+        
+        /// Transactions referencing this address, newest first
+        #[arg(long, value_name = "PUBKEY")]
+        address: Option<Pubkey>,
+        
+        /// Most transactions fetched for --address, counting those of interrupted runs
+        #[arg(long, requires = "address")]
+        limit: Option<u64>,
+        
+        /// Start over, ignoring the checkpoint of an interrupted run
+        #[arg(long)]
+        restart: bool,
+    },
+    
+    /// Delete the stored transactions and per-slot records from before a slot
+    Prune {
+        /// First slot kept; by default the first of the `storage_retention_days` period
+        #[arg(long)]
+        before_slot: Option<u64>,
+        
+        /// Compact the database afterwards, reclaiming the space of the deleted entries
+        #[arg(long)]
+        compact: bool,
+    },
+    
+    /// Print storage statistics, reading the storage of a running node if need be
+    Stats {
+        /// Database to read; by default the configured `storage_path`
+        #[arg(long, value_name = "PATH")]
+        db: Option<String>,
+    },
+    
+    /// Generate an identity keypair and print its public key
+    Keygen {
+        /// Where to write the keypair; by default the configured `identity_keypair_path`
+        #[arg(long, value_name = "PATH")]
+        out: Option<String>,
+        
+        /// Overwrite an existing keypair file
+        #[arg(long)]
+        force: bool,
+    },
+    
+    /// Check the configuration, identity keypair, storage and cluster without starting the node
+    Verify,
+    
+    /// Work with the configuration file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    
+    /// Only join gossip, without ingesting or storing anything, and keep printing the cluster's
+    /// topology, versions and feature sets
+    Spy {
+        /// Seconds between reports
+        #[arg(long, default_value_t = 30)]
+        interval: u64,
+        
+        /// Also dump the gossip table to this directory with each report
+        #[arg(long, value_name = "DIR")]
+        export_dir: Option<String>,
+        
+        /// Exit after the first report
+        #[arg(long)]
+        once: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum QueryTarget {
+    /// A transaction, as `/tx/<signature>` returns it
+    Tx {
+        signature: String,
+    },
+    
+    /// The block header and transactions stored for a slot
+    Slot {
+        slot: u64,
+        
+        /// Most transactions printed
+        #[arg(long, default_value_t = 1000)]
+        limit: usize,
+    },
+    
+    /// The transactions referencing an address, newest first
+    Address {
+        pubkey: String,
+        
+        /// Most transactions printed
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Parse the configuration file and check its settings offline, listing every problem found
+    Validate,
+}
+
+/// Open the configured storage
+pub fn open_storage(config: &Config) -> Result<Storage> {
+    Ok(Storage::new(&config.storage_path)?.with_memo_index(config.node.index_memos))
+}
+
+/// `query`
+pub fn query(config: &Config, target: QueryTarget) -> Result<()> {
+    let storage = Storage::open_read_only(&config.storage_path)?;
+    let output = match target {
+        QueryTarget::Tx { signature } => {
+            let tx = storage
+                .get_transaction(&signature)?
+                .with_context(|| format!("Transaction {} is not stored", signature))?;
+            processed_view(&tx)?
+        }
+        QueryTarget::Slot { slot, limit } => {
+            let signatures = storage.get_slot_signatures(slot, None, limit)?.items;
+            let block = storage.get_block(slot)?;
+            if signatures.is_empty() && block.is_none() {
+                bail!("Nothing is stored for slot {}", slot);
+            }
+            json!({
+                "slot": slot,
+                "block": block,
+                "transactions": views(&storage, &signatures)?,
+            })
+        }
+        QueryTarget::Address { pubkey, limit } => {
+            pubkey.parse::<Pubkey>().map_err(|_| anyhow!("{} is not a valid address", pubkey))?;
+            let signatures: Vec<String> = storage
+                .get_address_signatures(&pubkey, None, None, limit)?
+                .into_iter()
+                .map(|(_, signature)| signature)
+                .collect();
+            Value::Array(views(&storage, &signatures)?)
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// The stored transactions of `signatures`, skipping those no longer stored
+fn views(storage: &Storage, signatures: &[String]) -> Result<Vec<Value>> {
+    let mut views = Vec::new();
+    for tx in storage.get_transactions(signatures)?.into_iter().flatten() {
+        views.push(processed_view(&tx)?);
+    }
+    Ok(views)
+}
+
+/// `export`
+pub fn export(config: &Config, from_slot: u64, to_slot: u64, format: ExportFormat, out: &str) -> Result<()> {
+    let storage = Storage::open_read_only(&config.storage_path)?;
+    let stats = export::export(&storage, from_slot, to_slot, format, out)?;
+    println!("{}", serde_json::to_string_pretty(&stats)?);
+    Ok(())
+}
+
+/// Parse `A..B` (inclusive) for `backfill --slots`
+fn parse_slot_range(range: &str) -> Result<(u64, u64), String> {
+    let (start, end) = range.split_once("..").ok_or("expected A..B")?;
+    let start: u64 = start.parse().map_err(|e| format!("{}: {}", start, e))?;
+    let end: u64 = end.parse().map_err(|e| format!("{}: {}", end, e))?;
+    if end < start {
+        return Err(format!("{} is before {}", end, start));
+    }
+    Ok((start, end))
+}
+
+/// `backfill`: the ingestion pipeline alone, fed from RPC instead of live sources
+pub async fn backfill(config: Config, network: &str, target: BackfillTarget, restart: bool) -> Result<()> {
+    let storage = open_storage(&config)?;
+    cluster_identity::validate(network, &config, Some(&storage), false, true).await?;
+    let rpc_url =
+        config.network.rpc_endpoints.first().cloned().context("Backfilling needs an entry in network.rpc_endpoints")?;
+    let pipeline = NetworkService::new(config, storage.clone()).await?.into_pipeline()?;
+    let summary = backfill::run(pipeline, &storage, &rpc_url, target, restart).await?;
+    println!("{}", serde_json::to_string_pretty(&summary)?);
+    Ok(())
+}
+
+/// `prune`
+pub fn prune(storage: &Storage, config: &Config, before_slot: Option<u64>, compact: bool) -> Result<()> {
+    let before_slot = match before_slot {
+        Some(slot) => slot,
+        None => {
+            let retention_days = config.node.storage_retention_days;
+            if retention_days == 0 {
+                bail!("node.storage_retention_days is 0 (keep forever), pass --before-slot");
+            }
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+            storage.first_slot_since(now - retention_days as i64 * 86_400)?.ok_or_else(|| {
+                anyhow!("No stored block predates the {} day retention period, pass --before-slot", retention_days)
+            })?
+        }
+    };
+    let stats = storage.prune(before_slot)?;
+    if compact {
+        storage.compact();
+    }
+    println!("{}", serde_json::to_string_pretty(&stats)?);
+    Ok(())
+}
+
+/// `stats`
+pub fn stats(config: &Config, db: Option<&str>) -> Result<()> {
+    let path = db.unwrap_or(&config.storage_path);
+    let storage = Storage::open_read_only(path)?;
+    let keyspaces = storage.get_keyspace_counts()?;
+    let first_slot = storage.get_next_stored_slot(0)?;
+    let latest_slot = storage.get_latest_stored_slot(u64::MAX)?;
+    let stats = json!({
+        "path": path,
+        "size_on_disk_bytes": size_on_disk(Path::new(path))?,
+        "transaction_count": keyspaces.get("tx").copied().unwrap_or(0),
+        "keyspaces": keyspaces,
+        "first_slot": first_slot,
+        "latest_slot": latest_slot,
+        "coverage": storage.get_coverage(0, u64::MAX)?,
+        "first_block_time": first_slot.map(|slot| stored_block_time(&storage, slot)).transpose()?.flatten(),
+        "latest_block_time": latest_slot.map(|slot| stored_block_time(&storage, slot)).transpose()?.flatten(),
+        "cluster": storage.get_cluster_identity()?,
+    });
+    println!("{}", serde_json::to_string_pretty(&stats)?);
+    Ok(())
+}
+
+/// Block time of `slot`, as recorded with its stored transactions
+fn stored_block_time(storage: &Storage, slot: u64) -> Result<Option<i64>> {
+    let page = storage.get_slot_signatures(slot, None, 1)?;
+    match page.items.first() {
+        Some(signature) => Ok(storage.get_transaction(signature)?.and_then(|tx| tx.transaction.block_time)),
+        None => Ok(None),
+    }
+}
+
+/// Bytes taken by the files under a directory
+fn size_on_disk(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() { size_on_disk(&entry.path())? } else { metadata.len() };
+    }
+    Ok(size)
+}
+
+/// `keygen`
+pub fn keygen(config: &Config, out: Option<&str>, force: bool) -> Result<()> {
+    let path = out
+        .or(config.identity_keypair_path().map(String::as_str))
+        .context("Pass --out, or set identity_keypair_path under [node] or [gossip]")?;
+    if Path::new(path).exists() && !force {
+        bail!("{} already exists, pass --force to overwrite it", path);
+    }
+    let keypair = Keypair::new();
+    write_keypair_file(&keypair, path).map_err(|e| anyhow!("Failed to write {}: {}", path, e))?;
+    println!("Wrote keypair {} to {}", keypair.pubkey(), path);
+    Ok(())
+}
+
+/// `config validate`: the configuration as the node would load it, presets and flags included
+pub fn validate_config(source: &ConfigSource) -> Result<()> {
+    let path = &source.path;
+    if !Path::new(path).exists() {
+        bail!("{} does not exist; pass --config, or run the node once to write the defaults there", path);
+    }
+    let config = source.load_existing()?;
+    let problems = config_check::check(&config);
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    let errors = problems.iter().filter(|problem| problem.severity == Severity::Error).count();
+    let warnings = problems.len() - errors;
+    if errors > 0 {
+        bail!("{} is invalid: {} errors, {} warnings", path, errors, warnings);
+    }
+    println!("{} is valid ({} warnings)", path, warnings);
+    Ok(())
+}
+
+/// `verify`: everything the node checks as it starts, without starting it or writing to storage
+pub async fn verify(network: &str, config: &Config, enable_gossip: bool) -> Result<()> {
+    config_check::ensure_valid(config)?;
+    TransactionProcessor::new(config).context("Processing configuration (filters, plugins, IDLs, labels)")?;
+    api::auth::ApiKeys::load(&config.api).context("API keys")?;
+    Notifier::new(&config.notifications, Arc::new(Metrics::default()))?;
+    println!("Configuration: ok");
+    
+    if let Some(path) = config.identity_keypair_path() {
+        let keypair = read_keypair_file(path).map_err(|e| anyhow!("Failed to read identity keypair {}: {}", path, e))?;
+        println!("Identity: {}", keypair.pubkey());
+    }
+    
+    if !Path::new(&config.storage_path).exists() {
+        bail!("No storage at {}; run creates it", config.storage_path);
+    }
+    // Read-only, so a running node's lock does not get in the way
+    let storage = Storage::open_read_only(&config.storage_path)?;
+    storage.check()?;
+    Webhooks::new(&config.webhooks, storage.clone(), Arc::new(Metrics::default()))?;
+    println!("Storage: {} ok", config.storage_path);
+    
+    let joins_gossip = enable_gossip || config.gossip.is_used();
+    cluster_identity::validate(network, config, Some(&storage), joins_gossip, false).await?;
+    println!("Cluster: {} ok", network);
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use solana_gossip::{cluster_info::ClusterInfo, crds::Cursor};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::decoder::vote;
+use crate::gossip::ClusterNode;
+use crate::validator_registry::ValidatorRegistry;
+
+/// Slots behind the newest gossip vote within which a validator counts as keeping up
+const PROPAGATION_SLOTS: u64 = 32;
+
+/// Validators this far behind are forgotten rather than counted as lagging
+const FORGET_AFTER_SLOTS: u64 = 1_000;
+
+/// Score drop between evaluations reported as degradation
+const SCORE_DROP: u8 = 10;
+
+// Thresholds below which a component is reported as an issue
+const MIN_PEER_SHARE: f64 = 0.5;
+const MIN_MAJORITY_VERSION_SHARE: f64 = 0.33;
+const MAX_DELINQUENT_STAKE_SHARE: f64 = 0.05;
+const MIN_VOTING_SHARE: f64 = 0.8;
+
+/// A health component past its threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthIssue {
+    /// Far fewer peers visible than at the peak
+    PeerLoss,
+    /// No version runs on a clear majority
+    VersionSplit,
+    Delinquency,
+    /// Many validators' gossip votes lag the cluster's newest
+    VoteLag,
+}
+
+/// Cluster health as observed through gossip. Shares are in [0, 1]; components that could not
+/// be observed (no versions, stakes or votes yet) are left out of the score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterHealth {
+    /// Unix time in milliseconds of the evaluation
+    pub timestamp: u64,
+    /// 0 (unusable) to 100 (healthy)
+    pub score: u8,
+    pub peers: usize,
+    /// Most peers seen since startup
+    pub peak_peers: usize,
+    pub majority_version: Option<String>,
+    /// Stake (or, without stakes, node) share running the most common version
+    pub majority_version_share: Option<f64>,
+    pub delinquent_stake_share: Option<f64>,
+    /// Share of validators voting in gossip whose newest vote is close to the cluster's
+    pub voting_share: Option<f64>,
+    /// Components past their thresholds
+    pub issues: Vec<HealthIssue>,
+}
+
+/// Tracks gossip observations between evaluations
+#[derive(Default)]
+pub struct ClusterHealthTracker {
+    vote_cursor: Cursor,
+    /// Newest slot each vote account voted on in gossip
+    latest_votes: HashMap<String, u64>,
+    peak_peers: usize,
+    previous: Option<ClusterHealth>,
+}
+
+impl ClusterHealthTracker {
+    /// Take in the votes pushed since the last call
+    pub fn observe_votes(&mut self, cluster_info: &ClusterInfo) {
+        for transaction in cluster_info.get_votes(&mut self.vote_cursor) {
+            for event in vote::decode_unlanded(&transaction) {
+                if let Some(voted_slot) = event.voted_slot() {
+                    let latest = self.latest_votes.entry(event.vote_account).or_default();
+                    *latest = (*latest).max(voted_slot);
+                }
+            }
+        }
+    }
+    
+    /// Score the cluster from the current peers, logging degradation and recovery
+    pub fn evaluate(&mut self, nodes: &[ClusterNode], validators: &ValidatorRegistry, timestamp: u64) -> ClusterHealth {
+        self.peak_peers = self.peak_peers.max(nodes.len());
+        let peer_share = match self.peak_peers {
+            0 => 0.0,
+            peak => nodes.len() as f64 / peak as f64,
+        };
+        
+        // Weigh versions by stake once stakes are known
+        let mut versions: HashMap<&str, u64> = HashMap::new();
+        let weighted = nodes.iter().any(|node| node.activated_stake > 0);
+        for node in nodes {
+            if let Some(version) = &node.version {
+                *versions.entry(version.as_str()).or_default() += if weighted { node.activated_stake } else { 1 };
+            }
+        }
+        let version_total: u64 = versions.values().sum();
+        let majority = versions.into_iter().max_by_key(|(_, weight)| *weight);
+        let majority_version_share = majority
+            .filter(|_| version_total > 0)
+            .map(|(_, weight)| weight as f64 / version_total as f64);
+        
+        let (total_stake, delinquent_stake) = validators.stake_totals();
+        let delinquent_stake_share = match total_stake {
+            0 => None,
+            total => Some(delinquent_stake as f64 / total as f64),
+        };
+        
+        let voting_share = self.latest_votes.values().max().copied().map(|newest| {
+            self.latest_votes.retain(|_, slot| *slot + FORGET_AFTER_SLOTS >= newest);
+            let keeping_up = self.latest_votes
+                .values()
+                .filter(|slot| **slot + PROPAGATION_SLOTS >= newest)
+                .count();
+            keeping_up as f64 / self.latest_votes.len() as f64
+        });
+        
+        let mut issues = Vec::new();
+        if peer_share < MIN_PEER_SHARE {
+            issues.push(HealthIssue::PeerLoss);
+        }
+        if majority_version_share.map_or(false, |share| share < MIN_MAJORITY_VERSION_SHARE) {
+            issues.push(HealthIssue::VersionSplit);
+        }
+        if delinquent_stake_share.map_or(false, |share| share > MAX_DELINQUENT_STAKE_SHARE) {
+            issues.push(HealthIssue::Delinquency);
+        }
+        if voting_share.map_or(false, |share| share < MIN_VOTING_SHARE) {
+            issues.push(HealthIssue::VoteLag);
+        }
+        
+        // Weighted mean of the observed components
+        let components = [
+            (Some(peer_share), 0.2),
+            (majority_version_share, 0.1),
+            (delinquent_stake_share.map(|share| 1.0 - share), 0.4),
+            (voting_share, 0.3),
+        ];
+        let (sum, weights) = components
+            .iter()
+            .filter_map(|(value, weight)| value.map(|value| (value * weight, *weight)))
+            .fold((0.0, 0.0), |(sum, weights), (value, weight)| (sum + value, weights + weight));
+        let score = (sum / weights * 100.0).round().clamp(0.0, 100.0) as u8;
+        
+        let health = ClusterHealth {
+            timestamp,
+            score,
+            peers: nodes.len(),
+            peak_peers: self.peak_peers,
+            majority_version: majority.map(|(version, _)| version.to_string()),
+            majority_version_share,
+            delinquent_stake_share,
+            voting_share,
+            issues,
+        };
+        self.report(&health);
+        self.previous = Some(health.clone());
+        health
+    }
+    
+    /// Log when issues appear or change, or the score drops sharply, and when they clear
+    fn report(&self, health: &ClusterHealth) {
+        let (previous_score, previous_issues) = match &self.previous {
+            Some(previous) => (previous.score, previous.issues.as_slice()),
+            None => (100, &[][..]),
+        };
+        
+        if health.issues.is_empty() {
+            if !previous_issues.is_empty() {
+                info!("Cluster health recovered: score {}", health.score);
+            }
+        } else if health.issues != previous_issues || health.score + SCORE_DROP <= previous_score {
+            let details: Vec<String> = health.issues.iter().map(|issue| health.describe(*issue)).collect();
+            warn!(
+                "Cluster health degraded: score {} (was {}): {}",
+                health.score,
+                previous_score,
+                details.join("; ")
+            );
+        }
+    }
+}
+
+impl ClusterHealth {
+    fn describe(&self, issue: HealthIssue) -> String {
+        let percent = |share: Option<f64>| share.unwrap_or_default() * 100.0;
+        match issue {
+            HealthIssue::PeerLoss => format!("only {} of {} peers visible", self.peers, self.peak_peers),
+            HealthIssue::VersionSplit => format!(
+                "most common version {} runs on only {:.0}%",
+                self.majority_version.as_deref().unwrap_or("unknown"),
+                percent(self.majority_version_share)
+            ),
+            HealthIssue::Delinquency => format!("{:.1}% of stake delinquent", percent(self.delinquent_stake_share)),
+            HealthIssue::VoteLag => format!(
+                "only {:.0}% of validators voting on recent slots",
+                percent(self.voting_share)
+            ),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Startup checks that the configured network, entrypoints, RPC endpoints and storage all belong
+//! to the same cluster
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::gossip::resolve_entrypoint;
+use crate::storage::Storage;
+
+/// Genesis hashes of the public clusters
+const KNOWN_CLUSTERS: &[(&str, &str)] = &[
+    ("mainnet-beta", "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d"),
+    ("testnet", "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY"),
+    ("devnet", "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG"),
+];
+
+/// The cluster a storage directory holds data for, recorded when it is first used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterIdentity {
+    pub network: String,
+    pub genesis_hash: Option<String>,
+}
+
+/// Refuse to start when the entrypoints, RPC endpoints or storage belong to a different cluster
+/// than `network`, or the entrypoints disagree among themselves. Entrypoints are only checked
+/// when `joins_gossip`, and endpoints that cannot be reached are skipped with a warning. The
+/// cluster is recorded in `storage` only when `record`, so a read-only check leaves it untouched.
+pub async fn validate(
+    network: &str,
+    config: &Config,
+    storage: Option<&Storage>,
+    joins_gossip: bool,
+    record: bool,
+) -> Result<()> {
+    let expected_genesis = genesis_hash_of(network);
+    // A local test validator gets a new genesis hash every time its ledger is reset
+    if expected_genesis.is_none() && network != "localhost" {
+        warn!("Unknown network {}; its genesis hash cannot be checked", network);
+    }
+    
+    if joins_gossip {
+        // Asking the entrypoints for their shred version blocks on each one in turn
+        let (network_name, config_clone) = (network.to_string(), config.clone());
+        tokio::task::spawn_blocking(move || check_entrypoints(&network_name, &config_clone)).await??;
+    }
+    
+    let genesis_hash = rpc_genesis_hash(config).await;
+    if let (Some((endpoint, genesis_hash)), Some(expected)) = (&genesis_hash, expected_genesis) {
+        if genesis_hash != expected {
+            bail!(
+                "RPC endpoint {} serves {} (genesis hash {}), but the network is {}. \
+                 Point network.rpc_endpoints at {} or pass --network {}",
+                endpoint,
+                cluster_name(genesis_hash),
+                genesis_hash,
+                network,
+                network,
+                cluster_name(genesis_hash)
+            );
+        }
+    }
+    
+    if let Some(storage) = storage {
+        let genesis_hash = genesis_hash.map(|(_, genesis_hash)| genesis_hash);
+        check_storage(network, genesis_hash, config, storage, record)?;
+    }
+    Ok(())
+}
+
+/// Check entrypoint host names for a different public cluster, and that all entrypoints
+/// answering an IP echo request (and `gossip.shred_version`, if set) agree on the shred version
+fn check_entrypoints(network: &str, config: &Config) -> Result<()> {
+    let mut shred_versions: BTreeMap<u16, Vec<&str>> = BTreeMap::new();
+    for entrypoint in config.gossip_entrypoints() {
+        let host = entrypoint.rsplit_once(':').map_or(entrypoint.as_str(), |(host, _)| host);
+        for (cluster, _) in KNOWN_CLUSTERS {
+            if *cluster != network && host.ends_with(&format!(".{}.solana.com", cluster)) {
+                bail!(
+                    "Gossip entrypoint {} belongs to {}, but the network is {}. \
+                     Use {} entrypoints or pass --network {}",
+                    entrypoint,
+                    cluster,
+                    network,
+                    network,
+                    cluster
+                );
+            }
+        }
+        
+        let addr = match resolve_entrypoint(entrypoint) {
+            Ok(addr) => addr,
+            Err(_) => continue,
+        };
+        match solana_net_utils::get_cluster_shred_version(&addr) {
+            Ok(shred_version) => shred_versions.entry(shred_version).or_default().push(entrypoint),
+            Err(e) => warn!("Could not check the shred version of entrypoint {}: {}", entrypoint, e),
+        }
+    }
+    
+    if shred_versions.len() > 1 {
+        let groups: Vec<String> = shred_versions
+            .iter()
+            .map(|(shred_version, entrypoints)| format!("{} from {}", shred_version, entrypoints.join(", ")))
+            .collect();
+        bail!(
+            "Gossip entrypoints belong to different clusters (shred versions {}); \
+             keep only entrypoints of the {} cluster",
+            groups.join("; "),
+            network
+        );
+    }
+    if let (Some((&cluster_version, entrypoints)), Some(configured)) =
+        (shred_versions.iter().next(), config.gossip.shred_version)
+    {
+        if cluster_version != configured {
+            bail!(
+                "gossip.shred_version is {}, but entrypoint {} reports shred version {}. \
+                 Remove gossip.shred_version to use the cluster's",
+                configured,
+                entrypoints[0],
+                cluster_version
+            );
+        }
+    }
+    if let Some(shred_version) = shred_versions.keys().next() {
+        info!("Gossip entrypoints agree on shred version {}", shred_version);
+    }
+    Ok(())
+}
+
+/// Genesis hash reported by the first RPC endpoint that answers, with that endpoint
+async fn rpc_genesis_hash(config: &Config) -> Option<(String, String)> {
+    for endpoint in &config.network.rpc_endpoints {
+        match RpcClient::new(endpoint.clone()).get_genesis_hash().await {
+            Ok(genesis_hash) => return Some((endpoint.clone(), genesis_hash.to_string())),
+            Err(e) => warn!("Could not get the genesis hash from {}: {}", endpoint, e),
+        }
+    }
+    None
+}
+
+/// Check `storage` was created for this cluster, recording the cluster if it is new and `record`
+fn check_storage(
+    network: &str,
+    genesis_hash: Option<String>,
+    config: &Config,
+    storage: &Storage,
+    record: bool,
+) -> Result<()> {
+    let stored = match storage.get_cluster_identity()? {
+        Some(stored) => stored,
+        None if !record => return Ok(()),
+        None => {
+            info!("Storage at {} recorded as {} data", config.storage_path, network);
+            return storage.store_cluster_identity(&ClusterIdentity {
+                network: network.to_string(),
+                genesis_hash,
+            });
+        }
+    };
+    
+    // Genesis hashes decide when both are known, so renamed or custom clusters still match
+    let same_cluster = match (&stored.genesis_hash, &genesis_hash) {
+        (Some(stored), Some(current)) => stored == current,
+        _ => stored.network == network,
+    };
+    if !same_cluster {
+        bail!(
+            "Storage at {} holds {} data, but the network is {}. \
+             Give each cluster its own storage_path",
+            config.storage_path,
+            stored.network,
+            network
+        );
+    }
+    
+    // Fill in a genesis hash that was unavailable when the cluster was recorded
+    if record && stored.genesis_hash.is_none() && genesis_hash.is_some() {
+        storage.store_cluster_identity(&ClusterIdentity { genesis_hash, ..stored })?;
+    }
+    Ok(())
+}
+
+fn genesis_hash_of(network: &str) -> Option<&'static str> {
+    KNOWN_CLUSTERS
+        .iter()
+        .find(|(cluster, _)| *cluster == network)
+        .map(|(_, genesis_hash)| *genesis_hash)
+}
+
+/// Name of the public cluster with this genesis hash, or "an unknown cluster"
+fn cluster_name(genesis_hash: &str) -> &'static str {
+    KNOWN_CLUSTERS
+        .iter()
+        .find(|(_, hash)| *hash == genesis_hash)
+        .map_or("an unknown cluster", |(cluster, _)| *cluster)
+}
@@ -0,0 +1,482 @@
+//! Checks of a parsed configuration that need no network: URL schemes, addresses and ports,
+//! files and directories, and settings contradicting each other. Every problem is reported at
+//! once, named by its TOML path, instead of the first one failing a task after startup.
+
+use anyhow::{bail, Result};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use tracing::warn;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{ChatService, Config};
+use crate::filter_expr::Expr;
+use crate::storage::EVENT_TYPES;
+
+/// Compression codecs librdkafka accepts for `kafka.compression`
+const KAFKA_COMPRESSIONS: [&str; 5] = ["none", "gzip", "snappy", "lz4", "zstd"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The node would fail, or do something other than configured
+    Error,
+    /// Allowed, but likely a mistake
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub severity: Severity,
+    /// TOML path of the setting, e.g. `network.rpc_endpoints[0]`
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.field, self.message)
+    }
+}
+
+/// Every problem found in `config`, errors and warnings in the order of the configuration
+pub fn check(config: &Config) -> Vec<Problem> {
+    let mut checks = Checks::default();
+    check_network(&mut checks, config);
+    check_node(&mut checks, config);
+    check_filters(&mut checks, config);
+    check_gossip(&mut checks, config);
+    check_api(&mut checks, config);
+    check_sinks(&mut checks, config);
+    checks.problems
+}
+
+/// Log the warnings in `config` and fail on its errors, listing them all
+pub fn ensure_valid(config: &Config) -> Result<()> {
+    let problems = check(config);
+    let mut errors = Vec::new();
+    for problem in problems {
+        match problem.severity {
+            Severity::Error => errors.push(problem.to_string()),
+            Severity::Warning => warn!("Configuration {}", problem),
+        }
+    }
+    if !errors.is_empty() {
+        bail!("Invalid configuration:\n  {}", errors.join("\n  "));
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Checks {
+    problems: Vec<Problem>,
+}
+
+impl Checks {
+    fn error(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.problems.push(Problem { severity: Severity::Error, field: field.into(), message: message.into() });
+    }
+    
+    fn warning(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.problems.push(Problem { severity: Severity::Warning, field: field.into(), message: message.into() });
+    }
+    
+    /// `value` must be a URL with one of `schemes`
+    fn url(&mut self, field: &str, value: &str, schemes: &[&str]) {
+        match reqwest::Url::parse(value) {
+            Ok(url) if !schemes.contains(&url.scheme()) => self.error(
+                field,
+                format!("{} uses {}://, expected {}", value, url.scheme(), expected_schemes(schemes)),
+            ),
+            Ok(url) if url.host_str().is_none() => self.error(field, format!("{} has no host", value)),
+            Ok(_) => {}
+            Err(e) => self.error(
+                field,
+                format!("{} is not a valid URL ({}), expected e.g. {}://host", value, e, schemes[0]),
+            ),
+        }
+    }
+    
+    /// `value` must be `host:port` with a non-zero port
+    fn host_port(&mut self, field: &str, value: &str) {
+        match value.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() => {
+                if !matches!(port.parse::<u16>(), Ok(port) if port > 0) {
+                    self.error(field, format!("{} has port {}, expected 1-65535", value, port));
+                }
+            }
+            _ => self.error(field, format!("{} is not host:port", value)),
+        }
+    }
+    
+    /// Every entry of `values` must be a base58 public key
+    fn pubkeys(&mut self, field: &str, values: &[String]) {
+        for (index, value) in values.iter().enumerate() {
+            if value.parse::<Pubkey>().is_err() {
+                self.error(format!("{}[{}]", field, index), format!("{} is not a base58 address", value));
+            }
+        }
+    }
+    
+    /// `path` must be an existing file
+    fn file(&mut self, field: &str, path: &str, hint: &str) {
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => {}
+            Ok(_) => self.error(field, format!("{} is not a file", path)),
+            Err(e) => self.error(field, format!("Cannot read {}: {}{}", path, e, hint)),
+        }
+    }
+    
+    /// Files can be created in `path`, or in the nearest existing directory above it when it
+    /// does not exist yet
+    fn writable_dir(&mut self, field: &str, path: &str) {
+        let existing = Path::new(path).ancestors().find(|dir| dir.as_os_str().is_empty() || dir.exists());
+        let dir = match existing {
+            Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+            Some(dir) => dir,
+            None => Path::new("/"),
+        };
+        if !dir.is_dir() {
+            self.error(field, format!("{} is not a directory", dir.display()));
+            return;
+        }
+        let probe = dir.join(".solana-node-write-check");
+        match fs::write(&probe, b"") {
+            Ok(()) => {
+                let _ = fs::remove_file(&probe);
+            }
+            Err(e) => self.error(field, format!("Cannot create files in {}: {}", dir.display(), e)),
+        }
+    }
+    
+    /// No entry may be in both lists
+    fn disjoint(&mut self, field: &str, include: &[String], other_field: &str, exclude: &[String]) {
+        let include: BTreeSet<&String> = include.iter().collect();
+        let both: Vec<&str> = exclude.iter().filter(|value| include.contains(value)).map(String::as_str).collect();
+        if !both.is_empty() {
+            let message = format!("{} also listed in {}; remove them from one of the two", both.join(", "), other_field);
+            self.error(field, message);
+        }
+    }
+}
+
+fn expected_schemes(schemes: &[&str]) -> String {
+    let schemes: Vec<String> = schemes.iter().map(|scheme| format!("{}://", scheme)).collect();
+    schemes.join(" or ")
+}
+
+fn check_network(checks: &mut Checks, config: &Config) {
+    let network = &config.network;
+    for (index, url) in network.rpc_endpoints.iter().enumerate() {
+        checks.url(&format!("network.rpc_endpoints[{}]", index), url, &["https", "http"]);
+    }
+    for (index, url) in network.websocket_endpoints.iter().enumerate() {
+        checks.url(&format!("network.websocket_endpoints[{}]", index), url, &["wss", "ws"]);
+    }
+    for (index, entrypoint) in network.gossip_entrypoints.iter().enumerate() {
+        checks.host_port(&format!("network.gossip_entrypoints[{}]", index), entrypoint);
+    }
+    if network.max_connections == 0 {
+        checks.error("network.max_connections", "Must be at least 1");
+    }
+    if network.websocket_endpoints.is_empty() && !config.gossip.tvu {
+        checks.warning(
+            "network.websocket_endpoints",
+            "Empty and gossip.tvu is off, so no transactions are ingested; add an endpoint or enable gossip.tvu",
+        );
+    }
+    if network.rpc_endpoints.is_empty() {
+        checks.warning("network.rpc_endpoints", "Empty; backfills, block tracking and the cluster check need one");
+    }
+}
+
+fn check_node(checks: &mut Checks, config: &Config) {
+    let node = &config.node;
+    if config.storage_path.is_empty() {
+        checks.error("storage_path", "Must not be empty");
+    } else {
+        checks.writable_dir("storage_path", &config.storage_path);
+    }
+    if let Some(path) = &node.identity_keypair_path {
+        checks.file("node.identity_keypair_path", path, "; create one with `solana-node keygen`");
+    }
+    for (index, path) in node.anchor_idls.iter().enumerate() {
+        checks.file(&format!("node.anchor_idls[{}]", index), path, "");
+    }
+    if let Some(path) = &node.address_labels {
+        checks.file("node.address_labels", path, "");
+    }
+    checks.pubkeys("node.watchlist", &node.watchlist);
+    if node.processing_workers == 0 {
+        checks.warning("node.processing_workers", "0 runs a single worker");
+    }
+    if let Some(level) = &node.log_level {
+        if let Err(e) = EnvFilter::try_new(level) {
+            checks.error("node.log_level", format!("Invalid log filter: {}", e));
+        }
+    }
+    for (index, plugin) in config.plugins.iter().enumerate() {
+        checks.file(&format!("plugins[{}].path", index), &plugin.path, "");
+        if plugin.fuel_limit == 0 {
+            checks.error(format!("plugins[{}].fuel_limit", index), "Must be at least 1");
+        }
+        if plugin.memory_limit_mb == 0 {
+            checks.error(format!("plugins[{}].memory_limit_mb", index), "Must be at least 1");
+        }
+    }
+    for program_id in config.program_registry.keys() {
+        if program_id.parse::<Pubkey>().is_err() {
+            checks.error(format!("program_registry.{}", program_id), "Key is not a base58 program ID");
+        }
+    }
+}
+
+fn check_filters(checks: &mut Checks, config: &Config) {
+    let filters = &config.filters;
+    checks.pubkeys("filters.include_programs", &filters.include_programs);
+    checks.pubkeys("filters.exclude_programs", &filters.exclude_programs);
+    checks.pubkeys("filters.include_accounts", &filters.include_accounts);
+    checks.pubkeys("filters.spam_blocklist", &filters.spam_blocklist);
+    checks.pubkeys("filters.spam_allowlist", &filters.spam_allowlist);
+    checks.disjoint(
+        "filters.include_programs",
+        &filters.include_programs,
+        "filters.exclude_programs",
+        &filters.exclude_programs,
+    );
+    checks.disjoint(
+        "filters.include_categories",
+        &filters.include_categories,
+        "filters.exclude_categories",
+        &filters.exclude_categories,
+    );
+    checks.disjoint(
+        "filters.include_labels",
+        &filters.include_labels,
+        "filters.exclude_labels",
+        &filters.exclude_labels,
+    );
+    checks.disjoint(
+        "filters.spam_blocklist",
+        &filters.spam_blocklist,
+        "filters.spam_allowlist",
+        &filters.spam_allowlist,
+    );
+    if let Some(expression) = &filters.expression {
+        if let Err(e) = Expr::parse(expression) {
+            checks.error("filters.expression", format!("{:#}", e));
+        }
+    }
+    if filters.multisig_only && filters.durable_nonce_only {
+        checks.warning(
+            "filters.multisig_only",
+            "Set together with filters.durable_nonce_only, so only multisig durable nonce transactions are stored",
+        );
+    }
+    if !filters.spam_allowlist.is_empty() && !filters.drop_spam {
+        checks.warning("filters.drop_spam", "Off, so filters.spam_allowlist has no effect");
+    }
+}
+
+fn check_gossip(checks: &mut Checks, config: &Config) {
+    let gossip = &config.gossip;
+    let bind_address = gossip.bind_address.parse::<SocketAddr>();
+    if bind_address.is_err() {
+        checks.error("gossip.bind_address", format!("{} is not ip:port, e.g. 0.0.0.0:8001", gossip.bind_address));
+    }
+    match solana_net_utils::parse_port_range(&gossip.port_range) {
+        Some((start, end)) => {
+            if start == 0 {
+                checks.error("gossip.port_range", "Starts at port 0, expected 1-65535");
+            } else if gossip.tvu && end - start < 3 {
+                let message = format!("{} holds too few ports for gossip, TVU and repair", gossip.port_range);
+                checks.error("gossip.port_range", message);
+            }
+        }
+        None => checks.error(
+            "gossip.port_range",
+            format!("{} is not start-end with start <= end, e.g. 8000-10000", gossip.port_range),
+        ),
+    }
+    if let Some(public_ip) = &gossip.public_ip {
+        if public_ip.parse::<IpAddr>().is_err() {
+            checks.error("gossip.public_ip", format!("{} is not an IP address", public_ip));
+        }
+    }
+    for (index, entrypoint) in gossip.entrypoints.iter().enumerate() {
+        checks.host_port(&format!("gossip.entrypoints[{}]", index), entrypoint);
+    }
+    if let Some(path) = &gossip.identity_keypair_path {
+        checks.file("gossip.identity_keypair_path", path, "; create one with `solana-node keygen --out`");
+    }
+    if let Some(path) = &gossip.geoip_country_db {
+        checks.file("gossip.geoip_country_db", path, "");
+    }
+    if let Some(path) = &gossip.geoip_asn_db {
+        checks.file("gossip.geoip_asn_db", path, "");
+    }
+    if gossip.crds_export_interval_secs > 0 {
+        checks.writable_dir("gossip.crds_export_dir", &gossip.crds_export_dir);
+    }
+    for (index, identity) in gossip.rpc_allowlist.iter().enumerate() {
+        if identity.parse::<Pubkey>().is_err() {
+            checks.error(format!("gossip.rpc_allowlist[{}]", index), format!("{} is not a node identity", identity));
+        }
+    }
+    if gossip.discover_rpc && gossip.rpc_allowlist.is_empty() {
+        checks.warning("gossip.rpc_allowlist", "Empty, so gossip.discover_rpc finds no RPC endpoints");
+    }
+    if gossip.tvu && config.network.rpc_endpoints.is_empty() {
+        checks.error(
+            "gossip.tvu",
+            "Needs an entry in network.rpc_endpoints, to fetch the leader schedule shreds are checked against",
+        );
+    }
+    let listens = gossip.tvu || gossip.vote_listener;
+    if listens && config.gossip_entrypoints().is_empty() && gossip.shred_version.is_none() {
+        checks.warning(
+            "gossip.shred_version",
+            "Unset and there is no entrypoint to ask, so no shreds or votes are received",
+        );
+    }
+}
+
+fn check_api(checks: &mut Checks, config: &Config) {
+    let api = &config.api;
+    if !api.enabled {
+        if api.tls.is_some() || api.grpc_port.is_some() {
+            checks.warning("api.enabled", "Off, so api.tls and api.grpc_port have no effect");
+        }
+        return;
+    }
+    if api.bind_address.parse::<IpAddr>().is_err() {
+        checks.error("api.bind_address", format!("{} is not an IP address, e.g. 127.0.0.1", api.bind_address));
+    }
+    if config.node.listen_port == 0 {
+        checks.error("node.listen_port", "Must be 1-65535 to serve the API");
+    }
+    match api.grpc_port {
+        Some(0) => checks.error("api.grpc_port", "Must be 1-65535"),
+        Some(port) if port == config.node.listen_port => {
+            checks.error("api.grpc_port", format!("Same as node.listen_port ({}); pick another port", port))
+        }
+        _ => {}
+    }
+    if let Some(tls) = &api.tls {
+        checks.file("api.tls.cert_path", &tls.cert_path, "");
+        checks.file("api.tls.key_path", &tls.key_path, "");
+    }
+    if let Some(path) = &api.keys_file {
+        checks.file("api.keys_file", path, "");
+    }
+    for (key, key_config) in &api.keys {
+        if key_config.scopes.is_empty() {
+            checks.warning(format!("api.keys.{}.scopes", key), "Empty, so every request with this key is refused");
+        }
+    }
+    let rate_limits = std::iter::once(("api.rate_limit".to_string(), api.rate_limit)).chain(
+        api.keys
+            .iter()
+            .filter_map(|(key, key_config)| Some((format!("api.keys.{}.rate_limit", key), key_config.rate_limit?))),
+    );
+    for (field, rate_limit) in rate_limits {
+        if rate_limit.requests_per_second.is_nan() || rate_limit.requests_per_second < 0.0 {
+            checks.error(format!("{}.requests_per_second", field), "Must be 0 (unlimited) or more");
+        } else if rate_limit.requests_per_second > 0.0 && rate_limit.burst == 0 {
+            checks.error(format!("{}.burst", field), "Must be at least 1 when requests are limited");
+        }
+    }
+    let origins = &api.cors.allowed_origins;
+    for (index, origin) in origins.iter().enumerate() {
+        if origin == "*" {
+            if origins.len() > 1 {
+                checks.warning("api.cors.allowed_origins", "Lists * alongside other origins, so any origin is allowed");
+            }
+        } else {
+            checks.url(&format!("api.cors.allowed_origins[{}]", index), origin, &["https", "http"]);
+        }
+    }
+    if let Some(url) = &api.upstream_rpc {
+        checks.url("api.upstream_rpc", url, &["https", "http"]);
+    }
+}
+
+fn check_sinks(checks: &mut Checks, config: &Config) {
+    for (index, webhook) in config.webhooks.iter().enumerate() {
+        let field = format!("webhooks[{}]", index);
+        checks.url(&format!("{}.url", field), &webhook.url, &["https", "http"]);
+        checks.pubkeys(&format!("{}.programs", field), &webhook.programs);
+        checks.pubkeys(&format!("{}.accounts", field), &webhook.accounts);
+        for event in webhook.events.iter().filter(|event| !EVENT_TYPES.contains(&event.as_str())) {
+            checks.error(
+                format!("{}.events", field),
+                format!("Unknown event type {}; expected one of {}", event, EVENT_TYPES.join(", ")),
+            );
+        }
+        if webhook.max_attempts == 0 {
+            checks.error(format!("{}.max_attempts", field), "Must be at least 1");
+        }
+        if webhook.timeout_secs == 0 {
+            checks.error(format!("{}.timeout_secs", field), "Must be at least 1");
+        }
+    }
+    
+    if let Some(kafka) = &config.kafka {
+        for broker in kafka.brokers.split(',') {
+            checks.host_port("kafka.brokers", broker.trim());
+        }
+        if !KAFKA_COMPRESSIONS.contains(&kafka.compression.as_str()) {
+            checks.error(
+                "kafka.compression",
+                format!("Unknown codec {}; expected one of {}", kafka.compression, KAFKA_COMPRESSIONS.join(", ")),
+            );
+        }
+        if kafka.events_topic.as_ref() == Some(&kafka.transactions_topic) {
+            checks.error("kafka.events_topic", "Same as kafka.transactions_topic; pick another topic");
+        }
+    }
+    if let Some(nats) = &config.nats {
+        checks.url("nats.url", &nats.url, &["nats", "tls", "ws", "wss"]);
+        if let Some(path) = &nats.credentials_file {
+            checks.file("nats.credentials_file", path, "");
+        }
+    }
+    if let Some(redis) = &config.redis {
+        checks.url("redis.url", &redis.url, &["redis", "rediss"]);
+        let program_ids: Vec<String> = redis.program_streams.keys().cloned().collect();
+        checks.pubkeys("redis.program_streams", &program_ids);
+    }
+    if let Some(amqp) = &config.amqp {
+        checks.url("amqp.url", &amqp.url, &["amqp", "amqps"]);
+    }
+    
+    for (index, notification) in config.notifications.iter().enumerate() {
+        let field = format!("notifications[{}]", index);
+        match notification.service {
+            ChatService::Slack | ChatService::Discord => {
+                match &notification.webhook_url {
+                    Some(url) => checks.url(&format!("{}.webhook_url", field), url, &["https", "http"]),
+                    None => checks.error(&field, "Slack and Discord need webhook_url"),
+                }
+                if notification.bot_token.is_some() || notification.chat_id.is_some() {
+                    checks.error(&field, "bot_token and chat_id are for Telegram; Slack and Discord use webhook_url");
+                }
+            }
+            ChatService::Telegram => {
+                if notification.bot_token.is_none() || notification.chat_id.is_none() {
+                    checks.error(&field, "Telegram needs bot_token and chat_id");
+                }
+                if notification.webhook_url.is_some() {
+                    checks.error(&field, "webhook_url is for Slack and Discord; Telegram uses bot_token and chat_id");
+                }
+            }
+        }
+        checks.pubkeys(&format!("{}.addresses", field), &notification.addresses);
+    }
+}
@@ -0,0 +1,211 @@
+//! Applying changes to the configuration while the node runs, on SIGHUP, when the file changes
+//! (`node.watch_config`) and on `POST /admin/reload`
+//!
+//! The settings of `RELOADABLE` take effect right away, for transactions processed from then on.
+//! Changes to any other setting are logged as needing a restart, on every reload until then, and
+//! the running values stay in effect. Unlike at startup, a missing file is not written with the
+//! defaults: the reload fails and the running configuration stays in effect.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+use toml::Value;
+use tracing::{error, info, warn};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::api::rate_limit::RateLimiter;
+use crate::config::{Config, ConfigSource};
+use crate::config_check;
+use crate::network::IngestionControl;
+
+/// Log filter used unless `RUST_LOG` or `node.log_level` set one
+pub const DEFAULT_LOG_LEVEL: &str = "solana_node=info";
+
+/// Settings applied on reload, with everything below them
+const RELOADABLE: &[&str] = &[
+    "filters",
+    "node.watchlist",
+    "node.anchor_idls",
+    "node.address_labels",
+    "node.max_log_messages",
+    "node.log_level",
+    "plugins",
+    "program_registry",
+    "api.rate_limit",
+];
+
+/// Interval between checks of the configuration file for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Handle swapping the filter of the installed log subscriber
+pub type LogFilter = reload::Handle<EnvFilter, Registry>;
+
+/// Settings found changed by a reload, as TOML paths
+#[derive(Debug, Default, Serialize)]
+pub struct ReloadOutcome {
+    /// Changed since the previous reload and now in effect
+    pub applied: Vec<String>,
+    /// Changed since startup, and still running with the values they started with
+    pub restart_required: Vec<String>,
+}
+
+pub struct ConfigReloader {
+    source: ConfigSource,
+    control: IngestionControl,
+    limiter: Arc<RateLimiter>,
+    log_filter: LogFilter,
+    /// Configuration the node started with
+    started: Config,
+    /// Configuration of the latest reload
+    loaded: Mutex<Config>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        source: ConfigSource,
+        config: &Config,
+        control: IngestionControl,
+        limiter: Arc<RateLimiter>,
+        log_filter: LogFilter,
+    ) -> Self {
+        Self {
+            source,
+            control,
+            limiter,
+            log_filter,
+            started: config.clone(),
+            loaded: Mutex::new(config.clone()),
+        }
+    }
+    
+    pub fn path(&self) -> &str {
+        &self.source.path
+    }
+    
+    /// Load the configuration again and apply the settings of `RELOADABLE`. A configuration that
+    /// fails to load or validate, or a missing file, leaves the running one in place. Reads the
+    /// files the settings name, so call it off the async runtime
+    pub fn reload(&self) -> Result<ReloadOutcome> {
+        let mut loaded = self.loaded.lock().unwrap();
+        let config = self.source.load_existing()?;
+        config_check::ensure_valid(&config)?;
+        
+        // Applied whether or not the settings changed, as the files they name may have
+        self.control.reload(&config)?;
+        self.limiter.set_default(config.api.rate_limit);
+        if config.node.log_level != loaded.node.log_level {
+            set_log_level(&self.log_filter, config.node.log_level.as_deref())?;
+        }
+        
+        let outcome = ReloadOutcome {
+            applied: changed_settings(&loaded, &config)?.into_iter().filter(|path| reloadable(path)).collect(),
+            restart_required: changed_settings(&self.started, &config)?
+                .into_iter()
+                .filter(|path| !reloadable(path))
+                .collect(),
+        };
+        for path in &outcome.applied {
+            info!("Configuration {} changed and applied", path);
+        }
+        for path in &outcome.restart_required {
+            warn!("Configuration {} changed, but only takes effect on restart", path);
+        }
+        *loaded = config;
+        Ok(outcome)
+    }
+}
+
+/// Reload on SIGHUP, and when `watch_file` as the configuration file changes, until the signal
+/// stream ends. Failed reloads are logged
+pub async fn watch(reloader: Arc<ConfigReloader>, watch_file: bool) -> Result<()> {
+    let path = reloader.path().to_string();
+    let mut hangups = signal(SignalKind::hangup())?;
+    let mut checks = tokio::time::interval(WATCH_INTERVAL);
+    let mut modified = modified_time(&path);
+    loop {
+        tokio::select! {
+            received = hangups.recv() => {
+                if received.is_none() {
+                    return Ok(());
+                }
+                info!("SIGHUP received, reloading {}", path);
+            }
+            _ = checks.tick(), if watch_file => {
+                // A file being replaced may be missing for a moment
+                let current = modified_time(&path);
+                if current.is_none() || current == modified {
+                    continue;
+                }
+                modified = current;
+                info!("{} changed, reloading it", path);
+            }
+        }
+        let reloading = reloader.clone();
+        match tokio::task::spawn_blocking(move || reloading.reload()).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => error!("Failed to reload {}, keeping the running configuration: {:#}", path, e),
+            Err(e) => error!("Reloading {} panicked: {}", path, e),
+        }
+    }
+}
+
+/// Filter logs by `level` (`DEFAULT_LOG_LEVEL` when unset), unless `RUST_LOG` is set, which takes
+/// precedence
+pub fn set_log_level(log_filter: &LogFilter, level: Option<&str>) -> Result<()> {
+    if std::env::var_os("RUST_LOG").is_some() {
+        if level.is_some() {
+            warn!("RUST_LOG is set, so node.log_level is ignored");
+        }
+        return Ok(());
+    }
+    let level = level.unwrap_or(DEFAULT_LOG_LEVEL);
+    let filter = EnvFilter::try_new(level).with_context(|| format!("Invalid log filter {}", level))?;
+    log_filter.reload(filter)?;
+    info!("Log filter set to {}", level);
+    Ok(())
+}
+
+fn reloadable(path: &str) -> bool {
+    RELOADABLE.iter().any(|setting| path == *setting || path.starts_with(&format!("{}.", setting)))
+}
+
+/// TOML paths of the settings differing between `before` and `after`, lists counting as one
+/// setting
+fn changed_settings(before: &Config, after: &Config) -> Result<Vec<String>> {
+    let mut before_settings = BTreeMap::new();
+    settings(&Value::try_from(before)?, String::new(), &mut before_settings);
+    let mut after_settings = BTreeMap::new();
+    settings(&Value::try_from(after)?, String::new(), &mut after_settings);
+    
+    let mut changed: Vec<String> = before_settings
+        .iter()
+        .filter(|(path, value)| after_settings.get(*path) != Some(*value))
+        .map(|(path, _)| path.clone())
+        .collect();
+    changed.extend(after_settings.keys().filter(|path| !before_settings.contains_key(*path)).cloned());
+    changed.sort();
+    Ok(changed)
+}
+
+/// Collect the settings below `value`, at `path`, into `out`
+fn settings(value: &Value, path: String, out: &mut BTreeMap<String, Value>) {
+    let child = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                settings(value, child(key), out);
+            }
+        }
+        _ => {
+            out.insert(path, value.clone());
+        }
+    }
+}
+
+/// Modification time of the file at `path`, if it can be read
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,94 @@
+//! JSON dumps of the gossip table for offline analysis of cluster state
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use solana_gossip::{cluster_info::ClusterInfo, crds::Cursor};
+use std::path::{Path, PathBuf};
+
+use crate::decoder::{vote, VoteEvent};
+use crate::gossip::{ClusterNode, SnapshotSource};
+
+/// Contents of the gossip table at one point in time
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrdsSnapshot {
+    /// Unix time in milliseconds the table was read
+    pub timestamp: u64,
+    pub node_pubkey: String,
+    pub shred_version: u16,
+    /// Table entries of any kind
+    pub crds_values: usize,
+    pub contact_infos: Vec<ClusterNode>,
+    pub votes: Vec<VoteEvent>,
+    pub epoch_slots: Vec<NodeEpochSlots>,
+    pub snapshot_hashes: Vec<SnapshotSource>,
+}
+
+/// Slots a node reports having completed, as inclusive ranges
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeEpochSlots {
+    pub pubkey: String,
+    pub slot_ranges: Vec<(u64, u64)>,
+}
+
+impl CrdsSnapshot {
+    /// Read the table. Peer listings come from the caller, which annotates them
+    pub fn capture(
+        cluster_info: &ClusterInfo,
+        contact_infos: Vec<ClusterNode>,
+        snapshot_hashes: Vec<SnapshotSource>,
+        timestamp: u64,
+    ) -> Self {
+        // A fresh cursor reads every entry still in the table
+        let votes = cluster_info
+            .get_votes(&mut Cursor::default())
+            .iter()
+            .flat_map(vote::decode_unlanded)
+            .collect();
+        let epoch_slots = cluster_info
+            .get_epoch_slots(&mut Cursor::default())
+            .into_iter()
+            .map(|epoch_slots| NodeEpochSlots {
+                pubkey: epoch_slots.from.to_string(),
+                slot_ranges: slot_ranges(epoch_slots.to_slots(0)),
+            })
+            .collect();
+        
+        Self {
+            timestamp,
+            node_pubkey: cluster_info.id().to_string(),
+            shred_version: cluster_info.my_shred_version(),
+            crds_values: cluster_info.gossip.crds.read().unwrap().len(),
+            contact_infos,
+            votes,
+            epoch_slots,
+            snapshot_hashes,
+        }
+    }
+    
+    /// Write the snapshot to `dir` as `crds-<timestamp>.json`, returning the file path
+    pub fn write_to(&self, dir: &str) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create export directory {}", dir))?;
+        let path = Path::new(dir).join(format!("crds-{}.json", self.timestamp));
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        serde_json::to_writer(std::io::BufWriter::new(file), self)?;
+        Ok(path)
+    }
+}
+
+/// Collapse slots into inclusive ranges of consecutive slots
+fn slot_ranges(mut slots: Vec<u64>) -> Vec<(u64, u64)> {
+    slots.sort_unstable();
+    slots.dedup();
+    
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for slot in slots {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == slot => *end = slot,
+            _ => ranges.push((slot, slot)),
+        }
+    }
+    ranges
+}
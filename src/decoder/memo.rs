@@ -0,0 +1,21 @@
+use serde_json::Value;
+
+use crate::transaction_processor::InstructionInfo;
+
+pub const MEMO_PROGRAM_ID: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+pub const MEMO_V1_PROGRAM_ID: &str = "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo";
+
+/// Extract the memo text from a Memo program instruction
+pub fn decode(instruction: &InstructionInfo) -> Option<String> {
+    if instruction.program_id != MEMO_PROGRAM_ID && instruction.program_id != MEMO_V1_PROGRAM_ID {
+        return None;
+    }
+    
+    // The RPC parser emits the memo as a plain JSON string
+    if let Some(Value::String(memo)) = &instruction.parsed {
+        return Some(memo.clone());
+    }
+    
+    let data = instruction.data.as_ref()?;
+    Some(String::from_utf8_lossy(data).into_owned())
+}
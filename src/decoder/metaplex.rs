@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+
+use super::{anchor_discriminator, read_u64};
+use crate::transaction_processor::InstructionInfo;
+
+pub const TOKEN_METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+pub const AUCTION_HOUSE_PROGRAM_ID: &str = "hausS13jsjafwWwGqZTUQRmWyvyxn9EQpqMwV1PBBmk";
+pub const CANDY_MACHINE_V2_PROGRAM_ID: &str = "cndy3Z4yapfJBmL3ShUp5exZKqR3z33thTzeNMm2gRZ";
+pub const CANDY_MACHINE_V3_PROGRAM_ID: &str = "CndyV3LdqHUfDLmE5naZjVN8rBZz4tqhdefbAnjHG3JR";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NftEventKind {
+    Mint,
+    List,
+    Sale,
+    Transfer,
+}
+
+/// NFT activity performed through Metaplex programs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NftEvent {
+    pub kind: NftEventKind,
+    pub mint: Option<String>,
+    /// Verified collection mint, when the transaction reveals it
+    pub collection: Option<String>,
+    pub token_account: Option<String>,
+    pub seller: Option<String>,
+    pub buyer: Option<String>,
+    /// Price in treasury mint base units (lamports for SOL-denominated sales)
+    pub price: Option<u64>,
+}
+
+impl NftEvent {
+    fn new(kind: NftEventKind, mint: Option<String>) -> Self {
+        Self {
+            kind,
+            mint,
+            collection: None,
+            token_account: None,
+            seller: None,
+            buyer: None,
+            price: None,
+        }
+    }
+}
+
+/// Decode a Token Metadata, Auction House or Candy Machine instruction into an NFT event
+pub fn decode(instruction: &InstructionInfo) -> Option<NftEvent> {
+    let data = instruction.data.as_ref()?;
+    let account = |index: usize| instruction.accounts.get(index).cloned();
+    
+    match instruction.program_id.as_str() {
+        TOKEN_METADATA_PROGRAM_ID => match data.first()? {
+            // CreateMasterEdition / CreateMasterEditionV3: [edition, mint, ...]
+            10 | 17 => Some(NftEvent::new(NftEventKind::Mint, account(1))),
+            // Create: [metadata, master_edition, mint, ...]; the master edition
+            // slot holds the program ID when creating a fungible asset
+            42 if account(1).as_deref() != Some(TOKEN_METADATA_PROGRAM_ID) => {
+                Some(NftEvent::new(NftEventKind::Mint, account(2)))
+            }
+            // Transfer: [token, token_owner, destination, destination_owner, mint, ...]
+            49 => {
+                let mut event = NftEvent::new(NftEventKind::Transfer, account(4));
+                event.token_account = account(0);
+                event.seller = account(1);
+                event.buyer = account(3);
+                Some(event)
+            }
+            _ => None,
+        },
+        AUCTION_HOUSE_PROGRAM_ID => {
+            let discriminator: [u8; 8] = data.get(..8)?.try_into().ok()?;
+            // Both take (trade_state_bump, free_trade_state_bump, program_as_signer_bump, price, size)
+            let price = read_u64(data, 11);
+            
+            if discriminator == anchor_discriminator("global:sell") {
+                // sell: [wallet, token_account, metadata, ...]
+                let mut event = NftEvent::new(NftEventKind::List, None);
+                event.seller = account(0);
+                event.token_account = account(1);
+                event.price = price;
+                Some(event)
+            } else if discriminator == anchor_discriminator("global:execute_sale") {
+                // execute_sale: [buyer, seller, token_account, token_mint, ...]
+                let mut event = NftEvent::new(NftEventKind::Sale, account(3));
+                event.buyer = account(0);
+                event.seller = account(1);
+                event.token_account = account(2);
+                event.price = price;
+                Some(event)
+            } else {
+                None
+            }
+        }
+        CANDY_MACHINE_V2_PROGRAM_ID => {
+            // mint_nft: [candy_machine, creator, payer, wallet, metadata, mint, ...]
+            if data.get(..8)? == anchor_discriminator("global:mint_nft") {
+                let mut event = NftEvent::new(NftEventKind::Mint, account(5));
+                event.buyer = account(2);
+                Some(event)
+            } else {
+                None
+            }
+        }
+        CANDY_MACHINE_V3_PROGRAM_ID => {
+            let discriminator = data.get(..8)?;
+            if discriminator == anchor_discriminator("global:mint_v2") {
+                // mint_v2: [.., payer (3), owner (4), nft_mint (5), .., collection_mint (12), ..]
+                let mut event = NftEvent::new(NftEventKind::Mint, account(5));
+                event.buyer = account(4);
+                event.collection = account(12);
+                Some(event)
+            } else if discriminator == anchor_discriminator("global:mint") {
+                // mint: [.., payer (3), nft_mint (4), .., collection_mint (9), ..]
+                let mut event = NftEvent::new(NftEventKind::Mint, account(4));
+                event.buyer = account(3);
+                event.collection = account(9);
+                Some(event)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Collection mint referenced by a Token Metadata collection verification instruction
+pub fn verified_collection(instruction: &InstructionInfo) -> Option<String> {
+    if instruction.program_id != TOKEN_METADATA_PROGRAM_ID {
+        return None;
+    }
+    
+    let index = match instruction.data.as_ref()?.first()? {
+        // VerifyCollection / VerifySizedCollectionItem: [metadata, authority, payer, collection_mint, ..]
+        18 | 30 => 3,
+        // SetAndVerifyCollection / SetAndVerifySizedCollectionItem: [.., update_authority, collection_mint, ..]
+        25 | 32 => 4,
+        _ => return None,
+    };
+    instruction.accounts.get(index).cloned()
+}
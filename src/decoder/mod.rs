@@ -0,0 +1,50 @@
+//! Decoders that turn instructions of well-known programs into typed events
+
+pub mod memo;
+pub mod metaplex;
+pub mod system;
+pub mod token;
+pub mod vote;
+
+use serde_json::Value;
+use solana_sdk::hash::hashv;
+
+pub use metaplex::NftEvent;
+pub use system::SystemEvent;
+pub use token::TokenEvent;
+pub use vote::VoteEvent;
+
+/// Split the JSON output of the RPC instruction parser into its type and info object
+fn parsed_instruction(parsed: &Value) -> Option<(&str, &Value)> {
+    let instruction_type = parsed.get("type")?.as_str()?;
+    let info = parsed.get("info").unwrap_or(&Value::Null);
+    Some((instruction_type, info))
+}
+
+/// Read a string field from a parsed instruction info object
+fn info_str(info: &Value, field: &str) -> Option<String> {
+    info.get(field)?.as_str().map(|s| s.to_string())
+}
+
+/// Read an amount from a parsed instruction info object (encoded as a string or number)
+fn info_u64(info: &Value, field: &str) -> Option<u64> {
+    match info.get(field)? {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.as_u64(),
+        _ => None,
+    }
+}
+
+/// Read a little-endian u64 from instruction data
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+}
+
+/// First 8 bytes of the SHA-256 of an Anchor sighash preimage (e.g. `global:execute_sale`)
+fn anchor_discriminator(preimage: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hashv(&[preimage.as_bytes()]).to_bytes()[..8]);
+    discriminator
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_sdk::bs58;
+
+use super::{info_str, info_u64, parsed_instruction, read_u64};
+use crate::transaction_processor::InstructionInfo;
+
+pub const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+
+/// A lamport movement or account change performed by a System Program instruction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SystemEvent {
+    Transfer {
+        source: String,
+        destination: String,
+        lamports: u64,
+    },
+    CreateAccount {
+        source: String,
+        new_account: String,
+        lamports: u64,
+        space: u64,
+        owner: String,
+    },
+    Assign {
+        account: String,
+        owner: String,
+    },
+    AdvanceNonce {
+        nonce_account: String,
+        nonce_authority: Option<String>,
+    },
+}
+
+/// Decode a System Program instruction into a system event
+pub fn decode(instruction: &InstructionInfo) -> Option<SystemEvent> {
+    if instruction.program_id != SYSTEM_PROGRAM_ID {
+        return None;
+    }
+    
+    match &instruction.parsed {
+        Some(parsed) => decode_parsed(parsed),
+        None => decode_raw(instruction),
+    }
+}
+
+fn decode_parsed(parsed: &Value) -> Option<SystemEvent> {
+    let (instruction_type, info) = parsed_instruction(parsed)?;
+    
+    match instruction_type {
+        "transfer" | "transferWithSeed" => Some(SystemEvent::Transfer {
+            source: info_str(info, "source")?,
+            destination: info_str(info, "destination")?,
+            lamports: info_u64(info, "lamports")?,
+        }),
+        "createAccount" | "createAccountWithSeed" => Some(SystemEvent::CreateAccount {
+            source: info_str(info, "source")?,
+            new_account: info_str(info, "newAccount")?,
+            lamports: info_u64(info, "lamports")?,
+            space: info_u64(info, "space")?,
+            owner: info_str(info, "owner")?,
+        }),
+        "assign" | "assignWithSeed" => Some(SystemEvent::Assign {
+            account: info_str(info, "account")?,
+            owner: info_str(info, "owner")?,
+        }),
+        "advanceNonce" => Some(SystemEvent::AdvanceNonce {
+            nonce_account: info_str(info, "nonceAccount")?,
+            nonce_authority: info_str(info, "nonceAuthority"),
+        }),
+        _ => None,
+    }
+}
+
+fn decode_raw(instruction: &InstructionInfo) -> Option<SystemEvent> {
+    let data = instruction.data.as_ref()?;
+    let account = |index: usize| instruction.accounts.get(index).cloned();
+    let pubkey = |offset: usize| data.get(offset..offset + 32).map(|bytes| bs58::encode(bytes).into_string());
+    
+    // Instructions are bincode-encoded with a little-endian u32 discriminant
+    let tag = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+    
+    match tag {
+        // CreateAccount { lamports, space, owner }: [funding, new account]
+        0 => Some(SystemEvent::CreateAccount {
+            source: account(0)?,
+            new_account: account(1)?,
+            lamports: read_u64(data, 4)?,
+            space: read_u64(data, 12)?,
+            owner: pubkey(20)?,
+        }),
+        // Assign { owner }: [account]
+        1 => Some(SystemEvent::Assign {
+            account: account(0)?,
+            owner: pubkey(4)?,
+        }),
+        // Transfer { lamports }: [from, to]
+        2 => Some(SystemEvent::Transfer {
+            source: account(0)?,
+            destination: account(1)?,
+            lamports: read_u64(data, 4)?,
+        }),
+        // AdvanceNonceAccount: [nonce account, recent blockhashes sysvar, nonce authority]
+        4 => Some(SystemEvent::AdvanceNonce {
+            nonce_account: account(0)?,
+            nonce_authority: account(2),
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,435 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use solana_sdk::bs58;
+
+use super::{info_str, info_u64, parsed_instruction, read_u64};
+use crate::transaction_processor::InstructionInfo;
+
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+
+/// A token movement or permission change performed by an SPL Token or Token-2022 instruction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TokenEvent {
+    Transfer {
+        source: String,
+        destination: String,
+        authority: Option<String>,
+        mint: Option<String>,
+        amount: u64,
+        decimals: Option<u8>,
+        /// Fee withheld by a Token-2022 transfer-fee mint
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fee: Option<u64>,
+    },
+    MintTo {
+        mint: String,
+        account: String,
+        authority: Option<String>,
+        amount: u64,
+        decimals: Option<u8>,
+    },
+    Burn {
+        account: String,
+        mint: String,
+        authority: Option<String>,
+        amount: u64,
+        decimals: Option<u8>,
+    },
+    Approve {
+        source: String,
+        delegate: String,
+        owner: Option<String>,
+        mint: Option<String>,
+        amount: u64,
+        decimals: Option<u8>,
+    },
+    CreateAssociatedAccount {
+        account: String,
+        wallet: String,
+        mint: String,
+        idempotent: bool,
+    },
+    /// A Token-2022 confidential transfer instruction; amounts are encrypted and not decoded
+    ConfidentialTransfer {
+        instruction: String,
+        account: Option<String>,
+        mint: Option<String>,
+    },
+    /// Token-2022 metadata pointer initialized or updated on a mint
+    MetadataPointer {
+        mint: String,
+        authority: Option<String>,
+        metadata_address: Option<String>,
+    },
+    /// An authority of a mint or token account changed (`new_authority` is None when revoked)
+    SetAuthority {
+        account: String,
+        authority_type: String,
+        authority: Option<String>,
+        new_authority: Option<String>,
+    },
+}
+
+/// Decode an SPL Token, Token-2022 or Associated Token Account instruction into a token event
+pub fn decode(instruction: &InstructionInfo) -> Option<TokenEvent> {
+    match instruction.program_id.as_str() {
+        TOKEN_PROGRAM_ID => match &instruction.parsed {
+            Some(parsed) => decode_parsed(parsed),
+            None => decode_raw(instruction),
+        },
+        // Token-2022 shares the SPL Token instruction layout and extends it with extensions
+        TOKEN_2022_PROGRAM_ID => match &instruction.parsed {
+            Some(parsed) => decode_parsed(parsed).or_else(|| decode_parsed_extension(parsed)),
+            None => decode_raw(instruction).or_else(|| decode_raw_extension(instruction)),
+        },
+        ASSOCIATED_TOKEN_PROGRAM_ID => decode_associated(instruction),
+        _ => None,
+    }
+}
+
+fn decode_parsed(parsed: &Value) -> Option<TokenEvent> {
+    let (instruction_type, info) = parsed_instruction(parsed)?;
+    
+    // Checked variants carry the amount and decimals in a `tokenAmount` object
+    let checked_amount = || {
+        let token_amount = info.get("tokenAmount")?;
+        let amount = info_u64(token_amount, "amount")?;
+        let decimals = token_amount.get("decimals")?.as_u64().map(|d| d as u8);
+        Some((amount, decimals))
+    };
+    let authority = info_str(info, "authority").or_else(|| info_str(info, "multisigAuthority"));
+    
+    match instruction_type {
+        "transfer" => Some(TokenEvent::Transfer {
+            source: info_str(info, "source")?,
+            destination: info_str(info, "destination")?,
+            authority,
+            mint: None,
+            amount: info_u64(info, "amount")?,
+            decimals: None,
+            fee: None,
+        }),
+        "transferChecked" => {
+            let (amount, decimals) = checked_amount()?;
+            Some(TokenEvent::Transfer {
+                source: info_str(info, "source")?,
+                destination: info_str(info, "destination")?,
+                authority,
+                mint: info_str(info, "mint"),
+                amount,
+                decimals,
+                fee: None,
+            })
+        }
+        "mintTo" | "mintToChecked" => {
+            let (amount, decimals) = match instruction_type {
+                "mintTo" => (info_u64(info, "amount")?, None),
+                _ => checked_amount()?,
+            };
+            Some(TokenEvent::MintTo {
+                mint: info_str(info, "mint")?,
+                account: info_str(info, "account")?,
+                authority: info_str(info, "mintAuthority")
+                    .or_else(|| info_str(info, "multisigMintAuthority")),
+                amount,
+                decimals,
+            })
+        }
+        "burn" | "burnChecked" => {
+            let (amount, decimals) = match instruction_type {
+                "burn" => (info_u64(info, "amount")?, None),
+                _ => checked_amount()?,
+            };
+            Some(TokenEvent::Burn {
+                account: info_str(info, "account")?,
+                mint: info_str(info, "mint")?,
+                authority,
+                amount,
+                decimals,
+            })
+        }
+        "approve" | "approveChecked" => {
+            let (amount, decimals) = match instruction_type {
+                "approve" => (info_u64(info, "amount")?, None),
+                _ => checked_amount()?,
+            };
+            Some(TokenEvent::Approve {
+                source: info_str(info, "source")?,
+                delegate: info_str(info, "delegate")?,
+                owner: info_str(info, "owner").or_else(|| info_str(info, "multisigOwner")),
+                mint: info_str(info, "mint"),
+                amount,
+                decimals,
+            })
+        }
+        "setAuthority" => Some(TokenEvent::SetAuthority {
+            account: info_str(info, "account").or_else(|| info_str(info, "mint"))?,
+            authority_type: info_str(info, "authorityType")?,
+            authority,
+            new_authority: info_str(info, "newAuthority"),
+        }),
+        _ => None,
+    }
+}
+
+/// Whether an SPL Token instruction creates or is authorized by an SPL multisig account
+pub fn uses_multisig(instruction: &InstructionInfo) -> bool {
+    if instruction.program_id != TOKEN_PROGRAM_ID && instruction.program_id != TOKEN_2022_PROGRAM_ID {
+        return false;
+    }
+    
+    match &instruction.parsed {
+        // The parser reports multisig authorities as `multisigOwner`, `multisigAuthority`, etc.
+        Some(parsed) => match parsed_instruction(parsed) {
+            Some((instruction_type, info)) => {
+                instruction_type.starts_with("initializeMultisig")
+                    || info.as_object().map_or(false, |info| info.keys().any(|key| key.starts_with("multisig")))
+            }
+            None => false,
+        },
+        // InitializeMultisig / InitializeMultisig2
+        None => matches!(instruction.data.as_deref().and_then(|data| data.first()), Some(2 | 19)),
+    }
+}
+
+fn decode_raw(instruction: &InstructionInfo) -> Option<TokenEvent> {
+    let data = instruction.data.as_ref()?;
+    let account = |index: usize| instruction.accounts.get(index).cloned();
+    let (tag, rest) = data.split_first()?;
+    let amount = read_u64(rest, 0);
+    let decimals = rest.get(8).copied();
+    
+    match tag {
+        // Transfer { amount }: [source, destination, authority]
+        3 => Some(TokenEvent::Transfer {
+            source: account(0)?,
+            destination: account(1)?,
+            authority: account(2),
+            mint: None,
+            amount: amount?,
+            decimals: None,
+            fee: None,
+        }),
+        // Approve { amount }: [source, delegate, owner]
+        4 => Some(TokenEvent::Approve {
+            source: account(0)?,
+            delegate: account(1)?,
+            owner: account(2),
+            mint: None,
+            amount: amount?,
+            decimals: None,
+        }),
+        // MintTo { amount } / MintToChecked { amount, decimals }: [mint, account, authority]
+        7 | 14 => Some(TokenEvent::MintTo {
+            mint: account(0)?,
+            account: account(1)?,
+            authority: account(2),
+            amount: amount?,
+            decimals: if *tag == 14 { decimals } else { None },
+        }),
+        // Burn { amount } / BurnChecked { amount, decimals }: [account, mint, authority]
+        8 | 15 => Some(TokenEvent::Burn {
+            account: account(0)?,
+            mint: account(1)?,
+            authority: account(2),
+            amount: amount?,
+            decimals: if *tag == 15 { decimals } else { None },
+        }),
+        // TransferChecked { amount, decimals }: [source, mint, destination, authority]
+        12 => Some(TokenEvent::Transfer {
+            source: account(0)?,
+            destination: account(2)?,
+            authority: account(3),
+            mint: account(1),
+            amount: amount?,
+            decimals,
+            fee: None,
+        }),
+        // ApproveChecked { amount, decimals }: [source, mint, delegate, owner]
+        13 => Some(TokenEvent::Approve {
+            source: account(0)?,
+            delegate: account(2)?,
+            owner: account(3),
+            mint: account(1),
+            amount: amount?,
+            decimals,
+        }),
+        // SetAuthority { authority_type, new_authority: COption<Pubkey> }: [account or mint, authority]
+        6 => {
+            let authority_type = *rest.first()?;
+            let new_authority = match rest.get(1)? {
+                0 => None,
+                _ => Some(bs58::encode(rest.get(2..34)?).into_string()),
+            };
+            Some(TokenEvent::SetAuthority {
+                account: account(0)?,
+                authority_type: AUTHORITY_TYPES
+                    .get(authority_type as usize)
+                    .copied()
+                    .unwrap_or("unknown")
+                    .to_string(),
+                authority: account(1),
+                new_authority,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Decode the parsed form of Token-2022 extension instructions
+fn decode_parsed_extension(parsed: &Value) -> Option<TokenEvent> {
+    let (instruction_type, info) = parsed_instruction(parsed)?;
+    
+    match instruction_type {
+        "transferCheckedWithFee" => {
+            let token_amount = info.get("tokenAmount")?;
+            Some(TokenEvent::Transfer {
+                source: info_str(info, "source")?,
+                destination: info_str(info, "destination")?,
+                authority: info_str(info, "authority").or_else(|| info_str(info, "multisigAuthority")),
+                mint: info_str(info, "mint"),
+                amount: info_u64(token_amount, "amount")?,
+                decimals: token_amount.get("decimals")?.as_u64().map(|d| d as u8),
+                fee: info.get("feeAmount").and_then(|fee| info_u64(fee, "amount")),
+            })
+        }
+        "initializeMetadataPointer" | "updateMetadataPointer" => Some(TokenEvent::MetadataPointer {
+            mint: info_str(info, "mint")?,
+            authority: info_str(info, "authority").or_else(|| info_str(info, "multisigAuthority")),
+            metadata_address: info_str(info, "metadataAddress"),
+        }),
+        confidential if confidential.contains("Confidential") || confidential == "confidentialTransfer" => {
+            Some(TokenEvent::ConfidentialTransfer {
+                instruction: confidential.to_string(),
+                account: info_str(info, "account").or_else(|| info_str(info, "source")),
+                mint: info_str(info, "mint"),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Decode raw Token-2022 extension instructions (transfer fee, confidential transfer, metadata pointer)
+fn decode_raw_extension(instruction: &InstructionInfo) -> Option<TokenEvent> {
+    let data = instruction.data.as_ref()?;
+    let account = |index: usize| instruction.accounts.get(index).cloned();
+    let (&tag, rest) = data.split_first()?;
+    let (&sub_tag, payload) = rest.split_first()?;
+    
+    match (tag, sub_tag) {
+        // TransferFeeExtension::TransferCheckedWithFee { amount, decimals, fee }:
+        // [source, mint, destination, authority]
+        (26, 1) => Some(TokenEvent::Transfer {
+            source: account(0)?,
+            destination: account(2)?,
+            authority: account(3),
+            mint: account(1),
+            amount: read_u64(payload, 0)?,
+            decimals: payload.get(8).copied(),
+            fee: read_u64(payload, 9),
+        }),
+        // ConfidentialTransferExtension: [account or mint, ...], layout varies by instruction
+        (27, sub_tag) => Some(TokenEvent::ConfidentialTransfer {
+            instruction: CONFIDENTIAL_TRANSFER_INSTRUCTIONS
+                .get(sub_tag as usize)
+                .copied()
+                .unwrap_or("unknown")
+                .to_string(),
+            account: account(0),
+            mint: None,
+        }),
+        // MetadataPointerExtension::Initialize { authority, metadata_address }: [mint]
+        (39, 0) => Some(TokenEvent::MetadataPointer {
+            mint: account(0)?,
+            authority: read_optional_pubkey(payload, 0),
+            metadata_address: read_optional_pubkey(payload, 32),
+        }),
+        // MetadataPointerExtension::Update { metadata_address }: [mint, authority]
+        (39, 1) => Some(TokenEvent::MetadataPointer {
+            mint: account(0)?,
+            authority: account(1),
+            metadata_address: read_optional_pubkey(payload, 0),
+        }),
+        _ => None,
+    }
+}
+
+/// Authority types of `SetAuthority`, indexed by their tag, named as by the RPC parser
+const AUTHORITY_TYPES: &[&str] = &[
+    "mintTokens",
+    "freezeAccount",
+    "accountOwner",
+    "closeAccount",
+    "transferFeeConfig",
+    "withheldWithdraw",
+    "closeMint",
+    "interestRate",
+    "permanentDelegate",
+    "confidentialTransferMint",
+    "transferHookProgramId",
+    "confidentialTransferFeeConfig",
+    "metadataPointer",
+    "groupPointer",
+    "groupMemberPointer",
+];
+
+/// Confidential transfer sub-instructions, indexed by their tag
+const CONFIDENTIAL_TRANSFER_INSTRUCTIONS: &[&str] = &[
+    "initializeConfidentialTransferMint",
+    "updateConfidentialTransferMint",
+    "configureConfidentialTransferAccount",
+    "approveConfidentialTransferAccount",
+    "emptyConfidentialTransferAccount",
+    "depositConfidentialTransfer",
+    "withdrawConfidentialTransfer",
+    "confidentialTransfer",
+    "applyPendingConfidentialTransferBalance",
+    "enableConfidentialTransferConfidentialCredits",
+    "disableConfidentialTransferConfidentialCredits",
+    "enableConfidentialTransferNonConfidentialCredits",
+    "disableNonConfidentialTransferConfidentialCredits",
+    "confidentialTransferWithSplitProofs",
+];
+
+/// Read an optional pubkey as encoded by Token-2022 (all zeroes means none)
+fn read_optional_pubkey(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..offset + 32)?;
+    if bytes.iter().all(|&byte| byte == 0) {
+        return None;
+    }
+    Some(bs58::encode(bytes).into_string())
+}
+
+fn decode_associated(instruction: &InstructionInfo) -> Option<TokenEvent> {
+    if let Some(parsed) = &instruction.parsed {
+        let (instruction_type, info) = parsed_instruction(parsed)?;
+        let idempotent = match instruction_type {
+            "create" => false,
+            "createIdempotent" => true,
+            _ => return None,
+        };
+        return Some(TokenEvent::CreateAssociatedAccount {
+            account: info_str(info, "account")?,
+            wallet: info_str(info, "wallet")?,
+            mint: info_str(info, "mint")?,
+            idempotent,
+        });
+    }
+    
+    // Create (empty data or 0) / CreateIdempotent (1): [payer, account, wallet, mint, ...]
+    let idempotent = match instruction.data.as_deref()?.first() {
+        None | Some(0) => false,
+        Some(1) => true,
+        _ => return None,
+    };
+    Some(TokenEvent::CreateAssociatedAccount {
+        account: instruction.accounts.get(1)?.clone(),
+        wallet: instruction.accounts.get(2)?.clone(),
+        mint: instruction.accounts.get(3)?.clone(),
+        idempotent,
+    })
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_sdk::{
+    message::AccountKeys,
+    program_utils::limited_deserialize,
+    transaction::Transaction,
+    vote::{instruction::VoteInstruction, state::VoteStateUpdate},
+};
+use solana_transaction_status::parse_instruction;
+
+use super::{info_str, parsed_instruction};
+use crate::transaction_processor::InstructionInfo;
+
+pub const VOTE_PROGRAM_ID: &str = "Vote111111111111111111111111111111111111111";
+
+/// Vote credits earned for a vote landing within the grace period (timely vote credits)
+const MAX_CREDITS_PER_SLOT: u64 = 16;
+const CREDITS_GRACE_SLOTS: u64 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteLockout {
+    pub slot: u64,
+    pub confirmation_count: u32,
+}
+
+/// Contents of a vote instruction cast by a validator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteEvent {
+    pub vote_account: String,
+    pub vote_authority: Option<String>,
+    /// Slots voted on (legacy votes) or the slots of the proposed tower (vote state updates)
+    pub slots: Vec<u64>,
+    pub lockouts: Vec<VoteLockout>,
+    pub root: Option<u64>,
+    pub timestamp: Option<i64>,
+    /// Slots between the newest voted slot and the slot the vote landed in
+    pub latency: Option<u64>,
+}
+
+impl VoteEvent {
+    /// Newest slot this vote votes for
+    pub fn voted_slot(&self) -> Option<u64> {
+        self.slots.iter().max().copied()
+    }
+    
+    /// Estimated credits earned for the newest voted slot under timely vote credits
+    pub fn estimated_credits(&self) -> u64 {
+        match self.latency {
+            Some(latency) if latency > CREDITS_GRACE_SLOTS => {
+                MAX_CREDITS_PER_SLOT.saturating_sub(latency - CREDITS_GRACE_SLOTS).max(1)
+            }
+            Some(_) => MAX_CREDITS_PER_SLOT,
+            None => 0,
+        }
+    }
+}
+
+/// Decode a Vote program instruction landed in `slot` into a vote event, from the RPC node's
+/// parse or else from the raw instruction data
+pub fn decode(instruction: &InstructionInfo, slot: u64) -> Option<VoteEvent> {
+    if instruction.program_id != VOTE_PROGRAM_ID {
+        return None;
+    }
+    
+    let mut event = match &instruction.parsed {
+        Some(parsed) => decode_parsed(parsed)?,
+        None => decode_raw(instruction)?,
+    };
+    event.latency = event.voted_slot().map(|voted| slot.saturating_sub(voted));
+    Some(event)
+}
+
+/// Decode a vote instruction parsed by the RPC node (`jsonParsed`)
+fn decode_parsed(parsed: &Value) -> Option<VoteEvent> {
+    let (instruction_type, info) = parsed_instruction(parsed)?;
+    let tower = match instruction_type {
+        "vote" | "voteSwitch" => info.get("vote")?,
+        "updatevotestate" | "updatevotestateswitch"
+        | "compactupdatevotestate" | "compactupdatevotestateswitch" => info.get("voteStateUpdate")?,
+        "towersync" | "towersyncswitch" => info.get("towerSync")?,
+        _ => return None,
+    };
+    
+    let lockouts: Vec<VoteLockout> = tower.get("lockouts")
+        .and_then(Value::as_array)
+        .map(|lockouts| {
+            lockouts.iter()
+                .filter_map(|lockout| {
+                    Some(VoteLockout {
+                        slot: lockout.get("slot")?.as_u64()?,
+                        confirmation_count: lockout.get("confirmation_count")?.as_u64()? as u32,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    let slots: Vec<u64> = match tower.get("slots").and_then(Value::as_array) {
+        Some(slots) => slots.iter().filter_map(Value::as_u64).collect(),
+        None => lockouts.iter().map(|lockout| lockout.slot).collect(),
+    };
+    
+    Some(VoteEvent {
+        vote_account: info_str(info, "voteAccount")?,
+        vote_authority: info_str(info, "voteAuthority"),
+        slots,
+        lockouts,
+        root: tower.get("root").and_then(Value::as_u64),
+        timestamp: tower.get("timestamp").and_then(Value::as_i64),
+        latency: None,
+    })
+}
+
+/// Decode the raw data of a vote instruction, fetched under the binary encodings. The vote
+/// account comes first among its accounts and the vote authority last (legacy votes pass the
+/// slot hashes and clock sysvars in between)
+fn decode_raw(instruction: &InstructionInfo) -> Option<VoteEvent> {
+    let data = instruction.data.as_deref()?;
+    let tower = |update: VoteStateUpdate| {
+        let lockouts: Vec<VoteLockout> = update
+            .lockouts
+            .iter()
+            .map(|lockout| VoteLockout { slot: lockout.slot(), confirmation_count: lockout.confirmation_count() })
+            .collect();
+        let slots = lockouts.iter().map(|lockout| lockout.slot).collect();
+        (slots, lockouts, update.root, update.timestamp)
+    };
+    let (slots, lockouts, root, timestamp) = match limited_deserialize(data).ok()? {
+        VoteInstruction::Vote(vote) | VoteInstruction::VoteSwitch(vote, _) => {
+            (vote.slots, Vec::new(), None, vote.timestamp)
+        }
+        VoteInstruction::UpdateVoteState(update)
+        | VoteInstruction::UpdateVoteStateSwitch(update, _)
+        | VoteInstruction::CompactUpdateVoteState(update)
+        | VoteInstruction::CompactUpdateVoteStateSwitch(update, _) => tower(update),
+        _ => return None,
+    };
+    
+    Some(VoteEvent {
+        vote_account: instruction.accounts.first()?.clone(),
+        vote_authority: instruction.accounts.get(1..).and_then(<[String]>::last).cloned(),
+        slots,
+        lockouts,
+        root,
+        timestamp,
+        latency: None,
+    })
+}
+
+/// Decode the votes of a transaction that has not landed in a block, such as one pushed over
+/// gossip. Their latency is unknown.
+pub fn decode_unlanded(transaction: &Transaction) -> Vec<VoteEvent> {
+    let account_keys = AccountKeys::new(&transaction.message.account_keys, None);
+    
+    transaction.message.instructions
+        .iter()
+        .filter_map(|instruction| {
+            let program_id = account_keys.get(instruction.program_id_index as usize)?;
+            let parsed = parse_instruction::parse(program_id, instruction, &account_keys, None).ok()?;
+            let info = InstructionInfo {
+                program_id: parsed.program_id,
+                accounts: Vec::new(),
+                data: None,
+                parsed: Some(parsed.parsed),
+                stack_height: None,
+            };
+            let mut event = decode(&info, 0)?;
+            event.latency = None;
+            Some(event)
+        })
+        .collect()
+}
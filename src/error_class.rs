@@ -0,0 +1,65 @@
+//! Stable taxonomy for failed transactions, so failure rates can be grouped meaningfully
+
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+/// Classify a transaction error into a stable, snake_case error class
+///
+/// Custom program errors keep their code (`custom_program_error_6001`) since codes are
+/// only meaningful together with the failing program.
+pub fn classify(error: &TransactionError) -> String {
+    match error {
+        TransactionError::InsufficientFundsForFee => "insufficient_funds_for_fee".to_string(),
+        TransactionError::InsufficientFundsForRent { .. } => "insufficient_funds_for_rent".to_string(),
+        TransactionError::BlockhashNotFound => "blockhash_expired".to_string(),
+        TransactionError::AlreadyProcessed => "already_processed".to_string(),
+        TransactionError::AccountInUse | TransactionError::AccountLoadedTwice => {
+            "account_in_use".to_string()
+        }
+        TransactionError::AccountNotFound | TransactionError::ProgramAccountNotFound => {
+            "account_not_found".to_string()
+        }
+        TransactionError::WouldExceedMaxBlockCostLimit
+        | TransactionError::WouldExceedMaxAccountCostLimit
+        | TransactionError::WouldExceedMaxVoteCostLimit
+        | TransactionError::WouldExceedAccountDataBlockLimit
+        | TransactionError::WouldExceedAccountDataTotalLimit => "cost_limit_exceeded".to_string(),
+        TransactionError::InstructionError(_, instruction_error) => match instruction_error {
+            InstructionError::Custom(code) => format!("custom_program_error_{}", code),
+            InstructionError::ComputationalBudgetExceeded => "compute_exceeded".to_string(),
+            InstructionError::InsufficientFunds => "insufficient_funds".to_string(),
+            InstructionError::ProgramFailedToComplete => "program_failed_to_complete".to_string(),
+            other => format!("instruction_error_{}", variant_name(other)),
+        },
+        other => variant_name(other),
+    }
+}
+
+/// Index of the instruction that failed, if the error is an instruction error
+pub fn failed_instruction_index(error: &TransactionError) -> Option<u8> {
+    match error {
+        TransactionError::InstructionError(index, _) => Some(*index),
+        _ => None,
+    }
+}
+
+/// Convert the Debug name of an enum variant (e.g. `InvalidAccountData`) to snake_case
+fn variant_name<T: std::fmt::Debug>(value: &T) -> String {
+    let debug = format!("{:?}", value);
+    let name = debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
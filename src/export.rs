@@ -0,0 +1,300 @@
+//! Export of stored transactions to files, for loading into analytics tools
+//!
+//! JSON lines hold each transaction as `/tx/<signature>` returns it. CSV and Parquet hold one flat
+//! row per transaction with the columns of `COLUMNS`; list values (event types) are joined with
+//! commas, and absent values are empty (CSV) or null (Parquet).
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use parquet::{
+    basic::Compression,
+    data_type::{BoolType, ByteArray, ByteArrayType, DataType, Int64Type},
+    file::{
+        properties::WriterProperties,
+        writer::{SerializedColumnWriter, SerializedFileWriter},
+    },
+    schema::parser::parse_message_type,
+};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::api::rest::processed_view;
+use crate::storage::{Storage, StoredTransaction};
+
+/// Signatures read from the store at once
+const PAGE_SIZE: usize = 1000;
+
+/// Rows buffered per Parquet row group
+const PARQUET_ROW_GROUP: usize = 50_000;
+
+/// Interval between progress reports
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Columns of the CSV and Parquet exports, in order
+const COLUMNS: [&str; 11] = [
+    "signature",
+    "slot",
+    "block_time",
+    "success",
+    "fee",
+    "compute_units_consumed",
+    "fee_payer",
+    "priority_fee_micro_lamports",
+    "error_class",
+    "memo",
+    "event_types",
+];
+
+/// Parquet schema of `COLUMNS`; every column is nullable so rows are written alike
+const PARQUET_SCHEMA: &str = "
+    message transaction {
+        OPTIONAL BYTE_ARRAY signature (UTF8);
+        OPTIONAL INT64 slot;
+        OPTIONAL INT64 block_time;
+        OPTIONAL BOOLEAN success;
+        OPTIONAL INT64 fee;
+        OPTIONAL INT64 compute_units_consumed;
+        OPTIONAL BYTE_ARRAY fee_payer (UTF8);
+        OPTIONAL INT64 priority_fee_micro_lamports;
+        OPTIONAL BYTE_ARRAY error_class (UTF8);
+        OPTIONAL BYTE_ARRAY memo (UTF8);
+        OPTIONAL BYTE_ARRAY event_types (UTF8);
+    }
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per line
+    Jsonl,
+    Csv,
+    /// Snappy-compressed Parquet
+    Parquet,
+}
+
+/// Outcome of an export
+#[derive(Debug, Default, Serialize)]
+pub struct ExportStats {
+    /// Slots with stored transactions
+    pub slots: u64,
+    pub transactions: u64,
+}
+
+/// A transaction as a flat row
+struct Row {
+    signature: String,
+    slot: u64,
+    block_time: Option<i64>,
+    success: Option<bool>,
+    fee: Option<u64>,
+    compute_units_consumed: Option<u64>,
+    fee_payer: Option<String>,
+    priority_fee_micro_lamports: Option<u64>,
+    error_class: Option<String>,
+    memo: Option<String>,
+    event_types: String,
+}
+
+enum Writer {
+    Jsonl(BufWriter<File>),
+    Csv(BufWriter<File>),
+    Parquet { writer: SerializedFileWriter<File>, rows: Vec<Row> },
+}
+
+/// Write the stored transactions of slots `from_slot` to `to_slot` (inclusive) to `out`, slot by
+/// slot, logging progress as it goes
+pub fn export(storage: &Storage, from_slot: u64, to_slot: u64, format: ExportFormat, out: &str) -> Result<ExportStats> {
+    if to_slot < from_slot {
+        bail!("--to-slot must not be before --from-slot");
+    }
+    let file = File::create(out).with_context(|| format!("Failed to create {}", out))?;
+    let mut writer = Writer::new(file, format)?;
+    let mut stats = ExportStats::default();
+    let mut last_report = Instant::now();
+    
+    let mut from = from_slot;
+    while let Some(slot) = storage.get_next_stored_slot(from)? {
+        if slot > to_slot {
+            break;
+        }
+        let mut cursor = None;
+        loop {
+            let page = storage.get_slot_signatures(slot, cursor.as_deref(), PAGE_SIZE)?;
+            for tx in storage.get_transactions(&page.items)?.into_iter().flatten() {
+                writer.write(&tx)?;
+                stats.transactions += 1;
+            }
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        stats.slots += 1;
+        
+        if last_report.elapsed() >= PROGRESS_INTERVAL {
+            info!(
+                "Exported {} transactions up to slot {} ({:.1}% of the range)",
+                stats.transactions,
+                slot,
+                (slot - from_slot + 1) as f64 * 100.0 / (to_slot - from_slot + 1) as f64
+            );
+            last_report = Instant::now();
+        }
+        match slot.checked_add(1) {
+            Some(next) => from = next,
+            None => break,
+        }
+    }
+    
+    writer.finish()?;
+    info!("Exported {} transactions from {} slots to {}", stats.transactions, stats.slots, out);
+    Ok(stats)
+}
+
+impl Writer {
+    fn new(file: File, format: ExportFormat) -> Result<Self> {
+        Ok(match format {
+            ExportFormat::Jsonl => Writer::Jsonl(BufWriter::new(file)),
+            ExportFormat::Csv => {
+                let mut out = BufWriter::new(file);
+                writeln!(out, "{}", COLUMNS.join(","))?;
+                Writer::Csv(out)
+            }
+            ExportFormat::Parquet => {
+                let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+                let properties = Arc::new(WriterProperties::builder().set_compression(Compression::SNAPPY).build());
+                Writer::Parquet {
+                    writer: SerializedFileWriter::new(file, schema, properties)?,
+                    rows: Vec::new(),
+                }
+            }
+        })
+    }
+    
+    fn write(&mut self, tx: &StoredTransaction) -> Result<()> {
+        match self {
+            Writer::Jsonl(out) => {
+                serde_json::to_writer(&mut *out, &processed_view(tx)?)?;
+                writeln!(out)?;
+            }
+            Writer::Csv(out) => {
+                let row = Row::from(tx);
+                let fields = [
+                    row.signature,
+                    row.slot.to_string(),
+                    text(row.block_time),
+                    text(row.success),
+                    text(row.fee),
+                    text(row.compute_units_consumed),
+                    row.fee_payer.unwrap_or_default(),
+                    text(row.priority_fee_micro_lamports),
+                    row.error_class.unwrap_or_default(),
+                    row.memo.unwrap_or_default(),
+                    row.event_types,
+                ];
+                let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                writeln!(out, "{}", fields.join(","))?;
+            }
+            Writer::Parquet { writer, rows } => {
+                rows.push(Row::from(tx));
+                if rows.len() >= PARQUET_ROW_GROUP {
+                    write_row_group(writer, rows)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    fn finish(self) -> Result<()> {
+        match self {
+            Writer::Jsonl(mut out) | Writer::Csv(mut out) => out.flush()?,
+            Writer::Parquet { mut writer, mut rows } => {
+                if !rows.is_empty() {
+                    write_row_group(&mut writer, &mut rows)?;
+                }
+                writer.close()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&StoredTransaction> for Row {
+    fn from(tx: &StoredTransaction) -> Self {
+        let meta = tx.transaction.transaction.meta.as_ref();
+        Row {
+            signature: tx.signature.clone(),
+            slot: tx.slot,
+            block_time: tx.transaction.block_time,
+            success: meta.map(|meta| meta.err.is_none()),
+            fee: meta.map(|meta| meta.fee),
+            compute_units_consumed: meta.and_then(|meta| Option::<u64>::from(meta.compute_units_consumed.clone())),
+            fee_payer: tx.fee_payer.clone(),
+            priority_fee_micro_lamports: tx.priority_fee_micro_lamports,
+            error_class: tx.error_class.clone(),
+            memo: tx.memo.clone(),
+            event_types: tx.event_types().join(","),
+        }
+    }
+}
+
+/// Write the buffered rows as a row group, one column after the other in `COLUMNS` order
+fn write_row_group(writer: &mut SerializedFileWriter<File>, rows: &mut Vec<Row>) -> Result<()> {
+    let mut row_group = writer.next_row_group()?;
+    let mut index = 0;
+    while let Some(mut column) = row_group.next_column()? {
+        let strings = |value: fn(&Row) -> Option<&String>| {
+            rows.iter().map(move |row| value(row).map(|value| ByteArray::from(value.as_str())))
+        };
+        let ints = |value: fn(&Row) -> Option<u64>| rows.iter().map(move |row| value(row).map(|value| value as i64));
+        match index {
+            0 => write_column::<ByteArrayType>(&mut column, strings(|row| Some(&row.signature)))?,
+            1 => write_column::<Int64Type>(&mut column, ints(|row| Some(row.slot)))?,
+            2 => write_column::<Int64Type>(&mut column, rows.iter().map(|row| row.block_time))?,
+            3 => write_column::<BoolType>(&mut column, rows.iter().map(|row| row.success))?,
+            4 => write_column::<Int64Type>(&mut column, ints(|row| row.fee))?,
+            5 => write_column::<Int64Type>(&mut column, ints(|row| row.compute_units_consumed))?,
+            6 => write_column::<ByteArrayType>(&mut column, strings(|row| row.fee_payer.as_ref()))?,
+            7 => write_column::<Int64Type>(&mut column, ints(|row| row.priority_fee_micro_lamports))?,
+            8 => write_column::<ByteArrayType>(&mut column, strings(|row| row.error_class.as_ref()))?,
+            9 => write_column::<ByteArrayType>(&mut column, strings(|row| row.memo.as_ref()))?,
+            _ => write_column::<ByteArrayType>(&mut column, strings(|row| Some(&row.event_types)))?,
+        }
+        column.close()?;
+        index += 1;
+    }
+    row_group.close()?;
+    rows.clear();
+    Ok(())
+}
+
+/// Write a nullable column, absent values as nulls
+fn write_column<T: DataType>(
+    column: &mut SerializedColumnWriter<'_>,
+    values: impl Iterator<Item = Option<T::T>>,
+) -> Result<()> {
+    let mut present = Vec::new();
+    let mut definition_levels = Vec::new();
+    for value in values {
+        definition_levels.push(i16::from(value.is_some()));
+        present.extend(value);
+    }
+    column.typed::<T>().write_batch(&present, Some(&definition_levels), None)?;
+    Ok(())
+}
+
+fn text<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Quote a CSV field holding a separator, quote or line break, doubling its quotes
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,369 @@
+//! A small expression language for storage filters, e.g.
+//! `fee > 10000 && programs contains "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4" && !is_vote`
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::transaction_processor::ProcessedTransaction;
+
+/// A compiled filter expression
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Literal),
+    /// A boolean field used on its own (e.g. `is_vote`)
+    Flag(Field),
+    Literal(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Fee,
+    Slot,
+    IsVote,
+    Failed,
+    ErrorClass,
+    FeePayer,
+    NumSigners,
+    IsMultisig,
+    IsDurableNonce,
+    ComputeUnits,
+    CuLimit,
+    PriorityFee,
+    InstructionCount,
+    WireSize,
+    Memo,
+    Programs,
+    Accounts,
+    WritableAccounts,
+    Categories,
+    Labels,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Number(u64),
+    String(String),
+    Bool(bool),
+}
+
+/// Value of a field for a particular transaction
+enum Value<'a> {
+    Number(Option<u64>),
+    String(Option<&'a str>),
+    Bool(bool),
+    List(Vec<&'a str>),
+}
+
+impl Field {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "fee" => Field::Fee,
+            "slot" => Field::Slot,
+            "is_vote" => Field::IsVote,
+            "failed" => Field::Failed,
+            "error_class" => Field::ErrorClass,
+            "fee_payer" => Field::FeePayer,
+            "num_signers" => Field::NumSigners,
+            "is_multisig" => Field::IsMultisig,
+            "is_durable_nonce" => Field::IsDurableNonce,
+            "compute_units" => Field::ComputeUnits,
+            "cu_limit" => Field::CuLimit,
+            "priority_fee" => Field::PriorityFee,
+            "instruction_count" => Field::InstructionCount,
+            "wire_size" => Field::WireSize,
+            "memo" => Field::Memo,
+            "programs" => Field::Programs,
+            "accounts" => Field::Accounts,
+            "writable_accounts" => Field::WritableAccounts,
+            "categories" => Field::Categories,
+            "labels" => Field::Labels,
+            _ => bail!("Unknown filter field `{}`", name),
+        })
+    }
+    
+    fn is_bool(self) -> bool {
+        matches!(self, Field::IsVote | Field::Failed | Field::IsMultisig | Field::IsDurableNonce)
+    }
+    
+    fn value(self, tx: &ProcessedTransaction) -> Value<'_> {
+        match self {
+            Field::Fee => Value::Number(Some(tx.fee)),
+            Field::Slot => Value::Number(Some(tx.slot)),
+            Field::IsVote => Value::Bool(tx.is_vote),
+            Field::Failed => Value::Bool(tx.error.is_some()),
+            Field::ErrorClass => Value::String(tx.error_class.as_deref()),
+            Field::FeePayer => Value::String(tx.fee_payer.as_deref()),
+            Field::NumSigners => Value::Number(Some(tx.num_signers as u64)),
+            Field::IsMultisig => Value::Bool(tx.is_multisig),
+            Field::IsDurableNonce => Value::Bool(tx.durable_nonce.is_some()),
+            Field::ComputeUnits => Value::Number(tx.compute_units_consumed),
+            Field::CuLimit => Value::Number(tx.cu_limit.map(u64::from)),
+            Field::PriorityFee => Value::Number(tx.priority_fee_micro_lamports),
+            Field::InstructionCount => Value::Number(Some(tx.instruction_count as u64)),
+            Field::WireSize => Value::Number(tx.wire_size.map(|size| size as u64)),
+            Field::Memo => Value::String(tx.memo.as_deref()),
+            Field::Programs => Value::List(tx.program_ids()),
+            Field::Accounts => Value::List(tx.account_keys.iter().map(String::as_str).collect()),
+            Field::WritableAccounts => Value::List(tx.writable_accounts()),
+            Field::Categories => Value::List(tx.program_categories.iter().map(String::as_str).collect()),
+            Field::Labels => Value::List(
+                tx.labeled_accounts
+                    .iter()
+                    .flat_map(|account| std::iter::once(account.label.as_str()).chain(account.category.as_deref()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Expr {
+    /// Compile a filter expression
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, position: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected {:?} in filter expression", token);
+        }
+        Ok(expr)
+    }
+    
+    /// Evaluate the expression against a processed transaction
+    pub fn evaluate(&self, tx: &ProcessedTransaction) -> bool {
+        match self {
+            Expr::And(left, right) => left.evaluate(tx) && right.evaluate(tx),
+            Expr::Or(left, right) => left.evaluate(tx) || right.evaluate(tx),
+            Expr::Not(inner) => !inner.evaluate(tx),
+            Expr::Flag(field) => matches!(field.value(tx), Value::Bool(true)),
+            Expr::Literal(value) => *value,
+            Expr::Compare(field, op, literal) => compare(field.value(tx), *op, literal),
+        }
+    }
+}
+
+fn compare(value: Value<'_>, op: Op, literal: &Literal) -> bool {
+    match (value, literal) {
+        (Value::Number(Some(value)), Literal::Number(expected)) => match op {
+            Op::Eq => value == *expected,
+            Op::Ne => value != *expected,
+            Op::Gt => value > *expected,
+            Op::Ge => value >= *expected,
+            Op::Lt => value < *expected,
+            Op::Le => value <= *expected,
+            Op::Contains => false,
+        },
+        (Value::String(value), Literal::String(expected)) => match op {
+            Op::Eq => value == Some(expected.as_str()),
+            Op::Ne => value != Some(expected.as_str()),
+            Op::Contains => value.map_or(false, |value| value.contains(expected.as_str())),
+            _ => false,
+        },
+        (Value::Bool(value), Literal::Bool(expected)) => match op {
+            Op::Eq => value == *expected,
+            Op::Ne => value != *expected,
+            _ => false,
+        },
+        (Value::List(values), Literal::String(expected)) => {
+            let contains = values.iter().any(|value| value == expected);
+            match op {
+                Op::Contains | Op::Eq => contains,
+                Op::Ne => !contains,
+                _ => false,
+            }
+        }
+        // Missing values (e.g. no compute units reported) only satisfy `!=`
+        (Value::Number(None), _) | (Value::String(None), _) => op == Op::Ne,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    String(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '&' | '|' => {
+                chars.next();
+                if chars.next() != Some(c) {
+                    bail!("Expected `{}{}` in filter expression", c, c);
+                }
+                tokens.push(if c == '&' { Token::And } else { Token::Or });
+            }
+            '!' | '=' | '>' | '<' => {
+                chars.next();
+                let followed_by_eq = chars.peek() == Some(&'=');
+                if followed_by_eq {
+                    chars.next();
+                }
+                tokens.push(match (c, followed_by_eq) {
+                    ('!', false) => Token::Not,
+                    ('!', true) => Token::Op(Op::Ne),
+                    ('=', true) => Token::Op(Op::Eq),
+                    ('>', false) => Token::Op(Op::Gt),
+                    ('>', true) => Token::Op(Op::Ge),
+                    ('<', false) => Token::Op(Op::Lt),
+                    ('<', true) => Token::Op(Op::Le),
+                    _ => bail!("Expected `==` in filter expression"),
+                });
+            }
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => bail!("Unterminated string in filter expression"),
+                    }
+                }
+                tokens.push(Token::String(value));
+            }
+            c if c.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_digit() || c == '_' {
+                        digits.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let number = digits.replace('_', "").parse()
+                    .map_err(|_| anyhow!("Invalid number `{}` in filter expression", digits))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        ident.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(match ident.as_str() {
+                    "contains" => Token::Op(Op::Contains),
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Ident(ident),
+                });
+            }
+            _ => bail!("Unexpected character `{}` in filter expression", c),
+        }
+    }
+    
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+    
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+    
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+    
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+    
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+    
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => bail!("Expected `)` in filter expression"),
+                }
+            }
+            Some(Token::Ident(name)) if name == "true" || name == "false" => Ok(Expr::Literal(name == "true")),
+            Some(Token::Ident(name)) => {
+                let field = Field::parse(&name)?;
+                let op = match self.peek() {
+                    Some(Token::Op(op)) => *op,
+                    _ if field.is_bool() => return Ok(Expr::Flag(field)),
+                    _ => bail!("Expected a comparison after `{}`", name),
+                };
+                self.next();
+                let literal = match self.next() {
+                    Some(Token::Number(number)) => Literal::Number(number),
+                    Some(Token::String(value)) => Literal::String(value),
+                    Some(Token::Ident(value)) if value == "true" || value == "false" => Literal::Bool(value == "true"),
+                    other => bail!("Expected a value after `{}`, found {:?}", name, other),
+                };
+                Ok(Expr::Compare(field, op, literal))
+            }
+            other => bail!("Unexpected {:?} in filter expression", other),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use maxminddb::{geoip2, Reader};
+use std::net::IpAddr;
+use tracing::info;
+
+use crate::config::GossipConfig;
+
+/// Location and network of an IP address
+#[derive(Debug, Clone, Default)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: Option<String>,
+    pub asn: Option<u32>,
+    /// Organization operating the autonomous system
+    pub as_org: Option<String>,
+}
+
+/// Lookups against local MaxMind databases (GeoLite2 or GeoIP2, Country or City, and ASN)
+pub struct GeoIp {
+    country: Option<Reader<Vec<u8>>>,
+    asn: Option<Reader<Vec<u8>>>,
+}
+
+impl GeoIp {
+    /// Open the databases configured under `[gossip]`, if any
+    pub fn from_config(config: &GossipConfig) -> Result<Option<Self>> {
+        if config.geoip_country_db.is_none() && config.geoip_asn_db.is_none() {
+            return Ok(None);
+        }
+        
+        let open = |path: &Option<String>| -> Result<Option<Reader<Vec<u8>>>> {
+            path.as_ref()
+                .map(|path| {
+                    info!("Loading GeoIP database {}", path);
+                    Reader::open_readfile(path).with_context(|| format!("Failed to open GeoIP database {}", path))
+                })
+                .transpose()
+        };
+        Ok(Some(Self {
+            country: open(&config.geoip_country_db)?,
+            asn: open(&config.geoip_asn_db)?,
+        }))
+    }
+    
+    /// Look up an address; fields the databases lack or do not cover stay unset
+    pub fn lookup(&self, ip: IpAddr) -> GeoInfo {
+        let country = self.country
+            .as_ref()
+            .and_then(|reader| reader.lookup::<geoip2::Country>(ip).ok())
+            .and_then(|record| record.country)
+            .and_then(|country| country.iso_code)
+            .map(str::to_string);
+        let asn = self.asn
+            .as_ref()
+            .and_then(|reader| reader.lookup::<geoip2::Asn>(ip).ok());
+        
+        GeoInfo {
+            country,
+            asn: asn.as_ref().and_then(|asn| asn.autonomous_system_number),
+            as_org: asn.and_then(|asn| asn.autonomous_system_organization).map(str::to_string),
+        }
+    }
+}
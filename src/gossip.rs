@@ -1,72 +1,494 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
 use solana_gossip::{
-    cluster_info::{ClusterInfo, Node},
-    contact_info::ContactInfo,
-    gossip_service::GossipService,
+    cluster_info::ClusterInfo,
+    contact_info::Protocol,
+    crds::Cursor,
+    legacy_contact_info::LegacyContactInfo,
 };
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_net_utils::PortRange;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
 use solana_sdk::{
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
+    signature::{read_keypair_file, Keypair, Signer},
+    timing::timestamp,
 };
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::sync::Arc;
-use tracing::{info, error};
+use solana_streamer::socket::SocketAddrSpace;
+use std::collections::{BTreeMap, HashSet};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use crate::cluster_health::ClusterHealthTracker;
+use crate::config::Config;
+use crate::crds_export::CrdsSnapshot;
+use crate::geoip::{GeoInfo, GeoIp};
+use crate::gossip_supervisor::GossipSupervisor;
+use crate::decoder::vote;
+use crate::leader_schedule::LeaderSchedule;
+use crate::repair;
+use crate::shred::{self, ShredAssembler};
+use crate::storage::Storage;
+use crate::validator_registry::ValidatorRegistry;
+use crate::version_report::VersionReport;
+
+/// Saved peers added to the configured entrypoints at startup
+const SAVED_PEER_ENTRYPOINTS: usize = 64;
+
+/// How often the peer table is saved and the top staked peers are logged
+const PEER_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often validator stakes and names are reloaded
+const VALIDATOR_REFRESH_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Interval between checks for the leader schedule of a new epoch
+const LEADER_SCHEDULE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the version distribution is reported and stored
+const VERSION_REPORT_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Number of top staked peers logged
+const TOP_STAKED_PEERS: usize = 5;
+
+/// Contact details of a discovered peer, saved so the node can rejoin the cluster without its
+/// configured entrypoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GossipPeer {
+    pub pubkey: String,
+    pub gossip: SocketAddr,
+    pub rpc: Option<SocketAddr>,
+    pub shred_version: u16,
+    /// Local time (milliseconds since the epoch) the peer was last heard from
+    pub last_seen: u64,
+}
+
+impl GossipPeer {
+    /// Snapshot a peer's contact info, if it advertises a usable gossip address
+    fn from_contact_info(info: &LegacyContactInfo, last_seen: u64) -> Option<Self> {
+        Some(Self {
+            pubkey: info.pubkey().to_string(),
+            gossip: info.gossip().ok()?,
+            rpc: info.rpc().ok(),
+            shred_version: info.shred_version(),
+            last_seen,
+        })
+    }
+}
+
+/// A gossip peer annotated with the stake and name of the validator it runs, if any
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterNode {
+    pub pubkey: String,
+    pub gossip: Option<SocketAddr>,
+    pub tpu: Option<SocketAddr>,
+    pub tvu: Option<SocketAddr>,
+    pub rpc: Option<SocketAddr>,
+    pub shred_version: u16,
+    /// Software version the node advertises, if it has pushed one
+    pub version: Option<String>,
+    /// Leading bytes of the identifier of the node's enabled feature set
+    pub feature_set: Option<u32>,
+    pub vote_account: Option<String>,
+    /// Activated stake in lamports (0 for unstaked nodes)
+    pub activated_stake: u64,
+    pub name: Option<String>,
+    /// Country code and autonomous system of the gossip IP, when GeoIP databases are configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asn: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_org: Option<String>,
+}
+
+/// A peer advertising snapshots in gossip. Snapshots are downloaded from its RPC address
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotSource {
+    pub pubkey: String,
+    pub rpc: SocketAddr,
+    pub full_slot: u64,
+    pub full_hash: String,
+    /// Newest incremental snapshot on top of the full one, if any
+    pub incremental_slot: Option<u64>,
+    pub incremental_hash: Option<String>,
+}
+
+impl SnapshotSource {
+    /// Slot of the newest snapshot the peer serves
+    pub fn latest_slot(&self) -> u64 {
+        self.incremental_slot.unwrap_or(self.full_slot)
+    }
+}
+
+/// Gossip activity of a running node. Message counters are cumulative since startup; churn
+/// is counted since the previous sample
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GossipMetrics {
+    pub peers: usize,
+    pub peers_joined: usize,
+    pub peers_left: usize,
+    /// Entries in the CRDS (cluster replicated data store) table
+    pub crds_values: usize,
+    /// Values received in push messages, and how many of those were already known
+    pub push_values_received: usize,
+    pub push_values_duplicate: usize,
+    pub push_messages_sent: usize,
+    /// New values inserted from pull responses
+    pub pull_values_inserted: usize,
+}
+
+/// Samples the metrics and peers of a gossip node from outside the task running it
+#[derive(Clone)]
+pub struct GossipMonitor {
+    cluster_info: Arc<ClusterInfo>,
+    validators: ValidatorRegistry,
+    geoip: Option<Arc<GeoIp>>,
+    /// Peers seen by the previous sample, for churn
+    previous_peers: Arc<Mutex<HashSet<Pubkey>>>,
+}
+
+impl GossipMonitor {
+    /// Current peers, highest stake first
+    pub fn cluster_nodes(&self) -> Vec<ClusterNode> {
+        cluster_nodes(&self.cluster_info, &self.validators, self.geoip.as_deref())
+    }
+    
+    /// Continue gossip under a new identity. Our contact info and version are re-signed and
+    /// pushed with the new key; peers drop the old identity once its entries expire
+    pub fn set_identity(&self, keypair: Keypair) {
+        info!("Switching gossip identity from {} to {}", self.cluster_info.id(), keypair.pubkey());
+        self.cluster_info.set_keypair(Arc::new(keypair));
+    }
+    
+    /// Current contents of the gossip table
+    pub fn crds_snapshot(&self) -> CrdsSnapshot {
+        CrdsSnapshot::capture(&self.cluster_info, self.cluster_nodes(), snapshot_sources(&self.cluster_info), timestamp())
+    }
+    
+    pub fn sample(&self) -> GossipMetrics {
+        let peers: HashSet<Pubkey> = self.cluster_info
+            .all_peers()
+            .iter()
+            .map(|(info, _)| *info.pubkey())
+            .collect();
+        let mut previous_peers = self.previous_peers.lock().unwrap();
+        let peers_joined = peers.difference(&previous_peers).count();
+        let peers_left = previous_peers.difference(&peers).count();
+        let metrics = GossipMetrics {
+            peers: peers.len(),
+            peers_joined,
+            peers_left,
+            crds_values: self.cluster_info.gossip.crds.read().unwrap().len(),
+            push_values_received: self.cluster_info.gossip.push.num_total.load(Ordering::Relaxed),
+            push_values_duplicate: self.cluster_info.gossip.push.num_old.load(Ordering::Relaxed),
+            push_messages_sent: self.cluster_info.gossip.push.num_pushes.load(Ordering::Relaxed),
+            pull_values_inserted: self.cluster_info.gossip.pull.num_pulls.load(Ordering::Relaxed),
+        };
+        *previous_peers = peers;
+        metrics
+    }
+}
+
+/// Where a node binds its sockets and which addresses it advertises and accepts
+pub struct SocketOptions {
+    /// Gossip socket address (port 0 = first free port in `port_range`)
+    pub bind_address: SocketAddr,
+    /// Ports the TVU and repair sockets, and a gossip socket with port 0, are picked from
+    pub port_range: PortRange,
+    /// IP advertised to peers (default: the bind IP)
+    pub public_ip: Option<IpAddr>,
+    /// Peer addresses gossip with; `Unspecified` also accepts private and loopback addresses
+    pub socket_addr_space: SocketAddrSpace,
+}
 
 pub struct P2PNode {
-    keypair: Arc<Keypair>,
+    /// Holds the node's identity, which may change while the node runs
     cluster_info: Arc<ClusterInfo>,
-    gossip_service: Option<GossipService>,
+    /// Bound at construction so address errors surface early; handed to the gossip service on start
+    gossip_socket: Option<UdpSocket>,
+    /// Gossip address advertised to peers
+    gossip_addr: SocketAddr,
+    /// Entrypoints, including saved peers, re-applied when gossip restarts
+    entrypoints: Vec<SocketAddr>,
+    /// Stop when another node advertises our identity
+    check_duplicate_instance: bool,
+    /// Thread running and restarting the gossip service
+    gossip_supervisor: Option<JoinHandle<()>>,
+    /// Turbine socket, handed to the shred receiver on start
+    tvu_socket: Option<UdpSocket>,
+    /// Socket repair requests are sent from and answered to
+    repair_socket: Option<UdpSocket>,
+    /// Transactions decoded from shreds, until taken for ingestion
+    transactions: Option<mpsc::Receiver<EncodedConfirmedTransactionWithStatusMeta>>,
+    transaction_sender: Option<mpsc::Sender<EncodedConfirmedTransactionWithStatusMeta>>,
+    /// Slot leaders shreds are checked against, loaded from `rpc_url` when the TVU is enabled
+    leader_schedule: Option<LeaderSchedule>,
+    exit: Arc<AtomicBool>,
+    /// Where the peer table is saved, if anywhere
+    storage: Option<Storage>,
+    /// Record gossip votes in `storage`
+    listen_votes: bool,
+    /// Stakes and names of validators, for annotating peers
+    validators: ValidatorRegistry,
+    /// Locates peers, if GeoIP databases are configured
+    geoip: Option<Arc<GeoIp>>,
+    /// Periodic gossip table dumps: directory and interval
+    crds_export: Option<(String, Duration)>,
+    /// RPC endpoint the validator registry is refreshed from, if any
+    rpc_url: Option<String>,
 }
 
 impl P2PNode {
+    /// Create a node with its gossip socket bound as described by `sockets`. With `tvu`, a
+    /// turbine socket is also bound in the port range and advertised, along with a socket for
+    /// repairing missed shreds.
     pub fn new(
         keypair: Keypair,
         entrypoints: Vec<SocketAddr>,
-        bind_address: SocketAddr,
+        sockets: SocketOptions,
+        shred_version: u16,
+        tvu: bool,
     ) -> Result<Self> {
+        let SocketOptions { bind_address, port_range, public_ip, socket_addr_space } = sockets;
         let keypair = Arc::new(keypair);
         let node_pubkey = keypair.pubkey();
         
-        // Create node identity
-        let node = Node::new_localhost_with_pubkey(&node_pubkey);
+        // Bind the gossip socket
+        let gossip_socket = if bind_address.port() == 0 {
+            solana_net_utils::bind_in_range(bind_address.ip(), port_range)
+                .map(|(_, socket)| socket)
+                .with_context(|| format!("No free gossip port in {}-{}", port_range.0, port_range.1))?
+        } else {
+            UdpSocket::bind(bind_address)
+                .with_context(|| format!("Failed to bind gossip socket to {}", bind_address))?
+        };
+        let local_addr = gossip_socket.local_addr()?;
+        
+        // Advertise an address other nodes can reach
+        let public_ip = public_ip.unwrap_or_else(|| local_addr.ip());
+        let gossip_addr = SocketAddr::new(public_ip, local_addr.port());
+        if public_ip.is_unspecified() || !socket_addr_space.check(&gossip_addr) {
+            warn!(
+                "Advertising gossip address {}, which other nodes cannot reach; set gossip.public_ip \
+                 (or gossip.allow_private_addr on a local cluster)",
+                public_ip
+            );
+        }
         
-        // Create contact info
-        let contact_info = ContactInfo::new_localhost(&node_pubkey, 0);
+        // Shred version 0 marks a node that does not take part in turbine; peers still answer
+        // its pull requests
+        let mut contact_info = ClusterInfo::gossip_contact_info(node_pubkey, gossip_addr, shred_version);
+        
+        // Bind and advertise the turbine socket; the repair socket only talks to peers we ask
+        let (tvu_socket, repair_socket) = if tvu {
+            let (port, socket) = solana_net_utils::bind_in_range(bind_address.ip(), port_range)
+                .with_context(|| format!("No free TVU port in {}-{}", port_range.0, port_range.1))?;
+            contact_info
+                .set_tvu(SocketAddr::new(public_ip, port))
+                .map_err(|e| anyhow!("Invalid TVU address: {:?}", e))?;
+            let (_, repair_socket) = solana_net_utils::bind_in_range(bind_address.ip(), port_range)
+                .with_context(|| format!("No free repair port in {}-{}", port_range.0, port_range.1))?;
+            (Some(socket), Some(repair_socket))
+        } else {
+            (None, None)
+        };
+        let (transaction_sender, transactions) = match tvu {
+            true => {
+                let (sender, receiver) = mpsc::channel(1000);
+                (Some(sender), Some(receiver))
+            }
+            false => (None, None),
+        };
         
         // Initialize cluster info
         let cluster_info = Arc::new(ClusterInfo::new(
             contact_info,
-            keypair.clone(),
-            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0),
+            keypair,
+            socket_addr_space,
         ));
         
-        // Set entrypoints
-        cluster_info.set_entrypoints(entrypoints);
+        set_entrypoints(&cluster_info, &entrypoints);
         
         Ok(Self {
-            keypair,
             cluster_info,
-            gossip_service: None,
+            gossip_socket: Some(gossip_socket),
+            gossip_addr,
+            entrypoints,
+            check_duplicate_instance: true,
+            gossip_supervisor: None,
+            tvu_socket,
+            repair_socket,
+            transactions,
+            transaction_sender,
+            leader_schedule: tvu.then(LeaderSchedule::new),
+            exit: Arc::new(AtomicBool::new(false)),
+            storage: None,
+            listen_votes: false,
+            validators: ValidatorRegistry::new(),
+            geoip: None,
+            crds_export: None,
+            rpc_url: None,
         })
     }
     
+    /// Build a node from the `[gossip]` section, falling back to `network.gossip_entrypoints`
+    /// and `node.identity_keypair_path` (or a fresh keypair) when unset there.
+    ///
+    /// Peers saved in `storage` by a previous run are added as entrypoints, and the peer table
+    /// is saved there while the node runs. Without storage the node keeps nothing.
+    pub fn from_config(config: &Config, storage: Option<Storage>) -> Result<Self> {
+        let gossip = &config.gossip;
+        
+        let mut entrypoints: Vec<SocketAddr> = config
+            .gossip_entrypoints()
+            .iter()
+            .filter_map(|entrypoint| match resolve_entrypoint(entrypoint) {
+                Ok(addr) => Some(addr),
+                Err(e) => {
+                    warn!("Skipping gossip entrypoint {}: {}", entrypoint, e);
+                    None
+                }
+            })
+            .collect();
+        
+        let keypair = match config.identity_keypair_path() {
+            Some(path) => read_keypair_file(path)
+                .map_err(|e| anyhow!("Failed to read identity keypair {}: {}", path, e))?,
+            None => Keypair::new(),
+        };
+        
+        let bind_address: SocketAddr = gossip.bind_address
+            .parse()
+            .with_context(|| format!("Invalid gossip bind address {}", gossip.bind_address))?;
+        let port_range = solana_net_utils::parse_port_range(&gossip.port_range)
+            .with_context(|| format!("Invalid gossip port range {}", gossip.port_range))?;
+        
+        // Without a configured public IP, ask an entrypoint which address we reach it from
+        let public_ip = match &gossip.public_ip {
+            Some(public_ip) => Some(
+                public_ip
+                    .parse()
+                    .with_context(|| format!("Invalid gossip public IP {}", public_ip))?,
+            ),
+            None if bind_address.ip().is_unspecified() => discover_public_ip(&entrypoints),
+            None => None,
+        };
+        
+        // Turbine only sends shreds, and peers only push votes, to nodes advertising the
+        // cluster's shred version
+        let shred_version = match gossip.shred_version {
+            Some(shred_version) => shred_version,
+            None if gossip.tvu || gossip.vote_listener => discover_shred_version(&entrypoints).unwrap_or_else(|| {
+                warn!("Could not determine the cluster shred version; set gossip.shred_version to receive shreds and votes");
+                0
+            }),
+            None => 0,
+        };
+        
+        // Fall back on recently seen peers in case the entrypoints are down
+        if let Some(storage) = &storage {
+            add_saved_peers(&mut entrypoints, storage)?;
+        }
+        
+        if entrypoints.is_empty() {
+            warn!("No usable gossip entrypoints; the node can only be discovered by peers");
+        }
+        
+        let sockets = SocketOptions {
+            bind_address,
+            port_range,
+            public_ip,
+            socket_addr_space: SocketAddrSpace::new(gossip.allow_private_addr),
+        };
+        let mut node = Self::new(keypair, entrypoints, sockets, shred_version, gossip.tvu)?;
+        node.storage = storage;
+        node.listen_votes = gossip.vote_listener;
+        node.check_duplicate_instance = gossip.check_duplicate_instance;
+        node.geoip = GeoIp::from_config(gossip)?.map(Arc::new);
+        if gossip.crds_export_interval_secs > 0 {
+            node.crds_export = Some((
+                gossip.crds_export_dir.clone(),
+                Duration::from_secs(gossip.crds_export_interval_secs),
+            ));
+        }
+        node.rpc_url = config.network.rpc_endpoints.first().cloned();
+        Ok(node)
+    }
+    
     pub async fn start(&mut self) -> Result<()> {
-        info!("Starting P2P node with pubkey: {}", self.keypair.pubkey());
+        info!("Starting P2P node with pubkey: {}", self.cluster_info.id());
+        
+        let gossip_socket = self.gossip_socket
+            .take()
+            .context("P2P node already started")?;
+        let gossip_addr = gossip_socket.local_addr()?;
         
-        // Start gossip service
-        let (gossip_service, _gossip_socket) = GossipService::new(
-            &self.cluster_info,
-            None, // bank_forks
-            self.cluster_info.socket_addr_space(),
-            None, // gossip_validators
-            None, // should_check_duplicate_instance
-            None, // stats_reporter_sender
-            solana_streamer::socket::SocketAddrSpace::Unspecified,
-        )?;
+        // Start the gossip service, restarted if its threads die. Its own duplicate instance
+        // check exits the process, so duplicates are detected in `monitor_cluster` instead
+        let supervisor = GossipSupervisor::new(
+            self.cluster_info.clone(),
+            gossip_addr,
+            self.entrypoints.clone(),
+            self.storage.clone(),
+            self.exit.clone(),
+        );
+        self.gossip_supervisor = Some(
+            std::thread::Builder::new()
+                .name("gossipSupervisor".to_string())
+                .spawn(move || supervisor.run(gossip_socket))?,
+        );
         
-        self.gossip_service = Some(gossip_service);
+        info!("Gossip service started on {}", gossip_addr);
+        
+        // Start receiving shreds, from turbine and from repair responses
+        if let (Some(tvu_socket), Some(repair_socket), Some(sender), Some(leaders)) = (
+            self.tvu_socket.take(),
+            self.repair_socket.take(),
+            self.transaction_sender.take(),
+            self.leader_schedule.clone(),
+        ) {
+            info!(
+                "Receiving shreds on {}, repairing from {}",
+                tvu_socket.local_addr()?,
+                repair_socket.local_addr()?
+            );
+            let assembler = Arc::new(Mutex::new(ShredAssembler::default()));
+            
+            let (exit, assembler_clone, sender_clone) = (self.exit.clone(), assembler.clone(), sender.clone());
+            let leaders_clone = leaders.clone();
+            std::thread::Builder::new()
+                .name("shredReceiver".to_string())
+                .spawn(move || receive_shreds(tvu_socket, assembler_clone, leaders_clone, exit, sender_clone, None))?;
+            
+            let repair_receive_socket = repair_socket.try_clone()?;
+            let (exit, assembler_clone, cluster_info) = (self.exit.clone(), assembler.clone(), self.cluster_info.clone());
+            std::thread::Builder::new()
+                .name("repairReceiver".to_string())
+                .spawn(move || {
+                    receive_shreds(repair_receive_socket, assembler_clone, leaders, exit, sender, Some(cluster_info))
+                })?;
+            
+            let (exit, cluster_info) = (self.exit.clone(), self.cluster_info.clone());
+            std::thread::Builder::new()
+                .name("shredRepair".to_string())
+                .spawn(move || repair::run_repair(cluster_info, repair_socket, assembler, exit))?;
+        }
         
-        info!("Gossip service started");
+        // Record votes as they propagate
+        if let (true, Some(storage)) = (self.listen_votes, self.storage.clone()) {
+            let (exit, cluster_info) = (self.exit.clone(), self.cluster_info.clone());
+            std::thread::Builder::new()
+                .name("gossipVotes".to_string())
+                .spawn(move || listen_votes(cluster_info, storage, exit))?;
+        }
         
         // Monitor cluster
         self.monitor_cluster().await?;
@@ -75,23 +497,481 @@ impl P2PNode {
     }
     
     async fn monitor_cluster(&self) -> Result<()> {
+        let rpc_client = self.rpc_url.clone().map(RpcClient::new);
+        let mut last_snapshot = Instant::now();
+        let mut last_validator_refresh: Option<Instant> = None;
+        let mut last_leader_refresh: Option<Instant> = None;
+        let mut health = ClusterHealthTracker::default();
+        let mut last_export = Instant::now();
+        let mut last_version_report: Option<Instant> = None;
         loop {
-            let all_peers = self.cluster_info.all_peers();
-            info!("Connected to {} peers", all_peers.len());
+            if let Some(other) = self.duplicate_instance() {
+                self.exit.store(true, Ordering::Relaxed);
+                return Err(anyhow!(
+                    "Another node is running with gossip identity {} at {}; stopped gossip. \
+                     Give each node its own gossip.identity_keypair_path",
+                    self.cluster_info.id(),
+                    other
+                ));
+            }
+            
+            if let Some(client) = &rpc_client {
+                if last_validator_refresh.map_or(true, |refreshed| refreshed.elapsed() >= VALIDATOR_REFRESH_INTERVAL) {
+                    if let Err(e) = self.validators.refresh(client).await {
+                        warn!("Failed to refresh validator stakes: {}", e);
+                    }
+                    last_validator_refresh = Some(Instant::now());
+                }
+            }
+            
+            // Retried on the next check when it fails, as shreds are dropped until it loads
+            if let (Some(client), Some(leaders)) = (&rpc_client, &self.leader_schedule) {
+                if last_leader_refresh.map_or(true, |refreshed| refreshed.elapsed() >= LEADER_SCHEDULE_REFRESH_INTERVAL) {
+                    match leaders.refresh(client).await {
+                        Ok(()) => last_leader_refresh = Some(Instant::now()),
+                        Err(e) => warn!("Failed to refresh the leader schedule: {}", e),
+                    }
+                }
+            }
+            
+            health.observe_votes(&self.cluster_info);
             
-            for peer in all_peers.iter().take(5) {
-                info!("Peer: {} at {}", peer.id, peer.gossip);
+            // Peer counts and gossip traffic are reported with the node statistics
+            if last_snapshot.elapsed() >= PEER_SNAPSHOT_INTERVAL {
+                let nodes = self.get_cluster_nodes();
+                let evaluation = health.evaluate(&nodes, &self.validators, timestamp());
+                if let Some(storage) = &self.storage {
+                    if let Err(e) = storage.store_cluster_health(&evaluation) {
+                        error!("Failed to store cluster health: {}", e);
+                    }
+                }
+                
+                for node in nodes.iter().take(TOP_STAKED_PEERS) {
+                    if node.activated_stake == 0 {
+                        break;
+                    }
+                    info!(
+                        "Staked peer: {} ({}) with {:.0} SOL at {}",
+                        node.pubkey,
+                        node.name.as_deref().unwrap_or("unnamed"),
+                        node.activated_stake as f64 / 1e9,
+                        node.gossip.map(|addr| addr.to_string()).unwrap_or_else(|| "-".to_string())
+                    );
+                }
+                
+                let sources = self.get_snapshot_sources();
+                if let Some(newest) = sources.first() {
+                    info!(
+                        "Snapshots: {} peers serving, newest at slot {} from {} ({})",
+                        sources.len(),
+                        newest.latest_slot(),
+                        newest.pubkey,
+                        newest.rpc
+                    );
+                }
+                
+                if last_version_report.map_or(true, |reported| reported.elapsed() >= VERSION_REPORT_INTERVAL) {
+                    let report = VersionReport::from_nodes(&nodes, timestamp());
+                    report.log();
+                    if let Some(storage) = &self.storage {
+                        if let Err(e) = storage.store_version_report(&report) {
+                            error!("Failed to store version report: {}", e);
+                        }
+                    }
+                    last_version_report = Some(Instant::now());
+                }
+                
+                if let Err(e) = self.save_peers() {
+                    error!("Failed to save gossip peers: {}", e);
+                }
+                last_snapshot = Instant::now();
             }
             
-            tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+            if let Some((dir, interval)) = &self.crds_export {
+                if last_export.elapsed() >= *interval {
+                    match self.crds_snapshot().write_to(dir) {
+                        Ok(path) => info!("Exported gossip table to {}", path.display()),
+                        Err(e) => error!("Failed to export gossip table: {}", e),
+                    }
+                    last_export = Instant::now();
+                }
+            }
+            
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+    }
+    
+    /// Gossip address of another node advertising our identity, if any. Both nodes keep
+    /// overwriting the identity's contact info, so a duplicate shows up within a few checks
+    fn duplicate_instance(&self) -> Option<SocketAddr> {
+        if !self.check_duplicate_instance {
+            return None;
+        }
+        self.cluster_info
+            .lookup_contact_info(&self.cluster_info.id(), |info| info.gossip().ok())
+            .flatten()
+            .filter(|addr| *addr != self.gossip_addr)
+    }
+    
+    /// Replace the saved peer table with the current one
+    fn save_peers(&self) -> Result<()> {
+        let storage = match &self.storage {
+            Some(storage) => storage,
+            None => return Ok(()),
+        };
+        let peers: Vec<GossipPeer> = self.cluster_info
+            .all_peers()
+            .iter()
+            .filter_map(|(info, last_seen)| GossipPeer::from_contact_info(info, *last_seen))
+            .collect();
+        
+        // An empty table means we lost the cluster, not that it is gone; keep the last snapshot
+        if peers.is_empty() {
+            return Ok(());
         }
+        storage.replace_gossip_peers(&peers)
     }
     
-    pub fn get_cluster_nodes(&self) -> Vec<ContactInfo> {
-        self.cluster_info.all_peers()
+    /// Transactions decoded from turbine shreds, when the TVU is enabled. They carry no status
+    /// metadata (fee, error, logs), since shreds precede execution
+    pub fn take_transactions(&mut self) -> Option<mpsc::Receiver<EncodedConfirmedTransactionWithStatusMeta>> {
+        self.transactions.take()
+    }
+    
+    /// Handle sampling this node's metrics, usable after the node is moved into its task
+    pub fn monitor(&self) -> GossipMonitor {
+        GossipMonitor {
+            cluster_info: self.cluster_info.clone(),
+            validators: self.validators.clone(),
+            geoip: self.geoip.clone(),
+            previous_peers: Arc::default(),
+        }
+    }
+    
+    /// Peers with their validator stake and name, highest stake first
+    pub fn get_cluster_nodes(&self) -> Vec<ClusterNode> {
+        cluster_nodes(&self.cluster_info, &self.validators, self.geoip.as_deref())
+    }
+    
+    /// Peers with an RPC address advertising snapshot hashes, newest snapshot first, for
+    /// picking where to bootstrap from
+    pub fn get_snapshot_sources(&self) -> Vec<SnapshotSource> {
+        snapshot_sources(&self.cluster_info)
+    }
+    
+    /// Current contents of the gossip table
+    pub fn crds_snapshot(&self) -> CrdsSnapshot {
+        CrdsSnapshot::capture(&self.cluster_info, self.get_cluster_nodes(), self.get_snapshot_sources(), timestamp())
     }
     
     pub fn get_node_pubkey(&self) -> Pubkey {
-        self.keypair.pubkey()
+        self.cluster_info.id()
+    }
+}
+
+impl Drop for P2PNode {
+    fn drop(&mut self) {
+        // Stop the gossip threads
+        self.exit.store(true, Ordering::Relaxed);
+        if let Some(gossip_supervisor) = self.gossip_supervisor.take() {
+            let _ = gossip_supervisor.join();
+        }
+    }
+}
+
+/// Decode transactions from the data shreds arriving on `socket` until shutdown, dropping shreds
+/// not signed by the leader of their slot (all of them until the leader schedule is loaded).
+/// With a `ping_identity`, the socket is a repair socket and repair pings are answered as that
+/// node's current identity.
+fn receive_shreds(
+    socket: UdpSocket,
+    assembler: Arc<Mutex<ShredAssembler>>,
+    leaders: LeaderSchedule,
+    exit: Arc<AtomicBool>,
+    sender: mpsc::Sender<EncodedConfirmedTransactionWithStatusMeta>,
+    ping_identity: Option<Arc<ClusterInfo>>,
+) {
+    // Wake up periodically to notice shutdown
+    if let Err(e) = socket.set_read_timeout(Some(Duration::from_secs(1))) {
+        error!("Failed to configure shred socket: {}", e);
+        return;
     }
-} 
\ No newline at end of file
+    let mut packet = [0u8; shred::PACKET_DATA_SIZE];
+    
+    while !exit.load(Ordering::Relaxed) {
+        let (len, from) = match socket.recv_from(&mut packet) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => continue,
+            Err(e) => {
+                warn!("Shred receive error: {}", e);
+                continue;
+            }
+        };
+        
+        // Peers ping before serving repairs to a new address
+        if let Some(cluster_info) = &ping_identity {
+            match repair::answer_ping(&cluster_info.keypair(), &packet[..len]) {
+                Ok(Some(pong)) => {
+                    if let Err(e) = socket.send_to(&pong, from) {
+                        debug!("Failed to answer repair ping from {}: {}", from, e);
+                    }
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    debug!("Invalid repair ping from {}: {}", from, e);
+                    continue;
+                }
+            }
+        }
+        
+        let data_shred = match shred::parse_data_shred(&packet[..len]) {
+            Some(data_shred) => data_shred,
+            None => continue,
+        };
+        match leaders.leader(data_shred.slot) {
+            Some(leader) if shred::verify_signature(&packet[..len], &leader) => {}
+            Some(leader) => {
+                debug!(
+                    "Dropping shred {} of slot {} from {}: not signed by leader {}",
+                    data_shred.index, data_shred.slot, from, leader
+                );
+                continue;
+            }
+            None => {
+                debug!("Dropping shred of slot {}: leader unknown", data_shred.slot);
+                continue;
+            }
+        }
+        
+        let completed = assembler.lock().unwrap().insert(data_shred);
+        for (slot, entries) in completed {
+            for transaction in entries.iter().flat_map(|entry| &entry.transactions) {
+                let encoded = match shred::encode_transaction(slot, transaction) {
+                    Ok(encoded) => encoded,
+                    Err(e) => {
+                        debug!("Failed to encode shred transaction in slot {}: {}", slot, e);
+                        continue;
+                    }
+                };
+                if sender.blocking_send(encoded).is_err() {
+                    // Ingestion stopped
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn snapshot_sources(cluster_info: &ClusterInfo) -> Vec<SnapshotSource> {
+    let mut sources: Vec<SnapshotSource> = cluster_info
+        .all_peers()
+        .iter()
+        .filter_map(|(info, _)| {
+            let rpc = info.rpc().ok()?;
+            let hashes = cluster_info.get_snapshot_hashes_for_node(info.pubkey())?;
+            let incremental = hashes.incremental.iter().max_by_key(|(slot, _)| *slot);
+            Some(SnapshotSource {
+                pubkey: info.pubkey().to_string(),
+                rpc,
+                full_slot: hashes.full.0,
+                full_hash: hashes.full.1.to_string(),
+                incremental_slot: incremental.map(|(slot, _)| *slot),
+                incremental_hash: incremental.map(|(_, hash)| hash.to_string()),
+            })
+        })
+        .collect();
+    sources.sort_by(|a, b| b.latest_slot().cmp(&a.latest_slot()));
+    sources
+}
+
+fn cluster_nodes(cluster_info: &ClusterInfo, validators: &ValidatorRegistry, geoip: Option<&GeoIp>) -> Vec<ClusterNode> {
+    let mut nodes: Vec<ClusterNode> = cluster_info
+        .all_peers()
+        .iter()
+        .map(|(info, _)| {
+            let pubkey = info.pubkey().to_string();
+            let validator = validators.get(&pubkey);
+            let version = cluster_info.get_node_version(info.pubkey());
+            let geo = match (geoip, info.gossip()) {
+                (Some(geoip), Ok(addr)) => geoip.lookup(addr.ip()),
+                _ => GeoInfo::default(),
+            };
+            ClusterNode {
+                gossip: info.gossip().ok(),
+                tpu: info.tpu(Protocol::UDP).ok(),
+                tvu: info.tvu(Protocol::UDP).ok(),
+                rpc: info.rpc().ok(),
+                shred_version: info.shred_version(),
+                version: version.as_ref().map(|version| version.to_string()),
+                feature_set: version.map(|version| version.feature_set),
+                vote_account: validator.as_ref().map(|validator| validator.vote_account.clone()),
+                activated_stake: validator.as_ref().map_or(0, |validator| validator.activated_stake),
+                name: validator.and_then(|validator| validator.name),
+                country: geo.country,
+                asn: geo.asn,
+                as_org: geo.as_org,
+                pubkey,
+            }
+        })
+        .collect();
+    nodes.sort_by(|a, b| b.activated_stake.cmp(&a.activated_stake));
+    nodes
+}
+
+/// Print peers as a table, like `solana gossip`
+pub fn print_cluster_nodes(nodes: &[ClusterNode]) {
+    let addr = |addr: &Option<SocketAddr>| addr.map(|addr| addr.to_string()).unwrap_or_else(|| "none".to_string());
+    let port = |addr: &Option<SocketAddr>| addr.map(|addr| addr.port().to_string()).unwrap_or_else(|| "none".to_string());
+    
+    println!(
+        "{:<15} | {:<44} | {:<6} | {:<5} | {:<5} | {:<21} | {:<7} | {:<8} | {}",
+        "IP Address", "Identity", "Gossip", "TPU", "TVU", "RPC Address", "Shred", "Stake", "Version"
+    );
+    for node in nodes {
+        let ip = node.gossip.map(|addr| addr.ip().to_string()).unwrap_or_else(|| "none".to_string());
+        println!(
+            "{:<15} | {:<44} | {:<6} | {:<5} | {:<5} | {:<21} | {:<7} | {:<8.0} | {}",
+            ip,
+            node.pubkey,
+            port(&node.gossip),
+            port(&node.tpu),
+            port(&node.tvu),
+            addr(&node.rpc),
+            node.shred_version,
+            node.activated_stake as f64 / 1e9,
+            node.version.as_deref().unwrap_or("unknown")
+        );
+    }
+    println!("Nodes: {}", nodes.len());
+}
+
+/// Print how peers (and, once known, stake) are spread over shred versions, software versions
+/// and feature sets
+pub fn print_cluster_summary(nodes: &[ClusterNode]) {
+    let total_stake: u64 = nodes.iter().map(|node| node.activated_stake).sum();
+    let with_rpc = nodes.iter().filter(|node| node.rpc.is_some()).count();
+    let with_tvu = nodes.iter().filter(|node| node.tvu.is_some()).count();
+    println!("Topology: {} nodes, {} with TVU, {} with RPC", nodes.len(), with_tvu, with_rpc);
+    
+    let print_distribution = |title: &str, key: &dyn Fn(&ClusterNode) -> String| {
+        let mut groups: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+        for node in nodes {
+            let group = groups.entry(key(node)).or_default();
+            group.0 += 1;
+            group.1 += node.activated_stake;
+        }
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        groups.sort_by(|a, b| (b.1 .1, b.1 .0).cmp(&(a.1 .1, a.1 .0)));
+        
+        println!("{}:", title);
+        for (name, (count, stake)) in groups {
+            match total_stake {
+                0 => println!("  {:<12} {:>6} nodes", name, count),
+                total => println!(
+                    "  {:<12} {:>6} nodes  {:>6.2}% stake",
+                    name,
+                    count,
+                    stake as f64 * 100.0 / total as f64
+                ),
+            }
+        }
+    };
+    print_distribution("Shred versions", &|node| node.shred_version.to_string());
+    print_distribution("Versions", &|node| node.version.clone().unwrap_or_else(|| "unknown".to_string()));
+    print_distribution("Feature sets", &|node| {
+        node.feature_set.map(|feature_set| feature_set.to_string()).unwrap_or_else(|| "unknown".to_string())
+    });
+    
+    // Decentralization, when GeoIP databases are configured
+    if nodes.iter().any(|node| node.country.is_some()) {
+        print_distribution("Countries", &|node| node.country.clone().unwrap_or_else(|| "unknown".to_string()));
+    }
+    if nodes.iter().any(|node| node.asn.is_some()) {
+        print_distribution("Autonomous systems", &|node| match (node.asn, &node.as_org) {
+            (Some(asn), Some(org)) => format!("AS{} {}", asn, org),
+            (Some(asn), None) => format!("AS{}", asn),
+            _ => "unknown".to_string(),
+        });
+    }
+}
+
+/// Record the votes inserted into the gossip table until shutdown
+fn listen_votes(cluster_info: Arc<ClusterInfo>, storage: Storage, exit: Arc<AtomicBool>) {
+    let mut cursor = Cursor::default();
+    
+    while !exit.load(Ordering::Relaxed) {
+        let votes: Vec<_> = cluster_info
+            .get_votes(&mut cursor)
+            .iter()
+            .flat_map(vote::decode_unlanded)
+            .collect();
+        if !votes.is_empty() {
+            if let Err(e) = storage.record_gossip_votes(&votes, timestamp()) {
+                error!("Failed to record gossip votes: {}", e);
+            }
+        }
+        
+        // About one slot
+        std::thread::sleep(Duration::from_millis(400));
+    }
+}
+
+/// Add peers saved by previous runs to `entrypoints`
+pub fn add_saved_peers(entrypoints: &mut Vec<SocketAddr>, storage: &Storage) -> Result<()> {
+    let saved_peers = storage.get_gossip_peers(SAVED_PEER_ENTRYPOINTS)?;
+    if !saved_peers.is_empty() {
+        info!("Loaded {} saved gossip peers", saved_peers.len());
+    }
+    for peer in saved_peers {
+        if !entrypoints.contains(&peer.gossip) {
+            entrypoints.push(peer.gossip);
+        }
+    }
+    Ok(())
+}
+
+pub fn set_entrypoints(cluster_info: &ClusterInfo, entrypoints: &[SocketAddr]) {
+    cluster_info.set_entrypoints(
+        entrypoints
+            .iter()
+            .map(LegacyContactInfo::new_gossip_entry_point)
+            .collect(),
+    );
+}
+
+/// Cluster shred version reported by the first entrypoint answering an IP echo request
+fn discover_shred_version(entrypoints: &[SocketAddr]) -> Option<u16> {
+    for entrypoint in entrypoints {
+        match solana_net_utils::get_cluster_shred_version(entrypoint) {
+            Ok(shred_version) => {
+                info!("Cluster shred version {} reported by {}", shred_version, entrypoint);
+                return Some(shred_version);
+            }
+            Err(e) => warn!("Failed to get shred version from {}: {}", entrypoint, e),
+        }
+    }
+    None
+}
+
+/// Public IP of this machine as seen by the first entrypoint answering an IP echo request
+fn discover_public_ip(entrypoints: &[SocketAddr]) -> Option<IpAddr> {
+    for entrypoint in entrypoints {
+        match solana_net_utils::get_public_ip_addr(entrypoint) {
+            Ok(public_ip) => {
+                info!("Public IP {} reported by {}", public_ip, entrypoint);
+                return Some(public_ip);
+            }
+            Err(e) => warn!("Failed to get public IP from {}: {}", entrypoint, e),
+        }
+    }
+    None
+}
+
+/// Resolve a `host:port` entrypoint, e.g. `entrypoint.mainnet-beta.solana.com:8001`
+pub fn resolve_entrypoint(entrypoint: &str) -> Result<SocketAddr> {
+    entrypoint
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("no addresses found"))
+}
@@ -0,0 +1,153 @@
+//! Keeps the gossip service running, restarting it with backoff when its threads die
+//!
+//! `GossipService` offers no way to observe its threads, so failures are detected from the side:
+//! a panic hook counts panics on the library's threads named `sol*Gossip*`, and the gossip loop
+//! is taken to have stopped when it no longer refreshes our contact info.
+
+use solana_gossip::{cluster_info::ClusterInfo, gossip_service::GossipService};
+use solana_sdk::timing::timestamp;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Once};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::gossip;
+use crate::storage::Storage;
+
+/// Pause before the first restart, doubled after each restart that does not stay up
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// A service running this long resets the backoff when it fails
+const STABLE_AFTER: Duration = Duration::from_secs(600);
+
+/// The gossip loop refreshes our contact info about every 7.5s
+const STALE_CONTACT_INFO: Duration = Duration::from_secs(60);
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Panics seen on gossip threads since startup
+static GOSSIP_PANICS: AtomicUsize = AtomicUsize::new(0);
+static PANIC_HOOK: Once = Once::new();
+
+pub struct GossipSupervisor {
+    cluster_info: Arc<ClusterInfo>,
+    /// Local address the gossip socket is bound to, and rebound to on restart
+    bind_addr: SocketAddr,
+    entrypoints: Vec<SocketAddr>,
+    /// Saved peers are added to the entrypoints again on restart
+    storage: Option<Storage>,
+    exit: Arc<AtomicBool>,
+}
+
+impl GossipSupervisor {
+    pub fn new(
+        cluster_info: Arc<ClusterInfo>,
+        bind_addr: SocketAddr,
+        entrypoints: Vec<SocketAddr>,
+        storage: Option<Storage>,
+        exit: Arc<AtomicBool>,
+    ) -> Self {
+        Self { cluster_info, bind_addr, entrypoints, storage, exit }
+    }
+    
+    /// Run the gossip service on `socket` until `exit` is set, restarting it when it fails
+    pub fn run(self, socket: UdpSocket) {
+        PANIC_HOOK.call_once(install_panic_hook);
+        
+        let mut socket = Some(socket);
+        let mut backoff = INITIAL_BACKOFF;
+        while !self.exit.load(Ordering::Relaxed) {
+            let socket = match socket.take().map_or_else(|| UdpSocket::bind(self.bind_addr), Ok) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    error!("Failed to rebind gossip socket to {}: {}; retrying in {:?}", self.bind_addr, e, backoff);
+                    self.sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    continue;
+                }
+            };
+            
+            // Each run gets its own exit flag so a failed service can be stopped on its own
+            let service_exit = Arc::new(AtomicBool::new(false));
+            let service = GossipService::new(
+                &self.cluster_info,
+                None, // bank_forks
+                socket,
+                None, // gossip_validators
+                false, // should_check_duplicate_instance
+                None, // stats_reporter_sender
+                service_exit.clone(),
+            );
+            let started = Instant::now();
+            
+            let failure = self.watch(started);
+            service_exit.store(true, Ordering::Relaxed);
+            let _ = service.join();
+            let failure = match failure {
+                Some(failure) => failure,
+                None => return,
+            };
+            
+            if started.elapsed() >= STABLE_AFTER {
+                backoff = INITIAL_BACKOFF;
+            }
+            error!("Gossip service {}; restarting in {:?}", failure, backoff);
+            self.sleep(backoff);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            self.reapply_entrypoints();
+        }
+    }
+    
+    /// Wait for the running service to fail, returning why, or for shutdown (`None`)
+    fn watch(&self, started: Instant) -> Option<String> {
+        let panics = GOSSIP_PANICS.load(Ordering::Relaxed);
+        loop {
+            std::thread::sleep(CHECK_INTERVAL);
+            if self.exit.load(Ordering::Relaxed) {
+                return None;
+            }
+            if GOSSIP_PANICS.load(Ordering::Relaxed) != panics {
+                return Some("thread panicked".to_string());
+            }
+            
+            // Our contact info predates the service until its first refresh
+            let age = timestamp().saturating_sub(self.cluster_info.my_contact_info().wallclock());
+            if started.elapsed() >= STALE_CONTACT_INFO && age >= STALE_CONTACT_INFO.as_millis() as u64 {
+                return Some(format!("stopped refreshing its contact info {}s ago", age / 1000));
+            }
+        }
+    }
+    
+    /// Point gossip at the configured entrypoints and the peers saved since startup
+    fn reapply_entrypoints(&self) {
+        let mut entrypoints = self.entrypoints.clone();
+        if let Some(storage) = &self.storage {
+            if let Err(e) = gossip::add_saved_peers(&mut entrypoints, storage) {
+                warn!("Failed to load saved gossip peers: {}", e);
+            }
+        }
+        info!("Restarting gossip with {} entrypoints", entrypoints.len());
+        gossip::set_entrypoints(&self.cluster_info, &entrypoints);
+    }
+    
+    /// Sleep for `duration`, waking early on shutdown
+    fn sleep(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        while !self.exit.load(Ordering::Relaxed) && Instant::now() < until {
+            std::thread::sleep(CHECK_INTERVAL.min(until.saturating_duration_since(Instant::now())));
+        }
+    }
+}
+
+/// Count panics on gossip threads, keeping the existing hook's reporting
+fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        if std::thread::current().name().map_or(false, |name| name.starts_with("sol") && name.contains("Gossip")) {
+            GOSSIP_PANICS.fetch_add(1, Ordering::Relaxed);
+        }
+        previous(panic_info);
+    }));
+}
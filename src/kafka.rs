@@ -0,0 +1,171 @@
+//! Kafka producer publishing processed transactions, for users feeding existing streaming platforms
+//!
+//! Every processed transaction is published to `transactions_topic` keyed by its signature, as
+//! JSON or protobuf. With an `events_topic`, each decoded swap, NFT, vote and Anchor event is also
+//! published there on its own, keyed by the signature of its transaction, so consumers of one kind
+//! of event need not decode whole transactions.
+//!
+//! Publishing is best effort: messages wait in librdkafka's memory until acknowledged, and are
+//! lost if the node stops first or their retries run out. Unlike the NATS outbox, nothing is
+//! buffered on disk.
+
+use anyhow::{Context, Result};
+use prost::Message;
+use rdkafka::{
+    config::ClientConfig,
+    error::{KafkaError, RDKafkaErrorCode},
+    producer::{DeliveryFuture, FutureProducer, FutureRecord},
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::sleep,
+};
+use tracing::{error, info, warn};
+
+use crate::api::feed::StreamedTransaction;
+use crate::config::{KafkaConfig, KafkaDelivery, SinkFormat};
+use crate::metrics::Metrics;
+
+/// Messages awaiting their delivery report; past it publishing waits for reports to come in
+const MAX_PENDING_REPORTS: usize = 100_000;
+
+/// Wait before enqueueing again when the producer queue is full
+const QUEUE_FULL_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Fields of the stored transaction holding its decoded events, with the event type of each
+const EVENT_FIELDS: [(&str, &str); 4] =
+    [("swap", "swap"), ("nft_events", "nft"), ("vote_events", "vote"), ("anchor_events", "anchor")];
+
+pub struct KafkaSink {
+    config: KafkaConfig,
+    producer: FutureProducer,
+    metrics: Arc<Metrics>,
+}
+
+impl KafkaSink {
+    pub fn new(config: &KafkaConfig, metrics: Arc<Metrics>) -> Result<Self> {
+        let mut client = ClientConfig::new();
+        client
+            .set("bootstrap.servers", &config.brokers)
+            .set("linger.ms", config.linger_ms.to_string())
+            .set("batch.num.messages", config.batch_size.to_string())
+            .set("compression.type", &config.compression);
+        match config.delivery {
+            KafkaDelivery::Leader => client.set("acks", "1").set("message.send.max.retries", "0"),
+            KafkaDelivery::AllReplicas => client.set("acks", "all"),
+            KafkaDelivery::Idempotent => client.set("acks", "all").set("enable.idempotence", "true"),
+        };
+        for (key, value) in &config.properties {
+            client.set(key, value);
+        }
+        let producer = client
+            .create()
+            .with_context(|| format!("Failed to create Kafka producer for {}", config.brokers))?;
+        
+        Ok(Self { config: config.clone(), producer, metrics })
+    }
+    
+    /// Publish the transactions of `feed` in the background
+    pub fn start(self, feed: &broadcast::Sender<StreamedTransaction>) {
+        info!("Publishing transactions to Kafka topic {} at {}", self.config.transactions_topic, self.config.brokers);
+        let (reports, pending) = mpsc::channel(MAX_PENDING_REPORTS);
+        tokio::spawn(Self::count_deliveries(pending, self.metrics.clone()));
+        tokio::spawn(self.publish(feed.subscribe(), reports));
+    }
+    
+    async fn publish(
+        self,
+        mut transactions: broadcast::Receiver<StreamedTransaction>,
+        reports: mpsc::Sender<DeliveryFuture>,
+    ) {
+        loop {
+            let tx = match transactions.recv().await {
+                Ok(tx) => tx,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Kafka producer fell behind, {} transactions not published", missed);
+                    self.metrics.kafka_failed.add(missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            
+            let payload = match self.config.format {
+                SinkFormat::Json => tx.json.as_bytes().to_vec(),
+                SinkFormat::Protobuf => tx.proto.encode_to_vec(),
+            };
+            let mut messages = vec![(self.config.transactions_topic.as_str(), payload)];
+            if let Some(topic) = &self.config.events_topic {
+                match events(&tx) {
+                    Ok(events) => messages.extend(events.into_iter().map(|event| (topic.as_str(), event))),
+                    Err(e) => error!("Failed to read the events of {}: {}", tx.signature, e),
+                }
+            }
+            
+            for (topic, payload) in messages {
+                let delivery = match self.send(topic, &tx.signature, &payload).await {
+                    Ok(delivery) => delivery,
+                    Err(e) => {
+                        warn!("Failed to publish {} to Kafka topic {}: {}", tx.signature, topic, e);
+                        self.metrics.kafka_failed.inc();
+                        continue;
+                    }
+                };
+                if reports.send(delivery).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+    
+    /// Enqueue a message, waiting while the producer queue is full
+    async fn send(&self, topic: &str, key: &str, payload: &[u8]) -> Result<DeliveryFuture, KafkaError> {
+        loop {
+            let record = FutureRecord::to(topic).key(key).payload(payload);
+            match self.producer.send_result(record) {
+                Ok(delivery) => return Ok(delivery),
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), _)) => sleep(QUEUE_FULL_BACKOFF).await,
+                Err((e, _)) => return Err(e),
+            }
+        }
+    }
+    
+    /// Await the delivery report of each message, in the order they were sent
+    async fn count_deliveries(mut pending: mpsc::Receiver<DeliveryFuture>, metrics: Arc<Metrics>) {
+        while let Some(delivery) = pending.recv().await {
+            match delivery.await {
+                Ok(Ok(_)) => metrics.kafka_published.inc(),
+                Ok(Err((e, _))) => {
+                    warn!("Kafka message not delivered: {}", e);
+                    metrics.kafka_failed.inc();
+                }
+                Err(_) => metrics.kafka_failed.inc(),
+            }
+        }
+    }
+}
+
+/// One JSON message per decoded event of `tx`
+fn events(tx: &StreamedTransaction) -> serde_json::Result<Vec<Vec<u8>>> {
+    let view: Value = serde_json::from_str(&tx.json)?;
+    let mut events = Vec::new();
+    for (field, event_type) in EVENT_FIELDS {
+        let decoded = match view.get(field) {
+            Some(Value::Array(decoded)) => decoded.iter().collect(),
+            Some(Value::Null) | None => Vec::new(),
+            Some(decoded) => vec![decoded],
+        };
+        for event in decoded {
+            events.push(serde_json::to_vec(&json!({
+                "signature": tx.signature,
+                "slot": view.get("slot"),
+                "block_time": view.get("block_time"),
+                "type": event_type,
+                "event": event,
+            }))?);
+        }
+    }
+    Ok(events)
+}
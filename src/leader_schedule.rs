@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+/// Leaders of one epoch
+struct EpochLeaders {
+    identities: Vec<Pubkey>,
+    /// Index into `identities` of the leader of each slot of the epoch, in order
+    leaders: Vec<u32>,
+}
+
+/// Slot leaders of the previous, current and next epochs, keyed by the first slot of each
+/// epoch and refreshed from RPC, for checking who signed a shred
+#[derive(Clone, Default)]
+pub struct LeaderSchedule {
+    epochs: Arc<RwLock<BTreeMap<u64, EpochLeaders>>>,
+}
+
+impl LeaderSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Leader of `slot`, if its epoch is loaded
+    pub fn leader(&self, slot: u64) -> Option<Pubkey> {
+        let epochs = self.epochs.read().unwrap();
+        let (first_slot, epoch) = epochs.range(..=slot).next_back()?;
+        let index = *epoch.leaders.get((slot - first_slot) as usize)?;
+        epoch.identities.get(index as usize).copied()
+    }
+    
+    /// Load the schedules of the current and next epochs from `getLeaderSchedule`, unless
+    /// loaded already, and forget the epochs before the previous one
+    pub async fn refresh(&self, client: &RpcClient) -> Result<()> {
+        let epoch_schedule = client.get_epoch_schedule().await?;
+        let current = client.get_epoch_info().await?.epoch;
+        for epoch in [current, current + 1] {
+            let first_slot = epoch_schedule.get_first_slot_in_epoch(epoch);
+            if self.epochs.read().unwrap().contains_key(&first_slot) {
+                continue;
+            }
+            // Known once the stakes it is drawn from are, an epoch ahead
+            let schedule = match client.get_leader_schedule(Some(first_slot)).await? {
+                Some(schedule) => schedule,
+                None => continue,
+            };
+            
+            let mut identities = Vec::new();
+            let mut leaders = vec![u32::MAX; epoch_schedule.get_slots_in_epoch(epoch) as usize];
+            let mut indexes = HashMap::new();
+            for (identity, slot_indexes) in schedule {
+                let pubkey = Pubkey::from_str(&identity).with_context(|| format!("Invalid leader {}", identity))?;
+                let index = *indexes.entry(pubkey).or_insert_with(|| {
+                    identities.push(pubkey);
+                    identities.len() as u32 - 1
+                });
+                for slot_index in slot_indexes {
+                    if let Some(leader) = leaders.get_mut(slot_index) {
+                        *leader = index;
+                    }
+                }
+            }
+            
+            info!("Loaded the leader schedule of epoch {} ({} leaders)", epoch, identities.len());
+            self.epochs.write().unwrap().insert(first_slot, EpochLeaders { identities, leaders });
+        }
+        
+        let oldest = epoch_schedule.get_first_slot_in_epoch(current.saturating_sub(1));
+        self.epochs.write().unwrap().retain(|first_slot, _| *first_slot >= oldest);
+        Ok(())
+    }
+}
@@ -1,49 +1,228 @@
+mod address_labels;
+mod amqp;
+mod anchor;
+mod api;
+mod backfill;
+mod blockhash_cache;
+mod cli;
+mod cluster_health;
+mod cluster_identity;
 mod config;
+mod config_check;
+mod config_reload;
+mod crds_export;
+mod decoder;
+mod error_class;
+mod export;
+mod filter_expr;
+mod geoip;
+mod kafka;
+mod gossip;
+mod gossip_supervisor;
+mod leader_schedule;
+mod metrics;
+mod mev;
+mod nats;
 mod network;
+mod notifications;
+mod plugin;
+mod program_registry;
+mod proto;
+mod redis_stream;
+mod repair;
+mod rpc_pool;
+mod security;
+mod shred;
+mod spam_blocklist;
 mod storage;
 mod transaction_processor;
+mod validator_registry;
+mod version_report;
+mod watchlist;
+mod webhook;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Parser;
-use tracing::{info, error};
+use cli::{Args, Command, ConfigCommand};
+use backfill::BackfillTarget;
+use solana_sdk::signature::read_keypair_file;
+use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{info, error, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// Path to configuration file
-    #[arg(short, long, default_value = "config.toml")]
-    config: String,
-    
-    /// Network to connect to (mainnet-beta, testnet, devnet)
-    #[arg(short, long, default_value = "mainnet-beta")]
-    network: String,
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
+    // Initialize logging, with a filter `node.log_level` can replace once the configuration loads
+    let (log_filter, log_filter_handle) = tracing_subscriber::reload::Layer::new(
+        tracing_subscriber::EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| config_reload::DEFAULT_LOG_LEVEL.into()),
+    );
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "solana_node=info".into()),
-        )
+        .with(log_filter)
         .with(tracing_subscriber::fmt::layer())
         .init();
-
+    
     let args = Args::parse();
+    let source = args.config_source();
     
-    info!("Starting Solana node...");
-    info!("Network: {}", args.network);
+    // Checked before loading, which would write a missing file with the defaults
+    if let Some(Command::Config { command: ConfigCommand::Validate }) = &args.command {
+        return cli::validate_config(&source);
+    }
     
-    // Load configuration
-    let config = config::load_config(&args.config)?;
+    // Load configuration: flags over environment over file over network presets and defaults
+    let config = source.load()?;
+    if config.node.log_level.is_some() {
+        config_reload::set_log_level(&log_filter_handle, config.node.log_level.as_deref())?;
+    }
+    
+    let command = match args.gossip_peers {
+        Some(seconds) if args.command.is_none() => {
+            warn!("--gossip-peers is deprecated, use `spy --once --interval {}`", seconds);
+            Command::Spy { interval: seconds, export_dir: None, once: true }
+        }
+        Some(_) => bail!("--gossip-peers stands for `spy --once` and cannot be combined with a subcommand"),
+        None => args.command.unwrap_or(Command::Run),
+    };
+    match command {
+        Command::Run => run(config, source, log_filter_handle, args.enable_gossip).await,
+        Command::Query { target } => cli::query(&config, target),
+        Command::Export { from_slot, to_slot, format, out } => cli::export(&config, from_slot, to_slot, format, &out),
+        Command::Backfill { slots, address, limit, restart } => {
+            let target = match (slots, address) {
+                (Some((start, end)), _) => BackfillTarget::Slots { start, end },
+                (None, Some(address)) => BackfillTarget::Address { address, limit },
+                (None, None) => bail!("Pass --slots or --address"),
+            };
+            cli::backfill(config, &args.network, target, restart).await
+        }
+        Command::Prune { before_slot, compact } => {
+            cli::prune(&cli::open_storage(&config)?, &config, before_slot, compact)
+        }
+        Command::Stats { db } => cli::stats(&config, db.as_deref()),
+        Command::Keygen { out, force } => cli::keygen(&config, out.as_deref(), force),
+        Command::Verify => cli::verify(&args.network, &config, args.enable_gossip).await,
+        Command::Spy { interval, export_dir, once } => {
+            cluster_identity::validate(&args.network, &config, None, true, false).await?;
+            spy(config, interval, export_dir, once).await
+        }
+        Command::Config { .. } => unreachable!("configuration commands run before it is loaded"),
+    }
+}
+
+/// Ingest transactions and serve the APIs until the node stops
+async fn run(
+    config: config::Config,
+    source: config::ConfigSource,
+    log_filter: config_reload::LogFilter,
+    enable_gossip: bool,
+) -> Result<()> {
+    let network = source.network.as_str();
+    info!("Starting Solana node...");
+    info!("Network: {} (storage {})", network, config.storage_path);
+    config_check::ensure_valid(&config)?;
     
     // Initialize storage
-    let storage = storage::Storage::new(&config.storage_path)?;
+    let storage = cli::open_storage(&config)?;
+    let joins_gossip = enable_gossip || config.gossip.is_used();
+    cluster_identity::validate(network, &config, Some(&storage), joins_gossip, true).await?;
+    
+    // Start gossip peer discovery alongside transaction collection, also for the TVU and vote
+    // listener alone
+    let mut gossip_monitor = None;
+    let mut shred_transactions = None;
+    if joins_gossip {
+        let mut p2p_node = gossip::P2PNode::from_config(&config, Some(storage.clone()))?;
+        gossip_monitor = Some(p2p_node.monitor());
+        shred_transactions = p2p_node.take_transactions();
+        tokio::spawn(async move {
+            if let Err(e) = p2p_node.start().await {
+                error!("Gossip error: {}", e);
+            }
+        });
+        
+        if let (Some(monitor), Some(path)) = (gossip_monitor.clone(), config.identity_keypair_path()) {
+            let path = path.clone();
+            tokio::spawn(async move {
+                if let Err(e) = reload_identity_on_signal(monitor, path).await {
+                    error!("Identity reload unavailable: {}", e);
+                }
+            });
+        }
+    }
     
     // Start network services
-    let network_service = network::NetworkService::new(config.clone(), storage.clone()).await?;
+    let mut network_service = network::NetworkService::new(config.clone(), storage.clone()).await?;
+    if let Some(monitor) = gossip_monitor {
+        network_service = network_service.with_gossip_monitor(monitor);
+    }
+    if let Some(transactions) = shred_transactions {
+        network_service = network_service.with_transaction_source(transactions);
+    }
+    
+    // Post processed transactions to the configured webhooks and publish them to Kafka, NATS, Redis and AMQP
+    let webhooks = webhook::Webhooks::new(&config.webhooks, storage.clone(), network_service.metrics())?;
+    webhooks.start(&network_service.transaction_feed());
+    if let Some(kafka) = &config.kafka {
+        kafka::KafkaSink::new(kafka, network_service.metrics())?.start(&network_service.transaction_feed());
+    }
+    if let Some(nats) = &config.nats {
+        nats::NatsSink::new(nats, storage.clone(), network_service.metrics())?.start(&network_service.transaction_feed());
+    }
+    if let Some(redis) = &config.redis {
+        redis_stream::RedisSink::new(redis, network_service.metrics())?.start(&network_service.transaction_feed());
+    }
+    if let Some(amqp) = &config.amqp {
+        amqp::AmqpSink::new(amqp, &config.program_registry, network_service.metrics())
+            .start(&network_service.transaction_feed());
+    }
+    
+    // Post watchlist hits, security alerts and lag alarms to chat channels
+    notifications::Notifier::new(&config.notifications, network_service.metrics())?.start(
+        &network_service.watchlist_feed(),
+        &network_service.security_alert_feed(),
+        network_service.ingestion_status(),
+    );
+    
+    // Apply configuration changes on SIGHUP and as the file changes
+    let keys = Arc::new(api::auth::ApiKeys::load(&config.api)?);
+    let limiter = Arc::new(api::rate_limit::RateLimiter::new(config.api.rate_limit, keys.clone()));
+    let reloader = Arc::new(config_reload::ConfigReloader::new(
+        source,
+        &config,
+        network_service.control(),
+        limiter.clone(),
+        log_filter,
+    ));
+    tokio::spawn(config_reload::watch(reloader.clone(), config.node.watch_config));
+    
+    // Answer queries from the local store and stream processed transactions
+    if config.api.enabled {
+        let config = config.clone();
+        let state = api::ApiState {
+            storage: storage.clone(),
+            processor: network_service.processor(),
+            transactions: network_service.transaction_feed(),
+            slots: network_service.slot_feed(),
+            limiter,
+            keys,
+            ingestion: network_service.ingestion_status(),
+            metrics: network_service.metrics(),
+            control: network_service.control(),
+            reloader,
+            ready_max_lag_slots: config.api.ready_max_lag_slots,
+            retention_days: config.node.storage_retention_days,
+            commitment: config.network.commitment,
+            upstream: config.api.upstream_rpc.as_deref().map(api::rpc::Upstream::new),
+            webhooks,
+        };
+        tokio::spawn(async move {
+            if let Err(e) = api::serve(&config, state).await {
+                error!("API error: {:#}", e);
+            }
+        });
+    }
     
     // Run the node
     match network_service.run().await {
@@ -53,3 +232,54 @@ async fn main() -> Result<()> {
     
     Ok(())
 }
+
+/// Switch the gossip identity to the keypair at `path` each time SIGUSR1 arrives, so it can be
+/// rotated by replacing the file and signalling the process
+async fn reload_identity_on_signal(monitor: gossip::GossipMonitor, path: String) -> Result<()> {
+    let mut signals = signal(SignalKind::user_defined1())?;
+    while signals.recv().await.is_some() {
+        match read_keypair_file(&path) {
+            Ok(keypair) => monitor.set_identity(keypair),
+            Err(e) => error!("Failed to read identity keypair {}: {}", path, e),
+        }
+    }
+    Ok(())
+}
+
+/// Observe the cluster through gossip alone until interrupted
+async fn spy(mut config: config::Config, interval: u64, export_dir: Option<String>, once: bool) -> Result<()> {
+    // Shreds and votes would only be stored
+    config.gossip.tvu = false;
+    config.gossip.vote_listener = false;
+    
+    let mut p2p_node = gossip::P2PNode::from_config(&config, None)?;
+    let monitor = p2p_node.monitor();
+    let mut gossip_task = tokio::spawn(async move { p2p_node.start().await });
+    
+    let mut reports = tokio::time::interval(std::time::Duration::from_secs(interval.max(1)));
+    reports.tick().await;
+    loop {
+        tokio::select! {
+            _ = reports.tick() => {
+                let nodes = monitor.cluster_nodes();
+                gossip::print_cluster_nodes(&nodes);
+                gossip::print_cluster_summary(&nodes);
+                println!();
+                
+                if let Some(dir) = &export_dir {
+                    match monitor.crds_snapshot().write_to(dir) {
+                        Ok(path) => info!("Exported gossip table to {}", path.display()),
+                        Err(e) => error!("Failed to export gossip table: {}", e),
+                    }
+                }
+                if once {
+                    return Ok(());
+                }
+            }
+            result = &mut gossip_task => {
+                // The gossip node only returns on failure
+                return result?;
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Node metrics in the Prometheus text exposition format, scraped from `/metrics`
+//!
+//! Counters are bumped by the ingestion pipeline and the API as they run; values that are cheap to
+//! read when scraped (slot lag, feed depths) are passed to `render` instead of being tracked here.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::storage::StorageStats;
+
+/// Upper bounds of the API latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 12] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Transactions handed to the processing pipeline
+    pub transactions_received: Counter,
+    /// Transactions taken off the pipeline queue by the workers
+    pub transactions_dispatched: Counter,
+    pub transactions_processed: Counter,
+    /// Transactions that could not be decoded
+    pub transactions_failed: Counter,
+    /// Transactions dropped by plugins or filters
+    pub transactions_filtered: Counter,
+    pub transactions_stored: Counter,
+    /// Transaction notifications whose details could not be fetched
+    pub fetch_errors: Counter,
+    pub webhooks_delivered: Counter,
+    /// Transactions moved to the dead-letter store after their webhook failed
+    pub webhooks_dead_lettered: Counter,
+    /// Kafka messages acknowledged by the brokers
+    pub kafka_published: Counter,
+    /// Kafka messages that could not be enqueued or delivered, and transactions dropped as the
+    /// producer fell behind
+    pub kafka_failed: Counter,
+    /// NATS messages acknowledged by JetStream
+    pub nats_published: Counter,
+    /// Transactions not added to the NATS outbox, as it was full or fell behind
+    pub nats_dropped: Counter,
+    /// Transactions added to the Redis streams
+    pub redis_published: Counter,
+    /// Transactions not added to the Redis streams, as Redis failed or the publisher fell behind
+    pub redis_failed: Counter,
+    /// AMQP messages confirmed by the broker
+    pub amqp_published: Counter,
+    /// AMQP messages refused by the broker, or transactions dropped as the publisher fell behind
+    pub amqp_failed: Counter,
+    /// Alerts posted to chat channels
+    pub notifications_sent: Counter,
+    /// Alerts not posted, as the chat service refused them or their queue was full
+    pub notifications_failed: Counter,
+    /// Stored transaction count, as of the last statistics report
+    storage_transactions: AtomicU64,
+    /// Estimated live data size, as of the last statistics report
+    storage_size_bytes: AtomicU64,
+    /// Request latencies per API route
+    api_requests: Mutex<BTreeMap<String, Histogram>>,
+    /// Transactions each webhook missed while it was behind the feed, by URL
+    webhooks_missed: Mutex<BTreeMap<String, u64>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+    
+    pub fn add(&self, count: u64) {
+        self.0.fetch_add(count, Ordering::Relaxed);
+    }
+    
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+/// Values read at scrape time
+pub struct Snapshot {
+    pub sources: usize,
+    pub cluster_slot: u64,
+    pub processed_slot: u64,
+    pub slot_lag: Option<u64>,
+    /// Pending items per broadcast feed
+    pub feed_depths: Vec<(&'static str, usize)>,
+}
+
+impl Metrics {
+    /// Storage statistics are gathered by a full scan, so they are refreshed periodically rather
+    /// than on every scrape
+    pub fn set_storage(&self, stats: &StorageStats) {
+        self.storage_transactions.store(stats.transaction_count, Ordering::Relaxed);
+        self.storage_size_bytes.store(stats.db_size_bytes, Ordering::Relaxed);
+    }
+    
+    /// Storage statistics as of the last report, every 30 seconds (zero before the first)
+    pub fn storage(&self) -> StorageStats {
+        StorageStats {
+            transaction_count: self.storage_transactions.load(Ordering::Relaxed),
+            db_size_bytes: self.storage_size_bytes.load(Ordering::Relaxed),
+        }
+    }
+    
+    pub fn record_webhook_missed(&self, url: &str, missed: u64) {
+        *self.webhooks_missed.lock().unwrap().entry(url.to_string()).or_default() += missed;
+    }
+    
+    pub fn record_api_request(&self, route: &str, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let mut requests = self.api_requests.lock().unwrap();
+        let histogram = requests.entry(route.to_string()).or_default();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            histogram.buckets[bucket] += 1;
+        }
+        histogram.count += 1;
+        histogram.sum += seconds;
+    }
+    
+    pub fn render(&self, snapshot: &Snapshot) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("transactions_received_total", "Transactions handed to the processing pipeline", &self.transactions_received),
+            ("transactions_processed_total", "Transactions decoded by the processing workers", &self.transactions_processed),
+            ("transactions_failed_total", "Transactions that could not be decoded", &self.transactions_failed),
+            ("transactions_filtered_total", "Transactions dropped by plugins or filters", &self.transactions_filtered),
+            ("transactions_stored_total", "Transactions written to the store", &self.transactions_stored),
+            ("fetch_errors_total", "Transaction notifications whose details could not be fetched", &self.fetch_errors),
+            ("webhooks_delivered_total", "Transactions delivered to webhooks", &self.webhooks_delivered),
+            ("webhooks_dead_lettered_total", "Transactions moved to the webhook dead-letter store", &self.webhooks_dead_lettered),
+            ("kafka_published_total", "Kafka messages acknowledged by the brokers", &self.kafka_published),
+            ("kafka_failed_total", "Kafka messages not delivered or transactions not published", &self.kafka_failed),
+            ("nats_published_total", "NATS messages acknowledged by JetStream", &self.nats_published),
+            ("nats_dropped_total", "Transactions not added to the NATS outbox", &self.nats_dropped),
+            ("redis_published_total", "Transactions added to the Redis streams", &self.redis_published),
+            ("redis_failed_total", "Transactions not added to the Redis streams", &self.redis_failed),
+            ("amqp_published_total", "AMQP messages confirmed by the broker", &self.amqp_published),
+            ("amqp_failed_total", "AMQP messages refused by the broker or not published", &self.amqp_failed),
+            ("notifications_sent_total", "Alerts posted to chat channels", &self.notifications_sent),
+            ("notifications_failed_total", "Alerts not posted to chat channels", &self.notifications_failed),
+        ];
+        for (name, help, counter) in counters {
+            metric(&mut out, name, "counter", help, counter.get());
+        }
+        
+        let name = "webhooks_missed_total";
+        header(&mut out, name, "counter", "Transactions a webhook missed while behind the feed");
+        for (url, missed) in self.webhooks_missed.lock().unwrap().iter() {
+            sample(&mut out, name, &[("webhook", url)], missed);
+        }
+        
+        metric(&mut out, "sources_connected", "gauge", "Transaction sources currently connected", snapshot.sources);
+        metric(&mut out, "cluster_slot", "gauge", "Newest slot of the cluster", snapshot.cluster_slot);
+        metric(&mut out, "processed_slot", "gauge", "Slot of the newest processed transaction", snapshot.processed_slot);
+        if let Some(lag) = snapshot.slot_lag {
+            metric(&mut out, "slot_lag", "gauge", "Slots processing trails the cluster by", lag);
+        }
+        
+        let queued = self.transactions_received.get().saturating_sub(self.transactions_dispatched.get());
+        header(&mut out, "channel_depth", "gauge", "Items waiting in a channel");
+        sample(&mut out, "channel_depth", &[("channel", "ingest")], queued);
+        for (feed, depth) in &snapshot.feed_depths {
+            sample(&mut out, "channel_depth", &[("channel", feed)], depth);
+        }
+        
+        let stored = self.storage_transactions.load(Ordering::Relaxed);
+        metric(&mut out, "storage_transactions", "gauge", "Stored transactions", stored);
+        let size = self.storage_size_bytes.load(Ordering::Relaxed);
+        metric(&mut out, "storage_size_bytes", "gauge", "Estimated size of the live data in the store", size);
+        
+        let name = "api_request_duration_seconds";
+        header(&mut out, name, "histogram", "Latency of API requests by route");
+        for (route, histogram) in self.api_requests.lock().unwrap().iter() {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let bound = bound.to_string();
+                sample(&mut out, &format!("{}_bucket", name), &[("route", route), ("le", &bound)], cumulative);
+            }
+            sample(&mut out, &format!("{}_bucket", name), &[("route", route), ("le", "+Inf")], histogram.count);
+            sample(&mut out, &format!("{}_sum", name), &[("route", route)], histogram.sum);
+            sample(&mut out, &format!("{}_count", name), &[("route", route)], histogram.count);
+        }
+        
+        out
+    }
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl Display) {
+    header(out, name, kind, help);
+    sample(out, name, &[], value);
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP solana_node_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE solana_node_{} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl Display) {
+    let _ = write!(out, "solana_node_{}", name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(label, value)| format!("{}=\"{}\"", label, value.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect();
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(out, " {}", value);
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::storage::StoredTransaction;
+
+/// Mint used to represent native SOL legs of a swap
+pub const NATIVE_SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Net token movement of a trader through a DEX in one transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Swap {
+    pub trader: String,
+    pub program_id: String,
+    pub mint_in: String,
+    pub amount_in: u64,
+    pub mint_out: String,
+    pub amount_out: u64,
+}
+
+impl Swap {
+    /// Pool identifier: the traded mint pair, independent of direction and venue
+    pub fn pool(&self) -> String {
+        if self.mint_in < self.mint_out {
+            format!("{}/{}", self.mint_in, self.mint_out)
+        } else {
+            format!("{}/{}", self.mint_out, self.mint_in)
+        }
+    }
+    
+    fn same_direction(&self, other: &Swap) -> bool {
+        self.mint_in == other.mint_in && self.mint_out == other.mint_out
+    }
+    
+    fn reverses(&self, other: &Swap) -> bool {
+        self.mint_in == other.mint_out && self.mint_out == other.mint_in
+    }
+}
+
+/// A suspected sandwich: the attacker trades ahead of and right after a victim in the same pool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sandwich {
+    pub slot: u64,
+    pub pool: String,
+    pub attacker: String,
+    pub front_run: String,
+    pub victim: String,
+    pub back_run: String,
+}
+
+/// Find sandwich patterns within each slot of a batch and tag the participating transactions.
+///
+/// Transactions are assumed to be in the order they were received, which approximates their
+/// order in the block; sandwiches split across batches are not detected.
+pub fn detect_sandwiches(transactions: &mut [StoredTransaction]) -> Vec<Sandwich> {
+    // Swap positions grouped by slot, in batch order
+    let mut slots: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+    for (position, tx) in transactions.iter().enumerate() {
+        if tx.swap.is_some() {
+            slots.entry(tx.slot).or_default().push(position);
+        }
+    }
+    
+    let mut sandwiches = Vec::new();
+    for (slot, positions) in slots {
+        let swap = |position: usize| transactions[position].swap.as_ref();
+        
+        for (i, &front) in positions.iter().enumerate() {
+            let front_swap = match swap(front) {
+                Some(swap) => swap,
+                None => continue,
+            };
+            
+            // The closest later trade by the same trader reversing the front-run closes the sandwich
+            let back = positions[i + 1..].iter().copied().find(|&position| {
+                swap(position).map_or(false, |back_swap| {
+                    back_swap.trader == front_swap.trader && back_swap.reverses(front_swap)
+                })
+            });
+            let back = match back {
+                Some(back) => back,
+                None => continue,
+            };
+            
+            // A victim trades in the same direction as the front-run, between the two legs
+            let victim = positions.iter().copied().find(|&position| {
+                position > front && position < back
+                    && swap(position).map_or(false, |victim_swap| {
+                        victim_swap.trader != front_swap.trader && victim_swap.same_direction(front_swap)
+                    })
+            });
+            
+            if let Some(victim) = victim {
+                sandwiches.push(Sandwich {
+                    slot,
+                    pool: front_swap.pool(),
+                    attacker: front_swap.trader.clone(),
+                    front_run: transactions[front].signature.clone(),
+                    victim: transactions[victim].signature.clone(),
+                    back_run: transactions[back].signature.clone(),
+                });
+            }
+        }
+    }
+    
+    // Tag every participating transaction with the sandwich it belongs to
+    for sandwich in &sandwiches {
+        for tx in transactions.iter_mut() {
+            if tx.signature == sandwich.front_run
+                || tx.signature == sandwich.victim
+                || tx.signature == sandwich.back_run
+            {
+                tx.sandwich = Some(sandwich.clone());
+            }
+        }
+    }
+    
+    sandwiches
+}
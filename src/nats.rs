@@ -0,0 +1,215 @@
+//! NATS JetStream publisher with a persisted outbox
+//!
+//! Each processed transaction is published to `<subject_prefix>.<program id>` for every program it
+//! invokes, so consumers subscribe to the programs they follow (`sol.tx.<program id>`) or to all of
+//! them (`sol.tx.>`). Transactions are written to an outbox in the store first and only removed
+//! once JetStream has acknowledged every subject, so what is pending survives restarts and NATS
+//! outages: delivery is at least once. Messages carry a `Nats-Msg-Id` of signature and program,
+//! letting JetStream discard the copies republished within its duplicate window.
+
+use anyhow::{anyhow, Context as _, Result};
+use async_nats::{jetstream, ConnectOptions, HeaderMap};
+use bytes::Bytes;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{
+    sync::{broadcast, Notify},
+    time::sleep,
+};
+use tracing::{error, info, warn};
+
+use crate::api::feed::StreamedTransaction;
+use crate::config::{redact_url, NatsConfig, SinkFormat};
+use crate::metrics::Metrics;
+use crate::storage::Storage;
+
+/// Outbox entries published at once, their acknowledgements awaited together
+const PUBLISH_BATCH: usize = 256;
+
+/// Wait before connecting or publishing again after a failure
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A transaction waiting to be published, with the subjects it goes to
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub signature: String,
+    /// Invoked programs, one subject each
+    pub programs: Vec<String>,
+    pub payload: Vec<u8>,
+}
+
+pub struct NatsSink {
+    config: NatsConfig,
+    storage: Storage,
+    metrics: Arc<Metrics>,
+    /// Entries in the outbox
+    pending: AtomicU64,
+    /// Sequence of the next entry, past those left from earlier runs
+    next_sequence: u64,
+    /// Woken when an entry is added
+    added: Notify,
+}
+
+impl NatsSink {
+    pub fn new(config: &NatsConfig, storage: Storage, metrics: Arc<Metrics>) -> Result<Self> {
+        let (pending, last_sequence) = storage.get_outbox_bounds()?;
+        if pending > 0 {
+            info!("{} transactions left in the NATS outbox, publishing them first", pending);
+        }
+        Ok(Self {
+            config: config.clone(),
+            storage,
+            metrics,
+            pending: AtomicU64::new(pending),
+            next_sequence: last_sequence.map_or(0, |last| last + 1),
+            added: Notify::new(),
+        })
+    }
+    
+    /// Add the transactions of `feed` to the outbox and publish it, in the background
+    pub fn start(self, feed: &broadcast::Sender<StreamedTransaction>) {
+        let sequence = self.next_sequence;
+        let sink = Arc::new(self);
+        tokio::spawn(sink.clone().enqueue(feed.subscribe(), sequence));
+        tokio::spawn(sink.publish());
+    }
+    
+    async fn enqueue(self: Arc<Self>, mut transactions: broadcast::Receiver<StreamedTransaction>, mut sequence: u64) {
+        loop {
+            let tx = match transactions.recv().await {
+                Ok(tx) => tx,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("NATS outbox fell behind, {} transactions not published", missed);
+                    self.metrics.nats_dropped.add(missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if self.pending.load(Ordering::Relaxed) >= self.config.max_outbox {
+                warn!("NATS outbox full ({} transactions), dropping {}", self.config.max_outbox, tx.signature);
+                self.metrics.nats_dropped.inc();
+                continue;
+            }
+            
+            let entry = OutboxEntry {
+                signature: tx.signature.clone(),
+                programs: tx.programs.clone(),
+                payload: match self.config.format {
+                    SinkFormat::Json => tx.json.as_bytes().to_vec(),
+                    SinkFormat::Protobuf => tx.proto.encode_to_vec(),
+                },
+            };
+            match self.storage.append_outbox(sequence, &entry) {
+                Ok(()) => {
+                    sequence += 1;
+                    self.pending.fetch_add(1, Ordering::Relaxed);
+                    self.added.notify_one();
+                }
+                Err(e) => error!("Failed to add {} to the NATS outbox: {:#}", tx.signature, e),
+            }
+        }
+    }
+    
+    async fn publish(self: Arc<Self>) {
+        let context = loop {
+            match self.connect().await {
+                Ok(context) => break context,
+                Err(e) => {
+                    let url = redact_url(&self.config.url);
+                    warn!("Failed to connect to NATS at {}: {:#}, retrying in {:?}", url, e, RETRY_INTERVAL);
+                    sleep(RETRY_INTERVAL).await;
+                }
+            }
+        };
+        info!("Publishing transactions to NATS subjects {}.<program id>", self.config.subject_prefix);
+        
+        loop {
+            let entries = match self.storage.get_outbox(PUBLISH_BATCH) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    error!("Failed to read the NATS outbox: {:#}", e);
+                    sleep(RETRY_INTERVAL).await;
+                    continue;
+                }
+            };
+            if entries.is_empty() {
+                self.added.notified().await;
+                continue;
+            }
+            
+            if let Err(e) = self.publish_batch(&context, &entries).await {
+                warn!("Failed to publish to NATS: {:#}, retrying in {:?}", e, RETRY_INTERVAL);
+                sleep(RETRY_INTERVAL).await;
+            }
+        }
+    }
+    
+    async fn connect(&self) -> Result<jetstream::Context> {
+        let mut options = ConnectOptions::new();
+        if let Some(path) = &self.config.credentials_file {
+            options = options
+                .credentials_file(path)
+                .await
+                .with_context(|| format!("Failed to read NATS credentials {}", path))?;
+        }
+        let client = options.connect(self.config.url.as_str()).await?;
+        let context = jetstream::new(client);
+        
+        if let Some(stream) = &self.config.stream {
+            context
+                .get_or_create_stream(jetstream::stream::Config {
+                    name: stream.clone(),
+                    subjects: vec![format!("{}.>", self.config.subject_prefix)],
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| anyhow!("Failed to create JetStream stream {}: {}", stream, e))?;
+        }
+        Ok(context)
+    }
+    
+    /// Publish every subject of `entries`, then remove the entries JetStream acknowledged entirely
+    async fn publish_batch(&self, context: &jetstream::Context, entries: &[(u64, OutboxEntry)]) -> Result<()> {
+        let mut acks = Vec::new();
+        for (sequence, entry) in entries {
+            let payload = Bytes::from(entry.payload.clone());
+            for program in &entry.programs {
+                let mut headers = HeaderMap::new();
+                headers.insert("Nats-Msg-Id", format!("{}.{}", entry.signature, program).as_str());
+                let subject = format!("{}.{}", self.config.subject_prefix, program);
+                let ack = context
+                    .publish_with_headers(subject, headers, payload.clone())
+                    .await
+                    .map_err(|e| anyhow!("{}", e))?;
+                acks.push((*sequence, ack));
+            }
+        }
+        
+        // Entries with a subject left unacknowledged stay in the outbox, to be published again
+        let mut failed = Vec::new();
+        let mut error = None;
+        for (sequence, ack) in acks {
+            if let Err(e) = ack.await {
+                failed.push(sequence);
+                error = Some(e);
+            } else {
+                self.metrics.nats_published.inc();
+            }
+        }
+        let published: Vec<u64> = entries
+            .iter()
+            .map(|(sequence, _)| *sequence)
+            .filter(|sequence| !failed.contains(sequence))
+            .collect();
+        self.storage.delete_outbox(&published)?;
+        self.pending.fetch_sub(published.len() as u64, Ordering::Relaxed);
+        
+        match error {
+            Some(e) => Err(anyhow!("{} messages not acknowledged: {}", failed.len(), e)),
+            None => Ok(()),
+        }
+    }
+}
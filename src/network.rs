@@ -1,67 +1,688 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use solana_client::{
-    nonblocking::pubsub_client::PubsubClient,
-    rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter, RpcTransactionConfig},
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_config::{RpcBlockConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter, RpcTransactionConfig},
     rpc_response::SlotUpdate,
 };
 use solana_sdk::{
     commitment_config::CommitmentConfig,
 };
-use solana_transaction_status::UiTransactionEncoding;
+use serde::Serialize;
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, TransactionDetails, UiConfirmedBlock, UiTransactionEncoding,
+};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::{
-    sync::mpsc,
+    sync::{broadcast, mpsc, watch, Semaphore},
+    task::JoinHandle,
     time::{interval, sleep},
 };
 use tracing::{info, error, warn, debug};
 use futures::StreamExt;
 
 use crate::{
+    api::feed::StreamedTransaction,
+    blockhash_cache::BlockhashCache,
     config::Config,
-    storage::{Storage, StoredTransaction},
-    transaction_processor::TransactionProcessor,
+    gossip::GossipMonitor,
+    metrics::Metrics,
+    mev,
+    plugin::PluginPipeline,
+    proto,
+    rpc_pool::{self, RpcPool},
+    security::SecurityAlert,
+    storage::{self, ProgramHealth, Storage, StoredBlock, StoredTransaction},
+    transaction_processor::{ProcessedTransaction, TransactionProcessor},
+    watchlist::WatchlistHit,
 };
 
 pub struct NetworkService {
     config: Config,
     storage: Storage,
-    processor: TransactionProcessor,
+    blockhashes: BlockhashCache,
+    feeds: Feeds,
+    /// Gossip node whose activity is included in the statistics, if one is running
+    gossip: Option<GossipMonitor>,
+    /// Endpoints transaction details are fetched from, besides each subscription's own
+    rpc_pool: RpcPool,
+    /// Transactions arriving from outside RPC (turbine shreds), taken when the service runs
+    transaction_source: Mutex<Option<mpsc::Receiver<EncodedConfirmedTransactionWithStatusMeta>>>,
+    /// Input of the processing pipeline, whose receiving end is taken when the service runs
+    pipeline: mpsc::Sender<EncodedConfirmedTransactionWithStatusMeta>,
+    pipeline_receiver: Mutex<Option<mpsc::Receiver<EncodedConfirmedTransactionWithStatusMeta>>>,
+    backfills: Arc<Mutex<Vec<BackfillJob>>>,
+    cache_fetches: Arc<CacheFetches>,
+}
+
+/// State shared by the pipeline tasks: real-time outputs of the processing workers for sinks to
+/// subscribe to, ingestion progress, and the runtime controls
+#[derive(Clone)]
+struct Feeds {
+    watchlist_hits: broadcast::Sender<WatchlistHit>,
+    /// Transactions flagged by the security heuristics, whether or not they are stored
+    security_alerts: broadcast::Sender<SecurityAlert>,
+    /// Transactions that passed plugins and filters, before they are stored
+    transactions: broadcast::Sender<StreamedTransaction>,
+    /// Confirmed blocks seen while tracking blockhashes
+    slots: broadcast::Sender<proto::SlotUpdate>,
+    /// Transaction sources currently connected
+    sources: watch::Sender<usize>,
+    /// Newest slot of the cluster, as polled while tracking blockhashes
+    cluster_slot: watch::Sender<u64>,
+    /// Newest rooted (finalized) slot of the cluster, polled alongside `cluster_slot`
+    rooted_slot: watch::Sender<u64>,
+    /// Slot of the newest transaction the workers processed
+    processed_slot: watch::Sender<u64>,
+    /// Transactions taken off the pipeline, in arrival order, whose results are all stored; stops
+    /// advancing once a batch fails to store
+    persisted: watch::Sender<u64>,
+    metrics: Arc<Metrics>,
+    /// Whether the sources drop incoming transactions instead of feeding the pipeline
+    paused: watch::Sender<bool>,
+    /// Processor the workers use, replaced when the configuration is reloaded
+    processor: watch::Sender<TransactionProcessor>,
+}
+
+/// Progress of ingestion, for health and readiness checks
+#[derive(Clone)]
+pub struct IngestionStatus {
+    pub sources: watch::Receiver<usize>,
+    /// 0 until the first poll
+    pub cluster_slot: watch::Receiver<u64>,
+    /// 0 until the first poll
+    pub rooted_slot: watch::Receiver<u64>,
+    /// 0 until the first transaction is processed
+    pub processed_slot: watch::Receiver<u64>,
+    /// Transactions taken off the pipeline, in arrival order, whose results are all stored
+    pub persisted: watch::Receiver<u64>,
+}
+
+impl IngestionStatus {
+    /// Slots between the cluster and the newest processed transaction, once both are known
+    pub fn lag(&self) -> Option<u64> {
+        match (*self.cluster_slot.borrow(), *self.processed_slot.borrow()) {
+            (0, _) | (_, 0) => None,
+            (cluster_slot, processed_slot) => Some(cluster_slot.saturating_sub(processed_slot)),
+        }
+    }
+}
+
+/// Operations on the running ingestion pipeline, for the admin API and the RPC passthrough
+#[derive(Clone)]
+pub struct IngestionControl {
+    feeds: Feeds,
+    storage: Storage,
+    pipeline: mpsc::Sender<EncodedConfirmedTransactionWithStatusMeta>,
+    /// Endpoint backfilled blocks are fetched from
+    rpc_url: Option<String>,
+    encoding: UiTransactionEncoding,
+    commitment: CommitmentConfig,
+    store_log_messages: bool,
+    backfills: Arc<Mutex<Vec<BackfillJob>>>,
+    cache_fetches: Arc<CacheFetches>,
+}
+
+/// Upstream fetches of the RPC cache in progress, so that concurrent misses for the same
+/// signature or block fetch it once, and that at most `MAX_CACHE_FETCHES` run at a time
+struct CacheFetches {
+    keys: Mutex<HashSet<String>>,
+    permits: Semaphore,
 }
 
+/// A fetch registered in `CacheFetches`, until dropped
+struct CacheFetch {
+    fetches: Arc<CacheFetches>,
+    key: String,
+}
+
+impl CacheFetch {
+    /// Register a fetch of `key`, unless one is in progress
+    fn start(fetches: &Arc<CacheFetches>, key: String) -> Option<Self> {
+        fetches.keys.lock().unwrap().insert(key.clone()).then(|| Self { fetches: fetches.clone(), key })
+    }
+}
+
+impl Drop for CacheFetch {
+    fn drop(&mut self) {
+        self.fetches.keys.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// Progress of a backfill, which feeds the transactions of past blocks into the pipeline
+#[derive(Debug, Clone, Serialize)]
+pub struct BackfillJob {
+    pub id: u64,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    /// Next slot to fetch; past `end_slot` once done
+    pub next_slot: u64,
+    pub blocks: u64,
+    /// Slots without a block, skipped by their leader or failing to fetch
+    pub skipped_slots: u64,
+    pub transactions: u64,
+    pub done: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl IngestionControl {
+    pub fn is_paused(&self) -> bool {
+        *self.feeds.paused.borrow()
+    }
+    
+    /// Pause or resume ingestion. While paused, sources drop the transactions they are notified
+    /// of and backfills wait; backfill the slots missed to fill the gap.
+    pub fn set_paused(&self, paused: bool) {
+        if self.feeds.paused.send_replace(paused) != paused {
+            info!("Ingestion {}", if paused { "paused" } else { "resumed" });
+        }
+    }
+    
+    /// Process transactions with the filters, watchlist, plugins, IDLs and labels of a reloaded configuration
+    pub fn reload(&self, config: &Config) -> Result<()> {
+        let processor = self.feeds.processor.borrow().reloaded(config)?;
+        self.feeds.processor.send_replace(processor);
+        info!("Reloaded processing configuration");
+        Ok(())
+    }
+    
+    /// Start fetching the blocks from `start_slot` to `end_slot` (inclusive) in the background
+    pub fn backfill(&self, start_slot: u64, end_slot: u64) -> Result<BackfillJob> {
+        if end_slot < start_slot || end_slot - start_slot >= MAX_BACKFILL_SLOTS {
+            bail!("A backfill covers 1 to {} slots, from start_slot to end_slot", MAX_BACKFILL_SLOTS);
+        }
+        let rpc_url = self.rpc_url.clone().context("Backfilling needs an entry in network.rpc_endpoints")?;
+        Ok(self.start_backfill(rpc_url, start_slot, end_slot))
+    }
+    
+    /// Fetch a transaction from `rpc_url` and feed it through the pipeline. Returns whether the
+    /// transaction was found
+    pub async fn backfill_transaction(&self, rpc_url: &str, signature: &str) -> Result<bool> {
+        let details = NetworkService::fetch_transaction_details(rpc_url, signature, self.encoding, self.commitment);
+        let tx = match details.await? {
+            Some(tx) => tx,
+            None => return Ok(false),
+        };
+        self.pipeline.send(tx).await.map_err(|_| anyhow!("Processing pipeline stopped"))?;
+        self.feeds.metrics.transactions_received.inc();
+        Ok(true)
+    }
+    
+    /// Fetch the block of a slot from `rpc_url` and store it with its transactions, so later
+    /// requests for it are answered from the store. Does nothing while the block is being fetched
+    pub async fn cache_block(&self, rpc_url: &str, slot: u64) -> Result<()> {
+        let _fetch = match CacheFetch::start(&self.cache_fetches, format!("block:{}", slot)) {
+            Some(fetch) => fetch,
+            None => return Ok(()),
+        };
+        let _permit = self.cache_fetches.permits.acquire().await?;
+        if self.storage.get_block(slot)?.is_some() {
+            return Ok(());
+        }
+        
+        let client = RpcClient::new_with_commitment(rpc_url.to_string(), self.commitment);
+        let mut block = client.get_block_with_config(slot, self.block_config()).await?;
+        let transactions = block
+            .transactions
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|transaction| EncodedConfirmedTransactionWithStatusMeta { slot, transaction, block_time: block.block_time })
+            .collect();
+        // Transactions first, so the block is never listed without them
+        self.cache(transactions).await?;
+        store_block(&self.storage, slot, &block);
+        Ok(())
+    }
+    
+    /// Fetch a transaction from `rpc_url` and store it, so later requests for it are answered
+    /// from the store (unless the filters drop it). Does nothing while the transaction is being
+    /// fetched
+    pub async fn cache_transaction(&self, rpc_url: &str, signature: &str) -> Result<()> {
+        let _fetch = match CacheFetch::start(&self.cache_fetches, format!("tx:{}", signature)) {
+            Some(fetch) => fetch,
+            None => return Ok(()),
+        };
+        let _permit = self.cache_fetches.permits.acquire().await?;
+        if self.storage.contains_transaction(signature)? {
+            return Ok(());
+        }
+        
+        let details = NetworkService::fetch_transaction_details(rpc_url, signature, self.encoding, self.commitment);
+        if let Some(tx) = details.await? {
+            self.cache(vec![tx]).await?;
+        }
+        Ok(())
+    }
+    
+    /// Process transactions fetched for the RPC cache on a blocking thread, and store those the
+    /// plugins and filters keep. Unlike ingested transactions, they are not streamed and not
+    /// counted into the aggregates, which would otherwise mix the past in with the live feed
+    async fn cache(&self, transactions: Vec<EncodedConfirmedTransactionWithStatusMeta>) -> Result<()> {
+        let processor = self.feeds.processor.borrow().clone();
+        let (storage, store_log_messages) = (self.storage.clone(), self.store_log_messages);
+        tokio::task::spawn_blocking(move || {
+            let mut plugins = processor.plugins().instantiate()?;
+            let mut stored = Vec::new();
+            for tx in transactions {
+                match processor.process_encoded_transaction(&tx) {
+                    Ok(processed) => stored.extend(NetworkService::process_transaction(
+                        &processor,
+                        &mut plugins,
+                        processed,
+                        tx,
+                        store_log_messages,
+                        None,
+                    )),
+                    Err(e) => error!("Failed to process transaction: {}", e),
+                }
+            }
+            storage.store_cached_transactions(&stored)
+        })
+        .await?
+    }
+    
+    /// Blocks are fetched with full transactions, in the configured encoding
+    fn block_config(&self) -> RpcBlockConfig {
+        RpcBlockConfig {
+            encoding: Some(self.encoding),
+            transaction_details: Some(TransactionDetails::Full),
+            rewards: Some(false),
+            commitment: Some(self.commitment),
+            max_supported_transaction_version: Some(0),
+        }
+    }
+    
+    fn start_backfill(&self, rpc_url: String, start_slot: u64, end_slot: u64) -> BackfillJob {
+        let job = {
+            let mut jobs = self.backfills.lock().unwrap();
+            let job = BackfillJob {
+                id: jobs.len() as u64 + 1,
+                start_slot,
+                end_slot,
+                next_slot: start_slot,
+                blocks: 0,
+                skipped_slots: 0,
+                transactions: 0,
+                done: false,
+                error: None,
+            };
+            jobs.push(job.clone());
+            job
+        };
+        info!("Backfill {} started for slots {} to {}", job.id, start_slot, end_slot);
+        tokio::spawn(self.clone().run_backfill(rpc_url, job.id, start_slot, end_slot));
+        job
+    }
+    
+    /// Commitment transactions and blocks are fetched at
+    pub fn commitment(&self) -> CommitmentConfig {
+        self.commitment
+    }
+    
+    /// Every backfill started since the node started, oldest first
+    pub fn backfills(&self) -> Vec<BackfillJob> {
+        self.backfills.lock().unwrap().clone()
+    }
+    
+    async fn run_backfill(self, rpc_url: String, id: u64, start_slot: u64, end_slot: u64) {
+        let client = RpcClient::new_with_commitment(rpc_url, self.commitment);
+        let config = self.block_config();
+        let mut paused = self.feeds.paused.subscribe();
+        
+        for slot in start_slot..=end_slot {
+            // The sender lives in `self.feeds`, so waiting cannot fail
+            let _ = paused.wait_for(|paused| !paused).await;
+            
+            let block = match client.get_block_with_config(slot, config).await {
+                Ok(block) => block,
+                Err(e) => {
+                    debug!("No block for slot {}: {}", slot, e);
+                    self.update_backfill(id, |job| {
+                        job.next_slot = slot + 1;
+                        job.skipped_slots += 1;
+                    });
+                    continue;
+                }
+            };
+            store_block(&self.storage, slot, &block);
+            
+            let mut transactions = 0;
+            for transaction in block.transactions.unwrap_or_default() {
+                let tx = EncodedConfirmedTransactionWithStatusMeta { slot, transaction, block_time: block.block_time };
+                if self.pipeline.send(tx).await.is_err() {
+                    self.update_backfill(id, |job| job.error = Some("Processing pipeline stopped".to_string()));
+                    return;
+                }
+                self.feeds.metrics.transactions_received.inc();
+                transactions += 1;
+            }
+            self.update_backfill(id, |job| {
+                job.next_slot = slot + 1;
+                job.blocks += 1;
+                job.transactions += transactions;
+            });
+        }
+        
+        self.update_backfill(id, |job| job.done = true);
+        info!("Backfill {} finished slots {} to {}", id, start_slot, end_slot);
+    }
+    
+    fn update_backfill(&self, id: u64, update: impl FnOnce(&mut BackfillJob)) {
+        if let Some(job) = self.backfills.lock().unwrap().iter_mut().find(|job| job.id == id) {
+            update(job);
+        }
+    }
+}
+
+/// The processing pipeline running without sources, fed through its control
+pub struct Pipeline {
+    control: IngestionControl,
+    processing: JoinHandle<()>,
+}
+
+impl Pipeline {
+    pub fn control(&self) -> &IngestionControl {
+        &self.control
+    }
+    
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.control.feeds.metrics.clone()
+    }
+    
+    /// Transactions handed to the pipeline so far
+    pub fn received(&self) -> u64 {
+        self.control.feeds.metrics.transactions_received.get()
+    }
+    
+    /// Transactions handed to the pipeline, in order, whose results are stored (or dropped by the
+    /// filters); transactions up to this count survive an interruption
+    pub fn persisted(&self) -> u64 {
+        *self.control.feeds.persisted.borrow()
+    }
+    
+    /// Close the pipeline and wait until the transactions handed to it are stored, returning the
+    /// final `persisted`. Backfills still running keep it open until they finish
+    pub async fn finish(self) -> Result<u64> {
+        let persisted = self.control.feeds.persisted.subscribe();
+        drop(self.control);
+        self.processing.await?;
+        let persisted = *persisted.borrow();
+        Ok(persisted)
+    }
+}
+
+/// Counts a connected transaction source while held
+struct ConnectedSource(watch::Sender<usize>);
+
+impl ConnectedSource {
+    fn new(sources: &watch::Sender<usize>) -> Self {
+        sources.send_modify(|count| *count += 1);
+        Self(sources.clone())
+    }
+}
+
+impl Drop for ConnectedSource {
+    fn drop(&mut self) {
+        self.0.send_modify(|count| *count -= 1);
+    }
+}
+
+/// Output of a processing worker for one transaction
+struct WorkerResult {
+    /// Arrival order of the transaction
+    sequence: u64,
+    outcome: Option<ProgramOutcome>,
+    /// Record to store, if the transaction passed plugins and filters
+    stored_tx: Option<StoredTransaction>,
+    watchlist_hits: Vec<WatchlistHit>,
+    security_alerts: Vec<SecurityAlert>,
+}
+
+/// Programs invoked by a transaction and whether it failed, counted towards program health
+struct ProgramOutcome {
+    signature: String,
+    slot: u64,
+    programs: Vec<String>,
+    failed: bool,
+}
+
+/// Signatures of the transactions processed most recently, to count each transaction once when
+/// several sources deliver it
+struct RecentSignatures {
+    signatures: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl RecentSignatures {
+    fn new() -> Self {
+        Self { signatures: HashSet::new(), order: VecDeque::new() }
+    }
+    
+    /// Remember `signature`, returning whether it is new
+    fn insert(&mut self, signature: &str) -> bool {
+        if !self.signatures.insert(signature.to_string()) {
+            return false;
+        }
+        self.order.push_back(signature.to_string());
+        while self.order.len() > RECENT_SIGNATURES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.signatures.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Signatures remembered by `RecentSignatures`, a few minutes of mainnet traffic
+const RECENT_SIGNATURES: usize = 200_000;
+
+/// Program health windows that failure rates are compared against
+const PROGRAM_HEALTH_BASELINE_WINDOWS: u64 = 10;
+
+/// Slots of blockhash history fetched at startup (the blockhash validity window)
+const BLOCKHASH_BACKFILL_SLOTS: u64 = 150;
+
+/// Most slots a single backfill may cover (roughly eleven hours)
+pub const MAX_BACKFILL_SLOTS: u64 = 100_000;
+
+/// Most upstream fetches of the RPC cache in progress at once
+const MAX_CACHE_FETCHES: usize = 4;
+
+/// Interval between health checks of the RPC endpoints peers advertise in gossip
+const RPC_DISCOVERY_INTERVAL: Duration = Duration::from_secs(300);
+
 impl NetworkService {
     pub async fn new(config: Config, storage: Storage) -> Result<Self> {
+        let blockhashes = BlockhashCache::new();
+        let processor = TransactionProcessor::new(&config)?.with_blockhash_cache(blockhashes.clone());
+        let (watchlist_hits, _) = broadcast::channel(1000);
+        let (security_alerts, _) = broadcast::channel(1000);
+        let (transactions, _) = broadcast::channel(1000);
+        let (slots, _) = broadcast::channel(1000);
+        let (pipeline, pipeline_receiver) = mpsc::channel(1000);
+        let feeds = Feeds {
+            watchlist_hits,
+            security_alerts,
+            transactions,
+            slots,
+            sources: watch::Sender::new(0),
+            cluster_slot: watch::Sender::new(0),
+            rooted_slot: watch::Sender::new(0),
+            processed_slot: watch::Sender::new(0),
+            persisted: watch::Sender::new(0),
+            metrics: Arc::new(Metrics::default()),
+            paused: watch::Sender::new(false),
+            processor: watch::Sender::new(processor),
+        };
+        
         Ok(Self {
             config,
             storage,
-            processor: TransactionProcessor::new(),
+            blockhashes,
+            feeds,
+            gossip: None,
+            rpc_pool: RpcPool::default(),
+            transaction_source: Mutex::new(None),
+            pipeline,
+            pipeline_receiver: Mutex::new(Some(pipeline_receiver)),
+            backfills: Arc::new(Mutex::new(Vec::new())),
+            cache_fetches: Arc::new(CacheFetches {
+                keys: Mutex::new(HashSet::new()),
+                permits: Semaphore::new(MAX_CACHE_FETCHES),
+            }),
         })
     }
     
+    /// Also ingest transactions from another source, such as turbine shreds
+    pub fn with_transaction_source(self, source: mpsc::Receiver<EncodedConfirmedTransactionWithStatusMeta>) -> Self {
+        *self.transaction_source.lock().unwrap() = Some(source);
+        self
+    }
+    
+    /// Feed of processed transactions, for streaming them to clients
+    pub fn transaction_feed(&self) -> broadcast::Sender<StreamedTransaction> {
+        self.feeds.transactions.clone()
+    }
+    
+    /// Feed of confirmed blocks, for streaming them to clients
+    pub fn slot_feed(&self) -> broadcast::Sender<proto::SlotUpdate> {
+        self.feeds.slots.clone()
+    }
+    
+    /// Feed of transactions touching watched addresses, for notifications
+    pub fn watchlist_feed(&self) -> broadcast::Sender<WatchlistHit> {
+        self.feeds.watchlist_hits.clone()
+    }
+    
+    /// Feed of security alerts, for notifications
+    pub fn security_alert_feed(&self) -> broadcast::Sender<SecurityAlert> {
+        self.feeds.security_alerts.clone()
+    }
+    
+    /// Connected sources and how far processing trails the cluster
+    pub fn ingestion_status(&self) -> IngestionStatus {
+        IngestionStatus {
+            sources: self.feeds.sources.subscribe(),
+            cluster_slot: self.feeds.cluster_slot.subscribe(),
+            rooted_slot: self.feeds.rooted_slot.subscribe(),
+            processed_slot: self.feeds.processed_slot.subscribe(),
+            persisted: self.feeds.persisted.subscribe(),
+        }
+    }
+    
+    /// Counters of the ingestion pipeline, which the API also records into
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.feeds.metrics.clone()
+    }
+    
+    /// The processor transactions are decoded with
+    pub fn processor(&self) -> TransactionProcessor {
+        self.feeds.processor.borrow().clone()
+    }
+    
+    /// Pausing, reloading and backfilling the pipeline while it runs
+    pub fn control(&self) -> IngestionControl {
+        IngestionControl {
+            feeds: self.feeds.clone(),
+            storage: self.storage.clone(),
+            pipeline: self.pipeline.clone(),
+            rpc_url: self.config.network.rpc_endpoints.first().cloned(),
+            encoding: transaction_encoding(&self.config),
+            commitment: self.config.network.commitment.as_commitment_config(),
+            store_log_messages: self.config.node.store_log_messages,
+            backfills: self.backfills.clone(),
+            cache_fetches: self.cache_fetches.clone(),
+        }
+    }
+    
+    /// Report the activity of a running gossip node with the node statistics
+    pub fn with_gossip_monitor(mut self, monitor: GossipMonitor) -> Self {
+        self.gossip = Some(monitor);
+        self
+    }
+    
+    /// Run the processing pipeline without listening for new transactions, to feed it through
+    /// its control alone (one-off backfills)
+    pub fn into_pipeline(self) -> Result<Pipeline> {
+        let receiver = self.pipeline_receiver.lock().unwrap().take().context("Network service already running")?;
+        let processing = tokio::spawn(Self::process_transactions(
+            receiver,
+            self.storage.clone(),
+            self.config.node.store_log_messages,
+            self.config.node.processing_workers,
+            self.feeds.clone(),
+        ));
+        Ok(Pipeline { control: self.control(), processing })
+    }
+    
     pub async fn run(&self) -> Result<()> {
         info!("Starting network service...");
         
-        // Create channels for transaction processing
-        let (tx_sender, tx_receiver) = mpsc::channel::<EncodedConfirmedTransactionWithStatusMeta>(1000);
+        let tx_sender = self.pipeline.clone();
+        let tx_receiver = self.pipeline_receiver.lock().unwrap().take().context("Network service already running")?;
         
         // Spawn transaction processor task
         let storage_clone = self.storage.clone();
-        let processor_clone = self.processor.clone();
         tokio::spawn(Self::process_transactions(
             tx_receiver,
             storage_clone,
-            processor_clone,
+            self.config.node.store_log_messages,
+            self.config.node.processing_workers,
+            self.feeds.clone(),
         ));
         
+        // Spawned even for an empty watchlist, which a reload may fill
+        tokio::spawn(Self::report_watchlist_hits(self.feeds.watchlist_hits.subscribe()));
+        
+        // Track confirmed blocks: the slot of each recent blockhash, and the headers getBlock serves
+        let commitment = self.config.network.commitment.as_commitment_config();
+        if let Some(rpc_url) = self.config.network.rpc_endpoints.first() {
+            tokio::spawn(Self::track_blockhashes(
+                rpc_url.clone(),
+                commitment,
+                self.storage.clone(),
+                self.blockhashes.clone(),
+                self.feeds.clone(),
+            ));
+        }
+        
+        // Spread fetches over healthy RPC nodes found in gossip
+        if let (true, Some(gossip)) = (self.config.gossip.discover_rpc, &self.gossip) {
+            tokio::spawn(Self::discover_rpc_endpoints(
+                gossip.clone(),
+                self.rpc_pool.clone(),
+                self.config.gossip.rpc_allowlist.clone(),
+                self.config.gossip.max_discovered_rpc,
+            ));
+        }
+        
         // Spawn WebSocket listeners for each endpoint
         let mut handles = vec![];
         
         for endpoint in &self.config.network.websocket_endpoints {
             let endpoint_clone = endpoint.clone();
+            let rpc_pool = self.rpc_pool.clone();
             let tx_sender_clone = tx_sender.clone();
+            let feeds = self.feeds.clone();
+            let include_votes = self.config.filters.store_votes;
+            let encoding = transaction_encoding(&self.config);
             
             let handle = tokio::spawn(async move {
                 loop {
-                    match Self::subscribe_to_transactions(&endpoint_clone, tx_sender_clone.clone()).await {
+                    match Self::subscribe_to_transactions(
+                        &endpoint_clone,
+                        &rpc_pool,
+                        tx_sender_clone.clone(),
+                        &feeds,
+                        include_votes,
+                        encoding,
+                        commitment,
+                    )
+                    .await
+                    {
                         Ok(_) => info!("WebSocket connection closed, reconnecting..."),
                         Err(e) => error!("WebSocket error: {}, reconnecting in 5s...", e),
                     }
@@ -72,9 +693,29 @@ impl NetworkService {
             handles.push(handle);
         }
         
+        // Feed transactions from other sources into the same pipeline
+        if let Some(mut source) = self.transaction_source.lock().unwrap().take() {
+            let tx_sender_clone = tx_sender.clone();
+            let connected = ConnectedSource::new(&self.feeds.sources);
+            let feeds = self.feeds.clone();
+            handles.push(tokio::spawn(async move {
+                let _connected = connected;
+                while let Some(tx) = source.recv().await {
+                    if *feeds.paused.borrow() {
+                        continue;
+                    }
+                    if let Err(e) = tx_sender_clone.send(tx).await {
+                        error!("Failed to send transaction to processor: {}", e);
+                        break;
+                    }
+                    feeds.metrics.transactions_received.inc();
+                }
+            }));
+        }
+        
         // Spawn statistics reporter
         let storage_clone = self.storage.clone();
-        tokio::spawn(Self::report_statistics(storage_clone));
+        tokio::spawn(Self::report_statistics(storage_clone, self.gossip.clone(), self.feeds.metrics.clone()));
         
         // Wait for all tasks
         for handle in handles {
@@ -86,23 +727,34 @@ impl NetworkService {
     
     async fn subscribe_to_transactions(
         endpoint: &str,
+        rpc_pool: &RpcPool,
         tx_sender: mpsc::Sender<EncodedConfirmedTransactionWithStatusMeta>,
+        feeds: &Feeds,
+        include_votes: bool,
+        encoding: UiTransactionEncoding,
+        commitment: CommitmentConfig,
     ) -> Result<()> {
         info!("Connecting to WebSocket: {}", endpoint);
         
         let pubsub_client = PubsubClient::new(endpoint).await?;
         
         // Subscribe to all transactions (you can filter by program ID if needed)
+        let filter = if include_votes {
+            RpcTransactionLogsFilter::AllWithVotes
+        } else {
+            RpcTransactionLogsFilter::All
+        };
         let (mut stream, _unsub) = pubsub_client
             .logs_subscribe(
-                RpcTransactionLogsFilter::All,
+                filter,
                 RpcTransactionLogsConfig {
-                    commitment: Some(CommitmentConfig::confirmed()),
+                    commitment: Some(commitment),
                 },
             )
             .await?;
         
         info!("Subscribed to transaction logs on {}", endpoint);
+        let _connected = ConnectedSource::new(&feeds.sources);
         
         // Also subscribe to slot updates for monitoring
         let (mut slot_stream, _slot_unsub) = pubsub_client
@@ -114,19 +766,30 @@ impl NetworkService {
             tokio::select! {
                 Some(log) = stream.next() => {
                     debug!("Received transaction log: {}", log.value.signature);
+                    if *feeds.paused.borrow() {
+                        continue;
+                    }
                     
-                    // Fetch full transaction details
-                    match Self::fetch_transaction_details(&endpoint, &log.value.signature).await {
-                        Ok(Some(tx)) => {
-                            if let Err(e) = tx_sender.send(tx).await {
-                                error!("Failed to send transaction to processor: {}", e);
-                            }
-                        }
+                    // Fetch full transaction details, falling back on the subscription's endpoint
+                    let fetch_endpoint = rpc_pool.pick(endpoint);
+                    let mut details =
+                        Self::fetch_transaction_details(&fetch_endpoint, &log.value.signature, encoding, commitment).await;
+                    if fetch_endpoint != endpoint && !matches!(details, Ok(Some(_))) {
+                        debug!("Fetching {} from {} failed, retrying on {}", log.value.signature, fetch_endpoint, endpoint);
+                        details =
+                            Self::fetch_transaction_details(endpoint, &log.value.signature, encoding, commitment).await;
+                    }
+                    match details {
+                        Ok(Some(tx)) => match tx_sender.send(tx).await {
+                            Ok(()) => feeds.metrics.transactions_received.inc(),
+                            Err(e) => error!("Failed to send transaction to processor: {}", e),
+                        },
                         Ok(None) => {
                             // Transaction might not be confirmed yet, skip for now
                             debug!("Transaction {} not found yet, might be pending", log.value.signature);
                         }
                         Err(e) => {
+                            feeds.metrics.fetch_errors.inc();
                             // Log as debug instead of error for expected cases
                             if e.to_string().contains("invalid type: null") {
                                 debug!("Transaction {} not yet available: {}", log.value.signature, e);
@@ -154,9 +817,96 @@ impl NetworkService {
         Ok(())
     }
     
+    /// Periodically replace the pool's discovered endpoints with the best RPC nodes in gossip
+    /// among the `allowlist` identities
+    async fn discover_rpc_endpoints(gossip: GossipMonitor, rpc_pool: RpcPool, allowlist: Vec<String>, limit: usize) {
+        let mut interval = interval(RPC_DISCOVERY_INTERVAL);
+        
+        loop {
+            interval.tick().await;
+            
+            let candidates = rpc_pool::discover(&gossip.cluster_nodes(), &allowlist, limit).await;
+            if candidates.is_empty() {
+                debug!("No healthy RPC endpoints found in gossip");
+            }
+            for candidate in &candidates {
+                info!(
+                    "Fetching from gossip RPC endpoint {} (slot {}, {} ms)",
+                    candidate.url,
+                    candidate.slot,
+                    candidate.latency.as_millis()
+                );
+            }
+            rpc_pool.set_discovered(candidates.into_iter().map(|candidate| candidate.url).collect());
+        }
+    }
+    
+    /// Poll confirmed block headers, recording the blockhash of each slot, storing the header and
+    /// announcing the block
+    async fn track_blockhashes(
+        rpc_url: String,
+        commitment: CommitmentConfig,
+        storage: Storage,
+        blockhashes: BlockhashCache,
+        feeds: Feeds,
+    ) {
+        let client = RpcClient::new_with_commitment(rpc_url, commitment);
+        let config = RpcBlockConfig {
+            encoding: None,
+            transaction_details: Some(TransactionDetails::None),
+            rewards: Some(false),
+            commitment: Some(commitment),
+            max_supported_transaction_version: Some(0),
+        };
+        let mut next_slot = None;
+        let mut interval = interval(Duration::from_secs(1));
+        
+        loop {
+            interval.tick().await;
+            
+            let tip = match client.get_slot().await {
+                Ok(slot) => slot,
+                Err(e) => {
+                    warn!("Failed to get slot for blockhash tracking: {}", e);
+                    continue;
+                }
+            };
+            feeds.cluster_slot.send_replace(tip);
+            match client.get_slot_with_commitment(CommitmentConfig::finalized()).await {
+                Ok(root) => {
+                    feeds.rooted_slot.send_replace(root);
+                }
+                Err(e) => warn!("Failed to get rooted slot: {}", e),
+            }
+            
+            let start = next_slot.unwrap_or_else(|| tip.saturating_sub(BLOCKHASH_BACKFILL_SLOTS));
+            for slot in start.max(tip.saturating_sub(BLOCKHASH_BACKFILL_SLOTS))..=tip {
+                match client.get_block_with_config(slot, config).await {
+                    Ok(block) => {
+                        // Sending only fails when nobody is subscribed
+                        let _ = feeds.slots.send(proto::SlotUpdate {
+                            slot,
+                            parent_slot: block.parent_slot,
+                            blockhash: block.blockhash.clone(),
+                            block_time: block.block_time,
+                            block_height: block.block_height,
+                        });
+                        store_block(&storage, slot, &block);
+                        blockhashes.insert(block.blockhash, slot);
+                    }
+                    // Skipped slots have no block
+                    Err(e) => debug!("No block for slot {}: {}", slot, e),
+                }
+            }
+            next_slot = Some(tip + 1);
+        }
+    }
+    
     async fn fetch_transaction_details(
         endpoint: &str,
         signature: &str,
+        encoding: UiTransactionEncoding,
+        commitment: CommitmentConfig,
     ) -> Result<Option<EncodedConfirmedTransactionWithStatusMeta>> {
         // Convert WebSocket URL to HTTP RPC URL
         let rpc_url = endpoint.replace("wss://", "https://").replace("ws://", "http://");
@@ -167,8 +917,8 @@ impl NetworkService {
         
         // Configure to support versioned transactions
         let config = RpcTransactionConfig {
-            encoding: Some(UiTransactionEncoding::JsonParsed),
-            commitment: Some(CommitmentConfig::confirmed()),
+            encoding: Some(encoding),
+            commitment: Some(commitment),
             max_supported_transaction_version: Some(0),
         };
         
@@ -184,58 +934,376 @@ impl NetworkService {
         }
     }
     
+    /// Process transactions on a pool of workers and store the results in batches taken in
+    /// arrival order, each committed slot by slot
     async fn process_transactions(
         mut rx: mpsc::Receiver<EncodedConfirmedTransactionWithStatusMeta>,
         storage: Storage,
-        processor: TransactionProcessor,
+        store_log_messages: bool,
+        workers: usize,
+        feeds: Feeds,
     ) {
+        let (result_sender, mut results) = mpsc::channel::<WorkerResult>(1000);
+        let mut work_senders = Vec::new();
+        for _ in 0..workers.max(1) {
+            let (work_sender, work_receiver) = mpsc::channel(100);
+            let (result_sender, feeds) = (result_sender.clone(), feeds.clone());
+            tokio::task::spawn_blocking(move || {
+                Self::processing_worker(work_receiver, result_sender, store_log_messages, feeds)
+            });
+            work_senders.push(work_sender);
+        }
+        drop(result_sender);
+        info!("Processing transactions on {} workers", work_senders.len());
+        
+        // Hand transactions to workers round-robin, numbered in arrival order
+        let metrics = feeds.metrics.clone();
+        tokio::spawn(async move {
+            let mut sequence = 0u64;
+            while let Some(tx) = rx.recv().await {
+                metrics.transactions_dispatched.inc();
+                let worker = &work_senders[(sequence % work_senders.len() as u64) as usize];
+                if worker.send((sequence, tx)).await.is_err() {
+                    error!("Processing worker stopped, dropping transaction");
+                }
+                sequence += 1;
+            }
+        });
+        
+        // Workers finish out of order; hold results until every earlier transaction is done
+        let mut pending = BTreeMap::new();
+        let mut next_sequence = 0u64;
         let mut batch = Vec::new();
+        let mut store_failed = false;
+        // Success and failure counts per program health window and program, in any order
+        let mut health: BTreeMap<(u64, String), ProgramHealth> = BTreeMap::new();
+        let mut counted = RecentSignatures::new();
+        let mut latest_slot = 0;
+        let mut spiking = Vec::new();
+        let mut hits = Vec::new();
+        let mut alerts = Vec::new();
         let mut interval = interval(Duration::from_secs(5));
         
         loop {
-            tokio::select! {
-                Some(tx) = rx.recv() => {
-                    // Process the transaction
-                    match processor.process_encoded_transaction(&tx) {
-                        Ok(processed) => {
-                            if processor.should_store_transaction(&processed) {
-                                info!("{}", processed.summary());
-                                
-                                let stored_tx = StoredTransaction {
-                                    signature: processed.signature.clone(),
-                                    slot: tx.slot,
-                                    timestamp: tx.block_time.unwrap_or(0),
-                                    transaction: tx,
-                                };
-                                
+            let closed = tokio::select! {
+                result = results.recv() => match result {
+                    Some(result) => {
+                        // Transactions delivered again, lately or after being stored, count once
+                        let outcome = result.outcome.filter(|outcome| {
+                            counted.insert(&outcome.signature)
+                                && !storage.contains_transaction(&outcome.signature).unwrap_or(false)
+                        });
+                        if let Some(outcome) = outcome {
+                            latest_slot = latest_slot.max(outcome.slot);
+                            let window = outcome.slot / storage::PROGRAM_HEALTH_WINDOW_SLOTS;
+                            for program_id in outcome.programs {
+                                health.entry((window, program_id)).or_default().add(outcome.failed);
+                            }
+                        }
+                        
+                        hits.extend(result.watchlist_hits);
+                        alerts.extend(result.security_alerts);
+                        pending.insert(result.sequence, result.stored_tx);
+                        while let Some(stored_tx) = pending.remove(&next_sequence) {
+                            next_sequence += 1;
+                            if let Some(stored_tx) = stored_tx {
                                 batch.push(stored_tx);
-                                
-                                // Store in batches for efficiency
-                                if batch.len() >= 100 {
-                                    if let Err(e) = storage.store_transactions_batch(&batch) {
-                                        error!("Failed to store batch: {}", e);
-                                    }
-                                    batch.clear();
-                                }
                             }
                         }
-                        Err(e) => error!("Failed to process transaction: {}", e),
+                        
+                        // Store in batches for efficiency
+                        if batch.len() >= 100 {
+                            store_failed |= !Self::flush_batch(&storage, &mut batch, &feeds.metrics);
+                            if !store_failed {
+                                feeds.persisted.send_replace(next_sequence);
+                            }
+                        }
+                        continue;
                     }
+                    // Every worker stopped, as the pipeline was closed: store what is left and stop
+                    None => true,
+                },
+                _ = interval.tick() => false,
+            };
+            
+            // Flush any remaining transactions
+            if !batch.is_empty() {
+                store_failed |= !Self::flush_batch(&storage, &mut batch, &feeds.metrics);
+            }
+            if !store_failed {
+                feeds.persisted.send_replace(next_sequence);
+            }
+            if !health.is_empty() {
+                if let Err(e) = storage.record_program_health(&health) {
+                    error!("Failed to record program health: {}", e);
+                }
+                health.clear();
+                Self::report_program_health(&storage, latest_slot, &mut spiking);
+            }
+            if !hits.is_empty() {
+                if let Err(e) = storage.store_watchlist_hits(&hits) {
+                    error!("Failed to store watchlist hits: {}", e);
                 }
-                _ = interval.tick() => {
-                    // Flush any remaining transactions
-                    if !batch.is_empty() {
-                        if let Err(e) = storage.store_transactions_batch(&batch) {
-                            error!("Failed to store batch: {}", e);
+                hits.clear();
+            }
+            if !alerts.is_empty() {
+                if let Err(e) = storage.store_security_alerts(&alerts) {
+                    error!("Failed to store security alerts: {}", e);
+                }
+                alerts.clear();
+            }
+            if closed {
+                break;
+            }
+        }
+    }
+    
+    /// Process the transactions handed to this worker on a blocking thread, with plugin
+    /// instances of its own
+    fn processing_worker(
+        mut work: mpsc::Receiver<(u64, EncodedConfirmedTransactionWithStatusMeta)>,
+        results: mpsc::Sender<WorkerResult>,
+        store_log_messages: bool,
+        feeds: Feeds,
+    ) {
+        let mut processor_updates = feeds.processor.subscribe();
+        let mut processor = processor_updates.borrow_and_update().clone();
+        let mut plugins = match processor.plugins().instantiate() {
+            Ok(plugins) => plugins,
+            Err(e) => {
+                error!("Failed to start processing worker: {:#}", e);
+                return;
+            }
+        };
+        while let Some((sequence, tx)) = work.blocking_recv() {
+            // Pick up a reloaded configuration between transactions
+            if processor_updates.has_changed().unwrap_or(false) {
+                let reloaded = processor_updates.borrow_and_update().clone();
+                match reloaded.plugins().instantiate() {
+                    Ok(reloaded_plugins) => {
+                        processor = reloaded;
+                        plugins = reloaded_plugins;
+                    }
+                    Err(e) => error!("Failed to apply reloaded configuration, keeping the previous one: {:#}", e),
+                }
+            }
+            let mut result = WorkerResult {
+                sequence,
+                outcome: None,
+                stored_tx: None,
+                watchlist_hits: Vec::new(),
+                security_alerts: Vec::new(),
+            };
+            match processor.process_encoded_transaction(&tx) {
+                Ok(processed) => {
+                    feeds.metrics.transactions_processed.inc();
+                    feeds.processed_slot.send_if_modified(|slot| {
+                        let newer = processed.slot > *slot;
+                        if newer {
+                            *slot = processed.slot;
                         }
-                        batch.clear();
+                        newer
+                    });
+                    
+                    // Counted before plugins and filters, so failures are tracked even when not
+                    // stored; transactions from shreds have no outcome yet
+                    result.outcome = tx.transaction.meta.is_some().then(|| ProgramOutcome {
+                        signature: processed.signature.clone(),
+                        slot: processed.slot,
+                        programs: processed.program_ids().into_iter().map(str::to_string).collect(),
+                        failed: processed.error.is_some(),
+                    });
+                    
+                    // Report watched addresses right away, whether or not the transaction is stored
+                    result.watchlist_hits = processor.watchlist().hits(&processed);
+                    for hit in &result.watchlist_hits {
+                        // Sending only fails when nobody is subscribed
+                        let _ = feeds.watchlist_hits.send(hit.clone());
+                    }
+                    
+                    for alert in &processed.security_alerts {
+                        warn!("Security alert ({:?}) in {}: {}", alert.kind, alert.signature, alert.description);
+                        let _ = feeds.security_alerts.send(alert.clone());
+                    }
+                    result.security_alerts = processed.security_alerts.clone();
+                    
+                    result.stored_tx = Self::process_transaction(
+                        &processor,
+                        &mut plugins,
+                        processed,
+                        tx,
+                        store_log_messages,
+                        Some(&feeds.transactions),
+                    );
+                    if result.stored_tx.is_none() {
+                        feeds.metrics.transactions_filtered.inc();
                     }
                 }
+                Err(e) => {
+                    feeds.metrics.transactions_failed.inc();
+                    error!("Failed to process transaction: {}", e);
+                }
+            }
+            
+            // Filtered and failed transactions are still reported so ordering can advance
+            if results.blocking_send(result).is_err() {
+                break;
             }
         }
     }
     
-    async fn report_statistics(storage: Storage) {
+    /// Run plugins and filters over a processed transaction, returning the record to store and
+    /// streaming it to the subscribers of `stream`, if given
+    fn process_transaction(
+        processor: &TransactionProcessor,
+        plugins: &mut PluginPipeline,
+        mut processed: ProcessedTransaction,
+        mut tx: EncodedConfirmedTransactionWithStatusMeta,
+        store_log_messages: bool,
+        stream: Option<&broadcast::Sender<StreamedTransaction>>,
+    ) -> Option<StoredTransaction> {
+        if !plugins.apply(&mut processed) || !processor.should_store_transaction(&processed) {
+            return None;
+        }
+        
+        info!("{}", processed.summary());
+        if !processed.account_changes.is_empty() {
+            debug!(
+                "{} account changes, rent locked: {}, released: {}",
+                processed.account_changes.len(),
+                processed.rent_locked(),
+                processed.rent_released()
+            );
+        }
+        
+        if !store_log_messages {
+            if let Some(meta) = tx.transaction.meta.as_mut() {
+                meta.log_messages = OptionSerializer::None;
+            }
+        }
+        
+        let stored_tx = StoredTransaction {
+            schema_version: storage::SCHEMA_VERSION,
+            signature: processed.signature.clone(),
+            slot: tx.slot,
+            timestamp: tx.block_time.unwrap_or(0),
+            transaction: tx,
+            fee_payer: processed.fee_payer.clone(),
+            wire_size: processed.wire_size,
+            priority_fee_micro_lamports: (!processed.is_vote)
+                .then(|| processed.priority_fee_micro_lamports.unwrap_or(0)),
+            durable_nonce: processed.durable_nonce.clone(),
+            signatures_verified: processed.signatures_verified,
+            blockhash_age: processed.blockhash_age,
+            swap: processed.swap.clone(),
+            sandwich: None,
+            memo: processed.memo.clone(),
+            program_categories: processed.program_categories.clone(),
+            nft_events: processed.nft_events.clone(),
+            token_events: processed.token_events.clone(),
+            vote_events: processed.vote_events.clone(),
+            error_class: processed.error_class.clone(),
+            anchor_events: processed.anchor_events.clone(),
+            account_changes: processed.account_changes.clone(),
+            call_graph: processed.call_graph(),
+            labeled_accounts: processed.labeled_accounts.clone(),
+            raw_instructions: processed.raw_instructions.clone(),
+            account_flags: processed.account_flags.clone(),
+            program_compute: processed.program_compute.clone(),
+            instructions: processed.instruction_records(),
+            account_keys: processed.account_keys.clone(),
+        };
+        
+        // Streamed as soon as it is processed, ahead of the ordered batch writes
+        if let Some(stream) = stream.filter(|stream| stream.receiver_count() > 0) {
+            match StreamedTransaction::new(&processed, &stored_tx) {
+                Ok(streamed) => {
+                    let _ = stream.send(streamed);
+                }
+                Err(e) => error!("Failed to serialize transaction for streaming: {}", e),
+            }
+        }
+        Some(stored_tx)
+    }
+    
+    /// Run batch-level analysis (MEV detection) and store the batch, returning whether it stored
+    fn flush_batch(storage: &Storage, batch: &mut Vec<StoredTransaction>, metrics: &Metrics) -> bool {
+        // Commit slot by slot; the sort is stable, so arrival order within a slot is kept
+        batch.sort_by_key(|tx| tx.slot);
+        
+        for sandwich in mev::detect_sandwiches(batch) {
+            info!(
+                "Suspected sandwich in slot {} on {} by {}: victim {}",
+                sandwich.slot, sandwich.pool, sandwich.attacker, sandwich.victim
+            );
+        }
+        
+        // Transactions from shreds are kept apart until a confirmed copy replaces them
+        let (unconfirmed, confirmed): (Vec<_>, Vec<_>) = batch.drain(..).partition(StoredTransaction::is_unconfirmed);
+        let stored = storage.store_transactions_batch(&confirmed).and_then(|()| {
+            let unconfirmed_count = storage.store_unconfirmed_transactions(&unconfirmed)?;
+            Ok(confirmed.len() + unconfirmed_count)
+        });
+        match stored {
+            Ok(count) => {
+                metrics.transactions_stored.add(count as u64);
+                true
+            }
+            Err(e) => {
+                error!("Failed to store batch: {}", e);
+                false
+            }
+        }
+    }
+    
+    /// Warn about programs whose failure rate started spiking since the last check
+    fn report_program_health(storage: &Storage, slot: u64, spiking: &mut Vec<String>) {
+        let reports = match storage.get_program_health(slot, PROGRAM_HEALTH_BASELINE_WINDOWS) {
+            Ok(reports) => reports,
+            Err(e) => {
+                error!("Failed to get program health: {}", e);
+                return;
+            }
+        };
+        
+        let mut now_spiking = Vec::new();
+        for report in reports.into_iter().filter(|report| report.spiking) {
+            if !spiking.contains(&report.program_id) {
+                warn!(
+                    "Failure rate of {} spiked to {:.1}% ({} of {} transactions), baseline {:.1}%",
+                    report.program_id,
+                    report.current.failure_rate() * 100.0,
+                    report.current.failures,
+                    report.current.transaction_count(),
+                    report.baseline.failure_rate() * 100.0
+                );
+            }
+            now_spiking.push(report.program_id);
+        }
+        *spiking = now_spiking;
+    }
+    
+    async fn report_watchlist_hits(mut hits: broadcast::Receiver<WatchlistHit>) {
+        loop {
+            match hits.recv().await {
+                Ok(hit) => info!(
+                    "Watchlist hit: {} in {} (slot {}, signer: {}, writable: {}{})",
+                    hit.address,
+                    hit.signature,
+                    hit.slot,
+                    hit.signer,
+                    hit.writable,
+                    if hit.failed { ", failed" } else { "" }
+                ),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Watchlist reporter fell behind, {} hits not logged", missed)
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+    
+    async fn report_statistics(storage: Storage, gossip: Option<GossipMonitor>, metrics: Arc<Metrics>) {
         let mut interval = interval(Duration::from_secs(30));
         
         loop {
@@ -248,12 +1316,54 @@ impl NetworkService {
                         stats.transaction_count,
                         stats.db_size_bytes as f64 / 1_048_576.0
                     );
+                    metrics.set_storage(&stats);
                 }
                 Err(e) => error!("Failed to get storage stats: {}", e),
             }
+            
+            if let Some(gossip) = &gossip {
+                let metrics = gossip.sample();
+                info!(
+                    "Gossip stats - Peers: {} (+{} -{}), CRDS values: {}, Push: {} received ({} duplicate), {} sent, Pull: {} inserted",
+                    metrics.peers,
+                    metrics.peers_joined,
+                    metrics.peers_left,
+                    metrics.crds_values,
+                    metrics.push_values_received,
+                    metrics.push_values_duplicate,
+                    metrics.push_messages_sent,
+                    metrics.pull_values_inserted
+                );
+            }
         }
     }
 }
 
+/// Transaction encoding sources fetch in: signature verification and instruction data capture
+/// need the raw transaction bytes, and so does the JSON-RPC API, to serve every encoding. The
+/// processor parses the instructions of raw transactions as the RPC node would
+fn transaction_encoding(config: &Config) -> UiTransactionEncoding {
+    if config.node.verify_signatures || config.node.store_instruction_data || config.api.enabled {
+        UiTransactionEncoding::Base64
+    } else {
+        UiTransactionEncoding::JsonParsed
+    }
+}
+
+/// Record the header of a fetched block
+fn store_block(storage: &Storage, slot: u64, block: &UiConfirmedBlock) {
+    let stored = StoredBlock {
+        slot,
+        parent_slot: block.parent_slot,
+        blockhash: block.blockhash.clone(),
+        previous_blockhash: block.previous_blockhash.clone(),
+        block_time: block.block_time,
+        block_height: block.block_height,
+    };
+    if let Err(e) = storage.store_block(&stored) {
+        error!("Failed to store block {}: {}", slot, e);
+    }
+}
+
 // Re-export for convenience
 use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta; 
\ No newline at end of file
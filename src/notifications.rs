@@ -0,0 +1,390 @@
+//! Chat alerts on Slack, Discord and Telegram
+//!
+//! Each configured channel is posted the watchlist hits, security alerts and ingestion lag alarms
+//! its routing rules select, formatted for its service. Posts to a channel are spaced out to stay
+//! within the services' rate limits; alerts raised faster than that wait in a queue, and are
+//! dropped once it is full. A lag alarm fires when processing stays more than the channel's
+//! `lag_slots` behind the cluster, and is followed by a notice once it catches up.
+
+use anyhow::{bail, Result};
+use reqwest::{header, StatusCode};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::{interval, sleep},
+};
+use tracing::{info, warn};
+
+use crate::config::{AlertTrigger, ChatService, NotificationConfig};
+use crate::metrics::Metrics;
+use crate::network::IngestionStatus;
+use crate::security::{AlertKind, SecurityAlert};
+use crate::watchlist::WatchlistHit;
+
+/// Alerts waiting to be posted per channel; past it new ones are dropped
+const QUEUE_SIZE: usize = 100;
+
+/// Shortest wait between two posts to a channel (Slack allows about one message per second)
+const MIN_POST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Attempts per alert, when the service is rate limiting or failing
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Wait before trying again when the service does not say how long to wait
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Interval between checks of the ingestion lag
+const LAG_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Consecutive checks past the threshold before the lag alarm fires, so short stalls stay quiet
+const LAG_ALARM_CHECKS: u32 = 3;
+
+/// Longest Discord message
+const DISCORD_MAX_LENGTH: usize = 2000;
+
+const TELEGRAM_API: &str = "https://api.telegram.org";
+
+#[derive(Debug, Clone)]
+enum Alert {
+    Watchlist(WatchlistHit),
+    Security(SecurityAlert),
+    /// Processing trails the cluster by `lag` slots, more than the channel's `threshold`
+    Lagging { lag: u64, threshold: u64 },
+    /// Processing is back within the threshold after a lag alarm
+    CaughtUp { lag: u64 },
+}
+
+/// An alert as a title and labelled values, rendered by each service its own way
+struct Message {
+    title: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+/// Why a post failed, and how long to wait before trying again when it is worth it
+struct Failure {
+    error: String,
+    retry_after: Option<Duration>,
+}
+
+/// A channel's routing rules and the queue of alerts waiting to be posted to it
+struct Route {
+    config: NotificationConfig,
+    queue: mpsc::Sender<Alert>,
+    name: String,
+}
+
+/// The configured chat channels, posting alerts once started
+pub struct Notifier {
+    configs: Vec<NotificationConfig>,
+    client: reqwest::Client,
+    metrics: Arc<Metrics>,
+}
+
+impl Notifier {
+    pub fn new(configs: &[NotificationConfig], metrics: Arc<Metrics>) -> Result<Self> {
+        for (index, config) in configs.iter().enumerate() {
+            let missing = match config.service {
+                ChatService::Slack | ChatService::Discord if config.webhook_url.is_none() => Some("webhook_url"),
+                ChatService::Telegram if config.bot_token.is_none() || config.chat_id.is_none() => {
+                    Some("bot_token and chat_id")
+                }
+                _ => None,
+            };
+            if let Some(missing) = missing {
+                bail!("Notification channel {} ({:?}) needs {}", index + 1, config.service, missing);
+            }
+        }
+        Ok(Self {
+            configs: configs.to_vec(),
+            client: reqwest::Client::new(),
+            metrics,
+        })
+    }
+    
+    /// Route the hits of `watchlist_hits`, the alerts of `security_alerts` and lag alarms from
+    /// `ingestion` to the channels, in the background
+    pub fn start(
+        self,
+        watchlist_hits: &broadcast::Sender<WatchlistHit>,
+        security_alerts: &broadcast::Sender<SecurityAlert>,
+        ingestion: IngestionStatus,
+    ) {
+        if self.configs.is_empty() {
+            return;
+        }
+        
+        let mut routes = Vec::new();
+        for (index, config) in self.configs.iter().enumerate() {
+            let name = format!("{:?} channel {}", config.service, index + 1);
+            info!("Posting alerts to {}", name);
+            let (queue, alerts) = mpsc::channel(QUEUE_SIZE);
+            tokio::spawn(post_alerts(config.clone(), name.clone(), self.client.clone(), self.metrics.clone(), alerts));
+            routes.push(Route { config: config.clone(), queue, name });
+        }
+        let routes = Arc::new(routes);
+        
+        if routes.iter().any(|route| route.routes(AlertTrigger::Watchlist)) {
+            let hits = watchlist_hits.subscribe();
+            tokio::spawn(dispatch(routes.clone(), hits, Alert::Watchlist, self.metrics.clone()));
+        }
+        if routes.iter().any(|route| route.routes(AlertTrigger::Security)) {
+            let alerts = security_alerts.subscribe();
+            tokio::spawn(dispatch(routes.clone(), alerts, Alert::Security, self.metrics.clone()));
+        }
+        if routes.iter().any(|route| route.routes(AlertTrigger::Lag)) {
+            tokio::spawn(watch_lag(routes, ingestion, self.metrics));
+        }
+    }
+}
+
+impl Route {
+    /// Whether the channel takes alerts of this trigger at all
+    fn routes(&self, trigger: AlertTrigger) -> bool {
+        self.config.alerts.is_empty() || self.config.alerts.contains(&trigger)
+    }
+    
+    fn selects(&self, alert: &Alert) -> bool {
+        match alert {
+            Alert::Watchlist(hit) => {
+                self.routes(AlertTrigger::Watchlist)
+                    && (self.config.addresses.is_empty() || self.config.addresses.contains(&hit.address))
+            }
+            Alert::Security(alert) => {
+                self.routes(AlertTrigger::Security)
+                    && (self.config.security_kinds.is_empty() || self.config.security_kinds.contains(&alert.kind))
+            }
+            Alert::Lagging { .. } | Alert::CaughtUp { .. } => self.routes(AlertTrigger::Lag),
+        }
+    }
+    
+    /// Queue `alert` for posting, dropping it when the queue is full
+    fn push(&self, alert: Alert, metrics: &Metrics) {
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.queue.try_send(alert) {
+            warn!("Alert queue of {} full, dropping an alert", self.name);
+            metrics.notifications_failed.inc();
+        }
+    }
+}
+
+/// Queue each item of `items` on the channels selecting it
+async fn dispatch<T: Clone>(
+    routes: Arc<Vec<Route>>,
+    mut items: broadcast::Receiver<T>,
+    to_alert: fn(T) -> Alert,
+    metrics: Arc<Metrics>,
+) {
+    loop {
+        let alert = match items.recv().await {
+            Ok(item) => to_alert(item),
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!("Notifications fell behind, {} alerts not posted", missed);
+                metrics.notifications_failed.add(missed);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        for route in routes.iter().filter(|route| route.selects(&alert)) {
+            route.push(alert.clone(), &metrics);
+        }
+    }
+}
+
+/// Raise a lag alarm on each channel whose threshold processing stays past, and a notice once it
+/// is back within it
+async fn watch_lag(routes: Arc<Vec<Route>>, ingestion: IngestionStatus, metrics: Arc<Metrics>) {
+    // Consecutive checks past the threshold, and whether the alarm is raised, per channel
+    let mut states = vec![(0, false); routes.len()];
+    let mut interval = interval(LAG_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let lag = match ingestion.lag() {
+            Some(lag) => lag,
+            None => continue,
+        };
+        for (route, (checks, raised)) in routes.iter().zip(states.iter_mut()) {
+            if !route.routes(AlertTrigger::Lag) {
+                continue;
+            }
+            if lag > route.config.lag_slots {
+                *checks += 1;
+                if *checks >= LAG_ALARM_CHECKS && !*raised {
+                    *raised = true;
+                    route.push(Alert::Lagging { lag, threshold: route.config.lag_slots }, &metrics);
+                }
+            } else {
+                *checks = 0;
+                if *raised {
+                    *raised = false;
+                    route.push(Alert::CaughtUp { lag }, &metrics);
+                }
+            }
+        }
+    }
+}
+
+/// Post the queued alerts of one channel in order, spaced out by `MIN_POST_INTERVAL`
+async fn post_alerts(
+    config: NotificationConfig,
+    name: String,
+    client: reqwest::Client,
+    metrics: Arc<Metrics>,
+    mut alerts: mpsc::Receiver<Alert>,
+) {
+    while let Some(alert) = alerts.recv().await {
+        let message = alert.message();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match post(&client, &config, &message).await {
+                Ok(()) => {
+                    metrics.notifications_sent.inc();
+                    break;
+                }
+                Err(Failure { error, retry_after: Some(wait) }) if attempts < MAX_ATTEMPTS => {
+                    warn!("Posting to {} failed ({}), retrying in {:?}", name, error, wait);
+                    sleep(wait).await;
+                }
+                Err(Failure { error, .. }) => {
+                    warn!("Failed to post \"{}\" to {}: {}", message.title, name, error);
+                    metrics.notifications_failed.inc();
+                    break;
+                }
+            }
+        }
+        sleep(MIN_POST_INTERVAL).await;
+    }
+}
+
+/// A single attempt
+async fn post(client: &reqwest::Client, config: &NotificationConfig, message: &Message) -> Result<(), Failure> {
+    // Checked by `Notifier::new`
+    let webhook_url = config.webhook_url.as_deref().unwrap_or_default();
+    let request = match config.service {
+        ChatService::Slack => client.post(webhook_url).json(&json!({ "text": message.slack() })),
+        ChatService::Discord => client.post(webhook_url).json(&json!({
+            "content": message.discord(),
+            "allowed_mentions": { "parse": [] },
+        })),
+        ChatService::Telegram => client
+            .post(format!("{}/bot{}/sendMessage", TELEGRAM_API, config.bot_token.as_deref().unwrap_or_default()))
+            .json(&json!({
+                "chat_id": config.chat_id,
+                "text": message.telegram(),
+                "parse_mode": "HTML",
+                "disable_web_page_preview": true,
+            })),
+    };
+    
+    // Without the URL, which holds the webhook or bot token
+    let response = request.timeout(POST_TIMEOUT).send().await.map_err(|e| Failure {
+        error: e.without_url().to_string(),
+        retry_after: Some(DEFAULT_RETRY_AFTER),
+    })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let retry_after = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok()?.parse::<f64>().ok())
+        .map(Duration::from_secs_f64);
+    Err(Failure {
+        error: format!("HTTP {}", status),
+        retry_after: match status {
+            StatusCode::TOO_MANY_REQUESTS => Some(retry_after.unwrap_or(DEFAULT_RETRY_AFTER)),
+            status if status.is_server_error() => Some(DEFAULT_RETRY_AFTER),
+            _ => None,
+        },
+    })
+}
+
+impl Alert {
+    fn message(&self) -> Message {
+        match self {
+            Alert::Watchlist(hit) => Message {
+                title: format!("Watchlist hit on {}", hit.address),
+                fields: vec![
+                    ("Transaction", hit.signature.clone()),
+                    ("Slot", hit.slot.to_string()),
+                    (
+                        "Role",
+                        match (hit.signer, hit.writable) {
+                            (true, _) => "signer",
+                            (false, true) => "writable",
+                            (false, false) => "read-only",
+                        }
+                        .to_string(),
+                    ),
+                    ("Programs", hit.programs.join(", ")),
+                    ("Status", if hit.failed { "failed" } else { "succeeded" }.to_string()),
+                ],
+            },
+            Alert::Security(alert) => {
+                let kind = match alert.kind {
+                    AlertKind::TokenDrain => "token drain",
+                    AlertKind::AuthorityChange => "authority change",
+                    AlertKind::MassApproval => "mass approval",
+                };
+                let mut fields = vec![
+                    ("Details", alert.description.clone()),
+                    ("Transaction", alert.signature.clone()),
+                    ("Slot", alert.slot.to_string()),
+                ];
+                if let Some(actor) = &alert.actor {
+                    fields.push(("Actor", actor.clone()));
+                }
+                if let Some(counterparty) = &alert.counterparty {
+                    fields.push(("Counterparty", counterparty.clone()));
+                }
+                Message { title: format!("Suspicious activity: {}", kind), fields }
+            }
+            Alert::Lagging { lag, threshold } => Message {
+                title: format!("Ingestion {} slots behind the cluster", lag),
+                fields: vec![("Threshold", format!("{} slots", threshold))],
+            },
+            Alert::CaughtUp { lag } => Message {
+                title: "Ingestion caught up with the cluster".to_string(),
+                fields: vec![("Lag", format!("{} slots", lag))],
+            },
+        }
+    }
+}
+
+impl Message {
+    /// Slack mrkdwn, where `&`, `<` and `>` must be escaped
+    fn slack(&self) -> String {
+        let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        let mut text = format!("*{}*", escape(&self.title));
+        for (label, value) in &self.fields {
+            text.push_str(&format!("\n{}: `{}`", label, escape(value)));
+        }
+        text
+    }
+    
+    /// Discord markdown, cut to the longest message Discord takes
+    fn discord(&self) -> String {
+        let mut text = format!("**{}**", self.title);
+        for (label, value) in &self.fields {
+            text.push_str(&format!("\n{}: `{}`", label, value.replace('`', "'")));
+        }
+        match text.char_indices().nth(DISCORD_MAX_LENGTH) {
+            Some((end, _)) => text[..end].to_string(),
+            None => text,
+        }
+    }
+    
+    /// Telegram HTML
+    fn telegram(&self) -> String {
+        let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        let mut text = format!("<b>{}</b>", escape(&self.title));
+        for (label, value) in &self.fields {
+            text.push_str(&format!("\n{}: <code>{}</code>", label, escape(value)));
+        }
+        text
+    }
+}
@@ -0,0 +1,258 @@
+//! WASM plugin stage for custom filters and transforms
+//!
+//! A plugin is a WASM module exporting:
+//! - `memory`: the module's linear memory
+//! - `alloc(len: i32) -> i32`: allocate `len` bytes for the host to write input into
+//! - `dealloc(ptr: i32, len: i32)` (optional): free an allocation, called for every input once
+//!   the plugin returns and for every output once the host has read it. Without it the plugin
+//!   starts over from a fresh instance for each transaction, so its memory cannot grow
+//! - `filter(ptr: i32, len: i32) -> i32` (optional): return 0 to drop the transaction
+//! - `transform(ptr: i32, len: i32) -> i64` (optional): return `(ptr << 32) | len` of the
+//!   modified transaction JSON, or 0 to leave the transaction unchanged. The signature must not
+//!   change
+//!
+//! The input passed to `filter` and `transform` is the JSON-serialized `ProcessedTransaction`.
+//! Each processing worker runs instances of its own, so a plugin keeping state across calls sees
+//! a share of the transactions only.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::sync::Arc;
+use tracing::{error, info};
+use wasmi::{Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+use crate::{config::PluginConfig, transaction_processor::ProcessedTransaction};
+
+/// Ordered set of loaded plugins every processed transaction is passed through
+pub struct PluginPipeline {
+    plugins: Vec<WasmPlugin>,
+}
+
+struct WasmPlugin {
+    name: String,
+    engine: Engine,
+    module: Arc<Module>,
+    store: Store<StoreLimits>,
+    exports: Exports,
+    fuel_limit: u64,
+    /// Bytes of linear memory an instance may grow to
+    memory_limit: usize,
+}
+
+/// Functions and memory of an instance of a plugin
+struct Exports {
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    dealloc: Option<TypedFunc<(i32, i32), ()>>,
+    filter: Option<TypedFunc<(i32, i32), i32>>,
+    transform: Option<TypedFunc<(i32, i32), i64>>,
+}
+
+impl PluginPipeline {
+    /// Load and instantiate all configured plugins
+    pub fn load(configs: &[PluginConfig]) -> Result<Self> {
+        let plugins = configs
+            .iter()
+            .map(WasmPlugin::load)
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(Self { plugins })
+    }
+    
+    /// Separate instances of the plugins, sharing their compiled modules, for a worker to run
+    /// without waiting on the others
+    pub fn instantiate(&self) -> Result<Self> {
+        let plugins = self
+            .plugins
+            .iter()
+            .map(WasmPlugin::instantiate)
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(Self { plugins })
+    }
+    
+    /// Run a transaction through the plugins, returning false if it was dropped
+    pub fn apply(&mut self, tx: &mut ProcessedTransaction) -> bool {
+        if self.plugins.is_empty() {
+            return true;
+        }
+        
+        match self.run(tx) {
+            Ok(keep) => keep,
+            Err(e) => {
+                // Keep the transaction so a faulty plugin doesn't silently lose data
+                error!("Plugin error for transaction {}: {:#}", tx.signature, e);
+                true
+            }
+        }
+    }
+    
+    /// Pass a transaction through every plugin in order, returning false if any plugin drops it
+    fn run(&mut self, tx: &mut ProcessedTransaction) -> Result<bool> {
+        for plugin in &mut self.plugins {
+            if !plugin.run(tx).with_context(|| format!("Plugin {} failed", plugin.name))? {
+                return Ok(false);
+            }
+        }
+        
+        Ok(true)
+    }
+}
+
+impl Exports {
+    fn instantiate(store: &mut Store<StoreLimits>, module: &Module) -> Result<Self> {
+        let linker = <Linker<StoreLimits>>::new(module.engine());
+        let instance: Instance = linker
+            .instantiate(&mut *store, module)?
+            .start(&mut *store)?;
+        
+        Ok(Self {
+            memory: instance
+                .get_memory(&*store, "memory")
+                .context("Plugin does not export `memory`")?,
+            alloc: instance.get_typed_func::<i32, i32>(&*store, "alloc")?,
+            dealloc: instance.get_typed_func::<(i32, i32), ()>(&*store, "dealloc").ok(),
+            filter: instance.get_typed_func::<(i32, i32), i32>(&*store, "filter").ok(),
+            transform: instance.get_typed_func::<(i32, i32), i64>(&*store, "transform").ok(),
+        })
+    }
+}
+
+impl WasmPlugin {
+    fn load(config: &PluginConfig) -> Result<Self> {
+        let bytes = fs::read(&config.path)
+            .with_context(|| format!("Failed to read plugin {}", config.path))?;
+        
+        let mut engine_config = wasmi::Config::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+        
+        let module = Module::new(&engine, &bytes[..])?;
+        let memory_limit = config.memory_limit_mb.saturating_mul(1024 * 1024);
+        let mut store = new_store(&engine, memory_limit);
+        let exports = Exports::instantiate(&mut store, &module)?;
+        
+        info!(
+            "Loaded plugin {} (filter: {}, transform: {}, dealloc: {})",
+            config.path,
+            exports.filter.is_some(),
+            exports.transform.is_some(),
+            exports.dealloc.is_some()
+        );
+        
+        Ok(Self {
+            name: config.path.clone(),
+            engine,
+            module: Arc::new(module),
+            store,
+            exports,
+            fuel_limit: config.fuel_limit,
+            memory_limit,
+        })
+    }
+    
+    /// A fresh instance of the same module
+    fn instantiate(&self) -> Result<Self> {
+        let mut store = new_store(&self.engine, self.memory_limit);
+        let exports = Exports::instantiate(&mut store, &self.module)
+            .with_context(|| format!("Failed to instantiate plugin {}", self.name))?;
+        
+        Ok(Self {
+            name: self.name.clone(),
+            engine: self.engine.clone(),
+            module: self.module.clone(),
+            store,
+            exports,
+            fuel_limit: self.fuel_limit,
+            memory_limit: self.memory_limit,
+        })
+    }
+    
+    fn run(&mut self, tx: &mut ProcessedTransaction) -> Result<bool> {
+        let result = self.call(tx);
+        // Without `dealloc`, the memory taken for this transaction is reclaimed by starting over
+        if self.exports.dealloc.is_none() {
+            self.store = new_store(&self.engine, self.memory_limit);
+            self.exports = Exports::instantiate(&mut self.store, &self.module)?;
+        }
+        result
+    }
+    
+    fn call(&mut self, tx: &mut ProcessedTransaction) -> Result<bool> {
+        if let Some(filter) = self.exports.filter {
+            let (ptr, len) = self.write_transaction(tx)?;
+            let keep = filter.call(&mut self.store, (ptr, len))? != 0;
+            self.free(ptr, len)?;
+            if !keep {
+                return Ok(false);
+            }
+        }
+        
+        if let Some(transform) = self.exports.transform {
+            let (ptr, len) = self.write_transaction(tx)?;
+            let result = transform.call(&mut self.store, (ptr, len))?;
+            if result != 0 {
+                let out_ptr = (result >> 32) as u32 as usize;
+                let out_len = result as u32 as usize;
+                // Parsed in place, so a bogus length cannot make the host allocate
+                let output = match self.exports.memory.data(&self.store).get(out_ptr..out_ptr + out_len) {
+                    Some(output) => output,
+                    None => bail!("Plugin returned {} bytes at {}, beyond its memory", out_len, out_ptr),
+                };
+                let transformed = serde_json::from_slice::<ProcessedTransaction>(output);
+                // A plugin may return its input, modified in place
+                if out_ptr as i32 != ptr {
+                    self.free(out_ptr as i32, out_len as i32)?;
+                }
+                let transformed = transformed.context("Plugin returned an invalid transaction")?;
+                if transformed.signature != tx.signature {
+                    bail!("Plugin changed the signature to {:?}", transformed.signature);
+                }
+                *tx = transformed;
+            }
+            self.free(ptr, len)?;
+        }
+        
+        Ok(true)
+    }
+    
+    /// Serialize the transaction into plugin memory, returning its pointer and length
+    fn write_transaction(&mut self, tx: &ProcessedTransaction) -> Result<(i32, i32)> {
+        self.refuel()?;
+        
+        let input = serde_json::to_vec(tx)?;
+        let len = i32::try_from(input.len()).context("Transaction too large for plugin")?;
+        let ptr = self.exports.alloc.call(&mut self.store, len)?;
+        self.exports.memory
+            .write(&mut self.store, ptr as u32 as usize, &input)
+            .map_err(wasmi::Error::from)?;
+        
+        Ok((ptr, len))
+    }
+    
+    /// Return an allocation to the plugin, if it can take it back
+    fn free(&mut self, ptr: i32, len: i32) -> Result<()> {
+        if let Some(dealloc) = self.exports.dealloc {
+            dealloc.call(&mut self.store, (ptr, len))?;
+        }
+        Ok(())
+    }
+    
+    /// Reset the fuel budget so a single call cannot run unbounded
+    fn refuel(&mut self) -> Result<()> {
+        let remaining = self.store.consume_fuel(0).map_err(wasmi::Error::from)?;
+        if remaining < self.fuel_limit {
+            self.store
+                .add_fuel(self.fuel_limit - remaining)
+                .map_err(wasmi::Error::from)?;
+        }
+        Ok(())
+    }
+}
+
+/// A store whose instances cannot grow their memory past `memory_limit` bytes
+fn new_store(engine: &Engine, memory_limit: usize) -> Store<StoreLimits> {
+    let mut store = Store::new(engine, StoreLimitsBuilder::new().memory_size(memory_limit).build());
+    store.limiter(|limits| limits);
+    store
+}
@@ -0,0 +1,81 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::config::ProgramLabel;
+
+/// Built-in program labels as (program ID, name, category)
+const KNOWN_PROGRAMS: &[(&str, &str, &str)] = &[
+    // Native and SPL programs
+    ("11111111111111111111111111111111", "System Program", "system"),
+    ("ComputeBudget111111111111111111111111111111", "Compute Budget", "system"),
+    ("AddressLookupTab1e1111111111111111111111111", "Address Lookup Table", "system"),
+    ("BPFLoaderUpgradeab1e11111111111111111111111", "BPF Upgradeable Loader", "system"),
+    ("Vote111111111111111111111111111111111111111", "Vote Program", "vote"),
+    ("Stake11111111111111111111111111111111111111", "Stake Program", "staking"),
+    ("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA", "SPL Token", "token"),
+    ("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb", "SPL Token-2022", "token"),
+    ("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL", "Associated Token Account", "token"),
+    ("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr", "Memo", "utility"),
+    ("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo", "Memo v1", "utility"),
+    ("namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX", "Name Service", "utility"),
+    ("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy", "SPL Stake Pool", "staking"),
+    // DEXes and aggregators
+    ("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", "Jupiter v6", "dex-aggregator"),
+    ("JUP4Fb2cqiRUcaTHdrPC8h2gNsA2ETXiPDD33WcGuJB", "Jupiter v4", "dex-aggregator"),
+    ("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc", "Orca Whirlpool", "dex"),
+    ("9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP", "Orca Token Swap v2", "dex"),
+    ("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8", "Raydium AMM v4", "dex"),
+    ("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK", "Raydium CLMM", "dex"),
+    ("srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX", "OpenBook (Serum v3)", "dex"),
+    ("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY", "Phoenix", "dex"),
+    ("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo", "Meteora DLMM", "dex"),
+    ("Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB", "Meteora Pools", "dex"),
+    ("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P", "Pump.fun", "launchpad"),
+    // NFTs
+    ("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s", "Metaplex Token Metadata", "nft"),
+    ("hausS13jsjafwWwGqZTUQRmWyvyxn9EQpqMwV1PBBmk", "Metaplex Auction House", "nft"),
+    ("cndy3Z4yapfJBmL3ShUp5exZKqR3z33thTzeNMm2gRZ", "Metaplex Candy Machine v2", "nft"),
+    ("CndyV3LdqHUfDLmE5naZjVN8rBZz4tqhdefbAnjHG3JR", "Metaplex Candy Machine v3", "nft"),
+    ("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY", "Metaplex Bubblegum", "nft"),
+    ("M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K", "Magic Eden v2", "nft-marketplace"),
+    ("TSWAPaqyCSx2KABk68Shruf4rp7CxcNi8hAsbdwmHbN", "Tensor Swap", "nft-marketplace"),
+    // DeFi
+    ("MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD", "Marinade", "staking"),
+    ("dRiftyHA39MWEi3m9aunc5MzRF1JYuBsbn6VPcn33UH", "Drift v2", "perps"),
+    ("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD", "Kamino Lend", "lending"),
+    ("MFv2hWf31Z9kbCa1snEPYctwafyhdvnV7FZnsebVacA", "marginfi v2", "lending"),
+    ("So1endDq2YkqhipRh3WViPa8hdiSpxWy6z3Z6tMCpAo", "Solend", "lending"),
+    ("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH", "Pyth Oracle", "oracle"),
+    // Multisig
+    ("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf", "Squads v4", "multisig"),
+    ("SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu", "Squads v3", "multisig"),
+];
+
+/// Maps program IDs to human-readable names and categories
+pub struct ProgramRegistry {
+    programs: HashMap<String, ProgramLabel>,
+}
+
+impl ProgramRegistry {
+    /// Build the registry from the built-in labels, with config entries taking precedence
+    pub fn new(overrides: &BTreeMap<String, ProgramLabel>) -> Self {
+        let mut programs: HashMap<String, ProgramLabel> = KNOWN_PROGRAMS
+            .iter()
+            .map(|(id, name, category)| {
+                let label = ProgramLabel {
+                    name: name.to_string(),
+                    category: category.to_string(),
+                };
+                (id.to_string(), label)
+            })
+            .collect();
+        
+        programs.extend(overrides.iter().map(|(id, label)| (id.clone(), label.clone())));
+        
+        Self { programs }
+    }
+    
+    /// Look up the label for a program ID
+    pub fn get(&self, program_id: &str) -> Option<&ProgramLabel> {
+        self.programs.get(program_id)
+    }
+}
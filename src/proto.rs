@@ -0,0 +1,796 @@
+//! Protobuf encoding of processed transactions for downstream sinks, non-Rust consumers and the
+//! gRPC API.
+//!
+//! The messages below are the prost equivalent of `proto/solana_node.proto` and must be kept in
+//! sync with it: new fields get new tags, existing tags are never renumbered or reused (see the
+//! compatibility rules at the top of the schema).
+
+use anyhow::{Context, Result};
+use prost::Message;
+
+use crate::address_labels;
+use crate::anchor;
+use crate::decoder::{metaplex, system, token, vote};
+use crate::mev;
+use crate::security;
+use crate::transaction_processor::{self, AccountChangeKind};
+
+/// `proto/solana_node.proto` compiled into a `FileDescriptorSet` by the build script, for gRPC
+/// reflection
+pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/solana_node_descriptor.bin"));
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ProcessedTransaction {
+    #[prost(uint32, tag = "1")]
+    pub schema_version: u32,
+    #[prost(string, tag = "2")]
+    pub signature: String,
+    #[prost(uint64, tag = "3")]
+    pub slot: u64,
+    #[prost(int64, optional, tag = "4")]
+    pub block_time: Option<i64>,
+    #[prost(uint64, tag = "5")]
+    pub fee: u64,
+    #[prost(string, optional, tag = "6")]
+    pub fee_payer: Option<String>,
+    #[prost(uint32, tag = "7")]
+    pub num_signers: u32,
+    #[prost(bool, tag = "8")]
+    pub is_multisig: bool,
+    #[prost(bool, tag = "9")]
+    pub is_vote: bool,
+    /// Transaction error as JSON
+    #[prost(string, optional, tag = "10")]
+    pub error: Option<String>,
+    #[prost(string, optional, tag = "11")]
+    pub error_class: Option<String>,
+    #[prost(uint32, optional, tag = "12")]
+    pub error_instruction_index: Option<u32>,
+    #[prost(uint64, optional, tag = "13")]
+    pub compute_units_consumed: Option<u64>,
+    #[prost(uint32, optional, tag = "14")]
+    pub cu_limit: Option<u32>,
+    #[prost(uint64, optional, tag = "15")]
+    pub priority_fee_micro_lamports: Option<u64>,
+    #[prost(string, repeated, tag = "16")]
+    pub account_keys: Vec<String>,
+    #[prost(uint32, tag = "17")]
+    pub instruction_count: u32,
+    #[prost(uint32, optional, tag = "18")]
+    pub wire_size: Option<u32>,
+    /// Top-level and CPI instructions in execution order
+    #[prost(message, repeated, tag = "19")]
+    pub instructions: Vec<Instruction>,
+    #[prost(string, repeated, tag = "20")]
+    pub log_messages: Vec<String>,
+    #[prost(message, repeated, tag = "21")]
+    pub token_events: Vec<TokenEvent>,
+    #[prost(message, repeated, tag = "22")]
+    pub system_events: Vec<SystemEvent>,
+    #[prost(string, optional, tag = "23")]
+    pub memo: Option<String>,
+    #[prost(string, repeated, tag = "24")]
+    pub program_labels: Vec<String>,
+    #[prost(string, repeated, tag = "25")]
+    pub program_categories: Vec<String>,
+    #[prost(message, repeated, tag = "26")]
+    pub nft_events: Vec<NftEvent>,
+    #[prost(message, repeated, tag = "27")]
+    pub vote_events: Vec<VoteEvent>,
+    #[prost(message, repeated, tag = "28")]
+    pub anchor_events: Vec<AnchorEvent>,
+    #[prost(message, repeated, tag = "29")]
+    pub account_changes: Vec<AccountChange>,
+    #[prost(message, repeated, tag = "30")]
+    pub labeled_accounts: Vec<LabeledAccount>,
+    #[prost(string, optional, tag = "31")]
+    pub durable_nonce: Option<String>,
+    #[prost(message, optional, tag = "32")]
+    pub swap: Option<Swap>,
+    #[prost(bool, optional, tag = "33")]
+    pub signatures_verified: Option<bool>,
+    #[prost(string, optional, tag = "34")]
+    pub recent_blockhash: Option<String>,
+    #[prost(uint64, optional, tag = "35")]
+    pub blockhash_age: Option<u64>,
+    /// Raw instruction data, when instruction data capture is enabled
+    #[prost(message, repeated, tag = "36")]
+    pub raw_instructions: Vec<RawInstruction>,
+    /// Writable and signer flags of each entry in `account_keys`, in the same order
+    #[prost(message, repeated, tag = "37")]
+    pub account_flags: Vec<AccountFlags>,
+    #[prost(message, repeated, tag = "38")]
+    pub security_alerts: Vec<SecurityAlert>,
+    /// Compute units attributed to each invoked program, from the program logs
+    #[prost(message, repeated, tag = "39")]
+    pub program_compute: Vec<ProgramCompute>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ProgramCompute {
+    #[prost(string, tag = "1")]
+    pub program_id: String,
+    #[prost(uint32, tag = "2")]
+    pub invocations: u32,
+    #[prost(uint64, tag = "3")]
+    pub compute_units: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AccountFlags {
+    #[prost(bool, tag = "1")]
+    pub writable: bool,
+    #[prost(bool, tag = "2")]
+    pub signer: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Instruction {
+    /// Position in the call tree, e.g. `2` or `2.0`
+    #[prost(string, tag = "1")]
+    pub index: String,
+    #[prost(string, tag = "2")]
+    pub program_id: String,
+    #[prost(string, repeated, tag = "3")]
+    pub accounts: Vec<String>,
+    #[prost(string, optional, tag = "4")]
+    pub discriminator: Option<String>,
+    #[prost(uint32, tag = "5")]
+    pub depth: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct RawInstruction {
+    #[prost(string, tag = "1")]
+    pub index: String,
+    #[prost(string, tag = "2")]
+    pub program_id: String,
+    #[prost(string, repeated, tag = "3")]
+    pub accounts: Vec<String>,
+    /// Base64-encoded instruction data
+    #[prost(string, tag = "4")]
+    pub data: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TokenEvent {
+    #[prost(oneof = "token_event::Event", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
+    pub event: Option<token_event::Event>,
+}
+
+pub mod token_event {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        Transfer(super::TokenTransfer),
+        #[prost(message, tag = "2")]
+        MintTo(super::TokenMintTo),
+        #[prost(message, tag = "3")]
+        Burn(super::TokenBurn),
+        #[prost(message, tag = "4")]
+        Approve(super::TokenApprove),
+        #[prost(message, tag = "5")]
+        CreateAssociatedAccount(super::CreateAssociatedAccount),
+        #[prost(message, tag = "6")]
+        ConfidentialTransfer(super::ConfidentialTransfer),
+        #[prost(message, tag = "7")]
+        MetadataPointer(super::MetadataPointer),
+        #[prost(message, tag = "8")]
+        SetAuthority(super::SetAuthority),
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TokenTransfer {
+    #[prost(string, tag = "1")]
+    pub source: String,
+    #[prost(string, tag = "2")]
+    pub destination: String,
+    #[prost(string, optional, tag = "3")]
+    pub authority: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub mint: Option<String>,
+    #[prost(uint64, tag = "5")]
+    pub amount: u64,
+    #[prost(uint32, optional, tag = "6")]
+    pub decimals: Option<u32>,
+    #[prost(uint64, optional, tag = "7")]
+    pub fee: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TokenMintTo {
+    #[prost(string, tag = "1")]
+    pub mint: String,
+    #[prost(string, tag = "2")]
+    pub account: String,
+    #[prost(string, optional, tag = "3")]
+    pub authority: Option<String>,
+    #[prost(uint64, tag = "4")]
+    pub amount: u64,
+    #[prost(uint32, optional, tag = "5")]
+    pub decimals: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TokenBurn {
+    #[prost(string, tag = "1")]
+    pub account: String,
+    #[prost(string, tag = "2")]
+    pub mint: String,
+    #[prost(string, optional, tag = "3")]
+    pub authority: Option<String>,
+    #[prost(uint64, tag = "4")]
+    pub amount: u64,
+    #[prost(uint32, optional, tag = "5")]
+    pub decimals: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TokenApprove {
+    #[prost(string, tag = "1")]
+    pub source: String,
+    #[prost(string, tag = "2")]
+    pub delegate: String,
+    #[prost(string, optional, tag = "3")]
+    pub owner: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub mint: Option<String>,
+    #[prost(uint64, tag = "5")]
+    pub amount: u64,
+    #[prost(uint32, optional, tag = "6")]
+    pub decimals: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct CreateAssociatedAccount {
+    #[prost(string, tag = "1")]
+    pub account: String,
+    #[prost(string, tag = "2")]
+    pub wallet: String,
+    #[prost(string, tag = "3")]
+    pub mint: String,
+    #[prost(bool, tag = "4")]
+    pub idempotent: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ConfidentialTransfer {
+    #[prost(string, tag = "1")]
+    pub instruction: String,
+    #[prost(string, optional, tag = "2")]
+    pub account: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub mint: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MetadataPointer {
+    #[prost(string, tag = "1")]
+    pub mint: String,
+    #[prost(string, optional, tag = "2")]
+    pub authority: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub metadata_address: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetAuthority {
+    #[prost(string, tag = "1")]
+    pub account: String,
+    #[prost(string, tag = "2")]
+    pub authority_type: String,
+    #[prost(string, optional, tag = "3")]
+    pub authority: Option<String>,
+    /// Unset when the authority was revoked
+    #[prost(string, optional, tag = "4")]
+    pub new_authority: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SystemEvent {
+    #[prost(oneof = "system_event::Event", tags = "1, 2, 3, 4")]
+    pub event: Option<system_event::Event>,
+}
+
+pub mod system_event {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        Transfer(super::SystemTransfer),
+        #[prost(message, tag = "2")]
+        CreateAccount(super::CreateAccount),
+        #[prost(message, tag = "3")]
+        Assign(super::Assign),
+        #[prost(message, tag = "4")]
+        AdvanceNonce(super::AdvanceNonce),
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SystemTransfer {
+    #[prost(string, tag = "1")]
+    pub source: String,
+    #[prost(string, tag = "2")]
+    pub destination: String,
+    #[prost(uint64, tag = "3")]
+    pub lamports: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct CreateAccount {
+    #[prost(string, tag = "1")]
+    pub source: String,
+    #[prost(string, tag = "2")]
+    pub new_account: String,
+    #[prost(uint64, tag = "3")]
+    pub lamports: u64,
+    #[prost(uint64, tag = "4")]
+    pub space: u64,
+    #[prost(string, tag = "5")]
+    pub owner: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Assign {
+    #[prost(string, tag = "1")]
+    pub account: String,
+    #[prost(string, tag = "2")]
+    pub owner: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AdvanceNonce {
+    #[prost(string, tag = "1")]
+    pub nonce_account: String,
+    #[prost(string, optional, tag = "2")]
+    pub nonce_authority: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct NftEvent {
+    #[prost(enumeration = "nft_event::Kind", tag = "1")]
+    pub kind: i32,
+    #[prost(string, optional, tag = "2")]
+    pub mint: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub collection: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub token_account: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub seller: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub buyer: Option<String>,
+    #[prost(uint64, optional, tag = "7")]
+    pub price: Option<u64>,
+}
+
+pub mod nft_event {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Kind {
+        Mint = 0,
+        List = 1,
+        Sale = 2,
+        Transfer = 3,
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct VoteEvent {
+    #[prost(string, tag = "1")]
+    pub vote_account: String,
+    #[prost(string, optional, tag = "2")]
+    pub vote_authority: Option<String>,
+    #[prost(uint64, repeated, tag = "3")]
+    pub slots: Vec<u64>,
+    #[prost(message, repeated, tag = "4")]
+    pub lockouts: Vec<VoteLockout>,
+    #[prost(uint64, optional, tag = "5")]
+    pub root: Option<u64>,
+    #[prost(int64, optional, tag = "6")]
+    pub timestamp: Option<i64>,
+    #[prost(uint64, optional, tag = "7")]
+    pub latency: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct VoteLockout {
+    #[prost(uint64, tag = "1")]
+    pub slot: u64,
+    #[prost(uint32, tag = "2")]
+    pub confirmation_count: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AnchorEvent {
+    #[prost(string, tag = "1")]
+    pub program_id: String,
+    #[prost(uint32, tag = "2")]
+    pub instruction_index: u32,
+    #[prost(string, tag = "3")]
+    pub name: String,
+    /// Decoded event fields as JSON
+    #[prost(string, tag = "4")]
+    pub data: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AccountChange {
+    #[prost(string, tag = "1")]
+    pub account: String,
+    #[prost(enumeration = "account_change::Kind", tag = "2")]
+    pub kind: i32,
+    #[prost(uint64, tag = "3")]
+    pub lamports: u64,
+    #[prost(string, optional, tag = "4")]
+    pub owner: Option<String>,
+}
+
+pub mod account_change {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Kind {
+        Created = 0,
+        Closed = 1,
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SecurityAlert {
+    #[prost(string, tag = "1")]
+    pub signature: String,
+    #[prost(uint64, tag = "2")]
+    pub slot: u64,
+    #[prost(enumeration = "security_alert::Kind", tag = "3")]
+    pub kind: i32,
+    #[prost(string, optional, tag = "4")]
+    pub actor: Option<String>,
+    #[prost(string, repeated, tag = "5")]
+    pub accounts: Vec<String>,
+    #[prost(string, optional, tag = "6")]
+    pub counterparty: Option<String>,
+    #[prost(string, tag = "7")]
+    pub description: String,
+}
+
+pub mod security_alert {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Kind {
+        TokenDrain = 0,
+        AuthorityChange = 1,
+        MassApproval = 2,
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct LabeledAccount {
+    #[prost(string, tag = "1")]
+    pub address: String,
+    #[prost(string, tag = "2")]
+    pub label: String,
+    #[prost(string, optional, tag = "3")]
+    pub category: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Swap {
+    #[prost(string, tag = "1")]
+    pub trader: String,
+    #[prost(string, tag = "2")]
+    pub program_id: String,
+    #[prost(string, tag = "3")]
+    pub mint_in: String,
+    #[prost(uint64, tag = "4")]
+    pub amount_in: u64,
+    #[prost(string, tag = "5")]
+    pub mint_out: String,
+    #[prost(uint64, tag = "6")]
+    pub amount_out: u64,
+}
+
+/// A confirmed block, as streamed by `Node.SubscribeSlots`
+#[derive(Clone, PartialEq, Message)]
+pub struct SlotUpdate {
+    #[prost(uint64, tag = "1")]
+    pub slot: u64,
+    #[prost(uint64, tag = "2")]
+    pub parent_slot: u64,
+    #[prost(string, tag = "3")]
+    pub blockhash: String,
+    #[prost(int64, optional, tag = "4")]
+    pub block_time: Option<i64>,
+    #[prost(uint64, optional, tag = "5")]
+    pub block_height: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SubscribeTransactionsRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub programs: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    pub accounts: Vec<String>,
+    #[prost(string, repeated, tag = "3")]
+    pub events: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SubscribeSlotsRequest {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetTransactionRequest {
+    #[prost(string, tag = "1")]
+    pub signature: String,
+}
+
+/// Encode a processed transaction as a `solana_node.v1.ProcessedTransaction` message
+pub fn encode(tx: &transaction_processor::ProcessedTransaction) -> Vec<u8> {
+    ProcessedTransaction::from(tx).encode_to_vec()
+}
+
+/// Decode a `solana_node.v1.ProcessedTransaction` message
+pub fn decode(bytes: &[u8]) -> Result<ProcessedTransaction> {
+    ProcessedTransaction::decode(bytes).context("Failed to decode protobuf transaction")
+}
+
+impl From<&transaction_processor::ProcessedTransaction> for ProcessedTransaction {
+    fn from(tx: &transaction_processor::ProcessedTransaction) -> Self {
+        Self {
+            schema_version: tx.schema_version,
+            signature: tx.signature.clone(),
+            slot: tx.slot,
+            block_time: tx.block_time,
+            fee: tx.fee,
+            fee_payer: tx.fee_payer.clone(),
+            num_signers: tx.num_signers as u32,
+            is_multisig: tx.is_multisig,
+            is_vote: tx.is_vote,
+            error: tx.error.as_ref().map(|error| error.to_string()),
+            error_class: tx.error_class.clone(),
+            error_instruction_index: tx.error_instruction_index.map(u32::from),
+            compute_units_consumed: tx.compute_units_consumed,
+            cu_limit: tx.cu_limit,
+            priority_fee_micro_lamports: tx.priority_fee_micro_lamports,
+            account_keys: tx.account_keys.clone(),
+            instruction_count: tx.instruction_count as u32,
+            wire_size: tx.wire_size.map(|size| size as u32),
+            instructions: tx.instruction_records().into_iter().map(Instruction::from).collect(),
+            log_messages: tx.log_messages.clone(),
+            token_events: tx.token_events.iter().map(TokenEvent::from).collect(),
+            system_events: tx.system_events.iter().map(SystemEvent::from).collect(),
+            memo: tx.memo.clone(),
+            program_labels: tx.program_labels.clone(),
+            program_categories: tx.program_categories.clone(),
+            nft_events: tx.nft_events.iter().map(NftEvent::from).collect(),
+            vote_events: tx.vote_events.iter().map(VoteEvent::from).collect(),
+            anchor_events: tx.anchor_events.iter().map(AnchorEvent::from).collect(),
+            account_changes: tx.account_changes.iter().map(AccountChange::from).collect(),
+            labeled_accounts: tx.labeled_accounts.iter().map(LabeledAccount::from).collect(),
+            durable_nonce: tx.durable_nonce.clone(),
+            swap: tx.swap.as_ref().map(Swap::from),
+            signatures_verified: tx.signatures_verified,
+            recent_blockhash: tx.recent_blockhash.clone(),
+            blockhash_age: tx.blockhash_age,
+            raw_instructions: tx.raw_instructions.iter().map(RawInstruction::from).collect(),
+            account_flags: tx
+                .account_flags
+                .iter()
+                .map(|flags| AccountFlags { writable: flags.writable, signer: flags.signer })
+                .collect(),
+            security_alerts: tx.security_alerts.iter().map(SecurityAlert::from).collect(),
+            program_compute: tx
+                .program_compute
+                .iter()
+                .map(|compute| ProgramCompute {
+                    program_id: compute.program_id.clone(),
+                    invocations: compute.invocations,
+                    compute_units: compute.compute_units,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<transaction_processor::InstructionRecord> for Instruction {
+    fn from(record: transaction_processor::InstructionRecord) -> Self {
+        Self {
+            index: record.index,
+            program_id: record.program_id,
+            accounts: record.accounts,
+            discriminator: record.discriminator,
+            depth: record.depth,
+        }
+    }
+}
+
+impl From<&transaction_processor::RawInstruction> for RawInstruction {
+    fn from(instruction: &transaction_processor::RawInstruction) -> Self {
+        Self {
+            index: instruction.index.clone(),
+            program_id: instruction.program_id.clone(),
+            accounts: instruction.accounts.clone(),
+            data: instruction.data.clone(),
+        }
+    }
+}
+
+impl From<&token::TokenEvent> for TokenEvent {
+    fn from(event: &token::TokenEvent) -> Self {
+        use token_event::Event;
+        
+        let event = match event.clone() {
+            token::TokenEvent::Transfer { source, destination, authority, mint, amount, decimals, fee } => {
+                Event::Transfer(TokenTransfer {
+                    source,
+                    destination,
+                    authority,
+                    mint,
+                    amount,
+                    decimals: decimals.map(u32::from),
+                    fee,
+                })
+            }
+            token::TokenEvent::MintTo { mint, account, authority, amount, decimals } => {
+                Event::MintTo(TokenMintTo { mint, account, authority, amount, decimals: decimals.map(u32::from) })
+            }
+            token::TokenEvent::Burn { account, mint, authority, amount, decimals } => {
+                Event::Burn(TokenBurn { account, mint, authority, amount, decimals: decimals.map(u32::from) })
+            }
+            token::TokenEvent::Approve { source, delegate, owner, mint, amount, decimals } => {
+                Event::Approve(TokenApprove {
+                    source,
+                    delegate,
+                    owner,
+                    mint,
+                    amount,
+                    decimals: decimals.map(u32::from),
+                })
+            }
+            token::TokenEvent::CreateAssociatedAccount { account, wallet, mint, idempotent } => {
+                Event::CreateAssociatedAccount(CreateAssociatedAccount { account, wallet, mint, idempotent })
+            }
+            token::TokenEvent::ConfidentialTransfer { instruction, account, mint } => {
+                Event::ConfidentialTransfer(ConfidentialTransfer { instruction, account, mint })
+            }
+            token::TokenEvent::MetadataPointer { mint, authority, metadata_address } => {
+                Event::MetadataPointer(MetadataPointer { mint, authority, metadata_address })
+            }
+            token::TokenEvent::SetAuthority { account, authority_type, authority, new_authority } => {
+                Event::SetAuthority(SetAuthority { account, authority_type, authority, new_authority })
+            }
+        };
+        
+        Self { event: Some(event) }
+    }
+}
+
+impl From<&system::SystemEvent> for SystemEvent {
+    fn from(event: &system::SystemEvent) -> Self {
+        use system_event::Event;
+        
+        let event = match event.clone() {
+            system::SystemEvent::Transfer { source, destination, lamports } => {
+                Event::Transfer(SystemTransfer { source, destination, lamports })
+            }
+            system::SystemEvent::CreateAccount { source, new_account, lamports, space, owner } => {
+                Event::CreateAccount(CreateAccount { source, new_account, lamports, space, owner })
+            }
+            system::SystemEvent::Assign { account, owner } => Event::Assign(Assign { account, owner }),
+            system::SystemEvent::AdvanceNonce { nonce_account, nonce_authority } => {
+                Event::AdvanceNonce(AdvanceNonce { nonce_account, nonce_authority })
+            }
+        };
+        
+        Self { event: Some(event) }
+    }
+}
+
+impl From<&metaplex::NftEvent> for NftEvent {
+    fn from(event: &metaplex::NftEvent) -> Self {
+        let kind = match event.kind {
+            metaplex::NftEventKind::Mint => nft_event::Kind::Mint,
+            metaplex::NftEventKind::List => nft_event::Kind::List,
+            metaplex::NftEventKind::Sale => nft_event::Kind::Sale,
+            metaplex::NftEventKind::Transfer => nft_event::Kind::Transfer,
+        };
+        
+        Self {
+            kind: kind as i32,
+            mint: event.mint.clone(),
+            collection: event.collection.clone(),
+            token_account: event.token_account.clone(),
+            seller: event.seller.clone(),
+            buyer: event.buyer.clone(),
+            price: event.price,
+        }
+    }
+}
+
+impl From<&vote::VoteEvent> for VoteEvent {
+    fn from(event: &vote::VoteEvent) -> Self {
+        Self {
+            vote_account: event.vote_account.clone(),
+            vote_authority: event.vote_authority.clone(),
+            slots: event.slots.clone(),
+            lockouts: event
+                .lockouts
+                .iter()
+                .map(|lockout| VoteLockout { slot: lockout.slot, confirmation_count: lockout.confirmation_count })
+                .collect(),
+            root: event.root,
+            timestamp: event.timestamp,
+            latency: event.latency,
+        }
+    }
+}
+
+impl From<&anchor::AnchorEvent> for AnchorEvent {
+    fn from(event: &anchor::AnchorEvent) -> Self {
+        Self {
+            program_id: event.program_id.clone(),
+            instruction_index: event.instruction_index as u32,
+            name: event.name.clone(),
+            data: event.data.to_string(),
+        }
+    }
+}
+
+impl From<&transaction_processor::AccountChange> for AccountChange {
+    fn from(change: &transaction_processor::AccountChange) -> Self {
+        let kind = match change.kind {
+            AccountChangeKind::Created => account_change::Kind::Created,
+            AccountChangeKind::Closed => account_change::Kind::Closed,
+        };
+        
+        Self {
+            account: change.account.clone(),
+            kind: kind as i32,
+            lamports: change.lamports,
+            owner: change.owner.clone(),
+        }
+    }
+}
+
+impl From<&security::SecurityAlert> for SecurityAlert {
+    fn from(alert: &security::SecurityAlert) -> Self {
+        let kind = match alert.kind {
+            security::AlertKind::TokenDrain => security_alert::Kind::TokenDrain,
+            security::AlertKind::AuthorityChange => security_alert::Kind::AuthorityChange,
+            security::AlertKind::MassApproval => security_alert::Kind::MassApproval,
+        };
+        
+        Self {
+            signature: alert.signature.clone(),
+            slot: alert.slot,
+            kind: kind as i32,
+            actor: alert.actor.clone(),
+            accounts: alert.accounts.clone(),
+            counterparty: alert.counterparty.clone(),
+            description: alert.description.clone(),
+        }
+    }
+}
+
+impl From<&address_labels::LabeledAccount> for LabeledAccount {
+    fn from(account: &address_labels::LabeledAccount) -> Self {
+        Self {
+            address: account.address.clone(),
+            label: account.label.clone(),
+            category: account.category.clone(),
+        }
+    }
+}
+
+impl From<&mev::Swap> for Swap {
+    fn from(swap: &mev::Swap) -> Self {
+        Self {
+            trader: swap.trader.clone(),
+            program_id: swap.program_id.clone(),
+            mint_in: swap.mint_in.clone(),
+            amount_in: swap.amount_in,
+            mint_out: swap.mint_out.clone(),
+            amount_out: swap.amount_out,
+        }
+    }
+}
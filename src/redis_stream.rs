@@ -0,0 +1,110 @@
+//! Redis Streams publisher, so lightweight consumers can tail recent transactions without Kafka
+//!
+//! Each processed transaction is added with `XADD` to the configured stream, and to the stream of
+//! every invoked program listed in `program_streams`, as an entry of `signature`, `slot` and `data`
+//! (the transaction as JSON or protobuf). Streams are trimmed to about `max_len` entries as they
+//! grow. Delivery is best effort: transactions arriving while Redis is unreachable are dropped.
+
+use anyhow::Result;
+use prost::Message;
+use redis::aio::ConnectionManager;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{sync::broadcast, time::sleep};
+use tracing::{info, warn};
+
+use crate::api::feed::StreamedTransaction;
+use crate::config::{redact_url, RedisConfig, SinkFormat};
+use crate::metrics::Metrics;
+
+/// Most transactions sent in one pipeline, taken from those already waiting
+const MAX_BATCH: usize = 100;
+
+/// Wait before connecting again after a failure
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct RedisSink {
+    config: RedisConfig,
+    client: redis::Client,
+    metrics: Arc<Metrics>,
+}
+
+impl RedisSink {
+    pub fn new(config: &RedisConfig, metrics: Arc<Metrics>) -> Result<Self> {
+        Ok(Self {
+            config: config.clone(),
+            client: redis::Client::open(config.url.as_str())?,
+            metrics,
+        })
+    }
+    
+    /// Add the transactions of `feed` to the streams in the background
+    pub fn start(self, feed: &broadcast::Sender<StreamedTransaction>) {
+        tokio::spawn(self.publish(feed.subscribe()));
+    }
+    
+    async fn publish(self, mut transactions: broadcast::Receiver<StreamedTransaction>) {
+        let mut connection = loop {
+            match ConnectionManager::new(self.client.clone()).await {
+                Ok(connection) => break connection,
+                Err(e) => {
+                    let url = redact_url(&self.config.url);
+                    warn!("Failed to connect to Redis at {}: {}, retrying in {:?}", url, e, RETRY_INTERVAL);
+                    sleep(RETRY_INTERVAL).await;
+                }
+            }
+        };
+        info!("Adding transactions to Redis stream {}", self.config.stream);
+        
+        loop {
+            let mut batch = match transactions.recv().await {
+                Ok(tx) => vec![tx],
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Redis publisher fell behind, {} transactions not added", missed);
+                    self.metrics.redis_failed.add(missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            while batch.len() < MAX_BATCH {
+                match transactions.try_recv() {
+                    Ok(tx) => batch.push(tx),
+                    Err(_) => break,
+                }
+            }
+            
+            let mut pipeline = redis::pipe();
+            for tx in &batch {
+                let payload = match self.config.format {
+                    SinkFormat::Json => tx.json.as_bytes().to_vec(),
+                    SinkFormat::Protobuf => tx.proto.encode_to_vec(),
+                };
+                let program_streams = tx.programs.iter().filter_map(|program| self.config.program_streams.get(program));
+                for stream in std::iter::once(&self.config.stream).chain(program_streams) {
+                    let command = pipeline.cmd("XADD").arg(stream);
+                    if self.config.max_len > 0 {
+                        command.arg("MAXLEN").arg("~").arg(self.config.max_len);
+                    }
+                    command
+                        .arg("*")
+                        .arg("signature")
+                        .arg(&tx.signature)
+                        .arg("slot")
+                        .arg(tx.proto.slot)
+                        .arg("data")
+                        .arg(&payload)
+                        .ignore();
+                }
+            }
+            
+            // The connection manager reconnects on its own; the failed batch is dropped
+            match pipeline.query_async::<_, ()>(&mut connection).await {
+                Ok(()) => self.metrics.redis_published.add(batch.len() as u64),
+                Err(e) => {
+                    warn!("Failed to add {} transactions to Redis: {}", batch.len(), e);
+                    self.metrics.redis_failed.add(batch.len() as u64);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Repair requests for shreds missed over turbine, sent to gossip peers
+//!
+//! Requests use the signed repair protocol validators serve on their `serve_repair` socket.
+//! Peers answer with the shred (followed by the request nonce), or first with a ping that must
+//! be answered before they serve this node.
+
+use anyhow::Result;
+use solana_gossip::{cluster_info::ClusterInfo, contact_info::Protocol, ping_pong::{Ping, Pong}};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    timing::timestamp,
+};
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::debug;
+
+use crate::shred::{MissingShred, ShredAssembler};
+
+/// `RepairProtocol` variants, bincode-encoded as little-endian u32 tags
+const REPAIR_PONG: u32 = 7;
+const REPAIR_WINDOW_INDEX: u32 = 8;
+const REPAIR_HIGHEST_WINDOW_INDEX: u32 = 9;
+
+/// Tag of `RepairResponse::Ping` followed by sender, token and signature
+const REPAIR_PING_SIZE: usize = 4 + 32 + 32 + 64;
+
+const SIGNATURE_BYTES: usize = 64;
+
+/// Pause between repair rounds
+const REPAIR_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Most requests sent per round
+const MAX_REPAIRS_PER_ROUND: usize = 128;
+
+/// Ask peers for the shreds `assembler` is missing until shutdown. Responses arrive on `socket`
+/// and are handled by the shred receiver.
+pub fn run_repair(
+    cluster_info: Arc<ClusterInfo>,
+    socket: UdpSocket,
+    assembler: Arc<Mutex<ShredAssembler>>,
+    exit: Arc<AtomicBool>,
+) {
+    let mut nonce: u32 = 0;
+    
+    while !exit.load(Ordering::Relaxed) {
+        std::thread::sleep(REPAIR_INTERVAL);
+        
+        let missing = assembler.lock().unwrap().missing_shreds(MAX_REPAIRS_PER_ROUND);
+        if missing.is_empty() {
+            continue;
+        }
+        let keypair = cluster_info.keypair().clone();
+        
+        let mut sent = 0;
+        for shred in missing {
+            let slot = match shred {
+                MissingShred::Index { slot, .. } | MissingShred::From { slot, .. } => slot,
+            };
+            // Spread requests over the peers that may hold the slot
+            let peers = cluster_info.repair_peers(slot);
+            if peers.is_empty() {
+                continue;
+            }
+            let peer = &peers[nonce as usize % peers.len()];
+            let addr = match peer.serve_repair(Protocol::UDP) {
+                Ok(addr) => addr,
+                Err(_) => continue,
+            };
+            
+            nonce = nonce.wrapping_add(1);
+            let request = repair_request(&keypair, peer.pubkey(), shred, nonce);
+            match socket.send_to(&request, addr) {
+                Ok(_) => sent += 1,
+                Err(e) => debug!("Failed to send repair request to {}: {}", addr, e),
+            }
+        }
+        
+        debug!("Requested {} missing shreds from gossip peers", sent);
+    }
+}
+
+/// A signed `WindowIndex` or `HighestWindowIndex` request
+fn repair_request(keypair: &Keypair, recipient: &Pubkey, shred: MissingShred, nonce: u32) -> Vec<u8> {
+    let (tag, slot, index) = match shred {
+        MissingShred::Index { slot, index } => (REPAIR_WINDOW_INDEX, slot, index),
+        MissingShred::From { slot, index } => (REPAIR_HIGHEST_WINDOW_INDEX, slot, index),
+    };
+    
+    // Tag, header (signature, sender, recipient, timestamp, nonce), slot, shred index
+    let mut request = Vec::with_capacity(4 + SIGNATURE_BYTES + 32 + 32 + 8 + 4 + 8 + 8);
+    request.extend_from_slice(&tag.to_le_bytes());
+    request.extend_from_slice(&[0u8; SIGNATURE_BYTES]);
+    request.extend_from_slice(keypair.pubkey().as_ref());
+    request.extend_from_slice(recipient.as_ref());
+    request.extend_from_slice(&timestamp().to_le_bytes());
+    request.extend_from_slice(&nonce.to_le_bytes());
+    request.extend_from_slice(&slot.to_le_bytes());
+    request.extend_from_slice(&(index as u64).to_le_bytes());
+    
+    // The signature covers everything but itself
+    let signed_data = [&request[..4], &request[4 + SIGNATURE_BYTES..]].concat();
+    let signature = keypair.sign_message(&signed_data);
+    request[4..4 + SIGNATURE_BYTES].copy_from_slice(signature.as_ref());
+    request
+}
+
+/// The pong answering a repair ping, if `packet` is one
+pub fn answer_ping(keypair: &Keypair, packet: &[u8]) -> Result<Option<Vec<u8>>> {
+    if packet.len() != REPAIR_PING_SIZE || packet[..4] != [0u8; 4] {
+        return Ok(None);
+    }
+    let ping: Ping<[u8; 32]> = bincode::deserialize(&packet[4..])?;
+    let pong = Pong::new(&ping, keypair)?;
+    
+    let mut response = REPAIR_PONG.to_le_bytes().to_vec();
+    response.extend(bincode::serialize(&pong)?);
+    Ok(Some(response))
+}
@@ -0,0 +1,99 @@
+//! RPC endpoints transaction details are fetched from, supplemented by endpoints peers advertise
+//! in gossip, for the peers the operator allows
+
+use futures::future::join_all;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::gossip::ClusterNode;
+
+/// Advertised RPC addresses health-checked per discovery round, most staked first
+const MAX_CANDIDATES: usize = 32;
+
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Slots a discovered endpoint may trail the most advanced one
+const MAX_SLOT_LAG: u64 = 32;
+
+/// Endpoints shared by the transaction subscriptions. Each subscription fetches from its own
+/// endpoint, taking turns with the discovered ones when there are any.
+#[derive(Clone, Default)]
+pub struct RpcPool {
+    discovered: Arc<RwLock<Vec<String>>>,
+    next: Arc<AtomicUsize>,
+}
+
+/// A discovered endpoint that passed its health check
+#[derive(Debug, Clone)]
+pub struct RpcCandidate {
+    pub url: String,
+    pub slot: u64,
+    pub latency: Duration,
+}
+
+impl RpcPool {
+    /// Endpoint for the next fetch: `own`, or one of the discovered endpoints, in turn
+    pub fn pick(&self, own: &str) -> String {
+        let discovered = self.discovered.read().unwrap();
+        let turn = self.next.fetch_add(1, Ordering::Relaxed) % (discovered.len() + 1);
+        match turn {
+            0 => own.to_string(),
+            turn => discovered[turn - 1].clone(),
+        }
+    }
+    
+    /// Replace the discovered endpoints
+    pub fn set_discovered(&self, urls: Vec<String>) {
+        *self.discovered.write().unwrap() = urls;
+    }
+}
+
+/// Health-check the RPC addresses advertised by those of `nodes` in `allowlist`, returning up to
+/// `limit` healthy, caught-up endpoints, fastest first
+pub async fn discover(nodes: &[ClusterNode], allowlist: &[String], limit: usize) -> Vec<RpcCandidate> {
+    let mut advertised: Vec<&ClusterNode> = nodes
+        .iter()
+        .filter(|node| node.rpc.is_some() && allowlist.contains(&node.pubkey))
+        .collect();
+    advertised.sort_by(|a, b| b.activated_stake.cmp(&a.activated_stake));
+    
+    let checks = advertised
+        .iter()
+        .take(MAX_CANDIDATES)
+        .filter_map(|node| node.rpc)
+        .map(health_check);
+    let mut candidates: Vec<RpcCandidate> = join_all(checks).await.into_iter().flatten().collect();
+    
+    // Healthy nodes may still trail the cluster by up to the node's own health threshold
+    let newest = candidates.iter().map(|candidate| candidate.slot).max().unwrap_or_default();
+    candidates.retain(|candidate| candidate.slot + MAX_SLOT_LAG >= newest);
+    candidates.sort_by_key(|candidate| candidate.latency);
+    candidates.truncate(limit);
+    candidates
+}
+
+async fn health_check(rpc: SocketAddr) -> Option<RpcCandidate> {
+    let url = format!("http://{}", rpc);
+    let client = RpcClient::new_with_timeout(url.clone(), HEALTH_CHECK_TIMEOUT);
+    let started = Instant::now();
+    
+    if let Err(e) = client.get_health().await {
+        debug!("RPC endpoint {} is unhealthy: {}", url, e);
+        return None;
+    }
+    match client.get_slot().await {
+        Ok(slot) => Some(RpcCandidate {
+            url,
+            slot,
+            latency: started.elapsed(),
+        }),
+        Err(e) => {
+            debug!("RPC endpoint {} did not report its slot: {}", url, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Heuristics flagging transactions that look like wallet drains or authority takeovers
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::decoder::TokenEvent;
+use crate::transaction_processor::ProcessedTransaction;
+
+/// Distinct token accounts one authority must move tokens out of to count as a sweep
+const DRAIN_MIN_ACCOUNTS: usize = 5;
+
+/// Distinct token accounts approved in one transaction to count as a mass approval
+const MASS_APPROVAL_MIN_ACCOUNTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AlertKind {
+    /// One authority moved tokens out of many token accounts
+    TokenDrain,
+    /// A mint or token account authority was handed to an address outside the transaction's signers
+    AuthorityChange,
+    /// Many token accounts were approved for spending at once
+    MassApproval,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityAlert {
+    pub signature: String,
+    pub slot: u64,
+    pub kind: AlertKind,
+    /// Authority that signed off on the activity
+    pub actor: Option<String>,
+    /// Token accounts or mints affected
+    pub accounts: Vec<String>,
+    /// Where control or funds went: the new authority, delegate or drain destination, when unique
+    pub counterparty: Option<String>,
+    pub description: String,
+}
+
+/// Run every heuristic over a processed transaction
+pub fn detect(tx: &ProcessedTransaction) -> Vec<SecurityAlert> {
+    let mut alerts = detect_drains(tx);
+    alerts.extend(detect_authority_changes(tx));
+    alerts.extend(detect_mass_approvals(tx));
+    alerts
+}
+
+fn detect_drains(tx: &ProcessedTransaction) -> Vec<SecurityAlert> {
+    // Source and destination accounts of token transfers, per authority
+    let mut transfers: BTreeMap<&str, (Vec<&str>, Vec<&str>)> = BTreeMap::new();
+    for event in &tx.token_events {
+        if let TokenEvent::Transfer { source, destination, authority: Some(authority), .. } = event {
+            let (sources, destinations) = transfers.entry(authority.as_str()).or_default();
+            if !sources.contains(&source.as_str()) {
+                sources.push(source);
+            }
+            if !destinations.contains(&destination.as_str()) {
+                destinations.push(destination);
+            }
+        }
+    }
+    
+    transfers
+        .into_iter()
+        .filter(|(_, (sources, _))| sources.len() >= DRAIN_MIN_ACCOUNTS)
+        .map(|(authority, (sources, destinations))| SecurityAlert {
+            signature: tx.signature.clone(),
+            slot: tx.slot,
+            kind: AlertKind::TokenDrain,
+            actor: Some(authority.to_string()),
+            accounts: sources.iter().map(|source| source.to_string()).collect(),
+            counterparty: (destinations.len() == 1).then(|| destinations[0].to_string()),
+            description: format!(
+                "{} swept {} token accounts into {} destination(s)",
+                authority,
+                sources.len(),
+                destinations.len()
+            ),
+        })
+        .collect()
+}
+
+fn detect_authority_changes(tx: &ProcessedTransaction) -> Vec<SecurityAlert> {
+    let signers = signers(tx);
+    
+    tx.token_events
+        .iter()
+        .filter_map(|event| match event {
+            TokenEvent::SetAuthority { account, authority_type, authority, new_authority: Some(new_authority) } => {
+                // Handing control to another signer or a labeled address is routine
+                let known = signers.contains(&new_authority.as_str())
+                    || tx.labeled_accounts.iter().any(|labeled| &labeled.address == new_authority);
+                (!known).then(|| SecurityAlert {
+                    signature: tx.signature.clone(),
+                    slot: tx.slot,
+                    kind: AlertKind::AuthorityChange,
+                    actor: authority.clone(),
+                    accounts: vec![account.clone()],
+                    counterparty: Some(new_authority.clone()),
+                    description: format!(
+                        "{} authority of {} handed to unknown address {}",
+                        authority_type, account, new_authority
+                    ),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn detect_mass_approvals(tx: &ProcessedTransaction) -> Vec<SecurityAlert> {
+    let mut sources: Vec<&str> = Vec::new();
+    let mut delegates: Vec<&str> = Vec::new();
+    let mut owner = None;
+    for event in &tx.token_events {
+        if let TokenEvent::Approve { source, delegate, owner: approver, .. } = event {
+            if !sources.contains(&source.as_str()) {
+                sources.push(source);
+            }
+            if !delegates.contains(&delegate.as_str()) {
+                delegates.push(delegate);
+            }
+            owner = owner.or(approver.as_ref());
+        }
+    }
+    
+    if sources.len() < MASS_APPROVAL_MIN_ACCOUNTS {
+        return Vec::new();
+    }
+    vec![SecurityAlert {
+        signature: tx.signature.clone(),
+        slot: tx.slot,
+        kind: AlertKind::MassApproval,
+        actor: owner.cloned(),
+        accounts: sources.iter().map(|source| source.to_string()).collect(),
+        counterparty: (delegates.len() == 1).then(|| delegates[0].to_string()),
+        description: format!(
+            "{} token accounts approved for {} delegate(s)",
+            sources.len(),
+            delegates.len()
+        ),
+    }]
+}
+
+fn signers(tx: &ProcessedTransaction) -> Vec<&str> {
+    tx.account_keys
+        .iter()
+        .zip(&tx.account_flags)
+        .filter(|(_, flags)| flags.signer)
+        .map(|(key, _)| key.as_str())
+        .collect()
+}
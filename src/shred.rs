@@ -0,0 +1,281 @@
+//! Minimal shred parsing and deshredding for transactions received over turbine
+//!
+//! Only data shreds are used, once their signature checks out against the slot leader: an entry
+//! batch is decoded once all of its data shreds have arrived. Coding shreds (erasure recovery)
+//! are not handled; lost shreds are instead reported as gaps for the repair service to fetch
+//! from peers.
+
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::Deserialize;
+use solana_sdk::{
+    hash::{hashv, Hash},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, EncodedTransactionWithStatusMeta,
+    TransactionBinaryEncoding,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::debug;
+
+/// Largest shred payload sent over UDP
+pub const PACKET_DATA_SIZE: usize = 1232;
+
+/// Common header (signature, variant, slot, index, version, FEC set index) plus data header
+/// (parent offset, flags, size)
+const SIZE_OF_DATA_SHRED_HEADERS: usize = 88;
+
+const SIZE_OF_SIGNATURE: usize = 64;
+
+/// Payload signed by the leader of a legacy data shred, after the signature
+const LEGACY_DATA_PAYLOAD: usize = 1228;
+
+/// Payload of a Merkle data shred, the Merkle proof (and retransmitter signature) at its end
+const MERKLE_DATA_PAYLOAD: usize = 1203;
+
+/// Bytes of each Merkle proof entry, a truncated hash
+const SIZE_OF_MERKLE_PROOF_ENTRY: usize = 20;
+
+const MERKLE_LEAF_PREFIX: &[u8] = b"\x00SOLANA_MERKLE_SHREDS_LEAF";
+const MERKLE_NODE_PREFIX: &[u8] = b"\x01SOLANA_MERKLE_SHREDS_NODE";
+
+const LEGACY_DATA_VARIANT: u8 = 0b1010_0101;
+const DATA_COMPLETE_FLAG: u8 = 0b0100_0000;
+const LAST_IN_SLOT_FLAG: u8 = 0b1100_0000;
+
+/// Slots behind the newest one after which incomplete slots are dropped
+const MAX_PENDING_SLOTS: u64 = 64;
+
+/// A ledger entry as serialized by the validator's shredder. The PoH fields are decoded only to
+/// reach the transactions (bincode is positional)
+#[derive(Debug, Deserialize)]
+pub struct Entry {
+    _num_hashes: u64,
+    _hash: Hash,
+    pub transactions: Vec<VersionedTransaction>,
+}
+
+/// Data shred fields needed to reassemble entry batches
+#[derive(Debug)]
+pub struct DataShred {
+    pub slot: u64,
+    pub index: u32,
+    /// Last shred of an entry batch
+    pub data_complete: bool,
+    /// Last shred of the slot
+    pub last_in_slot: bool,
+    pub data: Vec<u8>,
+}
+
+/// Shreds a slot is known to be missing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingShred {
+    /// A hole below the highest shred received
+    Index { slot: u64, index: u32 },
+    /// Shreds from `index` on, up to the unknown end of the slot
+    From { slot: u64, index: u32 },
+}
+
+/// Parse a data shred (legacy or Merkle), ignoring coding shreds and malformed packets
+pub fn parse_data_shred(packet: &[u8]) -> Option<DataShred> {
+    let variant = *packet.get(64)?;
+    // Merkle data shreds: 0x8_ (plain), 0x9_ (chained), 0xb_ (chained and resigned)
+    let is_data = variant == LEGACY_DATA_VARIANT || matches!(variant & 0xf0, 0x80 | 0x90 | 0xb0);
+    if !is_data || packet.len() < SIZE_OF_DATA_SHRED_HEADERS {
+        return None;
+    }
+    
+    let slot = u64::from_le_bytes(packet[65..73].try_into().ok()?);
+    let index = u32::from_le_bytes(packet[73..77].try_into().ok()?);
+    let flags = packet[85];
+    let size = u16::from_le_bytes(packet[86..88].try_into().ok()?) as usize;
+    if size < SIZE_OF_DATA_SHRED_HEADERS || size > packet.len() {
+        return None;
+    }
+    
+    Some(DataShred {
+        slot,
+        index,
+        data_complete: flags & DATA_COMPLETE_FLAG != 0,
+        last_in_slot: flags & LAST_IN_SLOT_FLAG == LAST_IN_SLOT_FLAG,
+        data: packet[SIZE_OF_DATA_SHRED_HEADERS..size].to_vec(),
+    })
+}
+
+/// Check the signature of a data shred parsed from `packet` against the slot leader. Legacy
+/// shreds sign their payload, Merkle shreds the root of their erasure batch's Merkle tree
+pub fn verify_signature(packet: &[u8], leader: &Pubkey) -> bool {
+    let signature = match packet.get(..SIZE_OF_SIGNATURE).and_then(|bytes| Signature::try_from(bytes).ok()) {
+        Some(signature) => signature,
+        None => return false,
+    };
+    match packet.get(SIZE_OF_SIGNATURE) {
+        Some(&LEGACY_DATA_VARIANT) => packet
+            .get(SIZE_OF_SIGNATURE..LEGACY_DATA_PAYLOAD)
+            .is_some_and(|payload| signature.verify(leader.as_ref(), payload)),
+        Some(&variant) => merkle_root(packet, variant).is_some_and(|root| signature.verify(leader.as_ref(), root.as_ref())),
+        None => false,
+    }
+}
+
+/// Merkle root a Merkle data shred's proof leads to from the shred itself
+fn merkle_root(packet: &[u8], variant: u8) -> Option<Hash> {
+    let proof_size = (variant & 0x0f) as usize * SIZE_OF_MERKLE_PROOF_ENTRY;
+    // Resigned shreds end with the retransmitter's signature
+    let trailer = if variant & 0xf0 == 0xb0 { SIZE_OF_SIGNATURE } else { 0 };
+    let proof_offset = MERKLE_DATA_PAYLOAD.checked_sub(proof_size + trailer)?;
+    let proof = packet.get(proof_offset..proof_offset + proof_size)?;
+    
+    // Position of the shred among the leaves: its index within the erasure batch
+    let index = u32::from_le_bytes(packet.get(73..77)?.try_into().ok()?);
+    let fec_set_index = u32::from_le_bytes(packet.get(79..83)?.try_into().ok()?);
+    let mut position = index.checked_sub(fec_set_index)?;
+    
+    let mut node = hashv(&[MERKLE_LEAF_PREFIX, packet.get(SIZE_OF_SIGNATURE..proof_offset)?]);
+    for sibling in proof.chunks(SIZE_OF_MERKLE_PROOF_ENTRY) {
+        let entry = &node.as_ref()[..SIZE_OF_MERKLE_PROOF_ENTRY];
+        node = if position % 2 == 0 {
+            hashv(&[MERKLE_NODE_PREFIX, entry, sibling])
+        } else {
+            hashv(&[MERKLE_NODE_PREFIX, sibling, entry])
+        };
+        position >>= 1;
+    }
+    (position == 0).then_some(node)
+}
+
+#[derive(Default)]
+struct SlotShreds {
+    data: BTreeMap<u32, Vec<u8>>,
+    /// Indexes of shreds ending an entry batch
+    batch_ends: BTreeSet<u32>,
+    /// First shred of the next batch to decode
+    next_index: u32,
+    /// Highest shred received so far
+    highest_index: Option<u32>,
+    /// Index of the slot's last shred, once received
+    last_index: Option<u32>,
+}
+
+impl SlotShreds {
+    fn is_complete(&self) -> bool {
+        self.last_index.map_or(false, |last| self.next_index > last)
+    }
+}
+
+/// Collects data shreds per slot and decodes entry batches as they complete
+#[derive(Default)]
+pub struct ShredAssembler {
+    slots: BTreeMap<u64, SlotShreds>,
+}
+
+impl ShredAssembler {
+    /// Add a shred, returning the entries of any batches it completed
+    pub fn insert(&mut self, shred: DataShred) -> Vec<(u64, Vec<Entry>)> {
+        let newest = self.slots.keys().next_back().copied().unwrap_or(shred.slot).max(shred.slot);
+        if shred.slot + MAX_PENDING_SLOTS < newest {
+            return Vec::new();
+        }
+        
+        // Slots skipped between the newest one and this shred never got any; track them as gaps
+        if let Some(&previous) = self.slots.keys().next_back() {
+            for gap in (previous + 1).max(newest.saturating_sub(MAX_PENDING_SLOTS))..shred.slot {
+                self.slots.entry(gap).or_default();
+            }
+        }
+        
+        let slot = self.slots.entry(shred.slot).or_default();
+        if shred.index < slot.next_index {
+            // Duplicate of a shred already decoded
+            return Vec::new();
+        }
+        if shred.data_complete {
+            slot.batch_ends.insert(shred.index);
+        }
+        if shred.last_in_slot {
+            slot.last_index = Some(shred.index);
+        }
+        slot.highest_index = slot.highest_index.max(Some(shred.index));
+        slot.data.insert(shred.index, shred.data);
+        
+        let mut completed = Vec::new();
+        while let Some(&end) = slot.batch_ends.range(slot.next_index..).next() {
+            if !(slot.next_index..=end).all(|index| slot.data.contains_key(&index)) {
+                break;
+            }
+            let mut payload = Vec::new();
+            for index in slot.next_index..=end {
+                payload.extend(slot.data.remove(&index).unwrap_or_default());
+            }
+            slot.next_index = end + 1;
+            match bincode::deserialize::<Vec<Entry>>(&payload) {
+                Ok(entries) => completed.push((shred.slot, entries)),
+                Err(e) => debug!("Failed to decode entries in slot {}: {}", shred.slot, e),
+            }
+        }
+        
+        // Finished slots are kept until they age out, so retransmitted duplicates are ignored
+        self.slots.retain(|slot, _| slot + MAX_PENDING_SLOTS >= newest);
+        
+        completed
+    }
+    
+    /// Up to `limit` shreds missing from incomplete slots, oldest first. The newest slot is
+    /// left out while turbine is still delivering it.
+    ///
+    /// Slots the leader skipped look like gaps too; repairs for them go unanswered until they
+    /// age out.
+    pub fn missing_shreds(&self, limit: usize) -> Vec<MissingShred> {
+        let newest = match self.slots.keys().next_back() {
+            Some(&newest) => newest,
+            None => return Vec::new(),
+        };
+        
+        let mut missing = Vec::new();
+        for (&slot, shreds) in self.slots.range(..newest) {
+            if shreds.is_complete() {
+                continue;
+            }
+            let highest = match shreds.highest_index {
+                Some(highest) => highest,
+                None => {
+                    missing.push(MissingShred::From { slot, index: 0 });
+                    continue;
+                }
+            };
+            for index in shreds.next_index..highest {
+                if !shreds.data.contains_key(&index) {
+                    missing.push(MissingShred::Index { slot, index });
+                }
+            }
+            if shreds.last_index.is_none() {
+                missing.push(MissingShred::From { slot, index: highest + 1 });
+            }
+            if missing.len() >= limit {
+                break;
+            }
+        }
+        
+        missing.truncate(limit);
+        missing
+    }
+}
+
+/// Wrap a transaction from a shred like an RPC response without status metadata
+pub fn encode_transaction(slot: u64, transaction: &VersionedTransaction) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+    Ok(EncodedConfirmedTransactionWithStatusMeta {
+        slot,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::Binary(
+                STANDARD.encode(bincode::serialize(transaction)?),
+                TransactionBinaryEncoding::Base64,
+            ),
+            meta: None,
+            version: Some(transaction.version()),
+        },
+        block_time: None,
+    })
+}
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use crate::{
+    config::FiltersConfig,
+    decoder::{SystemEvent, TokenEvent},
+    transaction_processor::ProcessedTransaction,
+};
+
+/// Largest SOL transfer, in lamports, treated as dust (0.00001 SOL)
+const DUST_LAMPORTS: u64 = 10_000;
+
+/// Distinct recipients a transaction has to dust before it counts as a dusting campaign
+const DUST_MIN_RECIPIENTS: usize = 3;
+
+/// Transactions dropped before storage: the configured `spam_blocklist`, always, and the built-in
+/// dusting and address-poisoning detection under `drop_spam`
+pub struct SpamBlocklist {
+    entries: HashSet<String>,
+    detect_dusting: bool,
+    allowlist: HashSet<String>,
+}
+
+impl SpamBlocklist {
+    /// Build the blocklist from the configured entries and exceptions
+    pub fn new(filters: &FiltersConfig) -> Self {
+        Self {
+            entries: filters.spam_blocklist.iter().cloned().collect(),
+            detect_dusting: filters.drop_spam,
+            allowlist: filters.spam_allowlist.iter().cloned().collect(),
+        }
+    }
+    
+    /// Reason for dropping `tx` as spam, if any
+    pub fn matches(&self, tx: &ProcessedTransaction) -> Option<&'static str> {
+        let program_ids = tx.program_ids();
+        let mut addresses = program_ids.iter().copied().chain(tx.account_keys.iter().map(String::as_str));
+        if !self.entries.is_empty() && addresses.clone().any(|address| self.entries.contains(address)) {
+            return Some("configured spam blocklist");
+        }
+        if !self.detect_dusting || addresses.any(|address| self.allowlist.contains(address)) {
+            return None;
+        }
+        dusting(tx)
+    }
+}
+
+/// Classify `tx` as dusting or address poisoning: every transfer it makes is dust, and it either
+/// dusts several recipients or moves zero tokens
+fn dusting(tx: &ProcessedTransaction) -> Option<&'static str> {
+    let mut recipients = HashSet::new();
+    for event in &tx.system_events {
+        if let SystemEvent::Transfer { destination, lamports, .. } = event {
+            if *lamports > DUST_LAMPORTS {
+                return None;
+            }
+            recipients.insert(destination.as_str());
+        }
+    }
+    
+    let mut zero_token_transfer = false;
+    for event in &tx.token_events {
+        if let TokenEvent::Transfer { amount, .. } = event {
+            if *amount > 0 {
+                return None;
+            }
+            zero_token_transfer = true;
+        }
+    }
+    
+    if zero_token_transfer {
+        Some("zero-amount token transfer (address poisoning)")
+    } else if recipients.len() >= DUST_MIN_RECIPIENTS {
+        Some("SOL dusting")
+    } else {
+        None
+    }
+}
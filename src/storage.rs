@@ -1,21 +1,170 @@
 use anyhow::{Result, Context};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use rocksdb::{DB, Options, WriteBatch};
 use serde::{Deserialize, Serialize};
 use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{
+    address_labels::LabeledAccount,
+    anchor::AnchorEvent,
+    cluster_health::ClusterHealth,
+    cluster_identity::ClusterIdentity,
+    decoder::{token::TokenEvent, NftEvent, VoteEvent},
+    gossip::GossipPeer,
+    mev::{Sandwich, Swap},
+    security::SecurityAlert,
+    transaction_processor::{
+        AccountChange, AccountChangeKind, AccountFlags, CpiCall, InstructionRecord, ProgramCompute, RawInstruction,
+    },
+    version_report::VersionReport,
+    watchlist::WatchlistHit,
+    nats::OutboxEntry,
+    webhook::DeadLetter,
+};
+
+/// Current version of the stored transaction record.
+///
+/// Between versions fields may only be added, with `#[serde(default)]`, never renamed or
+/// removed: records written by older releases stay readable and fields written by newer
+/// releases are ignored. Bump the version when the meaning of an existing field changes and
+/// migrate older records in `StoredTransaction::upgrade`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Event types streaming and search filters can select, as named in their `events`
+pub const EVENT_TYPES: &[&str] = &["swap", "nft", "vote", "anchor", "memo", "account_change", "error"];
+
+/// Slots per program health window (roughly one minute)
+pub const PROGRAM_HEALTH_WINDOW_SLOTS: u64 = 150;
+
+/// Minimum transactions in the latest window before a failure spike is reported
+const HEALTH_SPIKE_MIN_TRANSACTIONS: u64 = 20;
+
+/// Slots per program compute profile window (roughly one minute)
+pub const COMPUTE_PROFILE_WINDOW_SLOTS: u64 = 150;
+
+/// Keyspaces whose keys carry the zero-padded slot of each entry, cleared by `prune`
+const SLOT_KEYED_PREFIXES: [&str; 14] = [
+    "nft:", "event:", "mev:", "payer:", "acct:", "label:", "cpi:", "ix:", "watch:", "alert:", "rollup:slot:", "cu_price:",
+    "block:", "unconfirmed:",
+];
+
+/// Age in milliseconds past which dead letters are dropped, as newer ones are stored
+const DEAD_LETTER_TTL_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+/// Deletes written per batch while pruning
+const PRUNE_BATCH_KEYS: usize = 10_000;
+
+/// Most index entries one search reads; past it the page comes back short, with a cursor
+const MAX_SEARCH_SCANNED: usize = 10_000;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StoredTransaction {
+    /// Schema version the record was written with (0 for records predating versioning)
+    #[serde(default)]
+    pub schema_version: u32,
     pub signature: String,
     pub slot: u64,
     pub timestamp: i64,
     pub transaction: EncodedConfirmedTransactionWithStatusMeta,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_payer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wire_size: Option<usize>,
+    /// Compute-unit price paid (0 without a priority fee); absent for vote transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_fee_micro_lamports: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub durable_nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signatures_verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blockhash_age: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swap: Option<Swap>,
+    /// Suspected sandwich this transaction took part in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandwich: Option<Sandwich>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub program_categories: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nft_events: Vec<NftEvent>,
+    /// Token movements and permission changes, including those made via CPI
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub token_events: Vec<TokenEvent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vote_events: Vec<VoteEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_class: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anchor_events: Vec<AnchorEvent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub account_changes: Vec<AccountChange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub call_graph: Vec<CpiCall>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labeled_accounts: Vec<LabeledAccount>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub raw_instructions: Vec<RawInstruction>,
+    /// Writable and signer flags of each account key of the transaction, in message order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub account_flags: Vec<AccountFlags>,
+    /// Compute units attributed to each invoked program, from the program logs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub program_compute: Vec<ProgramCompute>,
+    /// Per-instruction records; indexed under `ix:` but not persisted with the transaction,
+    /// since they can be rebuilt from it
+    #[serde(skip)]
+    pub instructions: Vec<InstructionRecord>,
+    /// Every account the transaction references; indexed under `acct:` but not persisted with
+    /// the transaction, since it holds them
+    #[serde(skip)]
+    pub account_keys: Vec<String>,
+}
+
+impl StoredTransaction {
+    /// Decoded from shreds rather than fetched: the only transactions without status metadata,
+    /// as they were not executed yet
+    pub fn is_unconfirmed(&self) -> bool {
+        self.transaction.transaction.meta.is_none()
+    }
+    
+    /// The `EVENT_TYPES` the transaction carries
+    pub fn event_types(&self) -> Vec<&'static str> {
+        [
+            ("swap", self.swap.is_some()),
+            ("nft", !self.nft_events.is_empty()),
+            ("vote", !self.vote_events.is_empty()),
+            ("anchor", !self.anchor_events.is_empty()),
+            ("memo", self.memo.is_some()),
+            ("account_change", !self.account_changes.is_empty()),
+            ("error", self.error_class.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, present)| *present)
+        .map(|(event, _)| event)
+        .collect()
+    }
+    
+    /// Bring a record written by an older release up to the current schema
+    fn upgrade(mut self) -> Self {
+        // Version 0 records only lack fields added since, which deserialize to their defaults
+        if self.schema_version < SCHEMA_VERSION {
+            self.schema_version = SCHEMA_VERSION;
+        }
+        self
+    }
 }
 
 #[derive(Clone)]
 pub struct Storage {
     db: Arc<DB>,
+    index_memos: bool,
 }
 
 impl Storage {
@@ -36,9 +185,27 @@ impl Storage {
         
         Ok(Self {
             db: Arc::new(db),
+            index_memos: false,
+        })
+    }
+    
+    /// Open existing storage for reading only, alongside a node that may be writing to it. Reads
+    /// see the data as of opening
+    pub fn open_read_only(path: &str) -> Result<Self> {
+        let db = DB::open_for_read_only(&Options::default(), path, false)
+            .with_context(|| format!("Failed to open RocksDB at {} for reading", path))?;
+        Ok(Self {
+            db: Arc::new(db),
+            index_memos: false,
         })
     }
     
+    /// Enable the memo full-text index for newly stored transactions
+    pub fn with_memo_index(mut self, enabled: bool) -> Self {
+        self.index_memos = enabled;
+        self
+    }
+    
     /// Store a single transaction
     pub fn store_transaction(&self, tx: &StoredTransaction) -> Result<()> {
         let key = format!("tx:{}", tx.signature);
@@ -50,13 +217,41 @@ impl Storage {
         let slot_key = format!("slot:{}:{}", tx.slot, tx.signature);
         self.db.put(slot_key.as_bytes(), tx.signature.as_bytes())?;
         
+        for memo_key in self.memo_index_keys(tx) {
+            self.db.put(memo_key.as_bytes(), b"")?;
+        }
+        
         Ok(())
     }
     
-    /// Store multiple transactions in a batch
+    /// Store multiple transactions in a batch. A transaction already stored, or earlier in the
+    /// batch, is written again but not counted into the aggregates again, as sources routinely
+    /// deliver the same transaction more than once
     pub fn store_transactions_batch(&self, transactions: &[StoredTransaction]) -> Result<()> {
+        self.write_transactions(transactions, true)
+    }
+    
+    /// Store transactions fetched on demand for the RPC cache, indexed like the others but not
+    /// counted into any aggregate, as those cover what the node ingests
+    pub fn store_cached_transactions(&self, transactions: &[StoredTransaction]) -> Result<()> {
+        if transactions.is_empty() {
+            return Ok(());
+        }
+        self.write_transactions(transactions, false)
+    }
+    
+    fn write_transactions(&self, transactions: &[StoredTransaction], aggregate: bool) -> Result<()> {
         let mut batch = WriteBatch::default();
         
+        let stored = self.db.multi_get(transactions.iter().map(|tx| format!("tx:{}", tx.signature)));
+        let mut seen = HashSet::new();
+        let mut new_transactions: Vec<&StoredTransaction> = Vec::new();
+        for (tx, existing) in transactions.iter().zip(stored) {
+            if aggregate && existing?.is_none() && seen.insert(tx.signature.as_str()) {
+                new_transactions.push(tx);
+            }
+        }
+        
         for tx in transactions {
             let key = format!("tx:{}", tx.signature);
             let value = serde_json::to_vec(tx)?;
@@ -65,6 +260,227 @@ impl Storage {
             // Index by slot
             let slot_key = format!("slot:{}:{}", tx.slot, tx.signature);
             batch.put(slot_key.as_bytes(), tx.signature.as_bytes());
+            
+            // The confirmed transaction replaces the one decoded from shreds, if any
+            batch.delete(Self::unconfirmed_key(tx.slot, &tx.signature).as_bytes());
+            
+            for memo_key in self.memo_index_keys(tx) {
+                batch.put(memo_key.as_bytes(), b"");
+            }
+            
+            // Index NFT activity per collection
+            for (index, event) in tx.nft_events.iter().enumerate() {
+                if let Some(collection) = &event.collection {
+                    let nft_key = format!("nft:{}:{:020}:{}:{}", collection, tx.slot, tx.signature, index);
+                    batch.put(nft_key.as_bytes(), serde_json::to_vec(event)?);
+                }
+            }
+            
+            // Index Anchor events per program
+            for (index, event) in tx.anchor_events.iter().enumerate() {
+                let event_key = format!("event:{}:{:020}:{}:{}", event.program_id, tx.slot, tx.signature, index);
+                batch.put(event_key.as_bytes(), serde_json::to_vec(event)?);
+            }
+            
+            // Index suspected sandwiches once, under their victim
+            if let Some(sandwich) = tx.sandwich.as_ref().filter(|sandwich| sandwich.victim == tx.signature) {
+                let mev_key = format!("mev:{:020}:{}", tx.slot, tx.signature);
+                batch.put(mev_key.as_bytes(), serde_json::to_vec(sandwich)?);
+            }
+            
+            // Index by fee payer
+            if let Some(fee_payer) = &tx.fee_payer {
+                let payer_key = format!("payer:{}:{:020}:{}", fee_payer, tx.slot, tx.signature);
+                batch.put(payer_key.as_bytes(), b"");
+            }
+            
+            // Index by every referenced account (once per transaction and account)
+            let mut accounts: Vec<&str> = tx.account_keys.iter().map(String::as_str).collect();
+            accounts.sort_unstable();
+            accounts.dedup();
+            for account in accounts {
+                let account_key = Self::account_index_key(account, tx.slot, &tx.signature);
+                batch.put(account_key.as_bytes(), b"");
+            }
+            
+            // Index by address label and label category
+            let mut label_terms: Vec<&str> = Vec::new();
+            for account in &tx.labeled_accounts {
+                for term in std::iter::once(account.label.as_str()).chain(account.category.as_deref()) {
+                    if !label_terms.contains(&term) {
+                        label_terms.push(term);
+                    }
+                }
+            }
+            for term in label_terms {
+                let label_key = format!("label:{}:{:020}:{}", Self::label_term(term), tx.slot, tx.signature);
+                batch.put(label_key.as_bytes(), b"");
+            }
+            
+            // Index CPI edges by caller and callee (once per transaction and program pair)
+            let mut cpi_pairs: Vec<(&str, &str)> = Vec::new();
+            for call in &tx.call_graph {
+                let pair = (call.caller.as_str(), call.callee.as_str());
+                if !cpi_pairs.contains(&pair) {
+                    cpi_pairs.push(pair);
+                }
+            }
+            for (caller, callee) in cpi_pairs {
+                let cpi_key = format!("cpi:{}:{}:{:020}:{}", caller, callee, tx.slot, tx.signature);
+                batch.put(cpi_key.as_bytes(), b"");
+            }
+            
+            // Index every instruction (including CPIs) per program
+            for record in &tx.instructions {
+                let ix_key = format!("ix:{}:{:020}:{}:{}", record.program_id, tx.slot, tx.signature, record.index);
+                batch.put(ix_key.as_bytes(), serde_json::to_vec(record)?);
+            }
+        }
+        
+        // Update per-slot rollups
+        let mut rollups: BTreeMap<u64, SlotRollup> = BTreeMap::new();
+        for &tx in &new_transactions {
+            let rollup = rollups.entry(tx.slot).or_insert_with(|| SlotRollup {
+                slot: tx.slot,
+                ..Default::default()
+            });
+            rollup.add(tx);
+        }
+        
+        // Update compute-unit price statistics per slot and per invoked program
+        let mut cu_prices: BTreeMap<(u64, Option<&str>), CuPriceStats> = BTreeMap::new();
+        for &tx in &new_transactions {
+            let price = match tx.priority_fee_micro_lamports {
+                Some(price) => price,
+                None => continue,
+            };
+            cu_prices.entry((tx.slot, None)).or_default().add(price);
+            
+            let mut programs: Vec<&str> = Vec::new();
+            for record in &tx.instructions {
+                if !programs.contains(&record.program_id.as_str()) {
+                    programs.push(&record.program_id);
+                }
+            }
+            for program_id in programs {
+                cu_prices.entry((tx.slot, Some(program_id))).or_default().add(price);
+            }
+        }
+        
+        for ((slot, program_id), stats) in cu_prices {
+            let merged = match self.get_cu_price_stats(slot, program_id)? {
+                Some(existing) => existing.merge(&stats),
+                None => stats,
+            };
+            batch.put(Self::cu_price_key(slot, program_id).as_bytes(), serde_json::to_vec(&merged)?);
+        }
+        
+        // Update per-program compute profiles
+        let mut compute_profiles: BTreeMap<(&str, u64), ComputeProfile> = BTreeMap::new();
+        for &tx in &new_transactions {
+            let window = tx.slot / COMPUTE_PROFILE_WINDOW_SLOTS;
+            for compute in &tx.program_compute {
+                compute_profiles
+                    .entry((compute.program_id.as_str(), window))
+                    .or_insert_with(|| ComputeProfile {
+                        start_slot: window * COMPUTE_PROFILE_WINDOW_SLOTS,
+                        ..Default::default()
+                    })
+                    .add(compute);
+            }
+        }
+        
+        for ((program_id, window), profile) in compute_profiles {
+            let key = Self::compute_profile_key(program_id, window);
+            let merged = match self.db.get(key.as_bytes())? {
+                Some(data) => serde_json::from_slice::<ComputeProfile>(&data)?.merge(&profile),
+                None => profile,
+            };
+            batch.put(key.as_bytes(), serde_json::to_vec(&merged)?);
+        }
+        
+        // Update per-validator vote statistics
+        let mut vote_stats: BTreeMap<&str, VoteStats> = BTreeMap::new();
+        for &tx in &new_transactions {
+            for vote in &tx.vote_events {
+                vote_stats
+                    .entry(vote.vote_account.as_str())
+                    .or_insert_with(|| VoteStats {
+                        vote_account: vote.vote_account.clone(),
+                        ..Default::default()
+                    })
+                    .add(tx.slot, vote);
+            }
+        }
+        
+        // Update per-fee-payer statistics
+        let mut payer_stats: BTreeMap<&str, PayerStats> = BTreeMap::new();
+        for &tx in &new_transactions {
+            if let Some(fee_payer) = &tx.fee_payer {
+                payer_stats
+                    .entry(fee_payer.as_str())
+                    .or_insert_with(|| PayerStats {
+                        fee_payer: fee_payer.clone(),
+                        first_slot: tx.slot,
+                        ..Default::default()
+                    })
+                    .add(tx);
+            }
+        }
+        
+        for (fee_payer, stats) in payer_stats {
+            let merged = match self.get_payer_stats(fee_payer)? {
+                Some(existing) => existing.merge(&stats),
+                None => stats,
+            };
+            let key = format!("payer_stats:{}", fee_payer);
+            batch.put(key.as_bytes(), serde_json::to_vec(&merged)?);
+        }
+        
+        for (vote_account, stats) in vote_stats {
+            let merged = match self.get_vote_stats(vote_account)? {
+                Some(existing) => existing.merge(&stats),
+                None => stats,
+            };
+            let key = format!("vote_stats:{}", vote_account);
+            batch.put(key.as_bytes(), serde_json::to_vec(&merged)?);
+        }
+        
+        for (slot, rollup) in rollups {
+            let merged = match self.get_slot_rollup(slot)? {
+                Some(existing) => existing.merge(&rollup),
+                None => rollup,
+            };
+            let key = format!("rollup:slot:{:020}", slot);
+            batch.put(key.as_bytes(), serde_json::to_vec(&merged)?);
+        }
+        
+        // Update hourly and daily rollups, by block time
+        let mut time_rollups: BTreeMap<(RollupInterval, i64), TimeRollup> = BTreeMap::new();
+        for &tx in &new_transactions {
+            let block_time = match tx.transaction.block_time {
+                Some(block_time) => block_time,
+                None => continue,
+            };
+            for interval in [RollupInterval::Hour, RollupInterval::Day] {
+                let start_time = interval.start_of(block_time);
+                time_rollups
+                    .entry((interval, start_time))
+                    .or_insert_with(|| TimeRollup {
+                        start_time,
+                        ..Default::default()
+                    })
+                    .add(tx);
+            }
+        }
+        
+        for ((interval, start_time), rollup) in time_rollups {
+            let key = interval.key(start_time);
+            let merged = match self.db.get(key.as_bytes())? {
+                Some(data) => serde_json::from_slice::<TimeRollup>(&data)?.merge(&rollup),
+                None => rollup,
+            };
+            batch.put(key.as_bytes(), serde_json::to_vec(&merged)?);
         }
         
         self.db.write(batch)?;
@@ -73,14 +489,76 @@ impl Storage {
         Ok(())
     }
     
+    /// Retrieve transactions by signature in a single lookup, in the order of `signatures`
+    pub fn get_transactions<S: AsRef<str>>(&self, signatures: &[S]) -> Result<Vec<Option<StoredTransaction>>> {
+        let keys = signatures.iter().map(|signature| format!("tx:{}", signature.as_ref()));
+        
+        self.db
+            .multi_get(keys)
+            .into_iter()
+            .map(|data| match data? {
+                Some(data) => {
+                    let tx: StoredTransaction = serde_json::from_slice(&data)?;
+                    Ok(Some(tx.upgrade()))
+                }
+                None => Ok(None),
+            })
+            .collect()
+    }
+    
+    /// Whether a transaction is stored
+    pub fn contains_transaction(&self, signature: &str) -> Result<bool> {
+        Ok(self.db.get(format!("tx:{}", signature).as_bytes())?.is_some())
+    }
+    
+    /// Store transactions decoded from shreds, which precede execution, under `unconfirmed:`
+    /// apart from the confirmed ones, and without counting them into any aggregate or index.
+    /// Those already stored confirmed are skipped; returns how many were stored
+    pub fn store_unconfirmed_transactions(&self, transactions: &[StoredTransaction]) -> Result<usize> {
+        let mut batch = WriteBatch::default();
+        let mut count = 0;
+        
+        let stored = self.db.multi_get(transactions.iter().map(|tx| format!("tx:{}", tx.signature)));
+        for (tx, existing) in transactions.iter().zip(stored) {
+            if existing?.is_some() {
+                continue;
+            }
+            batch.put(Self::unconfirmed_key(tx.slot, &tx.signature).as_bytes(), serde_json::to_vec(tx)?);
+            count += 1;
+        }
+        
+        self.db.write(batch)?;
+        Ok(count)
+    }
+    
+    /// Transactions of a slot decoded from shreds and not stored confirmed (yet), in signature order
+    pub fn get_unconfirmed_transactions(&self, slot: u64) -> Result<Vec<StoredTransaction>> {
+        let prefix = format!("unconfirmed:{:020}:", slot);
+        let mut transactions = Vec::new();
+        
+        for item in self.db.prefix_iterator(prefix.as_bytes()) {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            transactions.push(serde_json::from_slice(&value)?);
+        }
+        
+        Ok(transactions)
+    }
+    
+    fn unconfirmed_key(slot: u64, signature: &str) -> String {
+        format!("unconfirmed:{:020}:{}", slot, signature)
+    }
+    
     /// Retrieve a transaction by signature
     pub fn get_transaction(&self, signature: &str) -> Result<Option<StoredTransaction>> {
         let key = format!("tx:{}", signature);
         
         match self.db.get(key.as_bytes())? {
             Some(data) => {
-                let tx = serde_json::from_slice(&data)?;
-                Ok(Some(tx))
+                let tx: StoredTransaction = serde_json::from_slice(&data)?;
+                Ok(Some(tx.upgrade()))
             }
             None => Ok(None),
         }
@@ -119,32 +597,1954 @@ impl Storage {
         Ok(transactions)
     }
     
-    /// Get database statistics
-    pub fn get_stats(&self) -> Result<StorageStats> {
-        let mut tx_count = 0;
-        let iter = self.db.prefix_iterator(b"tx:");
+    /// Find signatures of transactions whose memo contains the given word
+    pub fn search_memos(&self, word: &str, limit: usize) -> Result<Vec<String>> {
+        let prefix = format!("memo:{}:", word.to_lowercase());
+        let mut signatures = Vec::new();
         
-        for _ in iter {
-            tx_count += 1;
+        for item in self.db.prefix_iterator(prefix.as_bytes()) {
+            let (key, _) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+            match key_str.strip_prefix(prefix.as_str()) {
+                Some(signature) => signatures.push(signature.to_string()),
+                None => break,
+            }
+            if signatures.len() >= limit {
+                break;
+            }
         }
         
-        Ok(StorageStats {
-            transaction_count: tx_count,
-            db_size_bytes: self.estimate_db_size()?,
+        Ok(signatures)
+    }
+    
+    /// Get NFT events for a collection in slot order, with the signature of each transaction
+    pub fn get_collection_nft_events(
+        &self,
+        collection: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, NftEvent)>> {
+        let prefix = format!("nft:{}:", collection);
+        let mut events = Vec::new();
+        
+        for item in self.db.prefix_iterator(prefix.as_bytes()) {
+            let (key, value) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+            // Key layout: nft:{collection}:{slot}:{signature}:{index}
+            let signature = match key_str.strip_prefix(prefix.as_str()) {
+                Some(rest) => rest.split(':').nth(1).unwrap_or_default().to_string(),
+                None => break,
+            };
+            events.push((signature, serde_json::from_slice(&value)?));
+            if events.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(events)
+    }
+    
+    /// Get decoded Anchor events emitted by a program in slot order, with their transaction signature
+    pub fn get_program_events(
+        &self,
+        program_id: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, AnchorEvent)>> {
+        let prefix = format!("event:{}:", program_id);
+        let mut events = Vec::new();
+        
+        for item in self.db.prefix_iterator(prefix.as_bytes()) {
+            let (key, value) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+            // Key layout: event:{program}:{slot}:{signature}:{index}
+            let signature = match key_str.strip_prefix(prefix.as_str()) {
+                Some(rest) => rest.split(':').nth(1).unwrap_or_default().to_string(),
+                None => break,
+            };
+            events.push((signature, serde_json::from_slice(&value)?));
+            if events.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(events)
+    }
+    
+    /// Page through the instructions executed by a program (top-level or via CPI) in slot order,
+    /// with their transaction signature
+    pub fn get_program_instructions(
+        &self,
+        program_id: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<Page<(String, InstructionRecord)>> {
+        let prefix = format!("ix:{}:", program_id);
+        self.scan_page(&prefix, cursor, limit, rocksdb::Direction::Forward, |key, value| {
+            // Key layout: ix:{program}:{slot}:{signature}:{index}
+            let key_str = String::from_utf8_lossy(key);
+            let signature = key_str[prefix.len()..].split(':').nth(1).unwrap_or_default().to_string();
+            Ok((signature, serde_json::from_slice(value)?))
         })
     }
     
-    fn estimate_db_size(&self) -> Result<u64> {
-        // This is a rough estimate
-        let props = self.db.property_value("rocksdb.estimate-live-data-size")?
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(0);
-        Ok(props)
+    /// Instructions of one transaction, top-level and CPI, in execution order, read from the
+    /// `ix:` index of each of the `program_ids` it may have invoked
+    pub fn get_transaction_instructions(
+        &self,
+        slot: u64,
+        signature: &str,
+        program_ids: &[String],
+    ) -> Result<Vec<InstructionRecord>> {
+        let mut records: Vec<InstructionRecord> = Vec::new();
+        let mut seen = HashSet::new();
+        for program_id in program_ids {
+            if !seen.insert(program_id) {
+                continue;
+            }
+            let prefix = format!("ix:{}:{:020}:{}:", program_id, slot, signature);
+            for item in self.db.prefix_iterator(prefix.as_bytes()) {
+                let (key, value) = item?;
+                if !key.starts_with(prefix.as_bytes()) {
+                    break;
+                }
+                records.push(serde_json::from_slice(&value)?);
+            }
+        }
+        
+        // Indexes are dotted positions in the call tree, so compare them numerically
+        records.sort_by_cached_key(|record| {
+            record.index.split('.').map(|part| part.parse::<u32>().unwrap_or(u32::MAX)).collect::<Vec<_>>()
+        });
+        Ok(records)
     }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct StorageStats {
-    pub transaction_count: u64,
-    pub db_size_bytes: u64,
-} 
\ No newline at end of file
+    
+    /// Find signatures of transactions in which `caller` invoked `callee` via CPI, in slot order
+    pub fn get_cpi_transactions(&self, caller: &str, callee: &str, limit: usize) -> Result<Vec<String>> {
+        let prefix = format!("cpi:{}:{}:", caller, callee);
+        let mut signatures = Vec::new();
+        
+        for item in self.db.prefix_iterator(prefix.as_bytes()) {
+            let (key, _) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+            // Key layout: cpi:{caller}:{callee}:{slot}:{signature}
+            match key_str.strip_prefix(prefix.as_str()) {
+                Some(rest) => signatures.push(rest.split(':').nth(1).unwrap_or_default().to_string()),
+                None => break,
+            }
+            if signatures.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(signatures)
+    }
+    
+    /// Build memo index keys (`memo:{word}:{signature}`) for a transaction
+    fn memo_index_keys(&self, tx: &StoredTransaction) -> Vec<String> {
+        let memo = match (&tx.memo, self.index_memos) {
+            (Some(memo), true) => memo,
+            _ => return Vec::new(),
+        };
+        
+        let mut words: Vec<String> = memo
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.len() >= 2 && word.len() <= 64)
+            .map(|word| word.to_lowercase())
+            .collect();
+        words.sort();
+        words.dedup();
+        
+        words.into_iter()
+            .map(|word| format!("memo:{}:{}", word, tx.signature))
+            .collect()
+    }
+    
+    /// Page through the signatures of the stored transactions of a slot, in signature order
+    pub fn get_slot_signatures(&self, slot: u64, cursor: Option<&str>, limit: usize) -> Result<Page<String>> {
+        let prefix = format!("slot:{}:", slot);
+        self.scan_page(&prefix, cursor, limit, rocksdb::Direction::Forward, |_, value| {
+            Ok(String::from_utf8_lossy(value).into_owned())
+        })
+    }
+    
+    /// Page through the signatures of the transactions referencing an address, newest first
+    pub fn get_address_signature_page(&self, address: &str, cursor: Option<&str>, limit: usize) -> Result<Page<String>> {
+        let prefix = format!("acct:{}:", address);
+        self.scan_page(&prefix, cursor, limit, rocksdb::Direction::Reverse, |key, _| {
+            // Key layout: acct:{address}:{slot}:{signature}
+            let key_str = String::from_utf8_lossy(key);
+            Ok(key_str[prefix.len()..].split(':').nth(1).unwrap_or_default().to_string())
+        })
+    }
+    
+    /// Page through the signatures of the transactions matching `filter`, newest slot first.
+    ///
+    /// Candidates come from the index of the first account, else of the first program, else from
+    /// the slots of the slot range; the other accounts and programs are checked against their
+    /// indexes, and the remaining filters against the stored record.
+    pub fn search(&self, filter: &SearchFilter, cursor: Option<&str>, limit: usize) -> Result<Page<String>> {
+        let mut scan = SearchScan { filter, limit, items: Vec::new(), scanned: 0, last_key: None };
+        let stopped = match (filter.accounts.first(), filter.programs.first()) {
+            (Some(account), _) => self.search_index(&mut scan, &format!("acct:{}:", account), cursor)?,
+            (None, Some(program)) => self.search_index(&mut scan, &format!("ix:{}:", program), cursor)?,
+            (None, None) => self.search_slots(&mut scan, cursor)?,
+        };
+        
+        let next_cursor = match (stopped, scan.last_key) {
+            (true, Some(key)) => Some(URL_SAFE_NO_PAD.encode(key)),
+            _ => None,
+        };
+        Ok(Page { items: scan.items, next_cursor })
+    }
+    
+    /// Walk an index laid out as `{prefix}{slot}:{signature}` newest first; true if the scan
+    /// stopped before the end of the range
+    fn search_index(&self, scan: &mut SearchScan, prefix: &str, cursor: Option<&str>) -> Result<bool> {
+        let start_key = match (cursor, scan.filter.end_slot) {
+            (Some(cursor), _) => decode_cursor(prefix, cursor)?,
+            // Sorts after every entry of the end slot
+            (None, Some(end_slot)) => format!("{}{:020};", prefix, end_slot).into_bytes(),
+            (None, None) => prefix_upper_bound(prefix),
+        };
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Reverse));
+        for item in iter {
+            let (key, _) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            // `ix:` entries carry an instruction index after the signature, so a transaction can
+            // appear several times in a row; all of them sort after the key the cursor holds
+            let key_str = String::from_utf8_lossy(&key);
+            let mut parts = key_str[prefix.len()..].split(':');
+            let slot: u64 = parts.next().unwrap_or_default().parse()?;
+            let signature = parts.next().unwrap_or_default();
+            if scan.filter.start_slot.is_some_and(|start_slot| slot < start_slot) {
+                return Ok(false);
+            }
+            
+            let entry_key = format!("{}{:020}:{}", prefix, slot, signature);
+            if entry_key.as_bytes() == start_key.as_slice() {
+                continue;
+            }
+            if self.search_visit(scan, slot, signature, entry_key)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+    
+    /// Walk the slots of the slot range that have stored transactions, newest first, through
+    /// their rollups; true if the scan stopped before the start of the range
+    fn search_slots(&self, scan: &mut SearchScan, cursor: Option<&str>) -> Result<bool> {
+        let resume = cursor.map(|cursor| decode_cursor("slot:", cursor)).transpose()?;
+        let first_slot = match &resume {
+            // Key layout: slot:{slot}:{signature}
+            Some(key) => String::from_utf8_lossy(&key["slot:".len()..])
+                .split(':')
+                .next()
+                .and_then(|slot| slot.parse().ok())
+                .ok_or(InvalidCursor)?,
+            None => scan.filter.end_slot.unwrap_or(u64::MAX),
+        };
+        let start_slot = scan.filter.start_slot.unwrap_or(0);
+        
+        let rollup_key = format!("rollup:slot:{:020}", first_slot);
+        let rollups = self.db.iterator(rocksdb::IteratorMode::From(rollup_key.as_bytes(), rocksdb::Direction::Reverse));
+        for item in rollups {
+            let (key, _) = item?;
+            let slot = match key.strip_prefix(b"rollup:slot:".as_slice()) {
+                Some(slot) => String::from_utf8_lossy(slot).parse::<u64>()?,
+                None => break,
+            };
+            if slot < start_slot {
+                break;
+            }
+            
+            let prefix = format!("slot:{}:", slot);
+            let start_key = match &resume {
+                Some(key) if key.starts_with(prefix.as_bytes()) => key.clone(),
+                _ => prefix_upper_bound(&prefix),
+            };
+            let iter = self.db.iterator(rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Reverse));
+            for item in iter {
+                let (key, signature) = item?;
+                if !key.starts_with(prefix.as_bytes()) {
+                    break;
+                }
+                if key.as_ref() == start_key.as_slice() {
+                    continue;
+                }
+                let signature = String::from_utf8_lossy(&signature);
+                let key = String::from_utf8_lossy(&key).into_owned();
+                if self.search_visit(scan, slot, &signature, key)? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+    
+    /// Check one candidate, remembering `entry_key` to resume after; true once the page is full or
+    /// the scan budget is spent
+    fn search_visit(&self, scan: &mut SearchScan, slot: u64, signature: &str, entry_key: String) -> Result<bool> {
+        if scan.scanned >= MAX_SEARCH_SCANNED {
+            return Ok(true);
+        }
+        scan.scanned += 1;
+        
+        let repeated = scan.last_key.as_deref() == Some(entry_key.as_str());
+        scan.last_key = Some(entry_key);
+        if !repeated && self.search_matches(scan.filter, slot, signature)? {
+            scan.items.push(signature.to_string());
+        }
+        Ok(scan.items.len() >= scan.limit)
+    }
+    
+    fn search_matches(&self, filter: &SearchFilter, slot: u64, signature: &str) -> Result<bool> {
+        // Index lookups first, they are cheaper than loading the record
+        for account in &filter.accounts {
+            if self.db.get(Self::account_index_key(account, slot, signature).as_bytes())?.is_none() {
+                return Ok(false);
+            }
+        }
+        for program_id in &filter.programs {
+            let prefix = format!("ix:{}:{:020}:{}:", program_id, slot, signature);
+            let invoked = match self.db.prefix_iterator(prefix.as_bytes()).next() {
+                Some(item) => item?.0.starts_with(prefix.as_bytes()),
+                None => false,
+            };
+            if !invoked {
+                return Ok(false);
+            }
+        }
+        
+        let tx = match self.get_transaction(signature)? {
+            Some(tx) => tx,
+            None => return Ok(false),
+        };
+        if filter.start_time.is_some_and(|start_time| tx.timestamp < start_time)
+            || filter.end_time.is_some_and(|end_time| tx.timestamp > end_time)
+        {
+            return Ok(false);
+        }
+        
+        // Transactions without a status meta (from turbine) never match fee or status filters
+        let meta = tx.transaction.transaction.meta.as_ref();
+        let fee_matches = match meta.map(|meta| meta.fee) {
+            Some(fee) => filter.min_fee.unwrap_or(0) <= fee && fee <= filter.max_fee.unwrap_or(u64::MAX),
+            None => filter.min_fee.is_none() && filter.max_fee.is_none(),
+        };
+        if !fee_matches {
+            return Ok(false);
+        }
+        if let Some(success) = filter.success {
+            if meta.map(|meta| meta.err.is_none()) != Some(success) {
+                return Ok(false);
+            }
+        }
+        
+        Ok(filter.events.is_empty() || tx.event_types().iter().any(|event| filter.events.iter().any(|e| e == event)))
+    }
+    
+    /// Read up to `limit` entries under `prefix` in `direction`, continuing after `cursor` (the
+    /// `next_cursor` of the previous page). Cursors encode the key the page stopped at, so pages
+    /// stay consistent while new entries are written.
+    fn scan_page<T>(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+        direction: rocksdb::Direction,
+        mut read: impl FnMut(&[u8], &[u8]) -> Result<T>,
+    ) -> Result<Page<T>> {
+        let start_key = match cursor {
+            Some(cursor) => decode_cursor(prefix, cursor)?,
+            None => match direction {
+                rocksdb::Direction::Forward => prefix.as_bytes().to_vec(),
+                // Sorts after every key under the prefix
+                rocksdb::Direction::Reverse => prefix_upper_bound(prefix),
+            },
+        };
+        let mut items = Vec::new();
+        let mut last_key = None;
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(&start_key, direction));
+        
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            if cursor.is_some() && key.as_ref() == start_key.as_slice() {
+                continue;
+            }
+            items.push(read(&key, &value)?);
+            last_key = Some(key);
+            if items.len() >= limit {
+                break;
+            }
+        }
+        
+        let next_cursor = match last_key {
+            Some(key) if items.len() >= limit => Some(URL_SAFE_NO_PAD.encode(key)),
+            _ => None,
+        };
+        Ok(Page { items, next_cursor })
+    }
+    
+    /// Get the aggregated statistics of the newest slot with stored transactions
+    pub fn get_latest_slot_rollup(&self) -> Result<Option<SlotRollup>> {
+        let mut iter = self.db.iterator(rocksdb::IteratorMode::From(
+            b"rollup:slot;",
+            rocksdb::Direction::Reverse,
+        ));
+        
+        match iter.next() {
+            Some(item) => {
+                let (key, value) = item?;
+                if !key.starts_with(b"rollup:slot:") {
+                    return Ok(None);
+                }
+                Ok(Some(serde_json::from_slice(&value)?))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    /// Oldest slot from `from` on with stored transactions
+    pub fn get_next_stored_slot(&self, from: u64) -> Result<Option<u64>> {
+        let key = format!("rollup:slot:{:020}", from);
+        let mut iter = self.db.iterator(rocksdb::IteratorMode::From(
+            key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        match iter.next() {
+            Some(item) => {
+                let (key, _) = item?;
+                match key.strip_prefix(b"rollup:slot:".as_slice()) {
+                    Some(slot) => Ok(Some(String::from_utf8_lossy(slot).parse()?)),
+                    None => Ok(None),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+    
+    /// Newest slot up to `at_most` with stored transactions
+    pub fn get_latest_stored_slot(&self, at_most: u64) -> Result<Option<u64>> {
+        // The first key at or before the slot's own, walking back
+        let key = format!("rollup:slot:{:020}", at_most);
+        let mut iter = self.db.iterator(rocksdb::IteratorMode::From(
+            key.as_bytes(),
+            rocksdb::Direction::Reverse,
+        ));
+        
+        match iter.next() {
+            Some(item) => {
+                let (key, _) = item?;
+                match key.strip_prefix(b"rollup:slot:".as_slice()) {
+                    Some(slot) => Ok(Some(String::from_utf8_lossy(slot).parse()?)),
+                    None => Ok(None),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+    
+    /// Get the aggregated statistics for a single slot
+    pub fn get_slot_rollup(&self, slot: u64) -> Result<Option<SlotRollup>> {
+        let key = format!("rollup:slot:{:020}", slot);
+        
+        match self.db.get(key.as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Hourly or daily rollups of the periods starting from `start_time` to `end_time` (inclusive),
+    /// in time order. Periods without stored transactions are missing
+    pub fn get_time_rollups(&self, interval: RollupInterval, start_time: i64, end_time: i64) -> Result<Vec<TimeRollup>> {
+        let start_key = interval.key(interval.start_of(start_time));
+        let prefix = interval.key_prefix();
+        let mut rollups = Vec::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let rollup: TimeRollup = serde_json::from_slice(&value)?;
+            if rollup.start_time > end_time {
+                break;
+            }
+            rollups.push(rollup);
+        }
+        
+        Ok(rollups)
+    }
+    
+    /// Record the header of a confirmed block
+    pub fn store_block(&self, block: &StoredBlock) -> Result<()> {
+        let key = format!("block:{:020}", block.slot);
+        self.db.put(key.as_bytes(), serde_json::to_vec(block)?)?;
+        Ok(())
+    }
+    
+    /// Get the header of the block produced in a slot
+    pub fn get_block(&self, slot: u64) -> Result<Option<StoredBlock>> {
+        let key = format!("block:{:020}", slot);
+        
+        match self.db.get(key.as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Slots from `start_slot` to `end_slot` (inclusive) with a stored block, in ascending order
+    pub fn get_block_slots(&self, start_slot: u64, end_slot: u64, limit: usize) -> Result<Vec<u64>> {
+        let start_key = format!("block:{:020}", start_slot);
+        let mut slots = Vec::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, _) = item?;
+            let slot = match key.strip_prefix(b"block:".as_slice()) {
+                Some(slot) => String::from_utf8_lossy(slot).parse::<u64>()?,
+                None => break,
+            };
+            if slot > end_slot || slots.len() >= limit {
+                break;
+            }
+            slots.push(slot);
+        }
+        
+        Ok(slots)
+    }
+    
+    /// Ranges of slots from `start_slot` to `end_slot` (inclusive) with stored transactions, from
+    /// their rollups, in ascending order. A range extends to the next such slot when it follows
+    /// directly or its recorded block's parent ends the range, so skipped slots do not split it
+    /// but slots without stored transactions do
+    pub fn get_coverage(&self, start_slot: u64, end_slot: u64) -> Result<Vec<SlotRange>> {
+        let start_key = format!("rollup:slot:{:020}", start_slot);
+        let mut ranges: Vec<SlotRange> = Vec::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, _) = item?;
+            let slot = match key.strip_prefix(b"rollup:slot:") {
+                Some(slot) => String::from_utf8_lossy(slot).parse::<u64>()?,
+                None => break,
+            };
+            if slot > end_slot {
+                break;
+            }
+            let extends = match ranges.last() {
+                Some(range) => {
+                    range.end_slot + 1 == slot
+                        || self.get_block(slot)?.is_some_and(|block| block.parent_slot == range.end_slot)
+                }
+                None => false,
+            };
+            match ranges.last_mut() {
+                Some(range) if extends => {
+                    range.end_slot = slot;
+                    range.slots += 1;
+                }
+                _ => ranges.push(SlotRange { start_slot: slot, end_slot: slot, slots: 1 }),
+            }
+        }
+        
+        Ok(ranges)
+    }
+    
+    fn cu_price_key(slot: u64, program_id: Option<&str>) -> String {
+        match program_id {
+            Some(program_id) => format!("cu_price:program:{}:{:020}", program_id, slot),
+            None => format!("cu_price:slot:{:020}", slot),
+        }
+    }
+    
+    /// Get compute-unit price statistics for a slot, across all transactions or those invoking a program
+    pub fn get_cu_price_stats(&self, slot: u64, program_id: Option<&str>) -> Result<Option<CuPriceStats>> {
+        match self.db.get(Self::cu_price_key(slot, program_id).as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Combine compute-unit price statistics over a slot range, e.g. for local fee estimation
+    pub fn get_cu_price_stats_range(
+        &self,
+        program_id: Option<&str>,
+        start_slot: u64,
+        end_slot: u64,
+    ) -> Result<CuPriceStats> {
+        let prefix = match program_id {
+            Some(program_id) => format!("cu_price:program:{}:", program_id),
+            None => "cu_price:slot:".to_string(),
+        };
+        let start_key = Self::cu_price_key(start_slot, program_id);
+        let end_key = Self::cu_price_key(end_slot.saturating_add(1), program_id);
+        let mut combined = CuPriceStats::default();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) || key.as_ref() >= end_key.as_bytes() {
+                break;
+            }
+            let stats: CuPriceStats = serde_json::from_slice(&value)?;
+            combined = combined.merge(&stats);
+        }
+        
+        Ok(combined)
+    }
+    
+    /// Get aggregated vote statistics for a validator vote account
+    pub fn get_vote_stats(&self, vote_account: &str) -> Result<Option<VoteStats>> {
+        let key = format!("vote_stats:{}", vote_account);
+        
+        match self.db.get(key.as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Record votes observed in gossip at `seen_at` (Unix time in milliseconds). A vote is
+    /// counted only when newer than the validator's last one, as votes are gossiped again by the
+    /// validator and pushed on by peers
+    pub fn record_gossip_votes(&self, votes: &[VoteEvent], seen_at: u64) -> Result<()> {
+        let mut vote_stats: BTreeMap<&str, GossipVoteStats> = BTreeMap::new();
+        for vote in votes {
+            let vote_account = vote.vote_account.as_str();
+            if !vote_stats.contains_key(vote_account) {
+                let stats = self.get_gossip_vote_stats(vote_account)?.unwrap_or_else(|| GossipVoteStats {
+                    vote_account: vote.vote_account.clone(),
+                    ..Default::default()
+                });
+                vote_stats.insert(vote_account, stats);
+            }
+            let stats = vote_stats.get_mut(vote_account).unwrap();
+            if matches!(vote.voted_slot(), Some(voted_slot) if voted_slot > stats.last_voted_slot) {
+                stats.add(vote, seen_at);
+            }
+        }
+        
+        let mut batch = WriteBatch::default();
+        for (vote_account, stats) in vote_stats {
+            let key = format!("gossip_vote_stats:{}", vote_account);
+            batch.put(key.as_bytes(), serde_json::to_vec(&stats)?);
+        }
+        
+        self.db.write(batch)?;
+        Ok(())
+    }
+    
+    /// Get the gossip vote observations of a validator vote account
+    pub fn get_gossip_vote_stats(&self, vote_account: &str) -> Result<Option<GossipVoteStats>> {
+        let key = format!("gossip_vote_stats:{}", vote_account);
+        
+        match self.db.get(key.as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Get aggregated statistics for a fee payer
+    pub fn get_payer_stats(&self, fee_payer: &str) -> Result<Option<PayerStats>> {
+        let key = format!("payer_stats:{}", fee_payer);
+        
+        match self.db.get(key.as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Merge success and failure counts, keyed by window and program ID, into the stored windows
+    pub fn record_program_health(&self, health: &BTreeMap<(u64, String), ProgramHealth>) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        
+        for ((window, program_id), counts) in health {
+            let key = Self::program_health_key(*window, program_id);
+            let merged = match self.db.get(key.as_bytes())? {
+                Some(data) => serde_json::from_slice::<ProgramHealth>(&data)?.merge(counts),
+                None => counts.clone(),
+            };
+            batch.put(key.as_bytes(), serde_json::to_vec(&merged)?);
+        }
+        
+        self.db.write(batch)?;
+        Ok(())
+    }
+    
+    fn program_health_key(window: u64, program_id: &str) -> String {
+        format!("program_health:{:020}:{}", window, program_id)
+    }
+    
+    /// Compare each program's failure rate in the window containing `slot` against the
+    /// preceding `baseline_windows` windows, most alarming programs first
+    pub fn get_program_health(&self, slot: u64, baseline_windows: u64) -> Result<Vec<ProgramHealthReport>> {
+        let current_window = slot / PROGRAM_HEALTH_WINDOW_SLOTS;
+        let first_window = current_window.saturating_sub(baseline_windows);
+        let start_key = format!("program_health:{:020}:", first_window);
+        let end_key = format!("program_health:{:020}:", current_window + 1);
+        let mut reports: BTreeMap<String, ProgramHealthReport> = BTreeMap::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(b"program_health:") || key.as_ref() >= end_key.as_bytes() {
+                break;
+            }
+            let key = String::from_utf8_lossy(&key);
+            let mut parts = key.splitn(3, ':').skip(1);
+            let (window, program_id) = match (parts.next().and_then(|w| w.parse::<u64>().ok()), parts.next()) {
+                (Some(window), Some(program_id)) => (window, program_id),
+                _ => continue,
+            };
+            let counts: ProgramHealth = serde_json::from_slice(&value)?;
+            
+            let report = reports.entry(program_id.to_string()).or_insert_with(|| ProgramHealthReport {
+                program_id: program_id.to_string(),
+                ..Default::default()
+            });
+            if window == current_window {
+                report.current = report.current.clone().merge(&counts);
+            } else {
+                report.baseline = report.baseline.clone().merge(&counts);
+            }
+        }
+        
+        let mut reports: Vec<ProgramHealthReport> = reports
+            .into_values()
+            .map(|mut report| {
+                report.spiking = report.is_spiking();
+                report
+            })
+            .collect();
+        reports.sort_by(|a, b| {
+            b.spiking
+                .cmp(&a.spiking)
+                .then(b.failure_rate_increase().total_cmp(&a.failure_rate_increase()))
+        });
+        
+        Ok(reports)
+    }
+    
+    fn compute_profile_key(program_id: &str, window: u64) -> String {
+        format!("cu_profile:{}:{:020}", program_id, window)
+    }
+    
+    /// Get a program's compute profile for each window overlapping a slot range, in slot order
+    pub fn get_compute_profile(&self, program_id: &str, start_slot: u64, end_slot: u64) -> Result<Vec<ComputeProfile>> {
+        let prefix = format!("cu_profile:{}:", program_id);
+        let start_key = Self::compute_profile_key(program_id, start_slot / COMPUTE_PROFILE_WINDOW_SLOTS);
+        let end_key = Self::compute_profile_key(program_id, end_slot / COMPUTE_PROFILE_WINDOW_SLOTS + 1);
+        let mut profiles = Vec::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) || key.as_ref() >= end_key.as_bytes() {
+                break;
+            }
+            profiles.push(serde_json::from_slice(&value)?);
+        }
+        
+        Ok(profiles)
+    }
+    
+    /// Get suspected sandwiches in a slot range, in slot order
+    pub fn get_sandwiches(&self, start_slot: u64, end_slot: u64, limit: usize) -> Result<Vec<Sandwich>> {
+        let start_key = format!("mev:{:020}:", start_slot);
+        let end_key = format!("mev:{:020}:", end_slot.saturating_add(1));
+        let mut sandwiches = Vec::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(b"mev:") || key.as_ref() >= end_key.as_bytes() {
+                break;
+            }
+            sandwiches.push(serde_json::from_slice(&value)?);
+            if sandwiches.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(sandwiches)
+    }
+    
+    /// Find signatures of transactions referencing an address with the given label or label category, in slot order
+    pub fn get_label_transactions(&self, label: &str, limit: usize) -> Result<Vec<String>> {
+        let prefix = format!("label:{}:", Self::label_term(label));
+        let mut signatures = Vec::new();
+        
+        for item in self.db.prefix_iterator(prefix.as_bytes()) {
+            let (key, _) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+            // Key layout: label:{label}:{slot}:{signature}
+            match key_str.strip_prefix(prefix.as_str()) {
+                Some(rest) => signatures.push(rest.split(':').nth(1).unwrap_or_default().to_string()),
+                None => break,
+            }
+            if signatures.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(signatures)
+    }
+    
+    /// A label or category as it appears in `label:` keys, with `%` and `:` escaped so the key
+    /// splits at its separators and no label is a prefix of another
+    fn label_term(term: &str) -> String {
+        term.replace('%', "%25").replace(':', "%3A")
+    }
+    
+    fn account_index_key(address: &str, slot: u64, signature: &str) -> String {
+        format!("acct:{}:{:020}:{}", address, slot, signature)
+    }
+    
+    /// Find transactions referencing an address, newest first, as (slot, signature) pairs.
+    /// Starts after `before` and stops at `until` (both signatures of stored transactions).
+    /// Nothing is returned when `before` is not stored; an unknown `until` does not bound the search.
+    pub fn get_address_signatures(
+        &self,
+        address: &str,
+        before: Option<&str>,
+        until: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<(u64, String)>> {
+        let prefix = format!("acct:{}:", address);
+        let start_key = match before {
+            Some(before) => match self.get_transaction(before)? {
+                Some(tx) => Self::account_index_key(address, tx.slot, before),
+                None => return Ok(Vec::new()),
+            },
+            // Sorts after every key of the address
+            None => format!("acct:{};", address),
+        };
+        let end_key = match until {
+            Some(until) => self.get_transaction(until)?.map(|tx| Self::account_index_key(address, tx.slot, until)),
+            None => None,
+        };
+        let mut signatures = Vec::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Reverse,
+        ));
+        
+        for item in iter {
+            let (key, _) = item?;
+            if key.as_ref() == start_key.as_bytes() {
+                continue;
+            }
+            let past_until = end_key.as_ref().map_or(false, |end| key.as_ref() <= end.as_bytes());
+            if !key.starts_with(prefix.as_bytes()) || past_until {
+                break;
+            }
+            // Key layout: acct:{address}:{slot}:{signature}
+            let key_str = String::from_utf8_lossy(&key);
+            let mut parts = key_str[prefix.len()..].splitn(2, ':');
+            let slot = parts.next().and_then(|slot| slot.parse().ok()).unwrap_or_default();
+            signatures.push((slot, parts.next().unwrap_or_default().to_string()));
+            if signatures.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(signatures)
+    }
+    
+    /// Find signatures of transactions paid for by an account, in slot order
+    pub fn get_payer_transactions(&self, fee_payer: &str, limit: usize) -> Result<Vec<String>> {
+        let prefix = format!("payer:{}:", fee_payer);
+        let mut signatures = Vec::new();
+        
+        for item in self.db.prefix_iterator(prefix.as_bytes()) {
+            let (key, _) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+            // Key layout: payer:{fee_payer}:{slot}:{signature}
+            match key_str.strip_prefix(prefix.as_str()) {
+                Some(rest) => signatures.push(rest.split(':').nth(1).unwrap_or_default().to_string()),
+                None => break,
+            }
+            if signatures.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(signatures)
+    }
+    
+    /// Index watchlist hits under their watched address
+    pub fn store_watchlist_hits(&self, hits: &[WatchlistHit]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        
+        for hit in hits {
+            let key = format!("watch:{}:{:020}:{}", hit.address, hit.slot, hit.signature);
+            batch.put(key.as_bytes(), serde_json::to_vec(hit)?);
+        }
+        
+        self.db.write(batch)?;
+        Ok(())
+    }
+    
+    /// Get recorded activity of a watched address in slot order
+    pub fn get_watchlist_hits(&self, address: &str, limit: usize) -> Result<Vec<WatchlistHit>> {
+        let prefix = format!("watch:{}:", address);
+        let mut hits = Vec::new();
+        
+        for item in self.db.prefix_iterator(prefix.as_bytes()) {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            hits.push(serde_json::from_slice(&value)?);
+            if hits.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(hits)
+    }
+    
+    /// Replace the saved gossip peer table with a new snapshot
+    pub fn replace_gossip_peers(&self, peers: &[GossipPeer]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        
+        for item in self.db.prefix_iterator(b"gossip_peer:") {
+            let (key, _) = item?;
+            if !key.starts_with(b"gossip_peer:") {
+                break;
+            }
+            batch.delete(&key);
+        }
+        for peer in peers {
+            let key = format!("gossip_peer:{}", peer.pubkey);
+            batch.put(key.as_bytes(), serde_json::to_vec(peer)?);
+        }
+        
+        self.db.write(batch)?;
+        Ok(())
+    }
+    
+    /// Get saved gossip peers, most recently seen first
+    pub fn get_gossip_peers(&self, limit: usize) -> Result<Vec<GossipPeer>> {
+        let mut peers: Vec<GossipPeer> = Vec::new();
+        
+        for item in self.db.prefix_iterator(b"gossip_peer:") {
+            let (key, value) = item?;
+            if !key.starts_with(b"gossip_peer:") {
+                break;
+            }
+            peers.push(serde_json::from_slice(&value)?);
+        }
+        
+        peers.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+        peers.truncate(limit);
+        Ok(peers)
+    }
+    
+    /// Index security alerts by slot
+    pub fn store_security_alerts(&self, alerts: &[SecurityAlert]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        
+        // Alerts of a transaction are contiguous; number them within it
+        let mut index = 0;
+        for (position, alert) in alerts.iter().enumerate() {
+            index = match position.checked_sub(1).map(|previous| &alerts[previous]) {
+                Some(previous) if previous.signature == alert.signature => index + 1,
+                _ => 0,
+            };
+            let key = format!("alert:{:020}:{}:{}", alert.slot, alert.signature, index);
+            batch.put(key.as_bytes(), serde_json::to_vec(alert)?);
+        }
+        
+        self.db.write(batch)?;
+        Ok(())
+    }
+    
+    /// Get security alerts raised in a slot range, in slot order
+    pub fn get_security_alerts(&self, start_slot: u64, end_slot: u64, limit: usize) -> Result<Vec<SecurityAlert>> {
+        let start_key = format!("alert:{:020}:", start_slot);
+        let end_key = format!("alert:{:020}:", end_slot.saturating_add(1));
+        let mut alerts = Vec::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(b"alert:") || key.as_ref() >= end_key.as_bytes() {
+                break;
+            }
+            alerts.push(serde_json::from_slice(&value)?);
+            if alerts.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(alerts)
+    }
+    
+    /// Record a cluster health evaluation
+    pub fn store_cluster_health(&self, health: &ClusterHealth) -> Result<()> {
+        let key = format!("cluster_health:{:020}", health.timestamp);
+        self.db.put(key.as_bytes(), serde_json::to_vec(health)?)?;
+        Ok(())
+    }
+    
+    /// Get cluster health evaluations made since `since` (Unix time in milliseconds), oldest first
+    pub fn get_cluster_health(&self, since: u64, limit: usize) -> Result<Vec<ClusterHealth>> {
+        let start_key = format!("cluster_health:{:020}", since);
+        let mut evaluations = Vec::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(b"cluster_health:") {
+                break;
+            }
+            evaluations.push(serde_json::from_slice(&value)?);
+            if evaluations.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(evaluations)
+    }
+    
+    /// The cluster this storage holds data for, if recorded
+    pub fn get_cluster_identity(&self) -> Result<Option<ClusterIdentity>> {
+        match self.db.get(b"meta:cluster")? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+    
+    pub fn store_cluster_identity(&self, identity: &ClusterIdentity) -> Result<()> {
+        self.db.put(b"meta:cluster", serde_json::to_vec(identity)?)?;
+        Ok(())
+    }
+    
+    /// Progress of an interrupted command line backfill, keyed by what it backfills, as the JSON
+    /// the backfill stored
+    pub fn get_backfill_checkpoint(&self, target: &str) -> Result<Option<serde_json::Value>> {
+        match self.db.get(format!("backfill_checkpoint:{}", target).as_bytes())? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+    
+    pub fn store_backfill_checkpoint(&self, target: &str, checkpoint: &serde_json::Value) -> Result<()> {
+        self.db.put(format!("backfill_checkpoint:{}", target).as_bytes(), serde_json::to_vec(checkpoint)?)?;
+        Ok(())
+    }
+    
+    pub fn delete_backfill_checkpoint(&self, target: &str) -> Result<()> {
+        self.db.delete(format!("backfill_checkpoint:{}", target).as_bytes())?;
+        Ok(())
+    }
+    
+    pub fn store_version_report(&self, report: &VersionReport) -> Result<()> {
+        let key = format!("version_report:{:020}", report.timestamp);
+        self.db.put(key.as_bytes(), serde_json::to_vec(report)?)?;
+        Ok(())
+    }
+    
+    /// Get version reports made since `since` (Unix time in milliseconds), oldest first
+    pub fn get_version_reports(&self, since: u64, limit: usize) -> Result<Vec<VersionReport>> {
+        let start_key = format!("version_report:{:020}", since);
+        let mut reports = Vec::new();
+        
+        let iter = self.db.iterator(rocksdb::IteratorMode::From(
+            start_key.as_bytes(),
+            rocksdb::Direction::Forward,
+        ));
+        
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(b"version_report:") {
+                break;
+            }
+            reports.push(serde_json::from_slice(&value)?);
+            if reports.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(reports)
+    }
+    
+    /// Keep a transaction a webhook did not accept, for redelivery, and drop the dead letters
+    /// older than `DEAD_LETTER_TTL_MS`
+    pub fn store_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        // Key layout: dead_letter:{failed_at}:{signature}:{url}, so the expired ones come first
+        let expired_before = format!("dead_letter:{:020}", letter.failed_at.saturating_sub(DEAD_LETTER_TTL_MS));
+        for item in self.db.prefix_iterator(b"dead_letter:") {
+            let (key, _) = item?;
+            if !key.starts_with(b"dead_letter:") || *key >= *expired_before.as_bytes() {
+                break;
+            }
+            batch.delete(&key);
+        }
+        batch.put(dead_letter_key(letter).as_bytes(), serde_json::to_vec(letter)?);
+        self.db.write(batch)?;
+        Ok(())
+    }
+    
+    /// Get dead letters, oldest first
+    pub fn get_dead_letters(&self, limit: usize) -> Result<Vec<DeadLetter>> {
+        let mut letters = Vec::new();
+        
+        for item in self.db.prefix_iterator(b"dead_letter:") {
+            let (key, value) = item?;
+            if !key.starts_with(b"dead_letter:") {
+                break;
+            }
+            letters.push(serde_json::from_slice(&value)?);
+            if letters.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(letters)
+    }
+    
+    pub fn delete_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        self.db.delete(dead_letter_key(letter).as_bytes())?;
+        Ok(())
+    }
+    
+    /// Append a transaction to the NATS outbox under `sequence`, which must exceed every sequence
+    /// stored before. Entries are bincode-encoded, keeping binary payloads compact
+    pub fn append_outbox(&self, sequence: u64, entry: &OutboxEntry) -> Result<()> {
+        let key = format!("outbox:{:020}", sequence);
+        self.db.put(key.as_bytes(), bincode::serialize(entry)?)?;
+        Ok(())
+    }
+    
+    /// Get the oldest outbox entries with their sequences
+    pub fn get_outbox(&self, limit: usize) -> Result<Vec<(u64, OutboxEntry)>> {
+        let mut entries = Vec::new();
+        
+        for item in self.db.prefix_iterator(b"outbox:") {
+            let (key, value) = item?;
+            if !key.starts_with(b"outbox:") {
+                break;
+            }
+            entries.push((outbox_sequence(&key)?, bincode::deserialize(&value)?));
+            if entries.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(entries)
+    }
+    
+    pub fn delete_outbox(&self, sequences: &[u64]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        for sequence in sequences {
+            batch.delete(format!("outbox:{:020}", sequence).as_bytes());
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+    
+    /// Number of outbox entries and the newest sequence, read once at startup
+    pub fn get_outbox_bounds(&self) -> Result<(u64, Option<u64>)> {
+        let mut count = 0;
+        let mut last = None;
+        
+        for item in self.db.prefix_iterator(b"outbox:") {
+            let (key, _) = item?;
+            if !key.starts_with(b"outbox:") {
+                break;
+            }
+            count += 1;
+            last = Some(outbox_sequence(&key)?);
+        }
+        
+        Ok((count, last))
+    }
+    
+    /// Check that the database answers reads
+    pub fn check(&self) -> Result<()> {
+        self.db.get(b"health_check")?;
+        Ok(())
+    }
+    
+    /// First slot whose stored block was produced at or after `timestamp`, as long as an older
+    /// block is stored too (otherwise where that slot falls is unknown)
+    pub fn first_slot_since(&self, timestamp: i64) -> Result<Option<u64>> {
+        let mut older_seen = false;
+        for item in self.db.prefix_iterator(b"block:") {
+            let (key, value) = item?;
+            if !key.starts_with(b"block:") {
+                break;
+            }
+            let block: StoredBlock = serde_json::from_slice(&value)?;
+            match block.block_time {
+                Some(block_time) if block_time >= timestamp => return Ok(older_seen.then_some(block.slot)),
+                Some(_) => older_seen = true,
+                None => {}
+            }
+        }
+        Ok(None)
+    }
+    
+    /// Delete the transactions from before `before_slot`, their index entries, and the per-slot
+    /// records (rollups, blocks, alerts, webhook dead letters, ...) of those slots. Lifetime and
+    /// windowed aggregates (payer and vote statistics, program health, compute profiles) are kept.
+    pub fn prune(&self, before_slot: u64) -> Result<PruneStats> {
+        let mut stats = PruneStats { before_slot, ..Default::default() };
+        let mut batch = WriteBatch::default();
+        
+        // The slot index is not zero-padded, so every entry is visited
+        for item in self.db.prefix_iterator(b"slot:") {
+            let (key, signature) = item?;
+            if !key.starts_with(b"slot:") {
+                break;
+            }
+            // Key layout: slot:{slot}:{signature}
+            let key_str = String::from_utf8_lossy(&key);
+            let slot: u64 = key_str["slot:".len()..].split(':').next().unwrap_or_default().parse()?;
+            if slot >= before_slot {
+                continue;
+            }
+            
+            let signature = String::from_utf8_lossy(&signature);
+            if self.index_memos {
+                if let Some(tx) = self.get_transaction(&signature)? {
+                    for memo_key in self.memo_index_keys(&tx) {
+                        batch.delete(memo_key.as_bytes());
+                        stats.keys += 1;
+                    }
+                }
+            }
+            batch.delete(format!("tx:{}", signature).as_bytes());
+            batch.delete(&key);
+            stats.transactions += 1;
+            stats.keys += 2;
+            if batch.len() >= PRUNE_BATCH_KEYS {
+                self.db.write(std::mem::take(&mut batch))?;
+            }
+        }
+        
+        for prefix in SLOT_KEYED_PREFIXES {
+            for item in self.db.prefix_iterator(prefix.as_bytes()) {
+                let (key, _) = item?;
+                if !key.starts_with(prefix.as_bytes()) {
+                    break;
+                }
+                // The slot is the last 20-digit component: signatures are longer and entry indexes
+                // shorter, while labels before it may be anything
+                let key_str = String::from_utf8_lossy(&key);
+                let slot = key_str
+                    .split(':')
+                    .rfind(|part| part.len() == 20 && part.bytes().all(|byte| byte.is_ascii_digit()))
+                    .and_then(|part| part.parse::<u64>().ok());
+                match slot {
+                    Some(slot) if slot < before_slot => {}
+                    _ => continue,
+                }
+                
+                batch.delete(&key);
+                stats.keys += 1;
+                if batch.len() >= PRUNE_BATCH_KEYS {
+                    self.db.write(std::mem::take(&mut batch))?;
+                }
+            }
+        }
+        
+        // Dead letters are keyed by failure time; their slot is in the payload
+        for item in self.db.prefix_iterator(b"dead_letter:") {
+            let (key, value) = item?;
+            if !key.starts_with(b"dead_letter:") {
+                break;
+            }
+            let letter: DeadLetter = serde_json::from_slice(&value)?;
+            if letter.payload.get("slot").and_then(|slot| slot.as_u64()).is_some_and(|slot| slot < before_slot) {
+                batch.delete(&key);
+                stats.keys += 1;
+                if batch.len() >= PRUNE_BATCH_KEYS {
+                    self.db.write(std::mem::take(&mut batch))?;
+                }
+            }
+        }
+        
+        self.db.write(batch)?;
+        info!(
+            "Pruned {} transactions ({} keys) from before slot {}",
+            stats.transactions, stats.keys, before_slot
+        );
+        Ok(stats)
+    }
+    
+    /// Compact the whole database, reclaiming the space of deleted and overwritten entries
+    pub fn compact(&self) {
+        self.db.compact_range::<&[u8], &[u8]>(None, None);
+        info!("Compacted storage");
+    }
+    
+    /// Get database statistics
+    pub fn get_stats(&self) -> Result<StorageStats> {
+        let mut tx_count = 0;
+        let iter = self.db.prefix_iterator(b"tx:");
+        
+        for item in iter {
+            // Keys sort past the prefix into other keyspaces (e.g. vote_stats:)
+            let (key, _) = item?;
+            if !key.starts_with(b"tx:") {
+                break;
+            }
+            tx_count += 1;
+        }
+        
+        Ok(StorageStats {
+            transaction_count: tx_count,
+            db_size_bytes: self.estimate_db_size()?,
+        })
+    }
+    
+    /// Entries per keyspace (the key up to its first `:`), counted over a full scan of the database
+    pub fn get_keyspace_counts(&self) -> Result<BTreeMap<String, u64>> {
+        let mut counts = BTreeMap::new();
+        for item in self.db.iterator(rocksdb::IteratorMode::Start) {
+            let (key, _) = item?;
+            let keyspace = key.split(|byte| *byte == b':').next().unwrap_or_default();
+            *counts.entry(String::from_utf8_lossy(keyspace).into_owned()).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+    
+    fn estimate_db_size(&self) -> Result<u64> {
+        // This is a rough estimate
+        let props = self.db.property_value("rocksdb.estimate-live-data-size")?
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(0);
+        Ok(props)
+    }
+}
+
+/// One page of a range scan; `next_cursor` is set when the range may hold more entries
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Filters of `Storage::search`; a transaction must match every filter that is set
+#[derive(Debug, Default)]
+pub struct SearchFilter {
+    /// Accounts the transaction must all reference
+    pub accounts: Vec<String>,
+    /// Programs the transaction must all invoke, directly or through CPI
+    pub programs: Vec<String>,
+    pub min_fee: Option<u64>,
+    pub max_fee: Option<u64>,
+    pub start_slot: Option<u64>,
+    pub end_slot: Option<u64>,
+    /// Unix timestamps, compared with the block time
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    /// Only successful (`true`) or failed (`false`) transactions
+    pub success: Option<bool>,
+    /// Event types (`EVENT_TYPES`) of which the transaction must carry at least one
+    pub events: Vec<String>,
+}
+
+/// Progress of one `Storage::search` page
+struct SearchScan<'a> {
+    filter: &'a SearchFilter,
+    limit: usize,
+    items: Vec<String>,
+    /// Index entries read so far
+    scanned: usize,
+    /// Entry the page would resume after
+    last_key: Option<String>,
+}
+
+/// A cursor that was not issued for the listing it was passed to
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid cursor")]
+pub struct InvalidCursor;
+
+/// Key a cursor points at, which must lie under `prefix`
+fn decode_cursor(prefix: &str, cursor: &str) -> Result<Vec<u8>> {
+    let key = URL_SAFE_NO_PAD.decode(cursor).ok().filter(|key| key.starts_with(prefix.as_bytes()));
+    Ok(key.ok_or(InvalidCursor)?)
+}
+
+/// Smallest key greater than every key starting with `prefix`
+fn prefix_upper_bound(prefix: &str) -> Vec<u8> {
+    let mut bound = prefix.as_bytes().to_vec();
+    while let Some(last) = bound.pop() {
+        if last < u8::MAX {
+            bound.push(last + 1);
+            break;
+        }
+    }
+    bound
+}
+
+fn outbox_sequence(key: &[u8]) -> Result<u64> {
+    std::str::from_utf8(&key[b"outbox:".len()..])?
+        .parse()
+        .context("Invalid outbox key")
+}
+
+/// Dead letters are ordered by failure time; the signature and webhook tell them apart
+fn dead_letter_key(letter: &DeadLetter) -> String {
+    format!("dead_letter:{:020}:{}:{}", letter.failed_at, letter.signature, letter.url)
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StorageStats {
+    pub transaction_count: u64,
+    pub db_size_bytes: u64,
+}
+
+/// Outcome of `Storage::prune`
+#[derive(Debug, Default, Serialize)]
+pub struct PruneStats {
+    pub before_slot: u64,
+    pub transactions: u64,
+    /// Keys deleted, including index entries and per-slot records
+    pub keys: u64,
+}
+
+/// Width in bytes of the wire size histogram buckets (packets are capped at 1232 bytes)
+const WIRE_SIZE_BUCKET: u64 = 128;
+
+/// Header of a confirmed block, as returned by `getBlock`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredBlock {
+    pub slot: u64,
+    pub parent_slot: u64,
+    pub blockhash: String,
+    pub previous_blockhash: String,
+    pub block_time: Option<i64>,
+    pub block_height: Option<u64>,
+}
+
+/// Slots with stored transactions and no gaps but skipped slots, as returned by
+/// `Storage::get_coverage`
+#[derive(Debug, Clone, Serialize)]
+pub struct SlotRange {
+    pub start_slot: u64,
+    pub end_slot: u64,
+    /// Slots in the range with stored transactions; the others were skipped by their leaders
+    pub slots: u64,
+}
+
+/// Aggregated statistics for the stored transactions of a single slot
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SlotRollup {
+    pub slot: u64,
+    pub transaction_count: u64,
+    pub total_fees: u64,
+    pub compute_units_consumed: u64,
+    /// Number of transactions invoking each known program category
+    #[serde(default)]
+    pub category_counts: BTreeMap<String, u64>,
+    /// Number of failed transactions per error class
+    #[serde(default)]
+    pub error_counts: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub accounts_created: u64,
+    #[serde(default)]
+    pub accounts_closed: u64,
+    /// Lamports locked in newly created accounts
+    #[serde(default)]
+    pub rent_locked: u64,
+    /// Lamports released by closed accounts
+    #[serde(default)]
+    pub rent_released: u64,
+    /// Number of accounts created per owning program
+    #[serde(default)]
+    pub accounts_created_by_owner: BTreeMap<String, u64>,
+    /// Number of transactions using a durable nonce
+    #[serde(default)]
+    pub durable_nonce_count: u64,
+    /// Number of transactions with a known wire size
+    #[serde(default)]
+    pub sized_transaction_count: u64,
+    #[serde(default)]
+    pub total_wire_bytes: u64,
+    #[serde(default)]
+    pub max_wire_size: u64,
+    /// Number of transactions per wire size bucket, keyed by the bucket's lower bound
+    #[serde(default)]
+    pub wire_size_histogram: BTreeMap<u64, u64>,
+}
+
+impl SlotRollup {
+    fn add(&mut self, tx: &StoredTransaction) {
+        let meta = tx.transaction.transaction.meta.as_ref();
+        
+        self.transaction_count += 1;
+        self.total_fees += meta.map(|meta| meta.fee).unwrap_or(0);
+        self.compute_units_consumed += meta
+            .and_then(|meta| Option::<u64>::from(meta.compute_units_consumed.clone()))
+            .unwrap_or(0);
+        for category in &tx.program_categories {
+            *self.category_counts.entry(category.clone()).or_default() += 1;
+        }
+        if let Some(error_class) = &tx.error_class {
+            *self.error_counts.entry(error_class.clone()).or_default() += 1;
+        }
+        if tx.durable_nonce.is_some() {
+            self.durable_nonce_count += 1;
+        }
+        if let Some(wire_size) = tx.wire_size {
+            let wire_size = wire_size as u64;
+            self.sized_transaction_count += 1;
+            self.total_wire_bytes += wire_size;
+            self.max_wire_size = self.max_wire_size.max(wire_size);
+            let bucket = wire_size / WIRE_SIZE_BUCKET * WIRE_SIZE_BUCKET;
+            *self.wire_size_histogram.entry(bucket).or_default() += 1;
+        }
+        for change in &tx.account_changes {
+            match change.kind {
+                AccountChangeKind::Created => {
+                    self.accounts_created += 1;
+                    self.rent_locked += change.lamports;
+                    if let Some(owner) = &change.owner {
+                        *self.accounts_created_by_owner.entry(owner.clone()).or_default() += 1;
+                    }
+                }
+                AccountChangeKind::Closed => {
+                    self.accounts_closed += 1;
+                    self.rent_released += change.lamports;
+                }
+            }
+        }
+    }
+    
+    fn merge(mut self, other: &SlotRollup) -> Self {
+        self.transaction_count += other.transaction_count;
+        self.total_fees += other.total_fees;
+        self.compute_units_consumed += other.compute_units_consumed;
+        for (category, count) in &other.category_counts {
+            *self.category_counts.entry(category.clone()).or_default() += count;
+        }
+        for (error_class, count) in &other.error_counts {
+            *self.error_counts.entry(error_class.clone()).or_default() += count;
+        }
+        self.accounts_created += other.accounts_created;
+        self.accounts_closed += other.accounts_closed;
+        self.rent_locked += other.rent_locked;
+        self.rent_released += other.rent_released;
+        for (owner, count) in &other.accounts_created_by_owner {
+            *self.accounts_created_by_owner.entry(owner.clone()).or_default() += count;
+        }
+        self.durable_nonce_count += other.durable_nonce_count;
+        self.sized_transaction_count += other.sized_transaction_count;
+        self.total_wire_bytes += other.total_wire_bytes;
+        self.max_wire_size = self.max_wire_size.max(other.max_wire_size);
+        for (bucket, count) in &other.wire_size_histogram {
+            *self.wire_size_histogram.entry(*bucket).or_default() += count;
+        }
+        self
+    }
+}
+
+/// Length of the periods of a `TimeRollup`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RollupInterval {
+    Hour,
+    Day,
+}
+
+impl RollupInterval {
+    pub fn seconds(self) -> i64 {
+        match self {
+            RollupInterval::Hour => 3_600,
+            RollupInterval::Day => 86_400,
+        }
+    }
+    
+    /// Start of the period holding a Unix time, or `i64::MIN` for times in the first period
+    pub fn start_of(self, time: i64) -> i64 {
+        time.saturating_sub(time.rem_euclid(self.seconds()))
+    }
+    
+    fn key_prefix(self) -> &'static str {
+        match self {
+            RollupInterval::Hour => "rollup:hour:",
+            RollupInterval::Day => "rollup:day:",
+        }
+    }
+    
+    fn key(self, start_time: i64) -> String {
+        format!("{}{:020}", self.key_prefix(), start_time)
+    }
+}
+
+/// Aggregated statistics for the stored transactions with block times in an hour or a day.
+/// Keyed by time rather than slot, they are kept when old transactions are pruned
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TimeRollup {
+    /// Unix time the period starts at
+    pub start_time: i64,
+    pub transaction_count: u64,
+    pub failed_count: u64,
+    pub total_fees: u64,
+    pub compute_units_consumed: u64,
+    /// Number of transactions invoking each program, directly or through CPI
+    pub program_counts: BTreeMap<String, u64>,
+}
+
+impl TimeRollup {
+    fn add(&mut self, tx: &StoredTransaction) {
+        let meta = tx.transaction.transaction.meta.as_ref();
+        
+        self.transaction_count += 1;
+        if meta.is_some_and(|meta| meta.err.is_some()) {
+            self.failed_count += 1;
+        }
+        self.total_fees += meta.map(|meta| meta.fee).unwrap_or(0);
+        self.compute_units_consumed += meta
+            .and_then(|meta| Option::<u64>::from(meta.compute_units_consumed.clone()))
+            .unwrap_or(0);
+        
+        let mut programs: Vec<&str> = Vec::new();
+        for record in &tx.instructions {
+            if !programs.contains(&record.program_id.as_str()) {
+                programs.push(&record.program_id);
+            }
+        }
+        for program_id in programs {
+            *self.program_counts.entry(program_id.to_string()).or_default() += 1;
+        }
+    }
+    
+    pub fn merge(mut self, other: &TimeRollup) -> Self {
+        self.transaction_count += other.transaction_count;
+        self.failed_count += other.failed_count;
+        self.total_fees += other.total_fees;
+        self.compute_units_consumed += other.compute_units_consumed;
+        for (program_id, count) in &other.program_counts {
+            *self.program_counts.entry(program_id.clone()).or_default() += count;
+        }
+        self
+    }
+}
+
+/// Votes of a single validator vote account as observed in gossip, typically a few slots
+/// before they land in a block (see `VoteStats`)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GossipVoteStats {
+    pub vote_account: String,
+    pub vote_count: u64,
+    pub last_voted_slot: u64,
+    pub root: Option<u64>,
+    /// Unix time in milliseconds the newest vote was observed
+    pub last_seen: u64,
+}
+
+impl GossipVoteStats {
+    fn add(&mut self, vote: &VoteEvent, seen_at: u64) {
+        self.vote_count += 1;
+        if let Some(voted_slot) = vote.voted_slot() {
+            self.last_voted_slot = self.last_voted_slot.max(voted_slot);
+        }
+        self.root = self.root.max(vote.root);
+        self.last_seen = self.last_seen.max(seen_at);
+    }
+}
+
+/// Aggregated vote activity for a single validator vote account
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct VoteStats {
+    pub vote_account: String,
+    pub vote_count: u64,
+    /// Sum of vote latencies in slots, for computing the average
+    pub total_latency: u64,
+    pub max_latency: u64,
+    pub last_voted_slot: u64,
+    pub last_landed_slot: u64,
+    pub estimated_credits: u64,
+}
+
+impl VoteStats {
+    fn add(&mut self, slot: u64, vote: &VoteEvent) {
+        self.vote_count += 1;
+        if let Some(latency) = vote.latency {
+            self.total_latency += latency;
+            self.max_latency = self.max_latency.max(latency);
+        }
+        if let Some(voted_slot) = vote.voted_slot() {
+            self.last_voted_slot = self.last_voted_slot.max(voted_slot);
+        }
+        self.last_landed_slot = self.last_landed_slot.max(slot);
+        self.estimated_credits += vote.estimated_credits();
+    }
+    
+    fn merge(mut self, other: &VoteStats) -> Self {
+        self.vote_count += other.vote_count;
+        self.total_latency += other.total_latency;
+        self.max_latency = self.max_latency.max(other.max_latency);
+        self.last_voted_slot = self.last_voted_slot.max(other.last_voted_slot);
+        self.last_landed_slot = self.last_landed_slot.max(other.last_landed_slot);
+        self.estimated_credits += other.estimated_credits;
+        self
+    }
+    
+    /// Average vote latency in slots
+    pub fn average_latency(&self) -> f64 {
+        if self.vote_count == 0 {
+            return 0.0;
+        }
+        self.total_latency as f64 / self.vote_count as f64
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PayerStats {
+    pub fee_payer: String,
+    pub transaction_count: u64,
+    pub total_fees: u64,
+    pub failed_count: u64,
+    pub first_slot: u64,
+    pub last_slot: u64,
+}
+
+impl PayerStats {
+    fn add(&mut self, tx: &StoredTransaction) {
+        let meta = tx.transaction.transaction.meta.as_ref();
+        
+        self.transaction_count += 1;
+        self.total_fees += meta.map(|meta| meta.fee).unwrap_or(0);
+        if meta.map_or(false, |meta| meta.err.is_some()) {
+            self.failed_count += 1;
+        }
+        self.first_slot = self.first_slot.min(tx.slot);
+        self.last_slot = self.last_slot.max(tx.slot);
+    }
+    
+    fn merge(mut self, other: &PayerStats) -> Self {
+        self.transaction_count += other.transaction_count;
+        self.total_fees += other.total_fees;
+        self.failed_count += other.failed_count;
+        self.first_slot = self.first_slot.min(other.first_slot);
+        self.last_slot = self.last_slot.max(other.last_slot);
+        self
+    }
+}
+
+/// Success and failure counts of transactions invoking a program (directly or via CPI)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProgramHealth {
+    pub successes: u64,
+    pub failures: u64,
+}
+
+impl ProgramHealth {
+    pub fn add(&mut self, failed: bool) {
+        if failed {
+            self.failures += 1;
+        } else {
+            self.successes += 1;
+        }
+    }
+    
+    fn merge(mut self, other: &ProgramHealth) -> Self {
+        self.successes += other.successes;
+        self.failures += other.failures;
+        self
+    }
+    
+    pub fn transaction_count(&self) -> u64 {
+        self.successes + self.failures
+    }
+    
+    pub fn failure_rate(&self) -> f64 {
+        match self.transaction_count() {
+            0 => 0.0,
+            count => self.failures as f64 / count as f64,
+        }
+    }
+}
+
+/// A program's failure rate in the latest window compared with the windows before it
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProgramHealthReport {
+    pub program_id: String,
+    pub current: ProgramHealth,
+    pub baseline: ProgramHealth,
+    /// Whether the failure rate in the latest window is well above the baseline
+    pub spiking: bool,
+}
+
+impl ProgramHealthReport {
+    pub fn failure_rate_increase(&self) -> f64 {
+        self.current.failure_rate() - self.baseline.failure_rate()
+    }
+    
+    /// A spike at least doubles the baseline failure rate and raises it by 10 points,
+    /// over enough transactions to be meaningful
+    fn is_spiking(&self) -> bool {
+        let baseline = self.baseline.failure_rate();
+        self.current.transaction_count() >= HEALTH_SPIKE_MIN_TRANSACTIONS
+            && self.current.failure_rate() >= (baseline * 2.0).max(baseline + 0.1)
+    }
+}
+
+/// Compute units a program spent itself (excluding its CPIs) over one window of slots
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ComputeProfile {
+    /// First slot of the window
+    pub start_slot: u64,
+    /// Transactions in which the program reported consumption
+    pub transaction_count: u64,
+    pub invocations: u64,
+    pub compute_units: u64,
+    /// Smallest and largest consumption within a single transaction
+    pub min: u64,
+    pub max: u64,
+}
+
+impl ComputeProfile {
+    fn add(&mut self, compute: &ProgramCompute) {
+        self.min = if self.transaction_count == 0 {
+            compute.compute_units
+        } else {
+            self.min.min(compute.compute_units)
+        };
+        self.max = self.max.max(compute.compute_units);
+        self.transaction_count += 1;
+        self.invocations += compute.invocations as u64;
+        self.compute_units += compute.compute_units;
+    }
+    
+    fn merge(mut self, other: &ComputeProfile) -> Self {
+        if other.transaction_count == 0 {
+            return self;
+        }
+        self.min = if self.transaction_count == 0 { other.min } else { self.min.min(other.min) };
+        self.max = self.max.max(other.max);
+        self.transaction_count += other.transaction_count;
+        self.invocations += other.invocations;
+        self.compute_units += other.compute_units;
+        self
+    }
+    
+    /// Average compute units per transaction invoking the program
+    pub fn average(&self) -> f64 {
+        if self.transaction_count == 0 {
+            return 0.0;
+        }
+        self.compute_units as f64 / self.transaction_count as f64
+    }
+}
+
+/// Log-scale histogram of compute-unit prices (micro-lamports) with derived percentiles.
+///
+/// Prices below 16 are counted exactly; larger prices are bucketed with 8 buckets per power
+/// of two, so percentiles are accurate to within 12.5% and histograms can be merged.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CuPriceStats {
+    pub transaction_count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    /// Number of transactions per price bucket, keyed by the bucket's lower bound
+    pub histogram: BTreeMap<u64, u64>,
+}
+
+impl CuPriceStats {
+    fn bucket(price: u64) -> u64 {
+        if price < 16 {
+            return price;
+        }
+        let shift = 63 - price.leading_zeros() - 3;
+        (price >> shift) << shift
+    }
+    
+    fn add(&mut self, price: u64) {
+        self.min = if self.transaction_count == 0 { price } else { self.min.min(price) };
+        self.max = self.max.max(price);
+        self.transaction_count += 1;
+        *self.histogram.entry(Self::bucket(price)).or_default() += 1;
+        self.update_percentiles();
+    }
+    
+    fn merge(mut self, other: &CuPriceStats) -> Self {
+        if other.transaction_count == 0 {
+            return self;
+        }
+        self.min = if self.transaction_count == 0 { other.min } else { self.min.min(other.min) };
+        self.max = self.max.max(other.max);
+        self.transaction_count += other.transaction_count;
+        for (bucket, count) in &other.histogram {
+            *self.histogram.entry(*bucket).or_default() += count;
+        }
+        self.update_percentiles();
+        self
+    }
+    
+    /// Lower bound of the bucket containing the given percentile (0-100)
+    pub fn percentile(&self, percentile: f64) -> u64 {
+        let rank = ((percentile / 100.0) * self.transaction_count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in &self.histogram {
+            seen += count;
+            if seen >= rank {
+                return (*bucket).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+    
+    fn update_percentiles(&mut self) {
+        self.p50 = self.percentile(50.0);
+        self.p90 = self.percentile(90.0);
+        self.p99 = self.percentile(99.0);
+    }
+}
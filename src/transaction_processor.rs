@@ -1,19 +1,128 @@
 use anyhow::{Result, Context};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use solana_sdk::{
+    bs58,
+    instruction::CompiledInstruction,
+    message::AccountKeys,
+    pubkey::Pubkey,
     signature::Signature,
 };
 use solana_transaction_status::{
+    parse_accounts::ParsedAccountSource,
+    parse_instruction,
+    EncodableWithMeta,
     EncodedConfirmedTransactionWithStatusMeta,
+    EncodedTransaction,
+    UiCompiledInstruction,
+    UiInnerInstructions,
+    UiInstruction,
+    UiLoadedAddresses,
+    UiParsedInstruction,
+    UiTransactionStatusMeta,
 };
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::str::FromStr;
-use tracing::{debug};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use crate::{
+    address_labels::{AddressLabels, LabeledAccount},
+    anchor::{AnchorEvent, IdlRegistry},
+    blockhash_cache::BlockhashCache,
+    config::{Config, FiltersConfig, ProgramLabel},
+    decoder::{self, NftEvent, SystemEvent, TokenEvent, VoteEvent},
+    error_class,
+    filter_expr::Expr,
+    mev::{self, Swap},
+    plugin::PluginPipeline,
+    program_registry::ProgramRegistry,
+    security::{self, SecurityAlert},
+    spam_blocklist::SpamBlocklist,
+    watchlist::Watchlist,
+};
+
+/// Version of the `ProcessedTransaction` layout handed to plugins and sinks; follows the
+/// same field addition rules as the stored record (see `storage::SCHEMA_VERSION`)
+pub const SCHEMA_VERSION: u32 = 1;
+
+const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+
+/// Squads v3 and v4 multisig programs
+const SQUADS_PROGRAM_IDS: &[&str] = &[
+    "SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu",
+    "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf",
+];
 
 #[derive(Clone)]
-pub struct TransactionProcessor;
+pub struct TransactionProcessor {
+    max_log_messages: usize,
+    verify_signatures: bool,
+    store_instruction_data: bool,
+    filters: FiltersConfig,
+    filter_expression: Option<Arc<Expr>>,
+    /// Loaded plugins, which workers instantiate to run
+    plugins: Arc<PluginPipeline>,
+    registry: Arc<ProgramRegistry>,
+    idls: Arc<IdlRegistry>,
+    address_labels: Arc<AddressLabels>,
+    spam_blocklist: Arc<SpamBlocklist>,
+    watchlist: Arc<Watchlist>,
+    blockhashes: Option<BlockhashCache>,
+}
 
 impl TransactionProcessor {
-    pub fn new() -> Self {
-        Self
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            max_log_messages: config.node.max_log_messages,
+            verify_signatures: config.node.verify_signatures,
+            store_instruction_data: config.node.store_instruction_data,
+            filters: config.filters.clone(),
+            filter_expression: config.filters.expression
+                .as_deref()
+                .map(|expression| Expr::parse(expression).context("Invalid filter expression"))
+                .transpose()?
+                .map(Arc::new),
+            plugins: Arc::new(PluginPipeline::load(&config.plugins)?),
+            registry: Arc::new(ProgramRegistry::new(&config.program_registry)),
+            idls: Arc::new(IdlRegistry::load(&config.node.anchor_idls)?),
+            address_labels: Arc::new(AddressLabels::load(config.node.address_labels.as_deref())?),
+            spam_blocklist: Arc::new(SpamBlocklist::new(&config.filters)),
+            watchlist: Arc::new(Watchlist::new(&config.node.watchlist)),
+            blockhashes: None,
+        })
+    }
+    
+    /// Annotate transactions with the age of their recent blockhash using the given cache
+    pub fn with_blockhash_cache(mut self, blockhashes: BlockhashCache) -> Self {
+        self.blockhashes = Some(blockhashes);
+        self
+    }
+    
+    /// Plugins of `plugins`
+    pub fn plugins(&self) -> &PluginPipeline {
+        &self.plugins
+    }
+    
+    /// Addresses of `node.watchlist`
+    pub fn watchlist(&self) -> &Watchlist {
+        &self.watchlist
+    }
+    
+    /// Name and category of a known program
+    pub fn program_label(&self, program_id: &str) -> Option<&ProgramLabel> {
+        self.registry.get(program_id)
+    }
+    
+    /// A processor for a reloaded `config`, keeping the blockhash cache and the settings that
+    /// decide how sources fetch transactions (`verify_signatures`, `store_instruction_data`),
+    /// which only change on restart
+    pub fn reloaded(&self, config: &Config) -> Result<Self> {
+        let mut processor = Self::new(config)?;
+        processor.verify_signatures = self.verify_signatures;
+        processor.store_instruction_data = self.store_instruction_data;
+        processor.blockhashes = self.blockhashes.clone();
+        Ok(processor)
     }
     
     /// Process an encoded transaction
@@ -24,20 +133,50 @@ impl TransactionProcessor {
         let slot = encoded_tx.slot;
         let block_time = encoded_tx.block_time;
         
-        // Extract transaction data
-        let transaction = &encoded_tx.transaction.transaction;
+        // Extract transaction data, decoding binary encodings into a raw message
+        let decoded;
+        let mut signatures_verified = None;
+        let transaction = match &encoded_tx.transaction.transaction {
+            transaction @ (EncodedTransaction::LegacyBinary(_) | EncodedTransaction::Binary(..)) => {
+                let versioned = transaction.decode()
+                    .ok_or_else(|| anyhow::anyhow!("Failed to decode binary transaction"))?;
+                
+                // Trust-but-verify: check every signature against the message bytes
+                if self.verify_signatures {
+                    let verified = versioned.verify_with_results().iter().all(|valid| *valid);
+                    if !verified {
+                        warn!(
+                            "Signature verification failed for transaction {} in slot {}",
+                            versioned.signatures.first().map(|s| s.to_string()).unwrap_or_default(),
+                            slot
+                        );
+                    }
+                    signatures_verified = Some(verified);
+                }
+                
+                decoded = versioned.json_encode();
+                &decoded
+            }
+            transaction => transaction,
+        };
         
         // Get signatures
         let signatures = match transaction {
-            solana_transaction_status::EncodedTransaction::Json(ui_tx) => ui_tx.signatures.clone(),
+            EncodedTransaction::Json(ui_tx) => ui_tx.signatures.clone(),
             _ => return Err(anyhow::anyhow!("Unsupported transaction encoding")),
         };
         let primary_signature = signatures.first()
             .ok_or_else(|| anyhow::anyhow!("No signatures found"))?
             .clone();
         
+        let recent_blockhash = Self::extract_recent_blockhash(transaction);
+        
         // Extract account keys
-        let account_keys = Self::extract_account_keys(&transaction)?;
+        let account_keys = Self::extract_account_keys(transaction, encoded_tx.transaction.meta.as_ref())?;
+        let account_flags = Self::extract_account_flags(transaction, encoded_tx.transaction.meta.as_ref());
+        
+        // The fee payer is always the first account: the first writable signer of the message
+        let fee_payer = account_keys.first().cloned();
         
         // Check if it's a vote transaction
         let is_vote = Self::is_vote_transaction(&account_keys);
@@ -49,29 +188,437 @@ impl TransactionProcessor {
             .unwrap_or(0);
         
         // Extract error status
-        let error = encoded_tx.transaction.meta
+        let transaction_error = encoded_tx.transaction.meta
+            .as_ref()
+            .and_then(|meta| meta.err.clone());
+        let error_class = transaction_error.as_ref().map(error_class::classify);
+        let error_instruction_index = transaction_error
             .as_ref()
-            .and_then(|meta| meta.err.clone())
+            .and_then(error_class::failed_instruction_index);
+        let error = transaction_error
             .map(|err| serde_json::to_value(err).unwrap_or(serde_json::Value::Null));
         
-        let processed = ProcessedTransaction {
+        // Extract compute units consumed (not reported by older RPC nodes)
+        let compute_units_consumed = encoded_tx.transaction.meta
+            .as_ref()
+            .and_then(|meta| meta.compute_units_consumed.clone().into());
+        
+        // Extract requested compute budget (limit and priority fee)
+        let instructions = Self::extract_instructions(transaction, &account_keys);
+        let (cu_limit, priority_fee_micro_lamports) = Self::parse_compute_budget(&instructions);
+        
+        // Attach inner (CPI) instructions to the top-level instructions that invoked them
+        let inner_instructions = Self::extract_inner_instructions(&encoded_tx.transaction, &account_keys);
+        let instructions = Self::build_instruction_tree(instructions, inner_instructions);
+        
+        // Extract program log messages, truncated to the configured limit
+        let mut log_messages: Vec<String> = encoded_tx.transaction.meta
+            .as_ref()
+            .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages.clone()))
+            .unwrap_or_default();
+        
+        // Attribute compute units to programs before truncating, so no invocations are lost
+        let program_compute = Self::attribute_compute_units(&log_messages);
+        
+        // Decode Anchor events before truncating, so no events are lost
+        let anchor_events = if self.idls.is_empty() {
+            Vec::new()
+        } else {
+            self.idls.decode_logs(&log_messages)
+        };
+        
+        if self.max_log_messages > 0 && log_messages.len() > self.max_log_messages {
+            let omitted = log_messages.len() - self.max_log_messages;
+            log_messages.truncate(self.max_log_messages);
+            log_messages.push(format!("... {} more log messages truncated", omitted));
+        }
+        
+        let mut processed = ProcessedTransaction {
+            schema_version: SCHEMA_VERSION,
             signature: primary_signature,
             slot,
             block_time,
             fee,
+            fee_payer,
+            num_signers: signatures.len(),
+            is_multisig: false,
             is_vote,
             error,
+            error_class,
+            error_instruction_index,
+            compute_units_consumed,
+            program_compute,
+            cu_limit,
+            priority_fee_micro_lamports,
             account_keys,
-            instruction_count: Self::count_instructions(&encoded_tx.transaction),
+            account_flags,
+            instruction_count: Self::count_instructions(transaction),
+            wire_size: Self::wire_size(transaction),
+            instructions,
+            log_messages,
+            token_events: Vec::new(),
+            system_events: Vec::new(),
+            memo: None,
+            program_labels: Vec::new(),
+            program_categories: Vec::new(),
+            nft_events: Vec::new(),
+            vote_events: Vec::new(),
+            anchor_events,
+            account_changes: Vec::new(),
+            labeled_accounts: Vec::new(),
+            durable_nonce: None,
+            swap: None,
+            signatures_verified,
+            recent_blockhash,
+            blockhash_age: None,
+            raw_instructions: Vec::new(),
+            security_alerts: Vec::new(),
         };
         
+        // Decode token and system activity, including instructions invoked via CPI
+        processed.token_events = processed.all_instructions()
+            .into_iter()
+            .filter_map(decoder::token::decode)
+            .collect();
+        processed.system_events = processed.all_instructions()
+            .into_iter()
+            .filter_map(decoder::system::decode)
+            .collect();
+        
+        // A transaction uses a durable nonce instead of a recent blockhash when its
+        // first instruction advances a nonce account
+        processed.durable_nonce = processed.instructions
+            .first()
+            .and_then(|node| decoder::system::decode(&node.instruction))
+            .and_then(|event| match event {
+                SystemEvent::AdvanceNonce { nonce_account, .. } => Some(nonce_account),
+                _ => None,
+            });
+        
+        // Slots between the block that produced the recent blockhash and inclusion
+        // (not meaningful for durable nonce transactions, whose "blockhash" is the nonce)
+        if let (Some(blockhashes), Some(blockhash), None) =
+            (&self.blockhashes, &processed.recent_blockhash, &processed.durable_nonce)
+        {
+            processed.blockhash_age = blockhashes
+                .get(blockhash)
+                .map(|blockhash_slot| slot.saturating_sub(blockhash_slot));
+        }
+        
+        // Surface memo text (multiple memos are joined line by line)
+        let memos: Vec<String> = processed.all_instructions()
+            .into_iter()
+            .filter_map(decoder::memo::decode)
+            .collect();
+        if !memos.is_empty() {
+            processed.memo = Some(memos.join("\n"));
+        }
+        
+        // Tag with labels of known programs
+        let labels: Vec<_> = processed.program_ids()
+            .into_iter()
+            .filter_map(|program_id| self.registry.get(program_id))
+            .collect();
+        for label in labels {
+            processed.program_labels.push(label.name.clone());
+            if !processed.program_categories.contains(&label.category) {
+                processed.program_categories.push(label.category.clone());
+            }
+        }
+        
+        if !self.address_labels.is_empty() {
+            processed.labeled_accounts = self.address_labels.tag(&processed.account_keys);
+        }
+        
+        // Flag Squads and SPL Token multisig involvement
+        processed.is_multisig = processed.all_instructions()
+            .into_iter()
+            .any(|ix| SQUADS_PROGRAM_IDS.contains(&ix.program_id.as_str()) || decoder::token::uses_multisig(ix));
+        
+        processed.nft_events = Self::extract_nft_events(&processed, encoded_tx);
+        
+        if processed.is_vote {
+            processed.vote_events = processed.all_instructions()
+                .into_iter()
+                .filter_map(|ix| decoder::vote::decode(ix, slot))
+                .collect();
+        }
+        
+        processed.account_changes = Self::extract_account_changes(&processed, encoded_tx);
+        
+        // Net token movement of the fee payer through a DEX, used for MEV analysis
+        let dex_program = processed.program_ids()
+            .into_iter()
+            .find(|program_id| {
+                self.registry
+                    .get(program_id)
+                    .map_or(false, |label| label.category == "dex" || label.category == "dex-aggregator")
+            })
+            .map(str::to_string);
+        if let Some(program_id) = dex_program {
+            processed.swap = Self::extract_swap(&processed, encoded_tx, program_id);
+        }
+        
+        processed.security_alerts = security::detect(&processed);
+        
+        if self.store_instruction_data {
+            processed.raw_instructions = processed.collect_raw_instructions();
+        }
+        
         Ok(processed)
     }
     
+    /// Decode Metaplex NFT activity, filling in mints and collections from the rest of the transaction
+    fn extract_nft_events(
+        processed: &ProcessedTransaction,
+        encoded_tx: &EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Vec<NftEvent> {
+        let instructions = processed.all_instructions();
+        let mut events: Vec<NftEvent> = Vec::new();
+        
+        for event in instructions.iter().filter_map(|ix| decoder::metaplex::decode(ix)) {
+            // Candy Machine mints CPI into Token Metadata, so merge duplicate mint events
+            let duplicate = events.iter_mut().find(|existing| {
+                existing.kind == event.kind && existing.mint.is_some() && existing.mint == event.mint
+            });
+            match duplicate {
+                Some(existing) => {
+                    existing.collection = existing.collection.take().or(event.collection);
+                    existing.buyer = existing.buyer.take().or(event.buyer);
+                }
+                None => events.push(event),
+            }
+        }
+        
+        if events.is_empty() {
+            return events;
+        }
+        
+        let collection = instructions.iter().find_map(|ix| decoder::metaplex::verified_collection(ix));
+        let token_mints = Self::token_account_mints(encoded_tx, &processed.account_keys);
+        
+        for event in &mut events {
+            if event.collection.is_none() {
+                event.collection = collection.clone();
+            }
+            if event.mint.is_none() {
+                event.mint = event.token_account
+                    .as_ref()
+                    .and_then(|account| token_mints.get(account).cloned());
+            }
+        }
+        
+        events
+    }
+    
+    /// Derive the swap made by the fee payer from its token (and SOL) balance changes
+    fn extract_swap(
+        processed: &ProcessedTransaction,
+        encoded_tx: &EncodedConfirmedTransactionWithStatusMeta,
+        program_id: String,
+    ) -> Option<Swap> {
+        let meta = encoded_tx.transaction.meta.as_ref()?;
+        let trader = processed.fee_payer.clone()?;
+        
+        // Net change per mint across the trader's token accounts
+        let mut deltas: HashMap<String, i128> = HashMap::new();
+        let balances = [(&meta.pre_token_balances, -1i128), (&meta.post_token_balances, 1)];
+        for (balances, sign) in balances {
+            let balances = Option::<&Vec<_>>::from(balances.as_ref()).into_iter().flatten();
+            for balance in balances {
+                if Option::<&String>::from(balance.owner.as_ref()) != Some(&trader) {
+                    continue;
+                }
+                let amount: i128 = balance.ui_token_amount.amount.parse().ok()?;
+                *deltas.entry(balance.mint.clone()).or_default() += sign * amount;
+            }
+        }
+        
+        // Native SOL spent or received, excluding the transaction fee
+        let lamports = meta.post_balances.first().copied().unwrap_or(0) as i128
+            - meta.pre_balances.first().copied().unwrap_or(0) as i128
+            + meta.fee as i128;
+        if lamports != 0 {
+            *deltas.entry(mev::NATIVE_SOL_MINT.to_string()).or_default() += lamports;
+        }
+        
+        let (mint_in, amount_in) = deltas.iter().min_by_key(|(_, delta)| **delta)?;
+        let (mint_out, amount_out) = deltas.iter().max_by_key(|(_, delta)| **delta)?;
+        if *amount_in >= 0 || *amount_out <= 0 {
+            return None;
+        }
+        
+        Some(Swap {
+            trader,
+            program_id,
+            mint_in: mint_in.clone(),
+            amount_in: amount_in.unsigned_abs() as u64,
+            mint_out: mint_out.clone(),
+            amount_out: *amount_out as u64,
+        })
+    }
+    
+    /// Detect accounts created (zero to non-zero balance) and closed (non-zero to zero balance)
+    fn extract_account_changes(
+        processed: &ProcessedTransaction,
+        encoded_tx: &EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Vec<AccountChange> {
+        let meta = match &encoded_tx.transaction.meta {
+            Some(meta) => meta,
+            None => return Vec::new(),
+        };
+        
+        let mut changes = Vec::new();
+        let balances = meta.pre_balances.iter().zip(&meta.post_balances);
+        
+        for (account, (&pre, &post)) in processed.account_keys.iter().zip(balances) {
+            let kind = match (pre, post) {
+                (0, post) if post > 0 => AccountChangeKind::Created,
+                (pre, 0) if pre > 0 => AccountChangeKind::Closed,
+                _ => continue,
+            };
+            
+            // Owner of a new account: from createAccount, or the System Program for plain transfers
+            let owner = match kind {
+                AccountChangeKind::Created => processed.system_events
+                    .iter()
+                    .find_map(|event| match event {
+                        SystemEvent::CreateAccount { new_account, owner, .. } if new_account == account => {
+                            Some(owner.clone())
+                        }
+                        _ => None,
+                    })
+                    .or_else(|| {
+                        processed.system_events
+                            .iter()
+                            .any(|event| matches!(event, SystemEvent::Transfer { destination, .. } if destination == account))
+                            .then(|| decoder::system::SYSTEM_PROGRAM_ID.to_string())
+                    }),
+                AccountChangeKind::Closed => None,
+            };
+            
+            changes.push(AccountChange {
+                account: account.clone(),
+                kind,
+                lamports: pre.max(post),
+                owner,
+            });
+        }
+        
+        changes
+    }
+    
+    /// Map token accounts touched by the transaction to their mints using the meta token balances
+    fn token_account_mints(
+        encoded_tx: &EncodedConfirmedTransactionWithStatusMeta,
+        account_keys: &[String],
+    ) -> HashMap<String, String> {
+        let mut mints = HashMap::new();
+        
+        if let Some(meta) = &encoded_tx.transaction.meta {
+            let balances = [&meta.pre_token_balances, &meta.post_token_balances]
+                .into_iter()
+                .filter_map(|balances| Option::<&Vec<_>>::from(balances.as_ref()))
+                .flatten();
+            for balance in balances {
+                if let Some(account) = account_keys.get(balance.account_index as usize) {
+                    mints.insert(account.clone(), balance.mint.clone());
+                }
+            }
+        }
+        
+        mints
+    }
+    
+    /// Compute the serialized size of a transaction from its message. Parsed messages keep the
+    /// data of partially decoded instructions only, so the size is unknown once the RPC node fully
+    /// parsed one (fetching Base64 measures every transaction)
+    fn wire_size(transaction: &EncodedTransaction) -> Option<usize> {
+        let ui_transaction = match transaction {
+            EncodedTransaction::Json(ui_transaction) => ui_transaction,
+            _ => return None,
+        };
+        
+        // Static account count, then the account count and data of each instruction
+        let (account_count, instructions, lookups) = match &ui_transaction.message {
+            solana_transaction_status::UiMessage::Raw(raw) => {
+                let instructions = raw
+                    .instructions
+                    .iter()
+                    .map(|instruction| (instruction.accounts.len(), instruction.data.as_str()))
+                    .collect::<Vec<_>>();
+                (raw.account_keys.len(), instructions, &raw.address_table_lookups)
+            }
+            solana_transaction_status::UiMessage::Parsed(parsed) => {
+                let instructions = parsed
+                    .instructions
+                    .iter()
+                    .map(|instruction| match instruction {
+                        UiInstruction::Compiled(compiled) => Some((compiled.accounts.len(), compiled.data.as_str())),
+                        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) => {
+                            Some((decoded.accounts.len(), decoded.data.as_str()))
+                        }
+                        UiInstruction::Parsed(UiParsedInstruction::Parsed(_)) => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                // Addresses loaded from lookup tables are listed too, but not part of the message
+                let account_count = parsed
+                    .account_keys
+                    .iter()
+                    .filter(|account| !matches!(account.source, Some(ParsedAccountSource::LookupTable)))
+                    .count();
+                (account_count, instructions, &parsed.address_table_lookups)
+            }
+        };
+        
+        // Compact-u16 length prefix
+        let short_vec = |len: usize| match len {
+            0..=0x7f => 1,
+            0x80..=0x3fff => 2,
+            _ => 3,
+        };
+        
+        let mut size = short_vec(ui_transaction.signatures.len()) + 64 * ui_transaction.signatures.len();
+        // Message header and recent blockhash
+        size += 3 + 32;
+        size += short_vec(account_count) + 32 * account_count;
+        size += short_vec(instructions.len());
+        for (account_count, data) in instructions {
+            let data_len = bs58::decode(data).into_vec().ok()?.len();
+            size += 1 + short_vec(account_count) + account_count;
+            size += short_vec(data_len) + data_len;
+        }
+        
+        // Versioned (v0) messages add a version prefix and address table lookups
+        if let Some(lookups) = lookups {
+            size += 1 + short_vec(lookups.len());
+            for lookup in lookups {
+                size += 32;
+                size += short_vec(lookup.writable_indexes.len()) + lookup.writable_indexes.len();
+                size += short_vec(lookup.readonly_indexes.len()) + lookup.readonly_indexes.len();
+            }
+        }
+        
+        Some(size)
+    }
+    
+    fn extract_recent_blockhash(transaction: &EncodedTransaction) -> Option<String> {
+        match transaction {
+            EncodedTransaction::Json(ui_transaction) => match &ui_transaction.message {
+                solana_transaction_status::UiMessage::Parsed(parsed) => Some(parsed.recent_blockhash.clone()),
+                solana_transaction_status::UiMessage::Raw(raw) => Some(raw.recent_blockhash.clone()),
+            },
+            _ => None,
+        }
+    }
+    
     /// Extract account keys from transaction
-    fn extract_account_keys(transaction: &solana_transaction_status::EncodedTransaction) -> Result<Vec<String>> {
+    pub fn extract_account_keys(
+        transaction: &EncodedTransaction,
+        meta: Option<&UiTransactionStatusMeta>,
+    ) -> Result<Vec<String>> {
         match transaction {
-            solana_transaction_status::EncodedTransaction::Json(ui_transaction) => {
+            EncodedTransaction::Json(ui_transaction) => {
                 match &ui_transaction.message {
                     solana_transaction_status::UiMessage::Parsed(parsed) => {
                         Ok(parsed.account_keys
@@ -80,7 +627,16 @@ impl TransactionProcessor {
                             .collect())
                     }
                     solana_transaction_status::UiMessage::Raw(raw) => {
-                        Ok(raw.account_keys.clone())
+                        // Raw v0 messages only list static keys; addresses loaded
+                        // from lookup tables follow them (writable, then readonly)
+                        let mut account_keys = raw.account_keys.clone();
+                        let loaded = meta
+                            .and_then(|meta| Option::<&UiLoadedAddresses>::from(meta.loaded_addresses.as_ref()));
+                        if let Some(loaded) = loaded {
+                            account_keys.extend(loaded.writable.iter().cloned());
+                            account_keys.extend(loaded.readonly.iter().cloned());
+                        }
+                        Ok(account_keys)
                     }
                 }
             }
@@ -88,6 +644,208 @@ impl TransactionProcessor {
         }
     }
     
+    /// Classify each account key as writable or read-only and signer or not
+    fn extract_account_flags(
+        transaction: &EncodedTransaction,
+        meta: Option<&UiTransactionStatusMeta>,
+    ) -> Vec<AccountFlags> {
+        let ui_transaction = match transaction {
+            EncodedTransaction::Json(ui_transaction) => ui_transaction,
+            _ => return Vec::new(),
+        };
+        
+        match &ui_transaction.message {
+            solana_transaction_status::UiMessage::Parsed(parsed) => {
+                parsed.account_keys
+                    .iter()
+                    .map(|ak| AccountFlags { writable: ak.writable, signer: ak.signer })
+                    .collect()
+            }
+            solana_transaction_status::UiMessage::Raw(raw) => {
+                // Static keys are ordered: writable signers, read-only signers,
+                // writable non-signers, read-only non-signers
+                let header = &raw.header;
+                let num_keys = raw.account_keys.len();
+                let num_signers = header.num_required_signatures as usize;
+                let writable_signers = num_signers.saturating_sub(header.num_readonly_signed_accounts as usize);
+                let writable_keys = num_keys.saturating_sub(header.num_readonly_unsigned_accounts as usize);
+                
+                let mut flags: Vec<AccountFlags> = (0..num_keys)
+                    .map(|index| AccountFlags {
+                        writable: if index < num_signers { index < writable_signers } else { index < writable_keys },
+                        signer: index < num_signers,
+                    })
+                    .collect();
+                
+                let loaded = meta
+                    .and_then(|meta| Option::<&UiLoadedAddresses>::from(meta.loaded_addresses.as_ref()));
+                if let Some(loaded) = loaded {
+                    flags.extend(loaded.writable.iter().map(|_| AccountFlags { writable: true, signer: false }));
+                    flags.extend(loaded.readonly.iter().map(|_| AccountFlags { writable: false, signer: false }));
+                }
+                flags
+            }
+        }
+    }
+    
+    /// Normalize top-level instructions across the parsed and raw message encodings
+    fn extract_instructions(
+        transaction: &EncodedTransaction,
+        account_keys: &[String],
+    ) -> Vec<InstructionInfo> {
+        match transaction {
+            EncodedTransaction::Json(ui_transaction) => {
+                match &ui_transaction.message {
+                    solana_transaction_status::UiMessage::Parsed(parsed) => {
+                        parsed.instructions
+                            .iter()
+                            .map(|ix| InstructionInfo::from_ui_instruction(ix, account_keys))
+                            .collect()
+                    }
+                    solana_transaction_status::UiMessage::Raw(raw) => {
+                        raw.instructions
+                            .iter()
+                            .map(|ix| InstructionInfo::from_compiled(ix, account_keys))
+                            .collect()
+                    }
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+    
+    /// Extract inner instructions from the transaction meta, keyed by top-level instruction index
+    fn extract_inner_instructions(
+        transaction_with_meta: &solana_transaction_status::EncodedTransactionWithStatusMeta,
+        account_keys: &[String],
+    ) -> Vec<(usize, Vec<InstructionInfo>)> {
+        transaction_with_meta.meta
+            .as_ref()
+            .and_then(|meta| Option::<&Vec<UiInnerInstructions>>::from(meta.inner_instructions.as_ref()))
+            .map(|inner| {
+                inner.iter()
+                    .map(|group| {
+                        let instructions = group.instructions
+                            .iter()
+                            .map(|ix| InstructionInfo::from_ui_instruction(ix, account_keys))
+                            .collect();
+                        (group.index as usize, instructions)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    
+    /// Build the instruction tree from top-level instructions and their flattened inner instructions
+    fn build_instruction_tree(
+        instructions: Vec<InstructionInfo>,
+        mut inner_instructions: Vec<(usize, Vec<InstructionInfo>)>,
+    ) -> Vec<InstructionNode> {
+        instructions
+            .into_iter()
+            .enumerate()
+            .map(|(index, instruction)| {
+                let inner = inner_instructions
+                    .iter_mut()
+                    .find(|(group_index, _)| *group_index == index)
+                    .map(|(_, group)| std::mem::take(group))
+                    .unwrap_or_default();
+                
+                InstructionNode {
+                    instruction,
+                    // Top-level instructions run at stack height 1, so CPIs start at 2
+                    inner: InstructionNode::build_children(&inner, 2),
+                }
+            })
+            .collect()
+    }
+    
+    /// Extract the requested compute unit limit and price from ComputeBudget instructions
+    fn parse_compute_budget(instructions: &[InstructionInfo]) -> (Option<u32>, Option<u64>) {
+        let mut cu_limit = None;
+        let mut cu_price = None;
+        
+        for ix in instructions.iter().filter(|ix| ix.program_id == COMPUTE_BUDGET_PROGRAM_ID) {
+            let data = match &ix.data {
+                Some(data) => data,
+                None => continue,
+            };
+            
+            match data.split_first() {
+                // SetComputeUnitLimit(u32)
+                Some((2, rest)) => {
+                    cu_limit = rest.get(..4)
+                        .and_then(|bytes| bytes.try_into().ok())
+                        .map(u32::from_le_bytes);
+                }
+                // SetComputeUnitPrice(u64), in micro-lamports per compute unit
+                Some((3, rest)) => {
+                    cu_price = rest.get(..8)
+                        .and_then(|bytes| bytes.try_into().ok())
+                        .map(u64::from_le_bytes);
+                }
+                _ => {}
+            }
+        }
+        
+        (cu_limit, cu_price)
+    }
+    
+    /// Attribute consumed compute units to the programs that spent them, using the runtime's
+    /// `invoke`, `consumed` and `success`/`failed` log lines.
+    ///
+    /// Each invocation is charged its own units only: what a program's CPIs consumed is
+    /// subtracted from its total and charged to the callees. Invocations without a `consumed`
+    /// line (builtin programs, truncated logs) are not attributed.
+    fn attribute_compute_units(log_messages: &[String]) -> Vec<ProgramCompute> {
+        // Open invocations as (program ID, units consumed by its CPIs, units it reported)
+        let mut stack: Vec<(&str, u64, Option<u64>)> = Vec::new();
+        let mut out: Vec<ProgramCompute> = Vec::new();
+        
+        for line in log_messages {
+            let rest = match line.strip_prefix("Program ") {
+                Some(rest) => rest,
+                None => continue,
+            };
+            let (program_id, event) = match rest.split_once(' ') {
+                Some(parts) => parts,
+                None => continue,
+            };
+            
+            if event.starts_with("invoke [") {
+                stack.push((program_id, 0, None));
+            } else if let Some(consumed) = event.strip_prefix("consumed ") {
+                let units = consumed.split(' ').next().and_then(|units| units.parse::<u64>().ok());
+                if let Some(frame) = stack.last_mut().filter(|frame| frame.0 == program_id) {
+                    frame.2 = units;
+                }
+            } else if event == "success" || event.starts_with("failed") {
+                let (cpi_units, units) = match stack.pop() {
+                    Some((invoked, cpi_units, Some(units))) if invoked == program_id => (cpi_units, units),
+                    _ => continue,
+                };
+                if let Some(caller) = stack.last_mut() {
+                    caller.1 += units;
+                }
+                
+                let own_units = units.saturating_sub(cpi_units);
+                match out.iter_mut().find(|entry| entry.program_id == program_id) {
+                    Some(entry) => {
+                        entry.invocations += 1;
+                        entry.compute_units += own_units;
+                    }
+                    None => out.push(ProgramCompute {
+                        program_id: program_id.to_string(),
+                        invocations: 1,
+                        compute_units: own_units,
+                    }),
+                }
+            }
+        }
+        
+        out
+    }
+    
     /// Check if transaction is a vote transaction
     fn is_vote_transaction(account_keys: &[String]) -> bool {
         const VOTE_PROGRAM_ID: &str = "Vote111111111111111111111111111111111111111";
@@ -95,9 +853,9 @@ impl TransactionProcessor {
     }
     
     /// Count number of instructions in transaction
-    fn count_instructions(transaction_with_meta: &solana_transaction_status::EncodedTransactionWithStatusMeta) -> usize {
-        match &transaction_with_meta.transaction {
-            solana_transaction_status::EncodedTransaction::Json(ui_transaction) => {
+    fn count_instructions(transaction: &EncodedTransaction) -> usize {
+        match transaction {
+            EncodedTransaction::Json(ui_transaction) => {
                 match &ui_transaction.message {
                     solana_transaction_status::UiMessage::Parsed(parsed) => {
                         parsed.instructions.len()
@@ -119,43 +877,490 @@ impl TransactionProcessor {
     
     /// Filter transactions based on criteria
     pub fn should_store_transaction(&self, tx: &ProcessedTransaction) -> bool {
+        let filters = &self.filters;
+        
         // Skip vote transactions if configured
-        if tx.is_vote {
+        if tx.is_vote && !filters.store_votes {
             debug!("Skipping vote transaction: {}", tx.signature);
             return false;
         }
         
         // Skip failed transactions if configured
-        if tx.error.is_some() {
+        if tx.error.is_some() && !filters.store_failed {
             debug!("Skipping failed transaction: {}", tx.signature);
             return false;
         }
         
+        if tx.fee < filters.min_fee {
+            debug!("Skipping transaction {} below minimum fee", tx.signature);
+            return false;
+        }
+        
+        if tx.num_signers < filters.min_signers {
+            debug!("Skipping transaction {} with fewer than {} signers", tx.signature, filters.min_signers);
+            return false;
+        }
+        
+        if filters.multisig_only && !tx.is_multisig {
+            return false;
+        }
+        
+        if filters.durable_nonce_only && tx.durable_nonce.is_none() {
+            return false;
+        }
+        
+        if let Some(reason) = self.spam_blocklist.matches(tx) {
+            debug!("Skipping spam transaction {} ({})", tx.signature, reason);
+            return false;
+        }
+        
+        let program_ids = tx.program_ids();
+        
+        if program_ids.iter().any(|id| filters.exclude_programs.iter().any(|p| p == id)) {
+            debug!("Skipping transaction {} invoking an excluded program", tx.signature);
+            return false;
+        }
+        
+        if !filters.include_programs.is_empty()
+            && !program_ids.iter().any(|id| filters.include_programs.iter().any(|p| p == id))
+        {
+            return false;
+        }
+        
+        if tx.program_categories.iter().any(|c| filters.exclude_categories.contains(c)) {
+            debug!("Skipping transaction {} invoking an excluded program category", tx.signature);
+            return false;
+        }
+        
+        if !filters.include_categories.is_empty()
+            && !tx.program_categories.iter().any(|c| filters.include_categories.contains(c))
+        {
+            return false;
+        }
+        
+        if tx.labeled_accounts.iter().any(|account| filters.exclude_labels.iter().any(|l| account.matches(l))) {
+            debug!("Skipping transaction {} referencing an excluded address label", tx.signature);
+            return false;
+        }
+        
+        if !filters.include_labels.is_empty()
+            && !tx.labeled_accounts.iter().any(|account| filters.include_labels.iter().any(|l| account.matches(l)))
+        {
+            return false;
+        }
+        
+        if !filters.include_accounts.is_empty()
+            && !tx.account_keys.iter().any(|key| filters.include_accounts.contains(key))
+        {
+            return false;
+        }
+        
+        if let Some(expression) = &self.filter_expression {
+            if !expression.evaluate(tx) {
+                debug!("Skipping transaction {} rejected by the filter expression", tx.signature);
+                return false;
+            }
+        }
+        
         true
     }
 }
 
-#[derive(Debug, Clone)]
+/// An instruction normalized across the encodings returned by RPC nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstructionInfo {
+    pub program_id: String,
+    pub accounts: Vec<String>,
+    /// Raw instruction data (unavailable when the RPC node fully parsed the instruction)
+    pub data: Option<Vec<u8>>,
+    /// Output of the RPC node's instruction parser, if it recognized the program; the same parser
+    /// is run locally on compiled instructions, as fetched in Base64
+    pub parsed: Option<serde_json::Value>,
+    pub stack_height: Option<u32>,
+}
+
+impl InstructionInfo {
+    fn from_ui_instruction(instruction: &UiInstruction, account_keys: &[String]) -> Self {
+        match instruction {
+            UiInstruction::Compiled(compiled) => Self::from_compiled(compiled, account_keys),
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => Self {
+                program_id: parsed.program_id.clone(),
+                accounts: Vec::new(),
+                data: None,
+                parsed: Some(parsed.parsed.clone()),
+                stack_height: parsed.stack_height,
+            },
+            UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) => Self {
+                program_id: decoded.program_id.clone(),
+                accounts: decoded.accounts.clone(),
+                data: bs58::decode(&decoded.data).into_vec().ok(),
+                parsed: None,
+                stack_height: decoded.stack_height,
+            },
+        }
+    }
+    
+    fn from_compiled(instruction: &UiCompiledInstruction, account_keys: &[String]) -> Self {
+        let resolve = |index: u8| account_keys
+            .get(index as usize)
+            .cloned()
+            .unwrap_or_default();
+        
+        let data = bs58::decode(&instruction.data).into_vec().ok();
+        Self {
+            program_id: resolve(instruction.program_id_index),
+            accounts: instruction.accounts.iter().map(|&index| resolve(index)).collect(),
+            parsed: data.as_ref().and_then(|data| Self::parse_compiled(instruction, data, account_keys)),
+            data,
+            stack_height: instruction.stack_height,
+        }
+    }
+    
+    /// Parse a compiled instruction as the RPC node does under `jsonParsed`, if it recognizes the
+    /// program
+    fn parse_compiled(
+        instruction: &UiCompiledInstruction,
+        data: &[u8],
+        account_keys: &[String],
+    ) -> Option<serde_json::Value> {
+        let program_id = Pubkey::from_str(account_keys.get(instruction.program_id_index as usize)?).ok()?;
+        let keys = account_keys.iter().map(|key| Pubkey::from_str(key)).collect::<Result<Vec<_>, _>>().ok()?;
+        let compiled = CompiledInstruction {
+            program_id_index: instruction.program_id_index,
+            accounts: instruction.accounts.clone(),
+            data: data.to_vec(),
+        };
+        parse_instruction::parse(&program_id, &compiled, &AccountKeys::new(&keys, None), instruction.stack_height)
+            .ok()
+            .map(|parsed| parsed.parsed)
+    }
+    
+    /// Identify which instruction of its program this is: the hex-encoded leading data bytes
+    /// (up to the 8-byte Anchor discriminator), or the instruction type reported by the RPC parser
+    pub fn discriminator(&self) -> Option<String> {
+        if let Some(data) = &self.data {
+            if data.is_empty() {
+                return None;
+            }
+            return Some(data.iter().take(8).map(|byte| format!("{:02x}", byte)).collect());
+        }
+        
+        self.parsed
+            .as_ref()
+            .and_then(|parsed| parsed.get("type"))
+            .and_then(|kind| kind.as_str())
+            .map(str::to_string)
+    }
+}
+
+/// An instruction together with the instructions it invoked via CPI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstructionNode {
+    pub instruction: InstructionInfo,
+    pub inner: Vec<InstructionNode>,
+}
+
+impl InstructionNode {
+    /// Rebuild the call tree from a flattened list of inner instructions using their stack heights
+    fn build_children(instructions: &[InstructionInfo], depth: u32) -> Vec<InstructionNode> {
+        let mut nodes = Vec::new();
+        let mut i = 0;
+        
+        while i < instructions.len() {
+            let start = i;
+            i += 1;
+            
+            // Everything deeper than the current depth belongs to this instruction
+            while i < instructions.len()
+                && instructions[i].stack_height.map_or(false, |height| height > depth)
+            {
+                i += 1;
+            }
+            
+            nodes.push(InstructionNode {
+                instruction: instructions[start].clone(),
+                inner: Self::build_children(&instructions[start + 1..i], depth + 1),
+            });
+        }
+        
+        nodes
+    }
+    
+    /// Collect this instruction and all instructions it invoked, in execution order
+    fn collect<'a>(&'a self, out: &mut Vec<&'a InstructionInfo>) {
+        out.push(&self.instruction);
+        for child in &self.inner {
+            child.collect(out);
+        }
+    }
+    
+    /// Collect the caller/callee edges below this instruction, skipping duplicates
+    fn collect_calls(&self, depth: u32, out: &mut Vec<CpiCall>) {
+        for child in &self.inner {
+            let call = CpiCall {
+                caller: self.instruction.program_id.clone(),
+                callee: child.instruction.program_id.clone(),
+                depth: depth + 1,
+            };
+            if !out.contains(&call) {
+                out.push(call);
+            }
+            child.collect_calls(depth + 1, out);
+        }
+    }
+    
+    /// Flatten this instruction and its CPIs into records indexed by their position in the tree
+    fn collect_records(&self, index: String, depth: u32, out: &mut Vec<InstructionRecord>) {
+        out.push(InstructionRecord {
+            index: index.clone(),
+            program_id: self.instruction.program_id.clone(),
+            accounts: self.instruction.accounts.clone(),
+            discriminator: self.instruction.discriminator(),
+            depth,
+        });
+        for (position, child) in self.inner.iter().enumerate() {
+            child.collect_records(format!("{}.{}", index, position), depth + 1, out);
+        }
+    }
+    
+    /// Collect the raw data of this instruction and its CPIs, where the encoding preserved it
+    fn collect_raw(&self, index: String, out: &mut Vec<RawInstruction>) {
+        if let Some(data) = &self.instruction.data {
+            out.push(RawInstruction {
+                index: index.clone(),
+                program_id: self.instruction.program_id.clone(),
+                accounts: self.instruction.accounts.clone(),
+                data: STANDARD.encode(data),
+            });
+        }
+        for (position, child) in self.inner.iter().enumerate() {
+            child.collect_raw(format!("{}.{}", index, position), out);
+        }
+    }
+}
+
+/// An edge in a transaction's CPI call graph: `caller` invoked `callee` at the given depth
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CpiCall {
+    pub caller: String,
+    pub callee: String,
+    /// Depth of the invoked instruction (1 for CPIs made directly by a top-level instruction)
+    pub depth: u32,
+}
+
+/// A single executed instruction, flattened out of the call tree for indexing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionRecord {
+    /// Position in the call tree: `2` is the third top-level instruction, `2.0` its first CPI
+    pub index: String,
+    pub program_id: String,
+    pub accounts: Vec<String>,
+    pub discriminator: Option<String>,
+    /// 0 for top-level instructions, incremented for each level of CPI
+    pub depth: u32,
+}
+
+/// Compute units a program spent itself, excluding those of the programs it invoked via CPI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramCompute {
+    pub program_id: String,
+    /// Invocations with a reported consumption, top-level and CPI
+    pub invocations: u32,
+    pub compute_units: u64,
+}
+
+/// Access an account was granted by the transaction message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountFlags {
+    pub writable: bool,
+    pub signer: bool,
+}
+
+/// Raw data of an executed instruction, kept so it can be decoded retroactively once a decoder
+/// or IDL for its program is added
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawInstruction {
+    /// Position in the call tree, as in `InstructionRecord`
+    pub index: String,
+    pub program_id: String,
+    pub accounts: Vec<String>,
+    /// Base64-encoded instruction data
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AccountChangeKind {
+    Created,
+    Closed,
+}
+
+/// An account created or closed by a transaction, with the lamports locked or released
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountChange {
+    pub account: String,
+    pub kind: AccountChangeKind,
+    /// Lamports deposited into a created account (typically its rent-exempt reserve)
+    /// or withdrawn from a closed account
+    pub lamports: u64,
+    /// Program owning a created account, when known
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ProcessedTransaction {
+    pub schema_version: u32,
     pub signature: String,
     pub slot: u64,
     pub block_time: Option<i64>,
     pub fee: u64,
+    pub fee_payer: Option<String>,
+    pub num_signers: usize,
+    /// Whether a Squads or SPL Token multisig was involved
+    pub is_multisig: bool,
     pub is_vote: bool,
     pub error: Option<serde_json::Value>,
+    /// Stable classification of `error` (e.g. `blockhash_expired`, `custom_program_error_1`)
+    pub error_class: Option<String>,
+    pub error_instruction_index: Option<u8>,
+    pub compute_units_consumed: Option<u64>,
+    /// Compute units attributed to each invoked program, from the program logs
+    pub program_compute: Vec<ProgramCompute>,
+    pub cu_limit: Option<u32>,
+    pub priority_fee_micro_lamports: Option<u64>,
     pub account_keys: Vec<String>,
+    /// Writable and signer flags of each entry in `account_keys`, in the same order
+    pub account_flags: Vec<AccountFlags>,
     pub instruction_count: usize,
+    /// Serialized size in bytes (unknown when the RPC node fully parsed an instruction)
+    pub wire_size: Option<usize>,
+    pub instructions: Vec<InstructionNode>,
+    pub log_messages: Vec<String>,
+    pub token_events: Vec<TokenEvent>,
+    pub system_events: Vec<SystemEvent>,
+    pub memo: Option<String>,
+    pub program_labels: Vec<String>,
+    pub program_categories: Vec<String>,
+    pub nft_events: Vec<NftEvent>,
+    pub vote_events: Vec<VoteEvent>,
+    pub anchor_events: Vec<AnchorEvent>,
+    pub account_changes: Vec<AccountChange>,
+    /// Accounts with an entry in the user-provided address labels file
+    pub labeled_accounts: Vec<LabeledAccount>,
+    /// Nonce account advanced by a durable nonce transaction
+    pub durable_nonce: Option<String>,
+    /// Swap made by the fee payer through a known DEX
+    pub swap: Option<Swap>,
+    /// Result of local signature verification, when enabled for binary-encoded transactions
+    pub signatures_verified: Option<bool>,
+    pub recent_blockhash: Option<String>,
+    /// Slots between the recent blockhash's block and inclusion, when the blockhash is cached
+    pub blockhash_age: Option<u64>,
+    /// Raw instruction data, when instruction data capture is enabled
+    pub raw_instructions: Vec<RawInstruction>,
+    /// Suspected drains, authority takeovers and mass approvals
+    pub security_alerts: Vec<SecurityAlert>,
 }
 
 impl ProcessedTransaction {
+    /// Lamports locked in accounts created by this transaction
+    pub fn rent_locked(&self) -> u64 {
+        self.account_changes
+            .iter()
+            .filter(|change| change.kind == AccountChangeKind::Created)
+            .map(|change| change.lamports)
+            .sum()
+    }
+    
+    /// Lamports released from accounts closed by this transaction
+    pub fn rent_released(&self) -> u64 {
+        self.account_changes
+            .iter()
+            .filter(|change| change.kind == AccountChangeKind::Closed)
+            .map(|change| change.lamports)
+            .sum()
+    }
+    
+    /// All instructions in execution order, including inner (CPI) instructions
+    pub fn all_instructions(&self) -> Vec<&InstructionInfo> {
+        let mut out = Vec::new();
+        for node in &self.instructions {
+            node.collect(&mut out);
+        }
+        out
+    }
+    
+    /// Accounts this transaction could modify
+    pub fn writable_accounts(&self) -> Vec<&str> {
+        self.account_keys
+            .iter()
+            .zip(&self.account_flags)
+            .filter(|(_, flags)| flags.writable)
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+    
+    /// Unique program IDs invoked by this transaction, including via CPI
+    pub fn program_ids(&self) -> Vec<&str> {
+        let mut program_ids: Vec<&str> = Vec::new();
+        for ix in self.all_instructions() {
+            if !program_ids.contains(&ix.program_id.as_str()) {
+                program_ids.push(&ix.program_id);
+            }
+        }
+        program_ids
+    }
+    
+    /// Every executed instruction, top-level and CPI, as flat records in execution order
+    pub fn instruction_records(&self) -> Vec<InstructionRecord> {
+        let mut out = Vec::new();
+        for (index, node) in self.instructions.iter().enumerate() {
+            node.collect_records(index.to_string(), 0, &mut out);
+        }
+        out
+    }
+    
+    /// Raw data of every instruction whose data the encoding preserved, in execution order
+    fn collect_raw_instructions(&self) -> Vec<RawInstruction> {
+        let mut out = Vec::new();
+        for (index, node) in self.instructions.iter().enumerate() {
+            node.collect_raw(index.to_string(), &mut out);
+        }
+        out
+    }
+    
+    /// Which program invoked which via CPI, and at what depth
+    pub fn call_graph(&self) -> Vec<CpiCall> {
+        let mut out = Vec::new();
+        for node in &self.instructions {
+            node.collect_calls(0, &mut out);
+        }
+        out
+    }
+    
+    /// Number of inner (CPI) instructions executed by this transaction
+    pub fn inner_instruction_count(&self) -> usize {
+        self.all_instructions().len() - self.instructions.len()
+    }
+    
     /// Get a summary of the transaction
     pub fn summary(&self) -> String {
         format!(
-            "Tx {} | Slot: {} | Fee: {} | Instructions: {} | Accounts: {}",
-            &self.signature[..8],
+            "Tx {} | Slot: {} | Fee: {} | CU: {} | Instructions: {} ({} inner) | Accounts: {}",
+            self.signature.get(..8).unwrap_or(&self.signature),
             self.slot,
             self.fee,
+            self.compute_units_consumed
+                .map(|cu| cu.to_string())
+                .unwrap_or_else(|| "-".to_string()),
             self.instruction_count,
+            self.inner_instruction_count(),
             self.account_keys.len()
         )
     }
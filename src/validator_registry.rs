@@ -0,0 +1,131 @@
+use anyhow::Result;
+use serde::Serialize;
+use solana_account_decoder::{
+    parse_config::{parse_config, ConfigAccountType},
+    UiAccountEncoding,
+};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+const CONFIG_PROGRAM_ID: &str = "Config1111111111111111111111111111111111111";
+
+/// First key of every validator info config account
+const VALIDATOR_INFO_KEY: &str = "Va1idator1nfo111111111111111111111111111111";
+
+/// Stake and published identity of a validator
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidatorInfo {
+    pub vote_account: String,
+    /// Lamports of stake active in the current epoch
+    pub activated_stake: u64,
+    pub delinquent: bool,
+    /// Name published with `solana validator-info publish`
+    pub name: Option<String>,
+}
+
+/// Validators keyed by node identity, refreshed from RPC
+#[derive(Clone, Default)]
+pub struct ValidatorRegistry {
+    validators: Arc<RwLock<HashMap<String, ValidatorInfo>>>,
+}
+
+impl ValidatorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn get(&self, identity: &str) -> Option<ValidatorInfo> {
+        self.validators.read().unwrap().get(identity).cloned()
+    }
+    
+    /// Total and delinquent activated stake, in lamports
+    pub fn stake_totals(&self) -> (u64, u64) {
+        let validators = self.validators.read().unwrap();
+        let total = validators.values().map(|validator| validator.activated_stake).sum();
+        let delinquent = validators
+            .values()
+            .filter(|validator| validator.delinquent)
+            .map(|validator| validator.activated_stake)
+            .sum();
+        (total, delinquent)
+    }
+    
+    /// Reload stakes from `getVoteAccounts` and names from on-chain validator info
+    pub async fn refresh(&self, client: &RpcClient) -> Result<()> {
+        let vote_accounts = client.get_vote_accounts().await?;
+        let mut validators = HashMap::new();
+        for (accounts, delinquent) in [(vote_accounts.current, false), (vote_accounts.delinquent, true)] {
+            for account in accounts {
+                validators.insert(account.node_pubkey, ValidatorInfo {
+                    vote_account: account.vote_pubkey,
+                    activated_stake: account.activated_stake,
+                    delinquent,
+                    name: None,
+                });
+            }
+        }
+        
+        // Names are a nicety; keep the stakes if they cannot be fetched
+        match Self::fetch_names(client).await {
+            Ok(names) => {
+                for (identity, name) in names {
+                    if let Some(validator) = validators.get_mut(&identity) {
+                        validator.name = Some(name);
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to fetch validator names: {}", e),
+        }
+        
+        *self.validators.write().unwrap() = validators;
+        Ok(())
+    }
+    
+    /// Published names by validator identity
+    async fn fetch_names(client: &RpcClient) -> Result<HashMap<String, String>> {
+        let config_program = Pubkey::from_str(CONFIG_PROGRAM_ID)?;
+        let validator_info_key = Pubkey::from_str(VALIDATOR_INFO_KEY)?;
+        
+        // Config accounts start with a one-byte key count followed by the keys
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                1,
+                validator_info_key.to_bytes().to_vec(),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let accounts = client.get_program_accounts_with_config(&config_program, config).await?;
+        
+        let mut names = HashMap::new();
+        for (pubkey, account) in accounts {
+            let info = match parse_config(&account.data, &pubkey) {
+                Ok(ConfigAccountType::ValidatorInfo(info)) => info,
+                _ => continue,
+            };
+            // The second key is the validator identity that signed the info
+            let identity = info.keys.get(1).map(|key| key.pubkey.clone());
+            let name = info.config_data
+                .get("name")
+                .and_then(|name| name.as_str())
+                .map(str::to_string);
+            if let (Some(identity), Some(name)) = (identity, name) {
+                names.insert(identity, name);
+            }
+        }
+        
+        Ok(names)
+    }
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::gossip::ClusterNode;
+
+/// Versions listed in the log line; the stored report keeps all of them
+const LOGGED_VERSIONS: usize = 5;
+
+/// Nodes and stake running one software or shred version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionShare {
+    pub version: String,
+    pub nodes: usize,
+    /// Activated stake in lamports
+    pub stake: u64,
+}
+
+/// How the cluster's nodes and stake are spread over versions, for tracking upgrade adoption
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionReport {
+    /// Unix time in milliseconds of the report
+    pub timestamp: u64,
+    pub nodes: usize,
+    pub total_stake: u64,
+    /// Software versions, most stake (then nodes) first. Nodes that have not pushed a version
+    /// are counted as "unknown"
+    pub versions: Vec<VersionShare>,
+    pub shred_versions: Vec<VersionShare>,
+}
+
+impl VersionReport {
+    pub fn from_nodes(nodes: &[ClusterNode], timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            nodes: nodes.len(),
+            total_stake: nodes.iter().map(|node| node.activated_stake).sum(),
+            versions: shares(nodes, |node| node.version.clone().unwrap_or_else(|| "unknown".to_string())),
+            shred_versions: shares(nodes, |node| node.shred_version.to_string()),
+        }
+    }
+    
+    /// Share of stake, or of nodes while stakes are unknown, running `share`
+    pub fn fraction(&self, share: &VersionShare) -> f64 {
+        match self.total_stake {
+            0 if self.nodes == 0 => 0.0,
+            0 => share.nodes as f64 / self.nodes as f64,
+            total => share.stake as f64 / total as f64,
+        }
+    }
+    
+    pub fn log(&self) {
+        let describe = |shares: &[VersionShare], limit: usize| -> String {
+            let mut parts: Vec<String> = shares
+                .iter()
+                .take(limit)
+                .map(|share| format!("{} {:.1}% ({} nodes)", share.version, self.fraction(share) * 100.0, share.nodes))
+                .collect();
+            if shares.len() > limit {
+                parts.push(format!("{} more", shares.len() - limit));
+            }
+            parts.join(", ")
+        };
+        let weight = if self.total_stake > 0 { "stake" } else { "nodes" };
+        info!(
+            "Versions by {} across {} nodes: {}",
+            weight,
+            self.nodes,
+            describe(&self.versions, LOGGED_VERSIONS)
+        );
+        info!("Shred versions by {}: {}", weight, describe(&self.shred_versions, usize::MAX));
+    }
+}
+
+fn shares(nodes: &[ClusterNode], key: impl Fn(&ClusterNode) -> String) -> Vec<VersionShare> {
+    let mut groups: HashMap<String, VersionShare> = HashMap::new();
+    for node in nodes {
+        let version = key(node);
+        let share = groups.entry(version.clone()).or_insert_with(|| VersionShare { version, nodes: 0, stake: 0 });
+        share.nodes += 1;
+        share.stake += node.activated_stake;
+    }
+    
+    let mut shares: Vec<VersionShare> = groups.into_values().collect();
+    shares.sort_by(|a, b| (b.stake, b.nodes, &a.version).cmp(&(a.stake, a.nodes, &b.version)));
+    shares
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::transaction_processor::ProcessedTransaction;
+
+/// A processed transaction touching a watched address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchlistHit {
+    pub address: String,
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// Whether the watched address signed the transaction
+    pub signer: bool,
+    /// Whether the transaction could modify the watched account
+    pub writable: bool,
+    pub fee_payer: Option<String>,
+    pub programs: Vec<String>,
+    pub failed: bool,
+}
+
+/// Addresses whose activity is reported as it happens
+#[derive(Debug, Default)]
+pub struct Watchlist {
+    addresses: HashSet<String>,
+}
+
+impl Watchlist {
+    pub fn new(addresses: &[String]) -> Self {
+        Self {
+            addresses: addresses.iter().cloned().collect(),
+        }
+    }
+    
+    /// One hit per watched address among the transaction's account keys
+    pub fn hits(&self, tx: &ProcessedTransaction) -> Vec<WatchlistHit> {
+        if self.addresses.is_empty() {
+            return Vec::new();
+        }
+        
+        let mut hits: Vec<WatchlistHit> = Vec::new();
+        for (index, address) in tx.account_keys.iter().enumerate() {
+            if !self.addresses.contains(address) || hits.iter().any(|hit| &hit.address == address) {
+                continue;
+            }
+            let flags = tx.account_flags.get(index).copied().unwrap_or_default();
+            hits.push(WatchlistHit {
+                address: address.clone(),
+                signature: tx.signature.clone(),
+                slot: tx.slot,
+                block_time: tx.block_time,
+                signer: flags.signer,
+                writable: flags.writable,
+                fee_payer: tx.fee_payer.clone(),
+                programs: tx.program_ids().into_iter().map(str::to_string).collect(),
+                failed: tx.error.is_some(),
+            });
+        }
+        hits
+    }
+}
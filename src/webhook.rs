@@ -0,0 +1,236 @@
+//! Delivery of processed transactions to webhook endpoints
+//!
+//! Each configured webhook is POSTed the transactions matching its filter, as the JSON of the REST
+//! API, signed with its secret. Failed attempts are retried with exponential backoff; once
+//! `max_attempts` is used up, or the endpoint rejects the request outright (a 4xx other than 408
+//! and 429), the transaction is kept in the dead-letter store until redelivered from the admin API,
+//! pruned with its slot, or a week old. Transactions a webhook misses while behind the feed are
+//! counted per webhook.
+
+use anyhow::{anyhow, bail, Result};
+use hmac::{Hmac, Mac};
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{
+    sync::{broadcast, Semaphore},
+    time::sleep,
+};
+use tracing::{info, warn};
+
+use crate::api::feed::{StreamFilter, StreamedTransaction};
+use crate::config::WebhookConfig;
+use crate::metrics::Metrics;
+use crate::storage::Storage;
+
+/// HMAC-SHA256 of `<timestamp>.<body>`, as `sha256=<hex>`, when the webhook has a secret
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+/// Unix time in seconds the request was signed at, so receivers can reject replayed requests
+pub const TIMESTAMP_HEADER: &str = "x-timestamp";
+
+/// Deliveries in progress per webhook, retries included; past it the webhook stops reading the
+/// transaction feed, and misses the transactions the feed drops meanwhile
+const MAX_IN_FLIGHT: usize = 64;
+
+/// Longest wait between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// A transaction a webhook did not accept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub url: String,
+    pub signature: String,
+    pub payload: Value,
+    pub attempts: u32,
+    /// Outcome of the last attempt
+    pub error: String,
+    /// Unix time in milliseconds
+    pub failed_at: u64,
+}
+
+/// Outcome of sending dead letters again
+#[derive(Debug, Default, Serialize)]
+pub struct Redelivery {
+    pub delivered: usize,
+    /// Dead letters kept, their webhook failing again or no longer configured
+    pub failed: usize,
+}
+
+struct Webhook {
+    config: WebhookConfig,
+    filter: StreamFilter,
+}
+
+/// Why an attempt failed
+enum Failure {
+    /// Worth trying again: no answer, a timeout, a 5xx, 408 or 429
+    Transient(String),
+    /// The endpoint refused the request
+    Rejected(String),
+}
+
+/// The configured webhooks, delivering from the transaction feed once started
+#[derive(Clone)]
+pub struct Webhooks {
+    webhooks: Arc<Vec<Webhook>>,
+    client: reqwest::Client,
+    storage: Storage,
+    metrics: Arc<Metrics>,
+}
+
+impl Webhooks {
+    pub fn new(configs: &[WebhookConfig], storage: Storage, metrics: Arc<Metrics>) -> Result<Self> {
+        let mut webhooks = Vec::new();
+        for config in configs {
+            let filter = StreamFilter {
+                programs: config.programs.clone(),
+                accounts: config.accounts.clone(),
+                events: config.events.clone(),
+                replay_from_slot: None,
+            };
+            filter.validate().map_err(|e| anyhow!("Webhook {}: {}", config.url, e))?;
+            if config.max_attempts == 0 {
+                bail!("Webhook {}: max_attempts must be at least 1", config.url);
+            }
+            webhooks.push(Webhook { config: config.clone(), filter });
+        }
+        Ok(Self {
+            webhooks: Arc::new(webhooks),
+            client: reqwest::Client::new(),
+            storage,
+            metrics,
+        })
+    }
+    
+    /// Deliver the matching transactions of `feed` to each webhook, in the background
+    pub fn start(&self, feed: &broadcast::Sender<StreamedTransaction>) {
+        for (index, webhook) in self.webhooks.iter().enumerate() {
+            info!("Delivering transactions to webhook {}", webhook.config.url);
+            tokio::spawn(self.clone().deliver_feed(index, feed.subscribe()));
+        }
+    }
+    
+    /// Send up to `limit` dead letters again, oldest first, once each; the delivered ones are
+    /// removed from the store
+    pub async fn redeliver(&self, limit: usize) -> Result<Redelivery> {
+        let mut redelivery = Redelivery::default();
+        for letter in self.storage.get_dead_letters(limit)? {
+            let webhook = match self.webhooks.iter().find(|webhook| webhook.config.url == letter.url) {
+                Some(webhook) => webhook,
+                None => {
+                    redelivery.failed += 1;
+                    continue;
+                }
+            };
+            match self.post(&webhook.config, &letter.payload.to_string()).await {
+                Ok(()) => {
+                    self.storage.delete_dead_letter(&letter)?;
+                    self.metrics.webhooks_delivered.inc();
+                    redelivery.delivered += 1;
+                }
+                Err(_) => redelivery.failed += 1,
+            }
+        }
+        Ok(redelivery)
+    }
+    
+    async fn deliver_feed(self, index: usize, mut transactions: broadcast::Receiver<StreamedTransaction>) {
+        let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+        loop {
+            let webhook = &self.webhooks[index];
+            let tx = match transactions.recv().await {
+                Ok(tx) if webhook.filter.matches(&tx) => tx,
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Webhook {} fell behind, {} transactions not delivered", webhook.config.url, missed);
+                    self.metrics.record_webhook_missed(&webhook.config.url, missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let permit = match in_flight.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => break,
+            };
+            let webhooks = self.clone();
+            tokio::spawn(async move {
+                webhooks.deliver(index, tx).await;
+                drop(permit);
+            });
+        }
+    }
+    
+    /// Post `tx` until the webhook accepts it, or move it to the dead-letter store
+    async fn deliver(&self, index: usize, tx: StreamedTransaction) {
+        let config = &self.webhooks[index].config;
+        let mut backoff = Duration::from_millis(config.initial_backoff_ms);
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            match self.post(config, &tx.json).await {
+                Ok(()) => {
+                    self.metrics.webhooks_delivered.inc();
+                    return;
+                }
+                Err(Failure::Transient(error)) if attempts < config.max_attempts => {
+                    warn!("Webhook {} failed for {} ({}), retrying in {:?}", config.url, tx.signature, error, backoff);
+                    sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                Err(Failure::Transient(error) | Failure::Rejected(error)) => break error,
+            }
+        };
+        
+        warn!("Webhook {} gave up on {} after {} attempts: {}", config.url, tx.signature, attempts, error);
+        let letter = serde_json::from_str(&tx.json).map(|payload| DeadLetter {
+            url: config.url.clone(),
+            signature: tx.signature.clone(),
+            payload,
+            attempts,
+            error,
+            failed_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+        });
+        match letter.map_err(anyhow::Error::from).and_then(|letter| self.storage.store_dead_letter(&letter)) {
+            Ok(()) => self.metrics.webhooks_dead_lettered.inc(),
+            Err(e) => warn!("Failed to store dead letter of {} for {}: {:#}", tx.signature, config.url, e),
+        }
+    }
+    
+    /// A single attempt
+    async fn post(&self, config: &WebhookConfig, body: &str) -> Result<(), Failure> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let mut request = self
+            .client
+            .post(&config.url)
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string());
+        if let Some(secret) = &config.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, timestamp, body));
+        }
+        
+        let response = request.body(body.to_string()).send().await.map_err(|e| Failure::Transient(e.to_string()))?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status @ (StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS) => {
+                Err(Failure::Transient(format!("HTTP {}", status)))
+            }
+            status if status.is_client_error() => Err(Failure::Rejected(format!("HTTP {}", status))),
+            status => Err(Failure::Transient(format!("HTTP {}", status))),
+        }
+    }
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `<timestamp>.<body>` keyed with `secret`
+fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    format!("sha256={}", digest.iter().map(|byte| format!("{:02x}", byte)).collect::<String>())
+}